@@ -7,7 +7,7 @@ use rand::{Rng, SeedableRng, rngs::StdRng};
 
 use walkers::sources;
 use walkers::{HttpOptions, HttpTiles, Map, MapMemory, Position, Projector, lon_lat};
-use walkers_extras::{Group, GroupedPlacesTree, LabeledSymbol, LabeledSymbolStyle, Place, Symbol};
+use walkers_extras::{Group, GroupedPlacesTree, LabeledSymbol, Place, Symbol};
 
 const POI_COUNT: usize = 2_000;
 const HALF_WIDTH_M: f64 = 1_200.0;
@@ -97,15 +97,11 @@ fn generate_poi(rng: &mut StdRng, center: Position) -> Vec<LabeledSymbol> {
         let lon = rng.random_range((center_lon - dlon)..(center_lon + dlon));
         let lat = rng.random_range((center_lat - dlat)..(center_lat + dlat));
 
-        out.push(LabeledSymbol {
-            position: lon_lat(lon, lat),
-            label: format!("POI #{:04}", i + 1),
-            symbol: Some(Symbol::Circle("•".to_string())),
-            style: LabeledSymbolStyle {
-                symbol_size: 5.0,
-                ..LabeledSymbolStyle::default()
-            },
-        });
+        out.push(
+            LabeledSymbol::new(lon_lat(lon, lat), format!("POI #{:04}", i + 1))
+                .with_symbol(Symbol::Circle("•".to_string()))
+                .with_size(5.0),
+        );
     }
     out
 }
@@ -113,7 +109,7 @@ fn generate_poi(rng: &mut StdRng, center: Position) -> Vec<LabeledSymbol> {
 struct ClusterApp {
     memory: MapMemory,
     rng: StdRng,
-    points: Vec<LabeledSymbol>,
+    points: Arc<Vec<LabeledSymbol>>,
     tiles: Option<HttpTiles>,
     avg_frame_ms: RollingAvg<120>,
     plugin: Option<Rc<GroupedPlacesTree<LabeledSymbol, DemoClusterGroup>>>,
@@ -125,7 +121,7 @@ impl ClusterApp {
         let mut app = Self {
             memory: MapMemory::default(),
             rng: StdRng::from_os_rng(),
-            points: Vec::new(),
+            points: Arc::default(),
             tiles: Some(HttpTiles::with_options(
                 sources::OpenStreetMap,
                 HttpOptions::default(),
@@ -144,7 +140,7 @@ impl ClusterApp {
     }
 
     fn regenerate_points(&mut self) {
-        self.points = generate_poi(&mut self.rng, Self::map_center());
+        self.points = Arc::new(generate_poi(&mut self.rng, Self::map_center()));
         self.avg_frame_ms.reset();
         self.stats.set(ClusterStats::default());
         self.rebuild_plugin();
@@ -195,7 +191,7 @@ impl eframe::App for ClusterApp {
                 self.rebuild_plugin();
             }
 
-            let mut map = Map::new(None, &mut self.memory, Self::map_center());
+            let mut map = Map::new(None, &mut self.memory, Self::map_center()).frame_stats(true);
             if let Some(tiles) = self.tiles.as_mut() {
                 map = map.with_layer(tiles, 1.0);
             }
@@ -219,6 +215,15 @@ impl eframe::App for ClusterApp {
                 "\nClusters: {} (max size {})",
                 stats.clusters, stats.max_size
             ));
+            if let Some(frame) = self.memory.frame_stats() {
+                summary.push_str(&format!(
+                    "\nTiles: {} ({} upscaled, {} loading)\nPlugins: {:.1} ms",
+                    frame.tiles_drawn,
+                    frame.tiles_upscaled,
+                    frame.placeholders,
+                    frame.plugins_time.as_secs_f64() * 1_000.0
+                ));
+            }
 
             let painter = ui.painter_at(map_response.response.rect);
             painter.text(
@@ -255,6 +260,7 @@ impl walkers::Plugin for StatsHandle {
     fn run(
         self: Box<Self>,
         ui: &mut egui::Ui,
+        _painter: &egui::Painter,
         response: &egui::Response,
         projector: &walkers::Projector,
         memory: &MapMemory,
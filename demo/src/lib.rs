@@ -3,9 +3,10 @@ mod plugins;
 mod tiles;
 mod windows;
 
-use egui::{Button, CentralPanel, Context, DragPanButtons, Frame, OpenUrl, Rect, Vec2};
+use egui::{Align2, Button, CentralPanel, Context, DragPanButtons, Frame, OpenUrl, Rect, Vec2};
 use tiles::{TilesKind, providers};
 use walkers::{Map, MapMemory};
+use walkers_extras::{CoordinateFormat, CoordinateReadout, LoadingIndicator};
 
 use crate::tiles::Providers;
 
@@ -40,10 +41,13 @@ impl eframe::App for MyApp {
                 .available
                 .get_mut(&self.providers.selected)
                 .unwrap();
-            let attributions: Vec<_> = tiles
+            let tiles_in_progress = tiles
                 .iter()
-                .map(|tile| tile.as_ref().attribution())
-                .collect();
+                .map(|tiles| match tiles {
+                    TilesKind::Http(tiles) => tiles.stats().in_progress,
+                    _ => 0,
+                })
+                .sum();
 
             // In egui, widgets are constructed and consumed in each frame.
             let mut map = Map::new(None, &mut self.map_memory, my_position);
@@ -51,13 +55,25 @@ impl eframe::App for MyApp {
             // Various aspects of the map can be configured.
             map = map
                 .zoom_with_ctrl(self.zoom_with_ctrl)
-                .drag_pan_buttons(DragPanButtons::PRIMARY | DragPanButtons::SECONDARY);
+                .drag_pan_buttons(DragPanButtons::PRIMARY | DragPanButtons::SECONDARY)
+                .with_empty_coverage_hint("No map data for this area");
 
             // Optionally, plugins can be attached.
             map = map
                 .with_plugin(plugins::places())
                 .with_plugin(plugins::CustomShapes {})
-                .with_plugin(&mut self.click_watcher);
+                .with_plugin(&mut self.click_watcher)
+                .with_plugin(
+                    LoadingIndicator::new(tiles_in_progress)
+                        .with_anchor(Align2::CENTER_BOTTOM)
+                        .with_count(true),
+                )
+                .with_plugin(
+                    CoordinateReadout::default()
+                        .with_anchor(Align2::CENTER_TOP)
+                        .with_format(CoordinateFormat::Dms)
+                        .with_precision(1),
+                );
 
             // Multiple layers can be added.
             for (n, tiles) in tiles.iter_mut().enumerate() {
@@ -66,6 +82,9 @@ impl eframe::App for MyApp {
                 map = map.with_layer(tiles.as_mut(), transparency);
             }
 
+            // Attributions of all the layers and plugins, for the "Acknowledge" window.
+            let attributions = map.attributions();
+
             // Draw the map widget.
             let response = map.show(ui, |ui, _, projector, _| {
                 // You can add any additional contents to the map's UI here.
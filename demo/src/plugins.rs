@@ -1,5 +1,5 @@
-use egui::{Color32, Response, Ui};
-use walkers::{MapMemory, Plugin, Position, Projector};
+use egui::{Color32, Painter, Response, Ui};
+use walkers::{MapMemory, Plugin, PluginLayer, Position, Projector};
 use walkers_extras::{
     GroupedPlaces, LabeledSymbol, LabeledSymbolGroup, LabeledSymbolGroupStyle, LabeledSymbolStyle,
     Symbol,
@@ -19,6 +19,8 @@ pub fn places() -> impl Plugin {
                     symbol_size: 25.,
                     ..Default::default()
                 },
+                category: Some("transport".to_owned()),
+                selected: false,
             },
             LabeledSymbol {
                 position: places::dworcowa_bus_stop(),
@@ -30,12 +32,16 @@ pub fn places() -> impl Plugin {
                     symbol_background: Color32::WHITE.gamma_multiply(0.4),
                     ..Default::default()
                 },
+                category: Some("transport".to_owned()),
+                selected: false,
             },
             LabeledSymbol {
                 position: places::rynek(),
                 label: "Rynek".to_owned(),
                 symbol: None,
                 style: LabeledSymbolStyle::default(),
+                category: None,
+                selected: false,
             },
         ],
         LabeledSymbolGroup {
@@ -50,7 +56,8 @@ pub struct CustomShapes {}
 impl Plugin for CustomShapes {
     fn run(
         self: Box<Self>,
-        ui: &mut Ui,
+        _ui: &mut Ui,
+        painter: &Painter,
         response: &Response,
         projector: &Projector,
         _map_memory: &MapMemory,
@@ -69,12 +76,16 @@ impl Plugin for CustomShapes {
             .map(|hover_pos| hover_pos.distance(position) < radius)
             .unwrap_or(false);
 
-        ui.painter().circle_filled(
+        painter.circle_filled(
             position,
             radius,
             Color32::BLACK.gamma_multiply(if hovered { 0.5 } else { 0.2 }),
         );
     }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Shapes
+    }
 }
 
 #[derive(Default, Clone)]
@@ -101,7 +112,8 @@ impl ClickWatcher {
 impl Plugin for &mut ClickWatcher {
     fn run(
         self: Box<Self>,
-        ui: &mut Ui,
+        _ui: &mut Ui,
+        painter: &Painter,
         response: &Response,
         projector: &Projector,
         _map_memory: &MapMemory,
@@ -113,8 +125,7 @@ impl Plugin for &mut ClickWatcher {
         }
 
         if let Some(position) = self.clicked_at {
-            ui.painter()
-                .circle_filled(projector.project(position).to_pos2(), 5.0, Color32::BLUE);
+            painter.circle_filled(projector.project(position).to_pos2(), 5.0, Color32::BLUE);
         }
     }
 }
@@ -0,0 +1,267 @@
+//! Packing raster tiles into a few large textures, instead of creating a texture per tile.
+
+use egui::{Color32, ColorImage, Context, Rect, TextureHandle, TextureOptions, pos2, vec2};
+
+use crate::Texture;
+
+/// Preferred width and height of a single atlas page, in pixels. It is small enough to be
+/// supported by WebGL everywhere.
+const PAGE_SIZE: usize = 2048;
+
+/// Empty pixels around each tile, filled with its edge pixels, so that linear filtering does not
+/// bleed the neighbouring tiles in.
+const GUTTER: usize = 1;
+
+/// Place of a tile in the [`TileAtlas`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Slot {
+    page: usize,
+    index: usize,
+}
+
+/// Tile uploaded to the [`TileAtlas`].
+#[derive(Clone)]
+pub(crate) struct AtlasTile {
+    /// Whole atlas page.
+    pub texture: Texture,
+
+    /// Part of the page occupied by the tile.
+    pub uv: Rect,
+
+    pub slot: Slot,
+}
+
+/// Set of large textures, "pages", divided into equal slots, each holding a single tile. Tiles
+/// are uploaded with [`TextureHandle::set_partial`], so slots of evicted tiles are reused without
+/// creating nor freeing any textures.
+pub(crate) struct TileAtlas {
+    ctx: Context,
+    options: TextureOptions,
+
+    /// Size of the tile images. Known once the first one arrives.
+    tile_size: Option<[usize; 2]>,
+    pages: Vec<TextureHandle>,
+    free: Vec<Slot>,
+
+    /// Slots of evicted tiles, along with the pass in which they were released. They might still
+    /// be drawn in that pass, so they cannot be overwritten until it ends.
+    released: Vec<(Slot, u64)>,
+}
+
+impl TileAtlas {
+    pub fn new(ctx: Context, options: TextureOptions) -> Self {
+        Self {
+            ctx,
+            options,
+            tile_size: None,
+            pages: Vec::new(),
+            free: Vec::new(),
+            released: Vec::new(),
+        }
+    }
+
+    /// Upload the image into a free slot. `None` if the image is of different size than the
+    /// previous ones, in which case it needs a texture of its own.
+    pub fn insert(&mut self, image: &ColorImage) -> Option<AtlasTile> {
+        let tile_size = *self.tile_size.get_or_insert(image.size);
+        if image.size != tile_size {
+            return None;
+        }
+
+        let slot = self.allocate();
+        let slot_size = slot_size(tile_size);
+        let columns = slots_per_side(slot_size[0]);
+        let [page_width, page_height] = page_size(tile_size);
+        let origin = [
+            (slot.index % columns) * slot_size[0],
+            (slot.index / columns) * slot_size[1],
+        ];
+
+        self.pages[slot.page].set_partial(origin, with_gutter(image), self.options);
+
+        let min = pos2(
+            (origin[0] + GUTTER) as f32 / page_width as f32,
+            (origin[1] + GUTTER) as f32 / page_height as f32,
+        );
+        let uv = Rect::from_min_size(
+            min,
+            vec2(
+                tile_size[0] as f32 / page_width as f32,
+                tile_size[1] as f32 / page_height as f32,
+            ),
+        );
+
+        Some(AtlasTile {
+            texture: Texture::Raster(self.pages[slot.page].clone()),
+            uv,
+            slot,
+        })
+    }
+
+    /// Drop all the pages, e.g. because they were lost along with the GPU context. Tiles have to
+    /// be inserted again.
+    pub fn clear(&mut self) {
+        self.tile_size = None;
+        self.pages.clear();
+        self.free.clear();
+        self.released.clear();
+    }
+
+    /// Make the slot available for other tiles, once the current pass ends.
+    pub fn release(&mut self, slot: Slot) {
+        self.released.push((slot, self.ctx.cumulative_pass_nr()));
+    }
+
+    fn allocate(&mut self) -> Slot {
+        let pass = self.ctx.cumulative_pass_nr();
+        let (ready, still_drawn): (Vec<_>, Vec<_>) = self
+            .released
+            .drain(..)
+            .partition(|(_, released_in)| *released_in < pass);
+        self.released = still_drawn;
+        self.free.extend(ready.into_iter().map(|(slot, _)| slot));
+
+        if let Some(slot) = self.free.pop() {
+            return slot;
+        }
+
+        // No free slots, so add a new page.
+        let tile_size = self.tile_size.unwrap_or([1, 1]);
+        let [slot_width, slot_height] = slot_size(tile_size);
+        let page = self.pages.len();
+        self.pages.push(self.ctx.load_texture(
+            format!("tile atlas {page}"),
+            ColorImage::filled(page_size(tile_size), Color32::TRANSPARENT),
+            self.options,
+        ));
+
+        let slots = slots_per_side(slot_width) * slots_per_side(slot_height);
+        // Reversed, so that slots are taken in order.
+        self.free
+            .extend((1..slots).rev().map(|index| Slot { page, index }));
+        Slot { page, index: 0 }
+    }
+
+    /// Number of textures used by the atlas.
+    #[cfg(test)]
+    pub fn pages(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+fn slot_size([width, height]: [usize; 2]) -> [usize; 2] {
+    [width + 2 * GUTTER, height + 2 * GUTTER]
+}
+
+/// How many slots fit in a page. At least one, even if the tiles are huge.
+fn slots_per_side(slot_size: usize) -> usize {
+    (PAGE_SIZE / slot_size).max(1)
+}
+
+fn page_size(tile_size: [usize; 2]) -> [usize; 2] {
+    let [width, height] = slot_size(tile_size);
+    [
+        slots_per_side(width) * width,
+        slots_per_side(height) * height,
+    ]
+}
+
+/// Surround the image with its own edge pixels.
+fn with_gutter(image: &ColorImage) -> ColorImage {
+    let [width, height] = image.size;
+    let [padded_width, padded_height] = slot_size(image.size);
+    let pixels = (0..padded_height)
+        .flat_map(|y| {
+            let y = y.saturating_sub(GUTTER).min(height - 1);
+            (0..padded_width).map(move |x| {
+                let x = x.saturating_sub(GUTTER).min(width - 1);
+                image.pixels[y * width + x]
+            })
+        })
+        .collect();
+    ColorImage::new([padded_width, padded_height], pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(color: Color32) -> ColorImage {
+        ColorImage::filled([256, 256], color)
+    }
+
+    fn atlas() -> TileAtlas {
+        TileAtlas::new(Context::default(), TextureOptions::default())
+    }
+
+    #[test]
+    fn tiles_share_a_texture() {
+        let mut atlas = atlas();
+        let first = atlas.insert(&tile(Color32::RED)).expect("tile fits");
+        let second = atlas.insert(&tile(Color32::GREEN)).expect("tile fits");
+
+        assert_eq!(atlas.pages(), 1);
+        assert_ne!(first.slot, second.slot);
+        assert!(!first.uv.intersects(second.uv));
+
+        // 7 x 7 slots of 258 pixels each.
+        assert_eq!(first.uv.min, pos2(1. / 1806., 1. / 1806.));
+        assert_eq!(second.uv.min, pos2(259. / 1806., 1. / 1806.));
+    }
+
+    #[test]
+    fn new_page_is_added_when_full() {
+        let mut atlas = atlas();
+        for _ in 0..49 {
+            atlas.insert(&tile(Color32::RED));
+        }
+        assert_eq!(atlas.pages(), 1);
+
+        atlas.insert(&tile(Color32::RED));
+        assert_eq!(atlas.pages(), 2);
+    }
+
+    #[test]
+    fn released_slot_is_reused_only_in_the_next_pass() {
+        let ctx = Context::default();
+        let mut atlas = TileAtlas::new(ctx.clone(), TextureOptions::default());
+        let mut slots = Vec::new();
+        for _ in 0..49 {
+            slots.push(atlas.insert(&tile(Color32::RED)).expect("tile fits").slot);
+        }
+
+        // Tile might have been drawn in this pass already, so its slot cannot be overwritten.
+        atlas.release(slots[3]);
+        let tile_in_same_pass = atlas.insert(&tile(Color32::RED)).expect("tile fits");
+        assert_eq!(atlas.pages(), 2);
+        assert_eq!(tile_in_same_pass.slot, Slot { page: 1, index: 0 });
+
+        let _ = ctx.run(Default::default(), |_| {});
+        atlas.release(tile_in_same_pass.slot);
+        let tile_in_next_pass = atlas.insert(&tile(Color32::RED)).expect("tile fits");
+        assert_eq!(tile_in_next_pass.slot, slots[3]);
+    }
+
+    #[test]
+    fn tiles_of_different_size_do_not_fit() {
+        let mut atlas = atlas();
+        assert!(atlas.insert(&tile(Color32::RED)).is_some());
+        assert!(
+            atlas
+                .insert(&ColorImage::filled([512, 512], Color32::RED))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn gutter_repeats_edge_pixels() {
+        let image = ColorImage::new([2, 1], vec![Color32::RED, Color32::GREEN]);
+        let padded = with_gutter(&image);
+        assert_eq!(padded.size, [4, 3]);
+        assert_eq!(
+            padded.pixels[4..8],
+            [Color32::RED, Color32::RED, Color32::GREEN, Color32::GREEN]
+        );
+        assert_eq!(padded.pixels[0..4], padded.pixels[4..8]);
+    }
+}
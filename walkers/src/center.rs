@@ -45,13 +45,16 @@ impl Center {
         my_position: Position,
         pull_to_my_position_threshold: f32,
         drag_pan_buttons: DragPanButtons,
+        reduced_motion: bool,
     ) -> bool {
         if dragged_by(response, drag_pan_buttons) {
             self.dragged_by(my_position, response);
             true
         } else if response.drag_stopped() {
-            self.drag_stopped(pull_to_my_position_threshold);
-            true
+            // Drag which stayed within the threshold never moved the map, and is a click instead.
+            let moving = matches!(self, Center::Moving { .. });
+            self.drag_stopped(pull_to_my_position_threshold, reduced_motion);
+            moving
         } else {
             false
         }
@@ -65,23 +68,47 @@ impl Center {
             self.adjusted_position().is_some()
         };
 
+        // Panning starts only once the pointer gets past the drag threshold, so catch up with the
+        // distance it has moved since it was pressed.
+        let direction = if matches!(self, Center::Moving { .. }) {
+            response.drag_delta()
+        } else {
+            response
+                .ctx
+                .input(|input| {
+                    input
+                        .pointer
+                        .press_origin()
+                        .zip(input.pointer.interact_pos())
+                })
+                .map_or(response.drag_delta(), |(origin, pos)| pos - origin)
+        };
+
         *self = Center::Moving {
             position: self
                 .adjusted_position()
                 .unwrap_or(AdjustedPosition::new(my_position)),
-            direction: response.drag_delta(),
+            direction,
             from_detached,
         };
     }
 
-    fn drag_stopped(&mut self, pull_to_my_position_threshold: f32) {
+    fn drag_stopped(&mut self, pull_to_my_position_threshold: f32, reduced_motion: bool) {
         if let Center::Moving {
             position,
             direction,
             from_detached,
         } = &self
         {
-            if *from_detached || position.offset_length() > pull_to_my_position_threshold {
+            let detach = *from_detached || position.offset_length() > pull_to_my_position_threshold;
+            if reduced_motion {
+                // Stop right away instead of gliding.
+                *self = if detach {
+                    Center::Exact(position.to_owned())
+                } else {
+                    Center::MyPosition
+                };
+            } else if detach {
                 *self = Center::Inertia {
                     position: position.clone(),
                     direction: direction.normalized(),
@@ -164,20 +191,20 @@ impl Center {
         self.detached().unwrap_or(my_position)
     }
 
-    /// Shift position by given number of pixels, if detached.
-    pub(crate) fn shift(self, offset: Vec2, zoom: f64) -> Self {
+    /// Keep the longitude within `-180..180` range, if detached.
+    pub(crate) fn wrap_longitude(self) -> Self {
         match self {
             Center::MyPosition => Center::MyPosition,
             Center::PulledToMyPosition(position) => {
-                Center::PulledToMyPosition(position.shift(offset, zoom))
+                Center::PulledToMyPosition(position.wrap_longitude())
             }
-            Center::Exact(position) => Center::Exact(position.shift(offset, zoom)),
+            Center::Exact(position) => Center::Exact(position.wrap_longitude()),
             Center::Moving {
                 position,
                 direction,
                 from_detached,
             } => Center::Moving {
-                position: position.shift(offset, zoom),
+                position: position.wrap_longitude(),
                 direction,
                 from_detached,
             },
@@ -186,7 +213,7 @@ impl Center {
                 direction,
                 amount,
             } => Center::Inertia {
-                position: position.shift(offset, zoom),
+                position: position.wrap_longitude(),
                 direction,
                 amount,
             },
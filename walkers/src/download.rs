@@ -1,26 +1,41 @@
 use std::{
+    num::NonZeroUsize,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
 };
 
 use bytes::Bytes;
-use egui::Context;
+use egui::{ColorImage, Context, TextureFilter};
 use futures::{
-    SinkExt, StreamExt,
+    SinkExt, Stream, StreamExt,
     future::{Either, select, select_all},
 };
 use reqwest_middleware::ClientWithMiddleware;
 
 use crate::{
     TileId,
-    http_tiles::HttpStats,
-    io::http_client,
-    sources::TileSource,
-    tiles::{Texture, TileError},
+    http_tiles::{HttpStats, Metrics, TileMetrics},
+    io::{http_client, remove_from_http_cache},
+    sources::{TileGrid, TileSource},
+    tiles::{Decoded, Texture, TileError, decode},
+};
+
+#[cfg(feature = "vector_tiles")]
+use crate::{
+    mvt::{LayerStyle, VectorStyle},
+    tiles::decode_with_style,
 };
 
 pub use reqwest::header::HeaderValue;
 
+/// Function receiving the updated metrics of [`crate::HttpTiles`]. See
+/// [`HttpOptions::on_metrics`].
+pub type MetricsCallback = Arc<dyn Fn(&TileMetrics) + Send + Sync>;
+
 /// Controls how [`crate::HttpTiles`] use the HTTP protocol, such as caching.
 pub struct HttpOptions {
     /// Path to the directory to store the HTTP cache.
@@ -44,6 +59,54 @@ pub struct HttpOptions {
     /// Many services have rate limits, and exceeding them may result in throttling, bans, or
     /// degraded service. Use the default value when in doubt.
     pub max_parallel_downloads: MaxParallelDownloads,
+
+    /// Maximum time a single tile request can take, including connecting and receiving the
+    /// body. Requests which take longer are cancelled, freeing the slot for other downloads,
+    /// and the tile will be requested again when it is needed.
+    pub request_timeout: Duration,
+
+    /// How raster tiles are sampled when drawn scaled, e.g. during fractional zoom or when
+    /// overzoomed. [`TextureFilter::Linear`] gives smooth results for raster imagery, while
+    /// [`TextureFilter::Nearest`] keeps the crisp, pixelated look. egui fixes the filter when a
+    /// texture is created, which happens in the background as tiles are downloaded, so it is an
+    /// option of the source rather than of the [`crate::Map`], which only draws the textures.
+    pub texture_filter: TextureFilter,
+
+    /// Maximum number of tiles kept in memory. When exceeded, least recently used tiles are
+    /// evicted, freeing their textures, and will be fetched again when needed.
+    pub cache_capacity: NonZeroUsize,
+
+    /// Pack raster tiles into a few large textures, instead of creating and freeing a texture
+    /// for each tile. It reduces the GPU work while panning, which matters on platforms where
+    /// creating textures is expensive. Disabled by default.
+    pub texture_atlas: bool,
+
+    /// Keep decoded raster tiles in memory, next to their textures, so that
+    /// [`crate::Tiles::reload_textures`] can upload them again without fetching. It roughly
+    /// doubles the memory used by tiles. Disabled by default.
+    pub keep_images: bool,
+
+    /// Query parameters appended to the URL of every tile, e.g. an API key or a style name, so
+    /// they do not need to be a part of the [`crate::sources::TileSource`]'s URL. Tiles are
+    /// cached by their full URLs, so changing these does not bring back tiles cached with the
+    /// old ones. See [`HttpOptions::with_query`].
+    pub query: Vec<(String, String)>,
+
+    /// Style of vector tiles, e.g. fetched from `.pbf` URLs. By default, they are drawn using
+    /// the built-in style, which expects the Protomaps schema. See [`HttpOptions::with_vector_style`].
+    #[cfg(feature = "vector_tiles")]
+    pub vector_style: Option<VectorStyle>,
+
+    /// Called when the tile server responds with an error status, e.g. because of an invalid API
+    /// key or an exceeded quota, so that the application can tell the user what is wrong instead
+    /// of showing a blank map. It is called from the download thread. See
+    /// [`HttpOptions::with_on_source_error`].
+    pub on_source_error: Option<Arc<dyn Fn(TileSourceError) + Send + Sync>>,
+
+    /// Called after each response from the tile server, with the updated
+    /// [`crate::HttpTiles::metrics`], e.g. to feed them to the application's telemetry. It is
+    /// called from the download thread. See [`HttpOptions::with_on_metrics`].
+    pub on_metrics: Option<MetricsCallback>,
 }
 
 impl Default for HttpOptions {
@@ -62,10 +125,101 @@ impl Default for HttpOptions {
             cache: None,
             user_agent,
             max_parallel_downloads: MaxParallelDownloads::default(),
+            request_timeout: Duration::from_secs(30),
+            texture_filter: TextureFilter::Linear,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            texture_atlas: false,
+            keep_images: false,
+            query: Vec::new(),
+            #[cfg(feature = "vector_tiles")]
+            vector_style: None,
+            on_source_error: None,
+            on_metrics: None,
         }
     }
 }
 
+impl HttpOptions {
+    /// Keep at most `capacity` tiles in memory. See [`HttpOptions::cache_capacity`].
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        self
+    }
+
+    /// Append a query parameter to the URL of every tile. See [`HttpOptions::query`].
+    pub fn with_query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.push((key.into(), value.into()));
+        self
+    }
+
+    /// Call the function when the tile server responds with an error status. Tiles which failed
+    /// are not requested again, so a rejected API key does not keep hammering the server.
+    ///
+    /// ```
+    /// # use walkers::{HttpOptions, TileSourceError};
+    /// let options = HttpOptions::default().with_on_source_error(|error| match error {
+    ///     TileSourceError::Unauthorized(_) => log::error!("Check your API key."),
+    ///     TileSourceError::RateLimited => log::error!("Upgrade your plan."),
+    ///     TileSourceError::Status(_) => {}
+    /// });
+    /// ```
+    pub fn with_on_source_error(
+        mut self,
+        callback: impl Fn(TileSourceError) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_source_error = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call the function after each response from the tile server. See
+    /// [`HttpOptions::on_metrics`].
+    ///
+    /// ```
+    /// # use walkers::HttpOptions;
+    /// let options = HttpOptions::default().with_on_metrics(|metrics| {
+    ///     log::debug!("{} bytes of tiles downloaded.", metrics.bytes_downloaded);
+    /// });
+    /// ```
+    pub fn with_on_metrics(
+        mut self,
+        callback: impl Fn(&TileMetrics) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_metrics = Some(Arc::new(callback));
+        self
+    }
+
+    /// Draw vector tiles using the given function, which maps the name of a layer to its style.
+    /// Layers for which it returns `None` are skipped.
+    ///
+    /// ```
+    /// # use walkers::{HttpOptions, LayerStyle};
+    /// # use egui::{Color32, Stroke};
+    /// let options = HttpOptions::default().with_vector_style(|layer| match layer {
+    ///     "water" => Some(LayerStyle {
+    ///         fill: Some(Color32::BLUE),
+    ///         ..Default::default()
+    ///     }),
+    ///     "roads" => Some(LayerStyle {
+    ///         stroke: Some(Stroke::new(1., Color32::GRAY)),
+    ///         ..Default::default()
+    ///     }),
+    ///     _ => None,
+    /// });
+    /// ```
+    #[cfg(feature = "vector_tiles")]
+    pub fn with_vector_style(
+        mut self,
+        style: impl Fn(&str) -> Option<LayerStyle> + Send + Sync + 'static,
+    ) -> Self {
+        self.vector_style = Some(Arc::new(style));
+        self
+    }
+}
+
+/// Just arbitrary value which seemed right.
+#[allow(clippy::unwrap_used)]
+pub(crate) const DEFAULT_CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(256).unwrap();
+
 /// Maximum number of parallel downloads.
 pub struct MaxParallelDownloads(pub usize);
 
@@ -113,6 +267,13 @@ pub enum Error {
 
     #[error("Fetch error: {0}")]
     Fetch(String),
+
+    #[error("Fetch timed out: {0}")]
+    Timeout(String),
+
+    /// Along with how long the source asked to wait before trying again, if it did.
+    #[error("Fetch failed, but might succeed later: {0}")]
+    Unavailable(String, Option<Duration>),
 }
 
 impl From<futures::channel::mpsc::SendError> for Error {
@@ -131,85 +292,261 @@ impl<T> From<std::sync::PoisonError<T>> for Error {
     }
 }
 
-/// Download and decode the tile.
+/// How the download loop turns raster tiles into textures.
+#[derive(Clone, Copy)]
+pub(crate) enum Upload {
+    /// Each tile gets its own texture, created right away.
+    Texture(TextureFilter),
+
+    /// Decoded image is passed to the main thread, which uploads it itself, e.g. into the texture
+    /// atlas.
+    Deferred,
+}
+
+/// What the download loop reports back to the main thread about a requested tile.
+pub(crate) enum Downloaded {
+    /// Tile was downloaded and decoded.
+    Tile(Texture),
+
+    /// Tile was downloaded and decoded, but still needs to be uploaded. See [`Upload::Deferred`].
+    Image(ColorImage),
+
+    /// Download took too long. Tile can be requested again.
+    TimedOut,
+
+    /// Fetch failed for a reason which might go away, e.g. a server error, a rate limit or a lost
+    /// connection. Tile is requested again after a while, or when the source said so.
+    Unavailable(Option<Duration>),
+
+    /// Tile could not be downloaded or decoded. Source has no data for it.
+    Missing,
+}
+
+/// Download and decode the tile. Result is tagged with the generation it was requested in.
 async fn download_and_decode(
     fetch: &impl Fetch,
     tile_id: TileId,
+    generation: u64,
+    upload: Upload,
     egui_ctx: &Context,
-) -> Result<(TileId, Texture), Error> {
-    download_and_decode_impl(fetch, tile_id, egui_ctx)
-        .await
-        .map(|tile| (tile_id, tile))
+) -> (TileId, u64, Result<Downloaded, Error>) {
+    (
+        tile_id,
+        generation,
+        download_and_decode_impl(fetch, tile_id, upload, egui_ctx).await,
+    )
 }
 
 async fn download_and_decode_impl(
     fetch: &impl Fetch,
     tile_id: TileId,
+    upload: Upload,
     egui_ctx: &Context,
-) -> Result<Texture, Error> {
-    let image = fetch
-        .fetch(tile_id)
-        .await
-        .map_err(|e| Error::Fetch(e.to_string()))?;
-    Ok(Texture::new(&image, egui_ctx)?)
+) -> Result<Downloaded, Error> {
+    let image = fetch_bytes(fetch, tile_id).await?;
+
+    let decoded = match fetch.decode(&image) {
+        Ok(decoded) => decoded,
+        Err(TileError::Empty) => return Err(TileError::Empty.into()),
+        Err(err) => {
+            // Response might have been truncated on the way, so give it a second chance
+            // before giving up.
+            log::debug!("Could not decode {tile_id:?}: {err}. Fetching it again.");
+            let image = fetch_bytes(fetch, tile_id).await?;
+            fetch.decode(&image)?
+        }
+    };
+
+    Ok(match (decoded, upload) {
+        (Decoded::Image(image), Upload::Texture(filter)) => Downloaded::Tile(
+            Texture::from_color_image_with_filter(image, egui_ctx, filter),
+        ),
+        (Decoded::Image(image), Upload::Deferred) => Downloaded::Image(image),
+        #[cfg(feature = "vector_tiles")]
+        (Decoded::Texture(texture), _) => Downloaded::Tile(texture),
+    })
+}
+
+async fn fetch_bytes(fetch: &impl Fetch, tile_id: TileId) -> Result<Bytes, Error> {
+    fetch.fetch(tile_id).await.map_err(|e| {
+        if fetch.is_timeout(&e) {
+            Error::Timeout(e.to_string())
+        } else if fetch.is_missing(&e) {
+            Error::Fetch(e.to_string())
+        } else {
+            Error::Unavailable(e.to_string(), fetch.retry_after(&e))
+        }
+    })
+}
+
+/// How often to repaint, at most, while tiles keep arriving.
+const REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Coalesces repaints requested by the download loop. The first tile which arrives is shown
+/// right away, the following ones at most once every [`REPAINT_INTERVAL`], and once all the
+/// downloads settle, there is a final repaint, so that nothing stays stale.
+#[derive(Default)]
+struct Repaint {
+    throttling: bool,
+}
+
+impl Repaint {
+    /// Called after each finished download. `settled` tells whether it was the last one in
+    /// progress.
+    fn download_finished(&mut self, egui_ctx: &Context, arrived: bool, settled: bool) {
+        if let Some(delay) = self.delay(arrived, settled) {
+            // egui keeps the earliest of the scheduled repaints, so these add up to one.
+            egui_ctx.request_repaint_after(delay);
+        }
+    }
+
+    fn delay(&mut self, arrived: bool, settled: bool) -> Option<Duration> {
+        let delay = if settled && (arrived || self.throttling) {
+            Some(Duration::ZERO)
+        } else if !arrived {
+            None
+        } else if self.throttling {
+            Some(REPAINT_INTERVAL)
+        } else {
+            Some(Duration::ZERO)
+        };
+
+        self.throttling = !settled && (arrived || self.throttling);
+        delay
+    }
 }
 
 async fn download_complete(
-    mut tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
-    egui_ctx: Context,
-    result: Result<(TileId, Texture), Error>,
+    mut tile_tx: futures::channel::mpsc::Sender<(TileId, u64, Downloaded)>,
+    egui_ctx: &Context,
+    repaint: &mut Repaint,
+    settled: bool,
+    stats: &Mutex<HttpStats>,
+    (tile_id, generation, result): (TileId, u64, Result<Downloaded, Error>),
 ) -> Result<(), Error> {
     match result {
-        Ok((tile_id, tile)) => {
-            tile_tx.send((tile_id, tile)).await.map_err(Error::from)?;
-            egui_ctx.request_repaint();
+        Ok(downloaded) => {
+            tile_tx
+                .send((tile_id, generation, downloaded))
+                .await
+                .map_err(Error::from)?;
+            repaint.download_finished(egui_ctx, true, settled);
         }
         Err(e) => {
             // It would probably be more consistent to push it to the caller, but it's not that
             // important right now.
             log::warn!("{e}");
+
+            {
+                let mut stats = stats.lock()?;
+                if let Error::Tile(_) = e {
+                    stats.failed_decodes += 1;
+                } else {
+                    stats.failed_downloads += 1;
+                }
+            }
+
+            let downloaded = match e {
+                Error::Timeout(_) => Downloaded::TimedOut,
+                Error::Unavailable(_, retry_after) => Downloaded::Unavailable(retry_after),
+                _ => Downloaded::Missing,
+            };
+
+            tile_tx
+                .send((tile_id, generation, downloaded))
+                .await
+                .map_err(Error::from)?;
+            repaint.download_finished(egui_ctx, false, settled);
         }
     };
 
     Ok(())
 }
 
+/// What the main thread asks the download loop to do.
+pub(crate) enum Request {
+    /// Download the tile.
+    Download(TileId),
+
+    /// Forget the tile, or all the tiles if `None`, wherever they are cached along the way. See
+    /// [`Fetch::invalidate`].
+    Invalidate(Option<TileId>),
+
+    /// Change the `{time}`. See [`Fetch::set_time`].
+    SetTime(Option<String>),
+}
+
 async fn download_continuously_impl(
     fetch: impl Fetch,
     stats: Arc<Mutex<HttpStats>>,
-    mut request_rx: futures::channel::mpsc::Receiver<TileId>,
-    tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
+    mut request_rx: impl Stream<Item = Request> + Unpin,
+    tile_tx: futures::channel::mpsc::Sender<(TileId, u64, Downloaded)>,
+    upload: Upload,
     egui_ctx: Context,
 ) -> Result<(), Error> {
     let mut downloads = Vec::new();
+    let mut repaint = Repaint::default();
+
+    // Bumped each time the `{time}` changes, so that the main thread can tell apart the tiles
+    // which were requested before.
+    let mut generation = 0;
 
     loop {
-        if downloads.is_empty() {
+        let request = if downloads.is_empty() {
             // Only new downloads might be requested.
-            let tile_id = request_rx.next().await.ok_or(Error::RequestChannelBroken)?;
-            let download = download_and_decode(&fetch, tile_id, &egui_ctx);
-            downloads.push(Box::pin(download));
+            Some(request_rx.next().await)
         } else if downloads.len() < fetch.max_concurrency() {
             // New downloads might be requested or ongoing downloads might be completed.
             match select(request_rx.next(), select_all(downloads.drain(..))).await {
                 // New download was requested.
                 Either::Left((request, remaining_downloads)) => {
-                    let tile_id = request.ok_or(Error::RequestChannelBroken)?;
-                    let download = download_and_decode(&fetch, tile_id, &egui_ctx);
                     downloads = remaining_downloads.into_inner();
-                    downloads.push(Box::pin(download));
+                    Some(request)
                 }
                 // Ongoing download was completed.
                 Either::Right(((result, _, remaining_downloads), _)) => {
-                    download_complete(tile_tx.to_owned(), egui_ctx.to_owned(), result).await?;
                     downloads = remaining_downloads;
+                    download_complete(
+                        tile_tx.to_owned(),
+                        &egui_ctx,
+                        &mut repaint,
+                        downloads.is_empty(),
+                        &stats,
+                        result,
+                    )
+                    .await?;
+                    None
                 }
             }
         } else {
             // Only ongoing downloads might be completed.
             let (result, _, remaining_downloads) = select_all(downloads.drain(..)).await;
-            download_complete(tile_tx.to_owned(), egui_ctx.to_owned(), result).await?;
             downloads = remaining_downloads;
+            download_complete(
+                tile_tx.to_owned(),
+                &egui_ctx,
+                &mut repaint,
+                downloads.is_empty(),
+                &stats,
+                result,
+            )
+            .await?;
+            None
+        };
+
+        if let Some(request) = request {
+            match request.ok_or(Error::RequestChannelBroken)? {
+                Request::Download(tile_id) => {
+                    let download =
+                        download_and_decode(&fetch, tile_id, generation, upload, &egui_ctx);
+                    downloads.push(Box::pin(download));
+                }
+                Request::Invalidate(tile_id) => fetch.invalidate(tile_id).await,
+                Request::SetTime(time) => {
+                    fetch.set_time(time);
+                    generation += 1;
+                }
+            }
         }
 
         // Update stats.
@@ -222,11 +559,12 @@ async fn download_continuously_impl(
 pub(crate) async fn download_continuously(
     fetch: impl Fetch,
     stats: Arc<Mutex<HttpStats>>,
-    request_rx: futures::channel::mpsc::Receiver<TileId>,
-    tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
+    request_rx: impl Stream<Item = Request> + Unpin,
+    tile_tx: futures::channel::mpsc::Sender<(TileId, u64, Downloaded)>,
+    upload: Upload,
     egui_ctx: Context,
 ) {
-    match download_continuously_impl(fetch, stats, request_rx, tile_tx, egui_ctx).await {
+    match download_continuously_impl(fetch, stats, request_rx, tile_tx, upload, egui_ctx).await {
         Ok(()) | Err(Error::TileChannelClosed) | Err(Error::RequestChannelBroken) => {
             log::debug!("Tile download loop finished.");
         }
@@ -236,12 +574,49 @@ pub(crate) async fn download_continuously(
     }
 }
 
+/// Error status of a tile server, classified into what the application can act upon. See
+/// [`HttpOptions::on_source_error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TileSourceError {
+    /// `401 Unauthorized` or `403 Forbidden`, usually because the API key is missing or invalid.
+    #[error("Tile server refused access (HTTP {0}). API key might be missing or invalid.")]
+    Unauthorized(u16),
+
+    /// `429 Too Many Requests`, when the quota or rate limit of the API key is exceeded.
+    #[error("Tile server rate limit exceeded.")]
+    RateLimited,
+
+    /// Any other error status, e.g. `404 Not Found` for tiles the source has no data for.
+    #[error("Tile server responded with HTTP {0}.")]
+    Status(u16),
+}
+
+impl TileSourceError {
+    /// Classify the HTTP status. `None` if it is not an error.
+    pub fn from_status(status: u16) -> Option<Self> {
+        match status {
+            401 | 403 => Some(Self::Unauthorized(status)),
+            429 => Some(Self::RateLimited),
+            400.. => Some(Self::Status(status)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum HttpFetchError {
     #[error(transparent)]
     HttpMiddleware(#[from] reqwest_middleware::Error),
     #[error(transparent)]
     Http(#[from] reqwest::Error),
+    /// Server responded with an error status, possibly saying when to try again.
+    #[error("{source}")]
+    Status {
+        source: reqwest::Error,
+        retry_after: Option<Duration>,
+    },
+    #[error("{0:?} is outside of the source's tile grid.")]
+    OutsideOfGrid(TileId),
 }
 
 pub trait Fetch {
@@ -255,6 +630,44 @@ pub trait Fetch {
     fn fetch(&self, tile_id: TileId) -> impl Future<Output = Result<Bytes, Self::Error>> + Send;
 
     fn max_concurrency(&self) -> usize;
+
+    /// Remove the tile, or all the tiles if `None`, from wherever they are cached on the way,
+    /// so that they are fetched anew.
+    #[cfg(target_arch = "wasm32")]
+    fn invalidate(&self, _tile_id: Option<TileId>) -> impl Future<Output = ()> {
+        async {}
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn invalidate(&self, _tile_id: Option<TileId>) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// Whether the error means that fetch took too long, and it can be tried again later.
+    fn is_timeout(&self, _error: &Self::Error) -> bool {
+        false
+    }
+
+    /// Whether the error means that the source has no data for the tile, so it is not worth
+    /// asking for it again. Otherwise, e.g. for a server error, it is tried again later. `true` by
+    /// default.
+    fn is_missing(&self, _error: &Self::Error) -> bool {
+        true
+    }
+
+    /// How long the source asked to wait before the tile is tried again, e.g. with the
+    /// `Retry-After` header. If `None`, the wait grows with each failed attempt.
+    fn retry_after(&self, _error: &Self::Error) -> Option<Duration> {
+        None
+    }
+
+    /// Value of the `{time}` token in the tile URLs. Fetches which do not support it ignore it.
+    fn set_time(&self, _time: Option<String>) {}
+
+    /// Decode the fetched data.
+    fn decode(&self, data: &[u8]) -> Result<Decoded, TileError> {
+        decode(data)
+    }
 }
 
 pub struct HttpFetch<S>
@@ -262,8 +675,20 @@ where
     S: TileSource + Send + 'static,
 {
     source: S,
+    grid: Option<TileGrid>,
     max_concurrency: usize,
+    request_timeout: Duration,
     client: ClientWithMiddleware,
+    cache: Option<PathBuf>,
+    time: Mutex<Option<String>>,
+    query: Vec<(String, String)>,
+    #[cfg(feature = "vector_tiles")]
+    vector_style: Option<VectorStyle>,
+    on_source_error: Option<Arc<dyn Fn(TileSourceError) + Send + Sync>>,
+    /// [`TileSourceError::Unauthorized`] is reported only once, as every tile fails the same way.
+    unauthorized_reported: AtomicBool,
+    on_metrics: Option<MetricsCallback>,
+    pub metrics: Arc<Metrics>,
 }
 
 impl<S> HttpFetch<S>
@@ -272,10 +697,50 @@ where
 {
     pub fn new(source: S, http_options: HttpOptions) -> Self {
         Self {
+            grid: source.tile_grid(),
             source,
             max_concurrency: http_options.max_parallel_downloads.0,
+            request_timeout: http_options.request_timeout,
             client: http_client(&http_options),
+            cache: http_options.cache,
+            time: Mutex::new(None),
+            query: http_options.query,
+            #[cfg(feature = "vector_tiles")]
+            vector_style: http_options.vector_style,
+            on_source_error: http_options.on_source_error,
+            unauthorized_reported: AtomicBool::new(false),
+            on_metrics: http_options.on_metrics,
+            metrics: Arc::default(),
+        }
+    }
+
+    /// URL of the tile, with the `{time}` filled in, if set, and the query parameters appended.
+    /// `None` if the tile is not in the source's grid.
+    fn tile_url(&self, tile_id: TileId) -> Option<String> {
+        let tile_id = match &self.grid {
+            Some(grid) => grid.tile(tile_id)?,
+            None => tile_id,
+        };
+        let url = self.source.tile_url(tile_id);
+        let url = match self.time.lock().as_deref() {
+            Ok(Some(time)) => url.replace("{time}", time),
+            _ => url,
+        };
+
+        if self.query.is_empty() {
+            return Some(url);
         }
+
+        Some(match reqwest::Url::parse(&url) {
+            Ok(mut parsed) => {
+                parsed.query_pairs_mut().extend_pairs(&self.query);
+                parsed.into()
+            }
+            Err(err) => {
+                log::warn!("Could not append query parameters to '{url}': {err}");
+                url
+            }
+        })
     }
 }
 
@@ -286,14 +751,232 @@ where
     type Error = HttpFetchError;
 
     async fn fetch(&self, tile_id: TileId) -> Result<Bytes, Self::Error> {
-        let url = self.source.tile_url(tile_id);
+        let url = self
+            .tile_url(tile_id)
+            .ok_or(HttpFetchError::OutsideOfGrid(tile_id))?;
         log::trace!("Downloading '{url}'.");
-        let image = self.client.get(&url).send().await?;
-        log::trace!("Downloaded '{}': {:?}.", url, image.status());
-        Ok(image.error_for_status()?.bytes().await?)
+        let mut request = self.client.get(&url).build()?;
+        *request.timeout_mut() = Some(self.request_timeout);
+        let started = web_time::Instant::now();
+        let image = self.client.execute(request).await?;
+        let status = image.status();
+        log::trace!("Downloaded '{url}': {status:?}.");
+        if let (Some(on_source_error), Some(error)) = (
+            &self.on_source_error,
+            TileSourceError::from_status(status.as_u16()),
+        ) {
+            let repeated = matches!(error, TileSourceError::Unauthorized(_))
+                && self.unauthorized_reported.swap(true, Ordering::Relaxed);
+            if !repeated {
+                on_source_error(error);
+            }
+        }
+
+        let retry_after = image
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+            .map(Duration::from_secs);
+        let bytes = match image.error_for_status() {
+            Ok(image) => image.bytes().await.map_err(HttpFetchError::from),
+            Err(source) => Err(HttpFetchError::Status {
+                source,
+                retry_after,
+            }),
+        };
+        let size = bytes.as_ref().map_or(0, Bytes::len);
+        self.metrics
+            .response_received(status.as_u16(), started.elapsed(), size);
+        if let Some(on_metrics) = &self.on_metrics {
+            on_metrics(&self.metrics.snapshot());
+        }
+        bytes
     }
 
     fn max_concurrency(&self) -> usize {
         self.max_concurrency
     }
+
+    async fn invalidate(&self, tile_id: Option<TileId>) {
+        if let Some(cache) = &self.cache {
+            let url = match tile_id {
+                Some(tile_id) => match self.tile_url(tile_id) {
+                    Some(url) => Some(url),
+                    None => return,
+                },
+                None => None,
+            };
+            remove_from_http_cache(cache, url.as_deref()).await;
+        }
+    }
+
+    fn is_timeout(&self, error: &Self::Error) -> bool {
+        match error {
+            HttpFetchError::Http(error)
+            | HttpFetchError::HttpMiddleware(reqwest_middleware::Error::Reqwest(error)) => {
+                error.is_timeout()
+            }
+            HttpFetchError::HttpMiddleware(_)
+            | HttpFetchError::Status { .. }
+            | HttpFetchError::OutsideOfGrid(_) => false,
+        }
+    }
+
+    fn is_missing(&self, error: &Self::Error) -> bool {
+        match error {
+            // "No tile here", or no access to it, which asking again is not going to change.
+            // `204 No Content` is not an error, its empty body is reported as missing when
+            // decoding. Rate limits and server errors go away, eventually.
+            HttpFetchError::Status { source, .. } => source
+                .status()
+                .is_some_and(|status| matches!(status.as_u16(), 401 | 403 | 404 | 410)),
+            // Neither is an invalid URL going to become valid.
+            HttpFetchError::Http(error)
+            | HttpFetchError::HttpMiddleware(reqwest_middleware::Error::Reqwest(error)) => {
+                error.is_builder()
+            }
+            HttpFetchError::OutsideOfGrid(_) => true,
+            HttpFetchError::HttpMiddleware(_) => false,
+        }
+    }
+
+    fn retry_after(&self, error: &Self::Error) -> Option<Duration> {
+        match error {
+            HttpFetchError::Status { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    fn set_time(&self, time: Option<String>) {
+        if let Ok(mut current) = self.time.lock() {
+            *current = time;
+        }
+    }
+
+    #[cfg(feature = "vector_tiles")]
+    fn decode(&self, data: &[u8]) -> Result<Decoded, TileError> {
+        match &self.vector_style {
+            Some(style) => decode_with_style(data, style),
+            None => decode(data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{Attribution, TileSource};
+    use crate::testing::attribution;
+
+    struct TestSource(&'static str);
+
+    impl TileSource for TestSource {
+        fn tile_url(&self, tile_id: TileId) -> String {
+            format!(
+                "{}/{}/{}/{}.png",
+                self.0, tile_id.zoom, tile_id.x, tile_id.y
+            )
+        }
+
+        fn attribution(&self) -> Attribution {
+            attribution("")
+        }
+    }
+
+    const TILE_ID: TileId = TileId {
+        x: 1,
+        y: 2,
+        zoom: 3,
+    };
+
+    #[test]
+    fn query_parameters_are_appended_to_tile_urls() {
+        let options = HttpOptions::default()
+            .with_query("apikey", "s3cr3t")
+            .with_query("style", "dark & moody");
+        let fetch = HttpFetch::new(TestSource("https://example.com"), options);
+        assert_eq!(
+            fetch.tile_url(TILE_ID).as_deref(),
+            Some("https://example.com/3/1/2.png?apikey=s3cr3t&style=dark+%26+moody")
+        );
+
+        // Query which is already a part of the URL is kept.
+        let source = crate::sources::UrlTemplate::new(
+            "https://example.com/{z}/{x}/{y}.png?v=2",
+            TestSource("").attribution(),
+        );
+        let fetch = HttpFetch::new(source, HttpOptions::default().with_query("style", "dark"));
+        assert_eq!(
+            fetch.tile_url(TILE_ID).as_deref(),
+            Some("https://example.com/3/1/2.png?v=2&style=dark")
+        );
+    }
+
+    #[test]
+    fn tile_urls_are_in_the_source_grid() {
+        let grid = TileGrid::with_top_level_tiles(2, 256).expect("power of two");
+        let source = crate::sources::UrlTemplate::new(
+            "https://example.com/{z}/{x}/{y}.png",
+            TestSource("").attribution(),
+        )
+        .with_grid(grid);
+        let fetch = HttpFetch::new(source, HttpOptions::default());
+
+        assert_eq!(
+            fetch.tile_url(TILE_ID).as_deref(),
+            Some("https://example.com/2/1/2.png")
+        );
+        assert_eq!(
+            fetch.tile_url(TileId {
+                x: 0,
+                y: 0,
+                zoom: 0
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn error_statuses_are_classified() {
+        assert_eq!(TileSourceError::from_status(200), None);
+        assert_eq!(TileSourceError::from_status(304), None);
+        assert_eq!(
+            TileSourceError::from_status(401),
+            Some(TileSourceError::Unauthorized(401))
+        );
+        assert_eq!(
+            TileSourceError::from_status(403),
+            Some(TileSourceError::Unauthorized(403))
+        );
+        assert_eq!(
+            TileSourceError::from_status(429),
+            Some(TileSourceError::RateLimited)
+        );
+        assert_eq!(
+            TileSourceError::from_status(503),
+            Some(TileSourceError::Status(503))
+        );
+    }
+
+    #[test]
+    fn repaints_are_coalesced_while_tiles_keep_arriving() {
+        let mut repaint = Repaint::default();
+
+        // First tile is shown right away.
+        assert_eq!(repaint.delay(true, false), Some(Duration::ZERO));
+
+        // Following ones wait for the next throttled repaint.
+        assert_eq!(repaint.delay(true, false), Some(REPAINT_INTERVAL));
+        assert_eq!(repaint.delay(false, false), None);
+        assert_eq!(repaint.delay(true, false), Some(REPAINT_INTERVAL));
+
+        // Everything settled, even if with a failure.
+        assert_eq!(repaint.delay(false, true), Some(Duration::ZERO));
+
+        // Nothing to catch up on.
+        assert_eq!(repaint.delay(false, true), None);
+
+        // Next batch starts right away again.
+        assert_eq!(repaint.delay(true, false), Some(Duration::ZERO));
+    }
 }
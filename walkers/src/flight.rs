@@ -0,0 +1,249 @@
+use crate::{
+    Position,
+    mercator::{project, unproject},
+    position::Pixels,
+};
+
+/// How long a flight takes, in seconds, unless it zooms out on the way.
+const FLIGHT_DURATION: f32 = 1.;
+
+/// Upper limit of how long a flight takes, in seconds, no matter how far it goes.
+const MAX_FLIGHT_DURATION: f32 = 4.;
+
+/// At the highest point of [`Easing::Arc`], both ends of the flight fit within this many pixels.
+const ARC_VIEW_SIZE: f64 = 512.;
+
+/// Width of the world at zoom 0, in which the flight's ends are projected.
+const WORLD_WIDTH: f64 = 256.;
+
+/// Trajectory of [`crate::MapMemory::fly_to`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    /// Constant speed all the way.
+    Linear,
+
+    /// Speeds up at the start and slows down before arriving.
+    EaseInOut,
+
+    /// Like [`Easing::EaseInOut`], but the map zooms out mid-flight, so that both ends are visible
+    /// at its highest point. `zoom_out_factor` scales how far it zooms out, `1.0` being just
+    /// enough to see both ends and `0.0` not zooming out at all. The longer the distance, the
+    /// longer the flight takes.
+    Arc { zoom_out_factor: f64 },
+}
+
+impl Easing {
+    /// Progress of the flight, given the fraction of its duration which has passed.
+    fn progress(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut | Easing::Arc { .. } => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+/// Ongoing [`crate::MapMemory::fly_to`].
+#[derive(Debug, Clone)]
+pub(crate) struct Flight {
+    /// Projected position and zoom where the flight started. Not known until the map is shown,
+    /// as the map might be following `my_position`.
+    from: Option<(Pixels, f64)>,
+    to: Position,
+    to_zoom: f64,
+    easing: Easing,
+    elapsed: f32,
+    /// Whether the destination is `my_position`, to be followed after arriving.
+    follow_my_position: bool,
+}
+
+impl Flight {
+    pub fn new(to: Position, to_zoom: f64, easing: Easing) -> Self {
+        Self {
+            from: None,
+            to,
+            to_zoom,
+            easing,
+            elapsed: 0.,
+            follow_my_position: false,
+        }
+    }
+
+    /// Flight to `my_position`, which is followed once the flight arrives.
+    pub fn to_my_position(my_position: Position, to_zoom: f64, easing: Easing) -> Self {
+        Self {
+            follow_my_position: true,
+            ..Self::new(my_position, to_zoom, easing)
+        }
+    }
+
+    /// Whether the map should follow `my_position` after arriving.
+    pub fn follows_my_position(&self) -> bool {
+        self.follow_my_position
+    }
+
+    /// Change the destination mid-flight, e.g. because `my_position` was updated. The flight
+    /// keeps its progress, so it smoothly bends towards the new destination.
+    pub fn retarget(&mut self, to: Position) {
+        self.to = to;
+    }
+
+    /// Move the flight forward in time. `from` and `from_zoom` are where the map is, and are only
+    /// used when the flight starts. Returns the new position and zoom, and whether the flight has
+    /// arrived.
+    pub fn advance(
+        &mut self,
+        delta_time: f32,
+        from: Position,
+        from_zoom: f64,
+    ) -> (Position, f64, bool) {
+        let (from, from_zoom) = *self.from.get_or_insert((project(from, 0.), from_zoom));
+        let to = self.projected_target(from);
+        let dip = self.dip(from, from_zoom, to);
+        let duration = (FLIGHT_DURATION * (1. + dip as f32 / 2.)).min(MAX_FLIGHT_DURATION);
+
+        self.elapsed += delta_time;
+        if self.elapsed >= duration {
+            return (self.to, self.to_zoom, true);
+        }
+
+        let t = (self.elapsed / duration) as f64;
+        let progress = self.easing.progress(t);
+        let position = Pixels::new(
+            from.x() + (to.x() - from.x()) * progress,
+            from.y() + (to.y() - from.y()) * progress,
+        );
+        let zoom = from_zoom + (self.to_zoom - from_zoom) * progress - dip * 4. * t * (1. - t);
+
+        (unproject(position, 0.), zoom.max(0.), false)
+    }
+
+    /// Target projected at zoom 0, shifted by the world's width if going the other way around it
+    /// is shorter.
+    fn projected_target(&self, from: Pixels) -> Pixels {
+        let to = project(self.to, 0.);
+        let dx = to.x() - from.x();
+        let x = if dx > WORLD_WIDTH / 2. {
+            to.x() - WORLD_WIDTH
+        } else if dx < -WORLD_WIDTH / 2. {
+            to.x() + WORLD_WIDTH
+        } else {
+            to.x()
+        };
+        Pixels::new(x, to.y())
+    }
+
+    /// How many zoom levels to zoom out at the highest point of the flight.
+    fn dip(&self, from: Pixels, from_zoom: f64, to: Pixels) -> f64 {
+        let Easing::Arc { zoom_out_factor } = self.easing else {
+            return 0.;
+        };
+
+        let min_zoom = from_zoom.min(self.to_zoom);
+        let distance = (to.x() - from.x()).hypot(to.y() - from.y()) * 2f64.powf(min_zoom);
+        let needed = (distance / ARC_VIEW_SIZE).log2().max(0.);
+        (needed * zoom_out_factor).clamp(0., min_zoom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lon_lat;
+
+    /// Exact in binary, so that the frames add up to the whole duration.
+    const FRAME: f32 = 1. / 64.;
+
+    /// Zoom levels seen throughout the flight, until it arrives.
+    fn fly(mut flight: Flight, from: Position, from_zoom: f64) -> Vec<f64> {
+        let mut zooms = Vec::new();
+        for _ in 0..1000 {
+            let (position, zoom, arrived) = flight.advance(FRAME, from, from_zoom);
+            zooms.push(zoom);
+            if arrived {
+                assert_eq!(position, flight.to);
+                return zooms;
+            }
+        }
+        panic!("flight did not arrive");
+    }
+
+    #[test]
+    fn flight_arrives_after_its_duration() {
+        let flight = Flight::new(lon_lat(2.35, 48.86), 12., Easing::Linear);
+        let zooms = fly(flight, lon_lat(17.04, 51.1), 10.);
+        assert_eq!(zooms.len(), 64);
+        assert_eq!(zooms.last(), Some(&12.));
+
+        // Zoom changes at a constant pace.
+        assert!((zooms[31] - 11.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ease_in_out_starts_and_ends_slowly() {
+        let flight = Flight::new(lon_lat(2.35, 48.86), 12., Easing::EaseInOut);
+        let zooms = fly(flight, lon_lat(17.04, 51.1), 10.);
+        assert_eq!(zooms.len(), 64);
+        assert!(zooms[1] - zooms[0] < zooms[32] - zooms[31]);
+        assert!(zooms[62] - zooms[61] < zooms[32] - zooms[31]);
+    }
+
+    #[test]
+    fn arc_zooms_out_on_long_flights_and_takes_longer() {
+        let arc = Easing::Arc {
+            zoom_out_factor: 1.,
+        };
+
+        // Between Wrocław and Paris, at zoom 10, it is about 30 tiles.
+        let zooms = fly(
+            Flight::new(lon_lat(2.35, 48.86), 10., arc),
+            lon_lat(17.04, 51.1),
+            10.,
+        );
+        assert!(zooms.len() > 64);
+        let highest = zooms.iter().copied().fold(f64::INFINITY, f64::min);
+        assert!(highest < 6., "zoomed out only to {highest}");
+
+        // Nothing to zoom out for when both ends are visible anyway.
+        let zooms = fly(
+            Flight::new(lon_lat(17.05, 51.1), 10., arc),
+            lon_lat(17.04, 51.1),
+            10.,
+        );
+        assert_eq!(zooms.len(), 64);
+        assert!(zooms.iter().all(|zoom| *zoom == 10.));
+    }
+
+    #[test]
+    fn flight_takes_the_shorter_way_around_the_world() {
+        let mut flight = Flight::new(lon_lat(-170., 0.), 5., Easing::Linear);
+        for _ in 0..32 {
+            flight.advance(FRAME, lon_lat(170., 0.), 5.);
+        }
+        let (position, _, _) = flight.advance(0., lon_lat(170., 0.), 5.);
+        assert!(
+            (position.x() - 180.).abs() < 1.,
+            "went through {}",
+            position.x()
+        );
+    }
+
+    #[test]
+    fn retargeted_flight_arrives_at_new_destination() {
+        let mut flight = Flight::to_my_position(lon_lat(2.35, 48.86), 10., Easing::EaseInOut);
+        let from = lon_lat(17.04, 51.1);
+        let (halfway, _, _) = (0..32)
+            .map(|_| flight.advance(FRAME, from, 10.))
+            .last()
+            .expect("some frames");
+
+        flight.retarget(lon_lat(2.4, 48.9));
+        let (position, _, _) = flight.advance(FRAME, from, 10.);
+        assert!(
+            (position.x() - halfway.x()).abs() < 0.5,
+            "jumped to {position:?}"
+        );
+
+        let zooms = fly(flight, from, 10.);
+        assert_eq!(zooms.len(), 31);
+    }
+}
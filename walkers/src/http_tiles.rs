@@ -1,10 +1,17 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use egui::Context;
 
-use crate::TileId;
 use crate::download::{HttpFetch, HttpOptions};
 use crate::loader::Loader;
-use crate::sources::{Attribution, TileSource};
+use crate::mercator::{tile_id, total_tiles};
+use crate::sources::{Attribution, TileGrid, TileSource};
 use crate::tiles::interpolate_from_lower_zoom;
+use crate::{BoundingBox, TileId};
 use crate::{TextureWithUv, Tiles};
 
 /// Downloads the tiles via HTTP. It must persist between frames.
@@ -12,7 +19,16 @@ pub struct HttpTiles {
     attribution: Attribution,
     loader: Loader,
     tile_size: u32,
+    min_zoom: u8,
     max_zoom: u8,
+    bounds: Option<BoundingBox>,
+    grid: Option<TileGrid>,
+    time: Option<String>,
+    /// Whether tile URLs have the `{time}` token, so that tiles can not be downloaded until it is
+    /// set.
+    needs_time: bool,
+    paused: bool,
+    metrics: Arc<Metrics>,
 }
 
 impl HttpTiles {
@@ -30,15 +46,49 @@ impl HttpTiles {
         S: TileSource + Sync + Send + 'static,
     {
         let attribution = source.attribution();
-        let tile_size = source.tile_size();
-        let max_zoom = source.max_zoom();
+        let grid = source.tile_grid();
+        let (tile_size, min_zoom, max_zoom) = match &grid {
+            Some(grid) => (
+                grid.tile_size(),
+                source.min_zoom().max(*grid.zoom_range().start()),
+                source.max_zoom().min(*grid.zoom_range().end()),
+            ),
+            None => (source.tile_size(), source.min_zoom(), source.max_zoom()),
+        };
+        let bounds = source.bounds();
+        let needs_time = source
+            .tile_url(TileId {
+                x: 0,
+                y: 0,
+                zoom: 0,
+            })
+            .contains("{time}");
+        let texture_filter = http_options.texture_filter;
+        let texture_atlas = http_options.texture_atlas;
+        let keep_images = http_options.keep_images;
+        let cache_capacity = http_options.cache_capacity;
         let fetch = HttpFetch::new(source, http_options);
+        let metrics = fetch.metrics.clone();
 
         Self {
             attribution,
-            loader: Loader::new(fetch, egui_ctx),
+            loader: Loader::new(
+                fetch,
+                texture_filter,
+                texture_atlas,
+                keep_images,
+                cache_capacity,
+                egui_ctx,
+            ),
             tile_size,
+            min_zoom,
             max_zoom,
+            bounds,
+            grid,
+            time: None,
+            needs_time,
+            paused: false,
+            metrics,
         }
     }
 
@@ -51,19 +101,148 @@ impl HttpTiles {
         }
     }
 
+    /// Drop all the downloaded tiles, both from memory and from the [`HttpOptions::cache`], so
+    /// that the visible ones are downloaded again. Useful when the source data has changed.
+    pub fn clear_cache(&mut self) {
+        self.loader.invalidate(None);
+    }
+
+    /// Like [`HttpTiles::clear_cache`], but for a single tile.
+    pub fn invalidate_tile(&mut self, tile_id: TileId) {
+        let tile_id = self.tile_id_to_download(tile_id);
+        self.loader.invalidate(Some(tile_id));
+    }
+
+    /// Set the value of the `{time}` token in tile URLs, e.g. of a [`crate::sources::UrlTemplate`],
+    /// for layers which change over time, such as weather radar. When it changes, visible tiles
+    /// are downloaded again, while the ones for the previous time stay on the map until replaced,
+    /// so moving a time slider does not blank the map. Tiles are cached by their URLs in the
+    /// [`HttpOptions::cache`], so different times do not collide. Until the time is set, tiles of
+    /// URLs with the `{time}` token are not downloaded at all.
+    pub fn set_time(&mut self, time: impl Into<String>) {
+        let time = Some(time.into());
+        if time != self.time {
+            self.time = time.clone();
+            self.loader.set_time(time);
+        }
+    }
+
+    /// Current value of the `{time}` token. See [`HttpTiles::set_time`].
+    pub fn time(&self) -> Option<&str> {
+        self.time.as_deref()
+    }
+
+    /// Stop downloading new tiles, e.g. on a metered connection, or resume it. Tiles which are
+    /// already loaded are still shown, and ones being downloaded right now still arrive, but
+    /// the rest stay as placeholders until resumed. Cache is kept.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Snapshot of the counters of the tile activity, e.g. to be fed to the application's
+    /// telemetry. See also [`HttpOptions::on_metrics`].
+    pub fn metrics(&self) -> TileMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// Whether downloading is paused. See [`HttpTiles::set_paused`].
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether all the tiles needed to show the area at given zoom level of the map are loaded, or
+    /// known to be missing. It schedules downloads of the ones which are not, so it is meant to be
+    /// polled until it returns `true`, e.g. before rendering a snapshot of the map, or in tests.
+    pub fn all_loaded(&mut self, bounds: impl Into<BoundingBox>, zoom: u8) -> bool {
+        self.loader.put_single_downloaded_tile_in_cache();
+
+        let bounds = bounds.into();
+        let zoom = tile_id(bounds.south_west(), zoom, self.tile_size).zoom;
+        if zoom < self.min_zoom {
+            // Nothing is shown anyway.
+            return true;
+        }
+
+        // Above the source's max zoom, the same few tiles are stretched over the whole area, so
+        // there is no point in going through the countless ones which would be interpolated.
+        let zoom = self.zoom_to_download(zoom);
+        let south_west = TileId::containing(bounds.south_west(), zoom);
+        let north_east = TileId::containing(bounds.north_east(), zoom);
+
+        // Corners at the very south edge of the world end up just outside of it.
+        let tiles = total_tiles(zoom);
+        let (north, south) = (north_east.y.min(tiles - 1), south_west.y.min(tiles - 1));
+
+        // Longitudes are not wrapped if the area crosses the antimeridian, so go east from its
+        // west edge, wrapping the columns. East edge itself belongs to the next column.
+        let column = |longitude: f64| (longitude + 180.) / 360. * tiles as f64;
+        let west = column(bounds.west()).floor() as i64;
+        let east = column(bounds.west() + bounds.longitude_span()).ceil() as i64 - 1;
+        let columns = (east - west + 1).clamp(1, tiles as i64);
+
+        let mut loaded = true;
+        for column in 0..columns {
+            for y in north..=south {
+                let tile_id = TileId {
+                    x: (west + column).rem_euclid(tiles as i64) as u32,
+                    y,
+                    zoom,
+                };
+                let tile_id = self.tile_id_to_download(tile_id);
+                if !self.loader.loaded(tile_id) {
+                    if self.downloading() {
+                        self.loader.make_sure_is_downloaded(tile_id);
+                    }
+                    loaded = false;
+                }
+            }
+        }
+        loaded
+    }
+
+    /// Whether new downloads can be started, i.e. they are not paused, and the `{time}` is set if
+    /// the URLs need it.
+    fn downloading(&self) -> bool {
+        !self.paused && (self.time.is_some() || !self.needs_time)
+    }
+
+    /// Tiles above source's max zoom, or at zoom levels its grid skips, are not downloaded, but
+    /// interpolated from the lower ones.
+    fn tile_id_to_download(&self, tile_id: TileId) -> TileId {
+        let zoom = self.zoom_to_download(tile_id.zoom);
+        if zoom < tile_id.zoom {
+            interpolate_from_lower_zoom(tile_id, zoom).0
+        } else {
+            tile_id
+        }
+    }
+
+    /// Zoom level which tiles of the given one are downloaded at. The map's zoom can be
+    /// fractional and go far beyond the source's max zoom, but only the levels the source has
+    /// are ever requested.
+    fn zoom_to_download(&self, zoom: u8) -> u8 {
+        let zoom = zoom.min(self.max_zoom);
+        self.grid
+            .as_ref()
+            .and_then(|grid| grid.zoom_at_or_below(zoom))
+            .unwrap_or(zoom)
+    }
+
     /// Get at tile, or interpolate it from lower zoom levels. This function does not start any
-    /// downloads.
-    fn get_from_cache_or_interpolate(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+    /// downloads. Returns the zoom level the texture came from.
+    fn get_from_cache_or_interpolate(&mut self, tile_id: TileId) -> Option<(TextureWithUv, u8)> {
         let mut zoom_candidate = tile_id.zoom;
 
         loop {
             let (zoomed_tile_id, uv) = interpolate_from_lower_zoom(tile_id, zoom_candidate);
 
-            if let Some(Some(texture)) = self.loader.cache.get(&zoomed_tile_id) {
-                break Some(TextureWithUv {
-                    texture: texture.clone(),
-                    uv,
-                });
+            if let Some(texture) = self
+                .loader
+                .cache
+                .get(&zoomed_tile_id)
+                .and_then(|cached| cached.texture(uv))
+            {
+                break Some((texture, zoom_candidate));
             }
 
             // Keep zooming out until we find a donor or there is no more zoom levels.
@@ -76,6 +255,95 @@ impl HttpTiles {
 pub struct HttpStats {
     /// Number of tiles that are currently being downloaded.
     pub in_progress: usize,
+
+    /// Number of tiles which could not be downloaded, e.g. because of a connection problem,
+    /// timeout or an HTTP error status.
+    pub failed_downloads: usize,
+
+    /// Number of tiles which were downloaded, but could not be decoded, e.g. because the server
+    /// responded with an HTML error page instead of an image. Each of these tiles was fetched
+    /// twice before giving up.
+    pub failed_decodes: usize,
+}
+
+/// Counters of the tile activity of [`HttpTiles`], collected all the time. See
+/// [`HttpTiles::metrics`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileMetrics {
+    /// Number of times a tile was asked for, e.g. by the map in each frame it is visible.
+    pub tiles_requested: u64,
+
+    /// Number of the requested tiles which were in memory, as opposed to being downloaded or
+    /// drawn from a lower zoom level's tile in the meantime.
+    pub cache_hits: u64,
+
+    /// Number of responses from the tile server, including the ones with an error status and
+    /// the ones served from [`HttpOptions::cache`].
+    pub responses: u64,
+
+    /// Sum of the sizes of the tiles received, in bytes.
+    pub bytes_downloaded: u64,
+
+    /// Average time from sending a request to receiving the whole response. `None` until the
+    /// first response.
+    pub average_latency: Option<Duration>,
+
+    /// Number of responses with each of the error statuses, e.g. `404`. Connection problems and
+    /// timeouts have no status, they are counted in [`HttpStats::failed_downloads`].
+    pub errors_by_status: BTreeMap<u16, u64>,
+}
+
+/// Live counters behind [`TileMetrics`], shared with the download loop. All but the error
+/// statuses, which are rare anyway, are atomics, so collecting them costs next to nothing.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    tiles_requested: AtomicU64,
+    cache_hits: AtomicU64,
+    responses: AtomicU64,
+    bytes_downloaded: AtomicU64,
+    latency_micros: AtomicU64,
+    errors_by_status: Mutex<BTreeMap<u16, u64>>,
+}
+
+impl Metrics {
+    pub fn tile_requested(&self, cache_hit: bool) {
+        self.tiles_requested.fetch_add(1, Ordering::Relaxed);
+        if cache_hit {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn response_received(&self, status: u16, latency: Duration, bytes: usize) {
+        self.responses.fetch_add(1, Ordering::Relaxed);
+        self.bytes_downloaded
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        self.latency_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+
+        if status >= 400 {
+            if let Ok(mut errors) = self.errors_by_status.lock() {
+                *errors.entry(status).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> TileMetrics {
+        let responses = self.responses.load(Ordering::Relaxed);
+        let latency_micros = self.latency_micros.load(Ordering::Relaxed);
+        TileMetrics {
+            tiles_requested: self.tiles_requested.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            responses,
+            bytes_downloaded: self.bytes_downloaded.load(Ordering::Relaxed),
+            average_latency: (responses > 0)
+                .then(|| Duration::from_micros(latency_micros / responses)),
+            errors_by_status: self
+                .errors_by_status
+                .lock()
+                .map(|errors| errors.clone())
+                .unwrap_or_default(),
+        }
+    }
 }
 
 impl Tiles for HttpTiles {
@@ -89,28 +357,49 @@ impl Tiles for HttpTiles {
     fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
         self.loader.put_single_downloaded_tile_in_cache();
 
-        if !tile_id.valid() {
+        if !tile_id.valid() || tile_id.zoom < self.min_zoom {
             return None;
         }
 
-        let tile_id_to_download = if tile_id.zoom > self.max_zoom {
-            interpolate_from_lower_zoom(tile_id, self.max_zoom).0
-        } else {
-            tile_id
-        };
+        let tile_id_to_download = self.tile_id_to_download(tile_id);
+        if self.downloading() {
+            self.loader.make_sure_is_downloaded(tile_id_to_download);
+        }
 
-        self.loader.make_sure_is_downloaded(tile_id_to_download);
-        self.get_from_cache_or_interpolate(tile_id)
+        let texture = self.get_from_cache_or_interpolate(tile_id);
+        let cache_hit = matches!(texture, Some((_, zoom)) if zoom == tile_id_to_download.zoom);
+        self.metrics.tile_requested(cache_hit);
+        texture.map(|(texture, _)| texture)
     }
 
     fn tile_size(&self) -> u32 {
         self.tile_size
     }
+
+    fn missing(&self, tile_id: TileId) -> bool {
+        self.loader.missing(self.tile_id_to_download(tile_id))
+    }
+
+    /// Tiles above source's max zoom are interpolated, so only the lower limit is imposed.
+    fn zoom_range(&self) -> RangeInclusive<u8> {
+        self.min_zoom..=TileId::MAX_ZOOM
+    }
+
+    /// Tiles are uploaded again from memory if [`HttpOptions::keep_images`] is enabled.
+    /// Otherwise, they are fetched again, usually from the [`HttpOptions::cache`].
+    fn reload_textures(&mut self) {
+        self.loader.reload_textures();
+    }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        self.bounds
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::download::MaxParallelDownloads;
+    use crate::testing::attribution;
 
     use super::*;
     use hypermocker::{
@@ -144,12 +433,7 @@ mod tests {
         }
 
         fn attribution(&self) -> Attribution {
-            Attribution {
-                text: "",
-                url: "",
-                logo_light: None,
-                logo_dark: None,
-            }
+            attribution("")
         }
     }
 
@@ -311,6 +595,478 @@ mod tests {
         awaiting_request.expect().await;
     }
 
+    #[tokio::test]
+    async fn tile_is_requested_again_if_download_times_out() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                request_timeout: Duration::from_millis(200),
+                ..Default::default()
+            },
+            Context::default(),
+        );
+
+        // Server never responds to the first request...
+        let mut first = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        assert!(!tiles.missing(TILE_ID));
+        first.expect().await;
+
+        // ...so it times out and the tile is requested again.
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        assert_eq!(tiles.stats().failed_downloads, 1);
+    }
+
+    #[tokio::test]
+    async fn view_is_loaded_once_all_its_tiles_are_downloaded_or_missing() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+
+        // Spans tiles 1/2 and 2/2 at zoom 3.
+        let bounds = (crate::lon_lat(-100., 50.), crate::lon_lat(-80., 55.));
+        let mut first = server.anticipate("/3/1/2.png").await;
+        let mut second = server.anticipate("/3/2/2.png").await;
+        assert!(!tiles.all_loaded(bounds, 3));
+
+        first.expect().await;
+        second.expect().await;
+        first
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert!(!tiles.all_loaded(bounds, 3));
+
+        second.respond_with_status(StatusCode::NOT_FOUND).await;
+        while !tiles.all_loaded(bounds, 3) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(tiles.at(TILE_ID).is_some());
+    }
+
+    #[tokio::test]
+    async fn view_across_the_antimeridian_is_loaded_on_both_sides() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+
+        // Two tiles wide at zoom 3, from 135 to 225 degrees of unwrapped longitude.
+        let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(512., 100.));
+        let mut memory = crate::MapMemory::default();
+        assert_eq!(memory.set_zoom(3.), Ok(()));
+        let bounds = crate::Projector::new(rect, &memory, crate::lon_lat(180., 30.)).bounds();
+        assert!(bounds.east() > 180.);
+
+        let mut west = server.anticipate("/3/7/3.png").await;
+        let mut east = server.anticipate("/3/0/3.png").await;
+        assert!(!tiles.all_loaded(bounds, 3));
+        west.expect().await;
+        let requested = tokio::time::timeout(Duration::from_secs(5), east.expect()).await;
+        assert!(
+            requested.is_ok(),
+            "tile east of the antimeridian was not requested"
+        );
+
+        west.respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert!(!tiles.all_loaded(bounds, 3));
+        east.respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        while !tiles.all_loaded(bounds, 3) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn nothing_is_downloaded_while_paused() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        tiles.set_paused(true);
+        assert!(tiles.paused());
+
+        // Loaded tile is still there...
+        assert!(tiles.at(TILE_ID).is_some());
+
+        // ...but others are not requested.
+        let other_tile_id = TileId {
+            x: 2,
+            y: 2,
+            zoom: 3,
+        };
+        assert!(tiles.at(other_tile_id).is_none());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(tiles.at(other_tile_id).is_none());
+        assert_eq!(tiles.stats().in_progress, 0);
+
+        tiles.set_paused(false);
+        server
+            .anticipate("/3/2/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, other_tile_id).await;
+    }
+
+    #[tokio::test]
+    async fn evicted_tile_is_downloaded_again() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions::default().with_cache_capacity(1),
+            Context::default(),
+        );
+
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        // Another tile does not fit in the cache along with the first one...
+        let other_tile_id = TileId {
+            x: 2,
+            y: 2,
+            zoom: 3,
+        };
+        server
+            .anticipate("/3/2/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, other_tile_id).await;
+
+        // ...so the first one needs to be downloaded again.
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn invalidated_tile_is_downloaded_again() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        tiles.invalidate_tile(TILE_ID);
+        let request = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        request
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        tiles.clear_cache();
+        let request = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        request
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn tile_invalidated_while_downloading_is_downloaded_again() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+        let mut stale = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        stale.expect().await;
+
+        // Data changes while the download is going, so its result is not shown.
+        tiles.invalidate_tile(TILE_ID);
+        let mut fresh = server.anticipate("/3/1/2.png").await;
+        stale
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        let poll = async {
+            loop {
+                assert!(tiles.at(TILE_ID).is_none());
+                assert!(!tiles.missing(TILE_ID));
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::select! {
+            _ = fresh.expect() => {}
+            _ = poll => {}
+        }
+
+        fresh
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn tiles_are_downloaded_again_when_time_changes() {
+        let _ = env_logger::try_init();
+
+        let server = hypermocker::Server::bind().await;
+        let source = crate::sources::UrlTemplate::new(
+            format!(
+                "http://localhost:{}/{{time}}/{{z}}/{{x}}/{{y}}.png",
+                server.port()
+            ),
+            TestSource::new(String::new()).attribution(),
+        );
+        let mut tiles = HttpTiles::new(source, Context::default());
+        tiles.set_time("1200");
+
+        server
+            .anticipate("/1200/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        let texture_id = |tiles: &mut HttpTiles| match tiles.at(TILE_ID).map(|t| t.texture) {
+            Some(crate::Texture::Raster(handle)) => Some(handle.id()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+        let previous = texture_id(&mut tiles);
+
+        // Setting the same time again changes nothing.
+        tiles.set_time("1200");
+        assert_eq!(texture_id(&mut tiles), previous);
+
+        // Tile for the previous time is shown until the new one arrives.
+        tiles.set_time("1210");
+        let mut request = server.anticipate("/1210/3/1/2.png").await;
+        assert_eq!(texture_id(&mut tiles), previous);
+        request.expect().await;
+        request
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+
+        while texture_id(&mut tiles) == previous {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(tiles.time(), Some("1210"));
+    }
+
+    fn source_with_time(server: &hypermocker::Server) -> crate::sources::UrlTemplate {
+        crate::sources::UrlTemplate::new(
+            format!(
+                "http://localhost:{}/{{time}}/{{z}}/{{x}}/{{y}}.png",
+                server.port()
+            ),
+            TestSource::new(String::new()).attribution(),
+        )
+    }
+
+    #[tokio::test]
+    async fn tiles_are_not_downloaded_until_time_is_set() {
+        let _ = env_logger::try_init();
+
+        let server = hypermocker::Server::bind().await;
+        let mut tiles = HttpTiles::new(source_with_time(&server), Context::default());
+
+        // Any request would be unexpected.
+        assert!(tiles.at(TILE_ID).is_none());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(tiles.at(TILE_ID).is_none());
+
+        tiles.set_time("1200");
+        let mut request = server.anticipate("/1200/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        request.expect().await;
+        request
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn tile_being_downloaded_is_requested_for_new_time_once_done() {
+        let _ = env_logger::try_init();
+
+        let server = hypermocker::Server::bind().await;
+        let mut tiles = HttpTiles::new(source_with_time(&server), Context::default());
+        tiles.set_time("1200");
+
+        let mut old = server.anticipate("/1200/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        old.expect().await;
+
+        // Not requested again while the previous download is still going.
+        tiles.set_time("1210");
+        for _ in 0..10 {
+            assert!(tiles.at(TILE_ID).is_none());
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Once it is done, the tile is requested for the new time.
+        let mut new = server.anticipate("/1210/3/1/2.png").await;
+        old.respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        let poll = async {
+            loop {
+                assert!(tiles.at(TILE_ID).is_none());
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::select! {
+            _ = new.expect() => {}
+            _ = poll => {}
+        }
+
+        new.respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn kept_images_are_uploaded_again_without_downloading() {
+        let _ = env_logger::try_init();
+
+        for texture_atlas in [false, true] {
+            let (server, source) = hypermocker_mock().await;
+            let mut tiles = HttpTiles::with_options(
+                source,
+                HttpOptions {
+                    texture_atlas,
+                    keep_images: true,
+                    ..Default::default()
+                },
+                Context::default(),
+            );
+
+            server
+                .anticipate("/3/1/2.png")
+                .await
+                .respond(include_bytes!("../assets/blank-255-tile.png"))
+                .await;
+            assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+            let texture_id = |tiles: &mut HttpTiles| match tiles.at(TILE_ID).map(|t| t.texture) {
+                Some(crate::Texture::Raster(handle)) => Some(handle.id()),
+                #[allow(unreachable_patterns)]
+                _ => None,
+            };
+            let previous = texture_id(&mut tiles);
+            assert!(previous.is_some());
+
+            tiles.reload_textures();
+            let reloaded = texture_id(&mut tiles);
+            assert!(reloaded.is_some());
+            assert_ne!(reloaded, previous);
+        }
+    }
+
+    #[tokio::test]
+    async fn tiles_are_downloaded_again_if_images_were_not_kept() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+
+        tiles.reload_textures();
+        let request = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        request
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn tiles_share_a_texture_when_atlas_is_enabled() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions {
+                texture_atlas: true,
+                ..Default::default()
+            },
+            Context::default(),
+        );
+
+        let other_tile_id = TileId {
+            x: 2,
+            y: 2,
+            zoom: 3,
+        };
+        for path in ["/3/1/2.png", "/3/2/2.png"] {
+            server
+                .anticipate(path)
+                .await
+                .respond(include_bytes!("../assets/blank-255-tile.png"))
+                .await;
+        }
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        assert_tile_to_become_available_eventually(&mut tiles, other_tile_id).await;
+
+        let (Some(first), Some(second)) = (tiles.at(TILE_ID), tiles.at(other_tile_id)) else {
+            panic!("tiles should be available");
+        };
+        let texture_id = |texture: &crate::Texture| match texture {
+            crate::Texture::Raster(handle) => Some(handle.id()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+        assert!(texture_id(&first.texture).is_some());
+        assert_eq!(texture_id(&first.texture), texture_id(&second.texture));
+        assert!(!first.uv.intersects(second.uv));
+    }
+
+    /// Keep asking for the tile, so that it is requested again when its time comes.
+    async fn poll_until(tiles: &mut HttpTiles, until: impl Future) {
+        let poll = async {
+            loop {
+                assert!(tiles.at(TILE_ID).is_none());
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::select! {
+            _ = until => {}
+            _ = poll => {}
+        }
+    }
+
     async fn assert_tile_is_empty_forever(tiles: &mut HttpTiles) {
         // Should be None now, and forever.
         assert!(tiles.at(TILE_ID).is_none());
@@ -331,6 +1087,163 @@ mod tests {
             .await;
 
         assert_tile_is_empty_forever(&mut tiles).await;
+        assert!(tiles.missing(TILE_ID));
+        assert_eq!(tiles.stats().failed_downloads, 1);
+        assert_eq!(tiles.stats().failed_decodes, 0);
+    }
+
+    #[tokio::test]
+    async fn tile_is_requested_again_if_server_fails() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+        let mut first = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        first.expect().await;
+
+        first
+            .respond_with_status(StatusCode::INTERNAL_SERVER_ERROR)
+            .await;
+        while tiles.at(TILE_ID).is_none() && tiles.stats().failed_downloads == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!tiles.missing(TILE_ID));
+
+        // Not right away, though. Server would get an unexpected request.
+        for _ in 0..30 {
+            assert!(tiles.at(TILE_ID).is_none());
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut second = server.anticipate("/3/1/2.png").await;
+        poll_until(&mut tiles, second.expect()).await;
+        second
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn tile_is_requested_again_when_server_says_so() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+        let mut first = server.anticipate("/3/1/2.png").await;
+        assert!(tiles.at(TILE_ID).is_none());
+        first.expect().await;
+        first
+            .respond_with_headers(StatusCode::TOO_MANY_REQUESTS, &[("Retry-After", "2")])
+            .await;
+        let failed = std::time::Instant::now();
+        while tiles.stats().failed_downloads == 0 {
+            assert!(tiles.at(TILE_ID).is_none());
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Longer than it would wait otherwise.
+        let mut second = server.anticipate("/3/1/2.png").await;
+        poll_until(&mut tiles, second.expect()).await;
+        assert!(failed.elapsed() >= Duration::from_secs(2));
+        second
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+    }
+
+    #[tokio::test]
+    async fn source_errors_are_reported() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let errors = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported = errors.clone();
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions::default().with_on_source_error(move |error| {
+                reported.lock().expect("not poisoned").push(error);
+            }),
+            Context::default(),
+        );
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond_with_status(StatusCode::UNAUTHORIZED)
+            .await;
+        assert_tile_is_empty_forever(&mut tiles).await;
+        assert!(tiles.missing(TILE_ID));
+
+        // Every tile is refused the same way, so that is reported only once.
+        let second = TileId { x: 2, ..TILE_ID };
+        server
+            .anticipate("/3/2/2.png")
+            .await
+            .respond_with_status(StatusCode::FORBIDDEN)
+            .await;
+        let third = TileId { x: 3, ..TILE_ID };
+        server
+            .anticipate("/3/3/2.png")
+            .await
+            .respond_with_status(StatusCode::NOT_FOUND)
+            .await;
+        while !tiles.missing(second) || !tiles.missing(third) {
+            tiles.at(second);
+            tiles.at(third);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(
+            *errors.lock().expect("not poisoned"),
+            [
+                crate::TileSourceError::Unauthorized(401),
+                crate::TileSourceError::Status(404)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tile_activity_is_counted() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let reported = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let report = reported.clone();
+        let mut tiles = HttpTiles::with_options(
+            source,
+            HttpOptions::default().with_on_metrics(move |metrics| {
+                report.lock().expect("not poisoned").push(metrics.clone());
+            }),
+            Context::default(),
+        );
+        let tile = include_bytes!("../assets/blank-255-tile.png");
+        server.anticipate("/3/1/2.png").await.respond(tile).await;
+        server
+            .anticipate("/3/2/2.png")
+            .await
+            .respond_with_status(StatusCode::NOT_FOUND)
+            .await;
+
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        let other = TileId { x: 2, ..TILE_ID };
+        while !tiles.missing(other) {
+            tiles.at(other);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let metrics = tiles.metrics();
+        assert!(metrics.tiles_requested >= 3);
+        assert_eq!(metrics.cache_hits, 1);
+        assert_eq!(metrics.responses, 2);
+        assert_eq!(metrics.bytes_downloaded, tile.len() as u64);
+        assert!(metrics.average_latency.is_some());
+        assert_eq!(metrics.errors_by_status, [(404, 1)].into());
+
+        // Same counters are reported after each response.
+        let reported = reported.lock().expect("not poisoned");
+        assert_eq!(reported.len(), 2);
+        assert_eq!(reported[1].bytes_downloaded, metrics.bytes_downloaded);
+        assert_eq!(reported[1].errors_by_status, metrics.errors_by_status);
     }
 
     #[tokio::test]
@@ -354,13 +1267,104 @@ mod tests {
 
         let (server, source) = hypermocker_mock().await;
         let mut tiles = HttpTiles::new(source, Context::default());
+        let mut first = server.anticipate("/3/1/2.png").await;
+
+        assert!(tiles.at(TILE_ID).is_none());
+        first.expect().await;
+
+        // Tile which cannot be decoded is fetched once again, in case it was truncated.
         server
             .anticipate("/3/1/2.png")
             .await
             .respond("definitely not an image")
             .await;
+        first.respond("definitely not an image").await;
 
         assert_tile_is_empty_forever(&mut tiles).await;
+        assert_eq!(tiles.stats().failed_downloads, 0);
+        assert_eq!(tiles.stats().failed_decodes, 1);
+    }
+
+    #[tokio::test]
+    async fn tile_is_fetched_again_if_it_could_not_be_decoded() {
+        let _ = env_logger::try_init();
+
+        let (server, source) = hypermocker_mock().await;
+        let mut tiles = HttpTiles::new(source, Context::default());
+        let mut first = server.anticipate("/3/1/2.png").await;
+
+        assert!(tiles.at(TILE_ID).is_none());
+        first.expect().await;
+
+        server
+            .anticipate("/3/1/2.png")
+            .await
+            .respond(include_bytes!("../assets/blank-255-tile.png"))
+            .await;
+        first.respond("truncated").await;
+
+        assert_tile_to_become_available_eventually(&mut tiles, TILE_ID).await;
+        assert_eq!(tiles.stats().failed_decodes, 0);
+    }
+
+    /// Tile source which records the tiles it was asked for, up to given max zoom.
+    struct RecordingSource {
+        max_zoom: u8,
+        requested: Arc<Mutex<Vec<TileId>>>,
+    }
+
+    impl TileSource for RecordingSource {
+        fn tile_url(&self, tile_id: TileId) -> String {
+            if let Ok(mut requested) = self.requested.lock() {
+                requested.push(tile_id);
+            }
+            "totally invalid url".to_string()
+        }
+
+        fn attribution(&self) -> Attribution {
+            attribution("")
+        }
+
+        fn max_zoom(&self) -> u8 {
+            self.max_zoom
+        }
+    }
+
+    #[tokio::test]
+    async fn tiles_above_max_zoom_are_never_requested() {
+        let _ = env_logger::try_init();
+
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let source = RecordingSource {
+            max_zoom: 12,
+            requested: requested.clone(),
+        };
+        let ctx = Context::default();
+        let mut tiles = HttpTiles::new(source, ctx.clone());
+        let mut memory = crate::MapMemory::default();
+        let position = crate::lon_lat(17.03664, 51.09916);
+
+        for zoom in [11.6, 12.4, 13.7, 18.2, 24.9] {
+            assert_eq!(memory.set_zoom(zoom), Ok(()));
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(crate::Map::new(Some(&mut tiles), &mut memory, position));
+                });
+            });
+
+            // Map is still drawn at the fractional zoom.
+            assert_eq!(memory.zoom(), zoom);
+        }
+
+        let bounds = (crate::lon_lat(16., 51.), crate::lon_lat(18., 52.));
+        assert!(!tiles.all_loaded(bounds, 26));
+
+        // Let the download loop pick the tiles up.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let requested = requested.lock().expect("not poisoned");
+        assert!(requested.iter().any(|tile_id| tile_id.zoom == 12));
+        assert!(requested.iter().all(|tile_id| tile_id.zoom <= 12));
     }
 
     /// Tile source, which gives invalid urls.
@@ -372,12 +1376,7 @@ mod tests {
         }
 
         fn attribution(&self) -> Attribution {
-            Attribution {
-                text: "",
-                url: "",
-                logo_light: None,
-                logo_dark: None,
-            }
+            attribution("")
         }
     }
 
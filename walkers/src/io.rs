@@ -32,13 +32,19 @@ mod web {
         }
         ClientBuilder::new(bare_client(http_options)).build()
     }
+
+    /// HTTP cache is handled by the browser, so there is nothing to remove.
+    pub async fn remove_from_http_cache(_path: &std::path::Path, _url: Option<&str>) {}
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 mod native {
     use super::{HttpOptions, bare_client};
-    use http_cache_reqwest::{CACacheManager, Cache, CacheMode, HttpCache, HttpCacheOptions};
+    use http_cache_reqwest::{
+        CACacheManager, Cache, CacheManager, CacheMode, HttpCache, HttpCacheOptions, Parts,
+    };
     use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+    use std::sync::Arc;
 
     pub struct Runtime {
         join_handle: Option<std::thread::JoinHandle<()>>,
@@ -95,13 +101,94 @@ mod native {
                     path: cache.clone(),
                     remove_opts: Default::default(),
                 },
-                options: HttpCacheOptions::default(),
+                options: HttpCacheOptions {
+                    cache_key: Some(Arc::new(|parts: &Parts| cache_key(&parts.uri.to_string()))),
+                    ..Default::default()
+                },
             }))
         } else {
             builder
         }
         .build()
     }
+
+    /// Remove the response for `url`, or all responses if `None`, from the HTTP cache stored in
+    /// `path`.
+    pub async fn remove_from_http_cache(path: &std::path::Path, url: Option<&str>) {
+        let manager = CACacheManager {
+            path: path.to_owned(),
+            remove_opts: Default::default(),
+        };
+
+        let result = match url {
+            Some(url) => manager.delete(&cache_key(&normalized(url))).await,
+            None => manager.clear().await,
+        };
+
+        if let Err(err) = result {
+            log::warn!("Could not remove tiles from the HTTP cache: {err}");
+        }
+    }
+
+    /// Key under which the response for `url` is stored in the HTTP cache. It is given to the
+    /// [`HttpCache`] explicitly, so that [`remove_from_http_cache`] can find the response again.
+    /// Same as the default one, so that caches from before it was set keep working.
+    fn cache_key(url: &str) -> String {
+        format!("GET:{url}")
+    }
+
+    fn normalized(url: &str) -> String {
+        reqwest::Url::parse(url)
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| url.to_owned())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn removed_response_is_gone_from_the_http_cache() {
+            let _ = env_logger::try_init();
+
+            let cache = std::env::temp_dir()
+                .join(format!("walkers-http-cache-test-{}", std::process::id()));
+            let server = hypermocker::Server::bind().await;
+            let url = format!("http://localhost:{}/3/1/2.png", server.port());
+            let client = http_client(&HttpOptions {
+                cache: Some(cache.clone()),
+                ..Default::default()
+            });
+            let manager = CACacheManager {
+                path: cache.clone(),
+                remove_opts: Default::default(),
+            };
+
+            let mut request = server.anticipate("/3/1/2.png").await;
+            let response = tokio::spawn(client.get(&url).send());
+            request.expect().await;
+            request
+                .respond_with_headers(
+                    hypermocker::StatusCode::OK,
+                    &[("cache-control", "max-age=3600")],
+                )
+                .await;
+            response
+                .await
+                .expect("request panicked")
+                .expect("request failed");
+
+            let key = cache_key(&normalized(&url));
+            let cached = manager.get(&key).await.expect("cache is readable");
+            assert!(cached.is_some());
+
+            remove_from_http_cache(&cache, Some(&url)).await;
+            let cached = manager.get(&key).await.expect("cache is readable");
+            assert!(cached.is_none());
+
+            let _ = std::fs::remove_dir_all(&cache);
+        }
+    }
 }
 
 fn bare_client(http_options: &HttpOptions) -> reqwest::Client {
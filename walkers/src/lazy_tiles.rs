@@ -0,0 +1,348 @@
+use std::{fmt::Display, future::Future, ops::RangeInclusive, pin::Pin};
+
+use egui::{Context, Rect, TextureHandle};
+use futures::channel::oneshot;
+
+use crate::{
+    BoundingBox, TextureWithUv, TileId, Tiles, io::Runtime, mercator::TILE_SIZE,
+    sources::Attribution,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+type Init<T> = Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<T, String>> + Send>> + Send>;
+
+/// On the web, futures are run on the main thread, so they do not need to be [`Send`].
+#[cfg(target_arch = "wasm32")]
+type Init<T> = Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<T, String>>>>>;
+
+enum State<T> {
+    Initializing {
+        #[allow(dead_code)] // Significant Drop, cancels the initialization.
+        runtime: Runtime,
+        result_rx: oneshot::Receiver<Result<T, String>>,
+    },
+    Ready(T),
+    Failed(String),
+    Cancelled,
+}
+
+/// [`Tiles`] which need to be initialized asynchronously before any tile can be requested, e.g.
+/// because the source's metadata needs to be fetched first. Initialization starts right away,
+/// in the background, and until it finishes, the map shows placeholders. Meanwhile,
+/// [`LazyTiles::initializing`] can be used to show a spinner, such as
+/// `walkers_extras::LoadingIndicator`.
+///
+/// ```
+/// # use walkers::{HttpTiles, LazyTiles, sources::OpenStreetMap};
+/// # async fn fetch_api_key() -> Result<String, std::io::Error> { Ok(String::new()) }
+/// fn tiles(egui_ctx: egui::Context) -> LazyTiles<HttpTiles> {
+///     let attribution = walkers::sources::TileSource::attribution(&OpenStreetMap);
+///     LazyTiles::new(attribution, egui_ctx.clone(), move || {
+///         let egui_ctx = egui_ctx.clone();
+///         async move {
+///             let _api_key = fetch_api_key().await?;
+///             Ok::<_, std::io::Error>(HttpTiles::new(OpenStreetMap, egui_ctx))
+///         }
+///     })
+/// }
+/// ```
+pub struct LazyTiles<T> {
+    init: Init<T>,
+    state: State<T>,
+    attribution: Attribution,
+    egui_ctx: Context,
+}
+
+impl<T> LazyTiles<T>
+where
+    T: Tiles + Send + 'static,
+{
+    /// Start initializing the tiles with the `init` function, which is called again on
+    /// [`LazyTiles::retry`]. `attribution` is shown until the tiles are ready.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new<F, Fut, E>(attribution: Attribution, egui_ctx: Context, init: F) -> Self
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        E: Display,
+    {
+        Self::with_init(
+            attribution,
+            egui_ctx,
+            Box::new(move || {
+                let future = init();
+                Box::pin(async move { future.await.map_err(|err| err.to_string()) })
+            }),
+        )
+    }
+
+    /// Start initializing the tiles with the `init` function, which is called again on
+    /// [`LazyTiles::retry`]. `attribution` is shown until the tiles are ready.
+    #[cfg(target_arch = "wasm32")]
+    pub fn new<F, Fut, E>(attribution: Attribution, egui_ctx: Context, init: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<T, E>> + 'static,
+        E: Display,
+    {
+        Self::with_init(
+            attribution,
+            egui_ctx,
+            Box::new(move || {
+                let future = init();
+                Box::pin(async move { future.await.map_err(|err| err.to_string()) })
+            }),
+        )
+    }
+
+    fn with_init(attribution: Attribution, egui_ctx: Context, init: Init<T>) -> Self {
+        let state = start(&init, &egui_ctx);
+
+        Self {
+            init,
+            state,
+            attribution,
+            egui_ctx,
+        }
+    }
+
+    /// Whether the initialization is still in progress.
+    pub fn initializing(&self) -> bool {
+        matches!(self.state, State::Initializing { .. })
+    }
+
+    /// Error returned by the last initialization, if it failed.
+    pub fn error(&self) -> Option<&str> {
+        match &self.state {
+            State::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Initialized tiles, if ready, e.g. to get [`crate::HttpTiles::stats`].
+    pub fn tiles(&mut self) -> Option<&mut T> {
+        self.poll();
+        match &mut self.state {
+            State::Ready(tiles) => Some(tiles),
+            _ => None,
+        }
+    }
+
+    /// Stop the initialization, if it is still in progress. Tiles stay uninitialized until
+    /// [`LazyTiles::retry`]. On the web, the initialization runs to completion anyway, but its
+    /// result is dropped.
+    pub fn cancel(&mut self) {
+        if self.initializing() {
+            self.state = State::Cancelled;
+        }
+    }
+
+    /// Start the initialization again, e.g. after it failed or was cancelled. Does nothing if
+    /// the tiles are already initialized.
+    pub fn retry(&mut self) {
+        self.poll();
+        if !matches!(self.state, State::Ready(_)) {
+            self.state = start(&self.init, &self.egui_ctx);
+        }
+    }
+
+    /// Check whether the initialization has finished.
+    fn poll(&mut self) {
+        let State::Initializing { result_rx, .. } = &mut self.state else {
+            return;
+        };
+
+        self.state = match result_rx.try_recv() {
+            Ok(None) => return,
+            Ok(Some(Ok(tiles))) => State::Ready(tiles),
+            Ok(Some(Err(error))) => {
+                log::warn!("Could not initialize tiles: {error}");
+                State::Failed(error)
+            }
+            Err(oneshot::Canceled) => State::Failed("Initialization was interrupted.".to_owned()),
+        };
+    }
+
+    fn ready(&self) -> Option<&T> {
+        match &self.state {
+            State::Ready(tiles) => Some(tiles),
+            _ => None,
+        }
+    }
+}
+
+/// Run the initialization in the background. Map is repainted once it finishes.
+fn start<T: Send + 'static>(init: &Init<T>, egui_ctx: &Context) -> State<T> {
+    let (result_tx, result_rx) = oneshot::channel();
+    let future = init();
+    let egui_ctx = egui_ctx.clone();
+
+    let runtime = Runtime::new(async move {
+        let _ = result_tx.send(future.await);
+        egui_ctx.request_repaint();
+    });
+
+    State::Initializing { runtime, result_rx }
+}
+
+impl<T> Tiles for LazyTiles<T>
+where
+    T: Tiles + Send + 'static,
+{
+    fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+        self.tiles()?.at(tile_id)
+    }
+
+    fn attribution(&self) -> Attribution {
+        self.ready()
+            .map(|tiles| tiles.attribution())
+            .unwrap_or_else(|| self.attribution.clone())
+    }
+
+    fn tile_size(&self) -> u32 {
+        self.ready()
+            .map(|tiles| tiles.tile_size())
+            .unwrap_or(TILE_SIZE)
+    }
+
+    /// Tiles are not considered missing while initializing, so that
+    /// [`crate::Map::with_empty_coverage_hint`] is not shown.
+    fn missing(&self, tile_id: TileId) -> bool {
+        self.ready().is_some_and(|tiles| tiles.missing(tile_id))
+    }
+
+    fn zoom_range(&self) -> RangeInclusive<u8> {
+        self.ready()
+            .map(|tiles| tiles.zoom_range())
+            .unwrap_or(0..=TileId::MAX_ZOOM)
+    }
+
+    fn reload_textures(&mut self) {
+        if let Some(tiles) = self.tiles() {
+            tiles.reload_textures();
+        }
+    }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        self.ready().and_then(|tiles| tiles.bounds())
+    }
+
+    fn preview(&mut self, zoom: u8) -> Option<(TextureHandle, Rect)> {
+        self.tiles()?.preview(zoom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Texture, testing::attribution};
+    use egui::{Color32, ColorImage, pos2};
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    const TILE_ID: TileId = TileId {
+        x: 1,
+        y: 2,
+        zoom: 3,
+    };
+
+    struct TestTiles(Texture);
+
+    impl Tiles for TestTiles {
+        fn at(&mut self, _tile_id: TileId) -> Option<TextureWithUv> {
+            Some(TextureWithUv::new(
+                self.0.clone(),
+                Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+            ))
+        }
+
+        fn attribution(&self) -> Attribution {
+            attribution("Ready")
+        }
+
+        fn tile_size(&self) -> u32 {
+            512
+        }
+    }
+
+    fn test_tiles(ctx: &Context) -> TestTiles {
+        let image = ColorImage::filled([256, 256], Color32::WHITE);
+        TestTiles(Texture::from_color_image(image, ctx))
+    }
+
+    fn wait_until_initialized<T: Tiles + Send + 'static>(tiles: &mut LazyTiles<T>) {
+        for _ in 0..500 {
+            tiles.at(TILE_ID);
+            if !tiles.initializing() {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("tiles were not initialized");
+    }
+
+    #[test]
+    fn tiles_are_available_once_initialized() {
+        let ctx = Context::default();
+        let inner = test_tiles(&ctx);
+        let inner = std::sync::Mutex::new(Some(inner));
+        let mut tiles = LazyTiles::new(attribution("Initializing"), ctx, move || {
+            let inner = inner.lock().expect("not poisoned").take();
+            async move { inner.ok_or("initialized twice") }
+        });
+
+        assert_eq!(tiles.attribution().text, "Initializing");
+        assert!(!tiles.missing(TILE_ID));
+
+        wait_until_initialized(&mut tiles);
+        assert!(tiles.at(TILE_ID).is_some());
+        assert_eq!(tiles.attribution().text, "Ready");
+        assert_eq!(tiles.tile_size(), 512);
+    }
+
+    #[test]
+    fn failed_initialization_can_be_retried() {
+        let ctx = Context::default();
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let attempt = attempts.clone();
+        let inner_ctx = ctx.clone();
+        let mut tiles = LazyTiles::new(attribution(""), ctx, move || {
+            let first = attempt.fetch_add(1, Ordering::SeqCst) == 0;
+            let inner = test_tiles(&inner_ctx);
+            async move {
+                if first {
+                    Err("metadata unavailable")
+                } else {
+                    Ok(inner)
+                }
+            }
+        });
+
+        wait_until_initialized(&mut tiles);
+        assert_eq!(tiles.error(), Some("metadata unavailable"));
+        assert!(tiles.at(TILE_ID).is_none());
+
+        tiles.retry();
+        assert!(tiles.initializing());
+        wait_until_initialized(&mut tiles);
+        assert_eq!(tiles.error(), None);
+        assert!(tiles.at(TILE_ID).is_some());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn initialization_can_be_cancelled() {
+        let ctx = Context::default();
+        let mut tiles = LazyTiles::new(attribution(""), ctx.clone(), move || {
+            futures::future::pending::<Result<TestTiles, String>>()
+        });
+        assert!(tiles.initializing());
+
+        tiles.cancel();
+        assert!(!tiles.initializing());
+        assert_eq!(tiles.error(), None);
+        assert!(tiles.at(TILE_ID).is_none());
+    }
+}
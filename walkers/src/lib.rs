@@ -1,36 +1,79 @@
-#![doc = include_str!("../README.md")]
+#![cfg_attr(feature = "widget", doc = include_str!("../README.md"))]
 #![deny(clippy::unwrap_used, rustdoc::broken_intra_doc_links)]
 
+// Coordinate math, which does not need egui.
+// TODO: I don't want it to be public.
+pub mod mercator;
+mod position;
+mod tile_id;
+
+pub use position::{BoundingBox, Position, PositionExt, filter_in_bounds, lat_lon, lon_lat};
+pub use tile_id::TileId;
+
+#[cfg(feature = "widget")]
+mod atlas;
+#[cfg(feature = "widget")]
 mod center;
+#[cfg(feature = "widget")]
 mod download;
+#[cfg(feature = "widget")]
+mod flight;
+#[cfg(feature = "widget")]
 mod http_tiles;
+#[cfg(feature = "widget")]
 mod io;
+#[cfg(feature = "widget")]
+mod lazy_tiles;
+#[cfg(feature = "widget")]
 mod loader;
+#[cfg(feature = "widget")]
 mod local_tiles;
+#[cfg(feature = "widget")]
 mod map;
+#[cfg(feature = "widget")]
 mod memory;
-
-// TODO: I don't want it to be public.
-pub mod mercator;
-
 #[cfg(feature = "vector_tiles")]
 mod mvt;
 #[cfg(feature = "vector_tiles")]
 mod pmtiles;
-mod position;
+#[cfg(feature = "widget")]
 mod projector;
+#[cfg(feature = "widget")]
 pub mod sources;
+#[cfg(feature = "widget")]
+mod swipe_compare;
+#[cfg(all(test, feature = "widget"))]
+mod testing;
+#[cfg(feature = "widget")]
 mod tiles;
+#[cfg(feature = "widget")]
 mod zoom;
 
-pub use download::{HeaderValue, HttpOptions, MaxParallelDownloads};
-pub use http_tiles::{HttpStats, HttpTiles};
+#[cfg(feature = "widget")]
+pub use download::{
+    HeaderValue, HttpOptions, MaxParallelDownloads, MetricsCallback, TileSourceError,
+};
+#[cfg(feature = "widget")]
+pub use flight::Easing;
+#[cfg(feature = "widget")]
+pub use http_tiles::{HttpStats, HttpTiles, TileMetrics};
+#[cfg(feature = "widget")]
+pub use lazy_tiles::LazyTiles;
+#[cfg(feature = "widget")]
 pub use local_tiles::LocalTiles;
-pub use map::{Map, Plugin};
+#[cfg(feature = "widget")]
+pub use map::{FrameStats, Map, Plugin, PluginLayer};
+#[cfg(feature = "widget")]
 pub use memory::MapMemory;
 #[cfg(feature = "vector_tiles")]
+pub use mvt::{LayerStyle, VectorStyle};
+#[cfg(feature = "vector_tiles")]
 pub use pmtiles::PmTiles;
-pub use position::{Position, lat_lon, lon_lat};
-pub use projector::Projector;
-pub use tiles::{Texture, TextureWithUv, TileId, Tiles};
+#[cfg(feature = "widget")]
+pub use projector::{Projector, WorldFile};
+#[cfg(feature = "widget")]
+pub use swipe_compare::SwipeCompare;
+#[cfg(feature = "widget")]
+pub use tiles::{Texture, TextureWithUv, Tiles};
+#[cfg(feature = "widget")]
 pub use zoom::InvalidZoom;
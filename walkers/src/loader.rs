@@ -1,87 +1,291 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use egui::Context;
-use futures::channel::mpsc::{Receiver, Sender, TrySendError, channel};
+use egui::{ColorImage, Context, Rect, TextureFilter, TextureOptions, pos2};
+use futures::{
+    StreamExt,
+    channel::mpsc::{Receiver, Sender, UnboundedSender, channel, unbounded},
+    stream::{PollNext, select_with_strategy},
+};
 use lru::LruCache;
 
 use crate::{
-    HttpStats, Texture, TileId,
-    download::{Fetch, download_continuously},
+    HttpStats, Texture, TextureWithUv, TileId,
+    atlas::{AtlasTile, TileAtlas},
+    download::{Downloaded, Fetch, Request, Upload, download_continuously},
     io::Runtime,
 };
 
+/// How long to wait before the first retry of a tile the source failed to provide. Each next
+/// attempt waits twice as long, up to [`MAX_RETRY_DELAY`].
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Tile the source failed to provide for now, e.g. due to a server error or a rate limit.
+struct Retry {
+    attempts: u32,
+    at: web_time::Instant,
+}
+
+/// State of a tile in the [`Loader`]'s cache.
+pub enum CachedTile {
+    /// Tile was requested, but it is not available yet.
+    Pending,
+
+    /// Tile was loaded and can be drawn. Decoded image is kept if it is a raster tile and
+    /// [`Loader::keep_images`] is enabled.
+    Valid(Texture, Option<ColorImage>),
+
+    /// Tile was loaded into the texture atlas and can be drawn.
+    InAtlas(AtlasTile, Option<ColorImage>),
+
+    /// Tile could not be loaded, e.g. because the source has no data for it.
+    Missing,
+}
+
+impl CachedTile {
+    /// Texture to draw the given part of the tile with, if loaded.
+    pub fn texture(&self, uv: Rect) -> Option<TextureWithUv> {
+        match self {
+            CachedTile::Valid(texture, _) => Some(TextureWithUv::new(texture.clone(), uv)),
+            CachedTile::InAtlas(tile, _) => {
+                // Map tile's UV onto its slot in the atlas.
+                let slot = tile.uv;
+                let uv = Rect::from_min_max(
+                    pos2(
+                        slot.min.x + uv.min.x * slot.width(),
+                        slot.min.y + uv.min.y * slot.height(),
+                    ),
+                    pos2(
+                        slot.min.x + uv.max.x * slot.width(),
+                        slot.min.y + uv.max.y * slot.height(),
+                    ),
+                );
+                Some(TextureWithUv::new(tile.texture.clone(), uv))
+            }
+            CachedTile::Pending | CachedTile::Missing => None,
+        }
+    }
+}
+
 /// Asynchronously load and cache tiles from different local and remote sources.
 pub struct Loader {
     /// Tiles to be fetched by the IO thread.
     pub request_tx: Sender<TileId>,
 
-    /// Tiles that got fetched and should be put in the cache.
-    pub tile_rx: Receiver<(TileId, Texture)>,
+    /// Requests other than downloads, e.g. to remove tiles from the HTTP cache. Unbounded, so
+    /// that these are never lost, unlike the download requests.
+    control_tx: UnboundedSender<Request>,
+
+    /// Tiles that got fetched and should be put in the cache, along with the generation they were
+    /// requested in.
+    pub tile_rx: Receiver<(TileId, u64, Downloaded)>,
 
-    pub cache: LruCache<TileId, Option<Texture>>,
+    /// Bumped each time the `{time}` changes. Tiles requested before are dropped once downloaded.
+    generation: u64,
+
+    /// Tiles being downloaded right now, so that they are not requested twice. Outdated ones, i.e.
+    /// requested before an invalidation or a `{time}` change, are dropped once downloaded, and
+    /// requested again.
+    downloading: HashMap<TileId, bool>,
+
+    /// Tiles which are not requested again until their time comes, not to hammer a failing source.
+    retries: HashMap<TileId, Retry>,
+
+    /// Tiles in the cache which were loaded for a different `{time}`. They are still shown, until
+    /// replaced, so that changing the time does not make the map blank.
+    stale: HashSet<TileId>,
+
+    pub cache: LruCache<TileId, CachedTile>,
     pub stats: Arc<Mutex<HttpStats>>,
 
+    /// Texture atlas, if enabled.
+    atlas: Option<TileAtlas>,
+
+    /// Filter of the textures created for tiles which do not fit in the atlas.
+    texture_filter: TextureFilter,
+
+    /// Whether decoded raster images are kept, so that textures can be created again.
+    keep_images: bool,
+
+    egui_ctx: Context,
+
     #[allow(dead_code)] // Significant Drop
     runtime: Runtime,
 }
 
 impl Loader {
-    pub fn new(fetch: impl Fetch + Send + Sync + 'static, egui_ctx: Context) -> Self {
-        let stats = Arc::new(Mutex::new(HttpStats { in_progress: 0 }));
+    pub fn new(
+        fetch: impl Fetch + Send + Sync + 'static,
+        texture_filter: TextureFilter,
+        texture_atlas: bool,
+        keep_images: bool,
+        cache_capacity: NonZeroUsize,
+        egui_ctx: Context,
+    ) -> Self {
+        let stats = Arc::new(Mutex::new(HttpStats::default()));
 
         // This ensures that newer requests are prioritized.
         let channel_size = fetch.max_concurrency();
 
         let (request_tx, request_rx) = channel(channel_size);
         let (tile_tx, tile_rx) = channel(channel_size);
+        let (control_tx, control_rx) = unbounded();
+
+        // Control requests go first, so that e.g. tiles requested after an invalidation are not
+        // taken from the cache.
+        let requests = select_with_strategy(
+            control_rx,
+            request_rx.map(Request::Download),
+            |_: &mut ()| PollNext::Left,
+        );
+
+        let atlas = texture_atlas.then(|| {
+            let options = TextureOptions {
+                magnification: texture_filter,
+                minification: texture_filter,
+                ..Default::default()
+            };
+            TileAtlas::new(egui_ctx.clone(), options)
+        });
+
+        // Images need to reach the main thread to end up in the atlas or to be kept.
+        let upload = if texture_atlas || keep_images {
+            Upload::Deferred
+        } else {
+            Upload::Texture(texture_filter)
+        };
 
         // This will run concurrently in a loop, handing downloads and talk with us via channels.
         let runtime = Runtime::new(download_continuously(
             fetch,
             stats.clone(),
-            request_rx,
+            requests,
             tile_tx,
-            egui_ctx,
+            upload,
+            egui_ctx.clone(),
         ));
 
-        // Just arbitrary value which seemed right.
-        #[allow(clippy::unwrap_used)]
-        let cache_size = std::num::NonZeroUsize::new(256).unwrap();
-
         Self {
-            cache: LruCache::new(cache_size),
+            cache: LruCache::new(cache_capacity),
             stats,
             request_tx,
+            control_tx,
             tile_rx,
+            generation: 0,
+            downloading: HashMap::new(),
+            retries: HashMap::new(),
+            stale: HashSet::new(),
+            atlas,
+            texture_filter,
+            keep_images,
+            egui_ctx,
             runtime,
         }
     }
 
     pub fn put_single_downloaded_tile_in_cache(&mut self) {
         // This is called every frame, so take just one at the time.
-        match self.tile_rx.try_next() {
-            Ok(Some((tile_id, tile))) => {
-                self.cache.put(tile_id, Some(tile));
-            }
+        let (tile_id, generation, downloaded) = match self.tile_rx.try_next() {
+            Ok(Some(downloaded)) => downloaded,
             Err(_) => {
                 // Just ignore. It means that no new tile was downloaded.
+                return;
             }
             Ok(None) => {
-                log::error!("IO thread is dead")
+                log::error!("IO thread is dead");
+                return;
+            }
+        };
+
+        let outdated = self.downloading.remove(&tile_id).unwrap_or(false);
+        if outdated || generation != self.generation {
+            log::trace!("Dropping outdated {tile_id:?}.");
+            // Placeholder goes away, so that the tile is requested again.
+            if let Some(CachedTile::Pending) = self.cache.peek(&tile_id) {
+                self.cache.pop(&tile_id);
+            }
+            return;
+        }
+
+        if !matches!(
+            downloaded,
+            Downloaded::TimedOut | Downloaded::Unavailable(_)
+        ) {
+            self.retries.remove(&tile_id);
+        }
+
+        match downloaded {
+            Downloaded::Tile(tile) => {
+                self.put(tile_id, CachedTile::Valid(tile, None));
+            }
+            Downloaded::Image(image) => {
+                let cached = self.upload(image);
+                self.put(tile_id, cached);
+            }
+            Downloaded::Missing => {
+                self.put(tile_id, CachedTile::Missing);
+            }
+            Downloaded::TimedOut => {
+                // Forget about it, so it will be requested again.
+                if let Some(cached) = self.cache.pop(&tile_id) {
+                    self.release(cached);
+                }
+            }
+            Downloaded::Unavailable(retry_after) => {
+                // Forget about it too, but ask again only after a while.
+                if let Some(cached) = self.cache.pop(&tile_id) {
+                    self.release(cached);
+                }
+                let attempts = self.retries.get(&tile_id).map_or(0, |retry| retry.attempts) + 1;
+                let delay = retry_after.unwrap_or_else(|| {
+                    RETRY_DELAY
+                        .saturating_mul(1 << (attempts - 1).min(16))
+                        .min(MAX_RETRY_DELAY)
+                });
+                log::debug!("Retrying {tile_id:?} in {delay:?}.");
+                self.retries.insert(
+                    tile_id,
+                    Retry {
+                        attempts,
+                        at: web_time::Instant::now() + delay,
+                    },
+                );
+                self.egui_ctx.request_repaint_after(delay);
             }
         }
     }
 
     pub fn make_sure_is_downloaded(&mut self, tile_id: TileId) {
-        match self.cache.try_get_or_insert(
-            tile_id,
-            || -> Result<Option<Texture>, TrySendError<TileId>> {
-                self.request_tx.try_send(tile_id)?;
+        // Also marks the tile as recently used.
+        let cached = self.cache.get(&tile_id).is_some();
+        if cached && !self.stale.contains(&tile_id) || self.downloading.contains_key(&tile_id) {
+            return;
+        }
+
+        if self
+            .retries
+            .get(&tile_id)
+            .is_some_and(|retry| web_time::Instant::now() < retry.at)
+        {
+            return;
+        }
+
+        match self.request_tx.try_send(tile_id) {
+            Ok(()) => {
                 log::trace!("Requested tile: {tile_id:?}");
-                Ok(None)
-            },
-        ) {
-            Ok(_) => {}
+                self.downloading.insert(tile_id, false);
+                // Stale tile is shown until the new one arrives.
+                let stale = self.stale.remove(&tile_id);
+                if !(stale && cached) {
+                    self.put(tile_id, CachedTile::Pending);
+                }
+            }
             Err(err) if err.is_full() => {
                 // Trying to download too many tiles at once.
                 log::trace!("Request queue is full.");
@@ -91,4 +295,141 @@ impl Loader {
             }
         }
     }
+
+    /// Drop the tile, or all the tiles if `None`, from the cache, so that it is loaded again
+    /// next time it is needed. Tiles which are still being downloaded are requested again once
+    /// the download finishes, as it might have been started before the data changed.
+    pub fn invalidate(&mut self, tile_id: Option<TileId>) {
+        self.send_control(Request::Invalidate(tile_id));
+        self.outdate_downloads(tile_id);
+
+        let tile_ids: Vec<_> = match tile_id {
+            Some(tile_id) => vec![tile_id],
+            None => self.cache.iter().map(|(tile_id, _)| *tile_id).collect(),
+        };
+
+        for tile_id in tile_ids {
+            if !matches!(self.cache.peek(&tile_id), Some(CachedTile::Pending) | None)
+                && let Some(cached) = self.cache.pop(&tile_id)
+            {
+                self.release(cached);
+            }
+        }
+    }
+
+    /// Change the value of the `{time}` token in tile URLs. Tiles in the cache are kept until
+    /// downloaded again for the new time.
+    pub fn set_time(&mut self, time: Option<String>) {
+        self.send_control(Request::SetTime(time));
+        self.generation += 1;
+
+        // Downloads in progress are for the previous time, so they are requested again once
+        // they finish, not to download the same tiles twice at once.
+        self.outdate_downloads(None);
+
+        self.stale = self
+            .cache
+            .iter()
+            .filter(|(_, cached)| !matches!(cached, CachedTile::Pending))
+            .map(|(tile_id, _)| *tile_id)
+            .collect();
+    }
+
+    /// Mark the download of the tile, or all of them if `None`, as outdated.
+    fn outdate_downloads(&mut self, tile_id: Option<TileId>) {
+        match tile_id {
+            Some(tile_id) => {
+                if let Some(outdated) = self.downloading.get_mut(&tile_id) {
+                    *outdated = true;
+                }
+            }
+            None => self
+                .downloading
+                .values_mut()
+                .for_each(|outdated| *outdated = true),
+        }
+    }
+
+    /// Create the textures again, e.g. after the GPU context was lost and they became invalid.
+    /// Tiles whose images were kept are uploaded from memory, the rest are dropped from the
+    /// cache, so that they are loaded again next time they are needed.
+    pub fn reload_textures(&mut self) {
+        if let Some(atlas) = self.atlas.as_mut() {
+            atlas.clear();
+        }
+
+        let tile_ids: Vec<_> = self.cache.iter().map(|(tile_id, _)| *tile_id).collect();
+        for tile_id in tile_ids {
+            // Peek, so that the order of eviction is kept.
+            let image = match self.cache.peek_mut(&tile_id) {
+                Some(CachedTile::Valid(_, image) | CachedTile::InAtlas(_, image))
+                    if image.is_some() =>
+                {
+                    image.take()
+                }
+                Some(
+                    CachedTile::Valid(Texture::Raster(_), None) | CachedTile::InAtlas(_, None),
+                ) => {
+                    // Nothing to upload from. Atlas was cleared, so there is no slot to release.
+                    self.cache.pop(&tile_id);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if let Some(image) = image {
+                let reloaded = self.upload(image);
+                if let Some(cached) = self.cache.peek_mut(&tile_id) {
+                    *cached = reloaded;
+                }
+            }
+        }
+    }
+
+    /// Upload the image into the atlas, or a texture of its own if it does not fit there.
+    fn upload(&mut self, image: ColorImage) -> CachedTile {
+        if let Some(tile) = self.atlas.as_mut().and_then(|atlas| atlas.insert(&image)) {
+            return CachedTile::InAtlas(tile, self.keep_images.then_some(image));
+        }
+
+        let kept = self.keep_images.then(|| image.clone());
+        CachedTile::Valid(
+            Texture::from_color_image_with_filter(image, &self.egui_ctx, self.texture_filter),
+            kept,
+        )
+    }
+
+    fn send_control(&self, request: Request) {
+        if self.control_tx.unbounded_send(request).is_err() {
+            log::error!("IO thread is dead");
+        }
+    }
+
+    /// Put the tile in the cache, releasing whatever it replaced or evicted.
+    fn put(&mut self, tile_id: TileId, cached: CachedTile) {
+        if let Some((_, evicted)) = self.cache.push(tile_id, cached) {
+            self.release(evicted);
+        }
+    }
+
+    fn release(&mut self, cached: CachedTile) {
+        if let (CachedTile::InAtlas(tile, _), Some(atlas)) = (cached, self.atlas.as_mut()) {
+            atlas.release(tile.slot);
+        }
+    }
+
+    /// Whether loading of the tile has finished, either with something to show or not. Tiles
+    /// loaded for a different `{time}` do not count.
+    pub fn loaded(&self, tile_id: TileId) -> bool {
+        !self.stale.contains(&tile_id)
+            && matches!(
+                self.cache.peek(&tile_id),
+                Some(CachedTile::Valid(..) | CachedTile::InAtlas(..) | CachedTile::Missing)
+            )
+    }
+
+    /// Whether loading of the tile has finished, but there is nothing to show.
+    pub fn missing(&self, tile_id: TileId) -> bool {
+        matches!(self.cache.peek(&tile_id), Some(CachedTile::Missing))
+    }
 }
@@ -16,6 +16,7 @@ pub struct LocalTiles {
     path: PathBuf,
     egui_ctx: egui::Context,
     cache: LruCache<TileId, CachedTexture>,
+    texture_filter: egui::TextureFilter,
 }
 
 impl LocalTiles {
@@ -28,13 +29,22 @@ impl LocalTiles {
             path: path.as_ref().into(),
             egui_ctx,
             cache: LruCache::new(cache_size),
+            texture_filter: egui::TextureFilter::Linear,
         }
     }
 
+    /// Sample tiles using the given filter when drawn scaled. Defaults to
+    /// [`egui::TextureFilter::Linear`]; use [`egui::TextureFilter::Nearest`] for a crisp,
+    /// pixelated look. It applies to textures created from now on, as tiles are loaded.
+    pub fn with_texture_filter(mut self, texture_filter: egui::TextureFilter) -> Self {
+        self.texture_filter = texture_filter;
+        self
+    }
+
     fn load_and_cache(&mut self, tile_id: TileId) -> CachedTexture {
         self.cache
             .get_or_insert(tile_id, || {
-                match load(&self.path, tile_id, &self.egui_ctx) {
+                match load(&self.path, tile_id, self.texture_filter, &self.egui_ctx) {
                     Ok(texture) => CachedTexture::Valid(texture),
                     Err(err) => {
                         trace!("Failed to load tile {tile_id:?}: {err}");
@@ -69,11 +79,21 @@ impl Tiles for LocalTiles {
     fn tile_size(&self) -> u32 {
         256
     }
+
+    fn missing(&self, tile_id: TileId) -> bool {
+        matches!(self.cache.peek(&tile_id), Some(CachedTexture::Invalid))
+    }
+
+    /// Tiles are read from the disk again.
+    fn reload_textures(&mut self) {
+        self.cache.clear();
+    }
 }
 
 fn load(
     tiles_dir: &Path,
     tile_id: TileId,
+    texture_filter: egui::TextureFilter,
     egui_ctx: &egui::Context,
 ) -> Result<Texture, Box<dyn std::error::Error>> {
     let path = PathBuf::from_iter(&[
@@ -83,5 +103,5 @@ fn load(
         format!("{}.png", tile_id.y).into(),
     ]);
     let bytes = std::fs::read(path)?;
-    Ok(Texture::new(&bytes, egui_ctx)?)
+    Ok(Texture::with_filter(&bytes, egui_ctx, texture_filter)?)
 }
@@ -1,14 +1,27 @@
 use egui::{
-    DragPanButtons, InnerResponse, PointerButton, Response, Sense, Ui, UiBuilder, Vec2, Widget,
+    Align, Align2, Color32, DragPanButtons, Event, FontId, Frame, Image, InnerResponse, Layout,
+    Painter, PointerButton, Pos2, Response, RichText, Sense, Stroke, StrokeKind, TouchPhase, Ui,
+    UiBuilder, Vec2, Widget, response::Flags,
 };
 
+use std::{ops::RangeInclusive, time::Duration};
+
 use crate::{
-    MapMemory, Position, Projector, Tiles, center::Center, position::AdjustedPosition,
-    tiles::draw_tiles,
+    MapMemory, Position, Projector, Tiles,
+    center::Center,
+    memory::{ZOOM_SNAPPING_DELAY, ZoomAnimation},
+    position::AdjustedPosition,
+    sources::Attribution,
+    tiles::{Coverage, draw_tiles},
 };
 
 /// Plugins allow drawing custom shapes on the map. After implementing this trait for your type,
 /// you can add it to the map with [`Map::with_plugin`]
+///
+/// Everything a plugin gets is a part of the public API: [`Projector`] to convert between
+/// geographical [`crate::Position`]s and screen coordinates, read-only [`MapMemory`], and egui's
+/// [`Ui`], [`Painter`] and [`Response`]. Plugins do not need anything else from this crate,
+/// so they can live in separate crates.
 pub trait Plugin {
     /// Function called at each frame.
     ///
@@ -17,31 +30,120 @@ pub trait Plugin {
     /// compute target screen coordinates and use one of the various egui methods to draw at these
     /// coordinates instead of relying on [`Ui`] layout system.
     ///
+    /// The provided [`Painter`], as well as the [`Ui`], is clipped to the map widget's rect, or
+    /// the part of it set with [`Map::with_clip_rect`], so shapes drawn with it never spill over
+    /// other widgets.
+    ///
     /// The provided [`Response`] is the response of the map widget itself and can be used to test
-    /// if the mouse is hovering or clicking on the map.
+    /// if the mouse is hovering or clicking on the map. If a plugin above this one takes the
+    /// pointer (see [`Plugin::takes_pointer`]), the response is neither hovered nor clicked.
+    ///
+    /// The provided [`MapMemory`] is the state of the map after handling user input in this frame.
+    ///
+    /// Plugins which animate on their own, e.g. a marker gliding between positions, should
+    /// schedule the next frame themselves with [`egui::Context::request_repaint`] or
+    /// [`egui::Context::request_repaint_after`], available via [`Ui::ctx`]. That way, the
+    /// application does not need to repaint continuously.
     fn run(
         self: Box<Self>,
         ui: &mut Ui,
+        painter: &Painter,
         response: &Response,
         projector: &Projector,
         map_memory: &MapMemory,
     );
+
+    /// Layer this plugin draws in. Plugins in lower layers are drawn first, so plugins in higher
+    /// layers appear on top of them. Within the same layer, plugins are drawn in the order they
+    /// were added.
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Markers
+    }
+
+    /// Whether the plugin has something interactive under the screen position, such as a marker,
+    /// and wants the pointer for itself. Before any plugin is run, the map asks them, from the
+    /// topmost down, and the first one which says so takes the pointer: plugins beneath it get a
+    /// [`Response`] which is neither hovered nor clicked, so a click on a marker does not also
+    /// select the polygon under it. Plugins above it are not affected. `false` by default.
+    fn takes_pointer(&self, _pos: Pos2, _projector: &Projector) -> bool {
+        false
+    }
+
+    /// Source of the data drawn by this plugin, which needs to be credited. It is shown along
+    /// with the tiles' attributions, see [`Map::with_attributions`].
+    fn attribution(&self) -> Option<Attribution> {
+        None
+    }
+}
+
+/// Draw order of [`Plugin`]s, from the bottom to the top.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PluginLayer {
+    /// Below the tiles, e.g. a sea fill showing through semi-transparent land tiles. Unlike the
+    /// other layers, these plugins are run before the tiles are drawn.
+    Background,
+
+    /// Shapes covering areas of the map, such as polygons, fills and lines.
+    Shapes,
+
+    /// Things placed at specific positions, such as markers and labels.
+    #[default]
+    Markers,
+
+    /// User interface elements which are not tied to the map's content, such as scale bars or
+    /// zoom controls.
+    Controls,
+}
+
+/// What the map did during the last frame. Collected only when enabled with
+/// [`Map::frame_stats`], and available afterwards via [`MapMemory::frame_stats`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FrameStats {
+    /// Tiles drawn, summed over the base tiles and all layers.
+    pub tiles_drawn: usize,
+
+    /// Tiles drawn from a part of a lower zoom level's tile, as the right one was not available.
+    /// These are included in [`FrameStats::tiles_drawn`].
+    pub tiles_upscaled: usize,
+
+    /// Visible tiles which were not drawn, because they are still being loaded.
+    pub placeholders: usize,
+
+    /// Visible tiles for which the source has no data.
+    pub tiles_missing: usize,
+
+    /// Time spent running all plugins.
+    pub plugins_time: Duration,
 }
 
 struct Layer<'a> {
     tiles: &'a mut dyn Tiles,
     transparency: f32,
+    clip_rect: Option<egui::Rect>,
 }
 
 struct Options {
     zoom_gesture_enabled: bool,
     drag_pan_buttons: DragPanButtons,
     zoom_speed: f64,
+    zoom_animation: Duration,
     double_click_to_zoom: bool,
     double_click_to_zoom_out: bool,
+    double_tap_to_zoom: bool,
     zoom_with_ctrl: bool,
     panning: bool,
+    pan_enabled: bool,
     pull_to_my_position_threshold: f32,
+    drag_threshold: f32,
+    empty_coverage_hint: Option<String>,
+    cooperative_gestures: bool,
+    zoom_range: Option<RangeInclusive<f64>>,
+    reduced_motion: bool,
+    attributions: Option<Align2>,
+    frame_stats: bool,
+    background: Option<Color32>,
+    tile_debug: bool,
+    clip_rect: Option<egui::Rect>,
 }
 
 impl Default for Options {
@@ -50,11 +152,24 @@ impl Default for Options {
             zoom_gesture_enabled: true,
             drag_pan_buttons: DragPanButtons::PRIMARY,
             zoom_speed: 2.0,
+            zoom_animation: Duration::ZERO,
             double_click_to_zoom: false,
             double_click_to_zoom_out: false,
+            double_tap_to_zoom: false,
             zoom_with_ctrl: true,
             panning: true,
+            pan_enabled: true,
             pull_to_my_position_threshold: 0.0,
+            drag_threshold: 8.0,
+            empty_coverage_hint: None,
+            cooperative_gestures: false,
+            zoom_range: None,
+            reduced_motion: false,
+            attributions: None,
+            frame_stats: false,
+            background: None,
+            tile_debug: false,
+            clip_rect: None,
         }
     }
 }
@@ -110,25 +225,77 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         self
     }
 
+    /// Add plugin like [`Map::with_plugin`], but fade everything it paints, with the [`Painter`]
+    /// or the [`Ui`] it gets, to given opacity, from `0.0`, invisible, to `1.0`, unchanged. Handy
+    /// for a layer opacity slider, without threading the opacity through the plugin's style.
+    pub fn with_plugin_opacity(self, plugin: impl Plugin + 'c, opacity: f32) -> Self {
+        self.with_plugin(WithOpacity { plugin, opacity })
+    }
+
     /// Add a tile layer. All layers are drawn on top of each other with given transparency.
     pub fn with_layer(mut self, tiles: &'b mut dyn Tiles, transparency: f32) -> Self {
         self.layers.push(Layer {
             tiles,
             transparency,
+            clip_rect: None,
+        });
+        self
+    }
+
+    /// Add a tile layer drawn only within the given rect, in screen coordinates, like the whole
+    /// map is with [`Map::with_clip_rect`]. Useful for comparing sources, see
+    /// [`crate::SwipeCompare`].
+    pub fn with_layer_clipped(
+        mut self,
+        tiles: &'b mut dyn Tiles,
+        transparency: f32,
+        clip_rect: egui::Rect,
+    ) -> Self {
+        self.layers.push(Layer {
+            tiles,
+            transparency,
+            clip_rect: Some(clip_rect),
         });
         self
     }
 
+    /// Add a tile layer which can be toggled, e.g. from a legend. Hidden layer is neither drawn nor
+    /// credited, and is not asked for tiles, so it does not start new downloads. As the [`Tiles`]
+    /// live outside of the map, their cache is kept and showing the layer again is instant.
+    pub fn with_layer_visible(
+        self,
+        tiles: &'b mut dyn Tiles,
+        transparency: f32,
+        visible: bool,
+    ) -> Self {
+        if visible {
+            self.with_layer(tiles, transparency)
+        } else {
+            self
+        }
+    }
+
+    /// Like [`Map::with_layer_visible`], but whether the layer is visible is looked up by its id
+    /// in the [`MapMemory`], so that a legend can toggle it with
+    /// [`MapMemory::set_layer_visible`].
+    pub fn with_layer_id(self, id: &str, tiles: &'b mut dyn Tiles, transparency: f32) -> Self {
+        let visible = self.memory.layer_visible(id);
+        self.with_layer_visible(tiles, transparency, visible)
+    }
+
     /// Set whether map should perform zoom gesture.
     ///
     /// Zoom is typically triggered by the mouse wheel while holding <kbd>ctrl</kbd> key on native
-    /// and web, and by pinch gesture on Android.
+    /// and web, and by pinch gesture on Android. When disabled, the user cannot zoom at all, also
+    /// by double clicking, which together with [`Map::with_pan_enabled`] allows locking the view.
     pub fn zoom_gesture(mut self, enabled: bool) -> Self {
         self.options.zoom_gesture_enabled = enabled;
         self
     }
 
-    /// Specify which pointer buttons can be used to pan by clicking and dragging.
+    /// Specify which pointer buttons can be used to pan by clicking and dragging. Primary by
+    /// default. Using a different one, e.g. [`DragPanButtons::MIDDLE`], leaves the primary button
+    /// to plugins, such as ones drawing on the map.
     pub fn drag_pan_buttons(mut self, buttons: DragPanButtons) -> Self {
         self.options.drag_pan_buttons = buttons;
         self
@@ -141,6 +308,16 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         self
     }
 
+    /// Glide to the zoom level picked by scrolling in roughly given time, instead of jumping at
+    /// each wheel notch. Notches scrolled in the meantime add up to the level being glided to,
+    /// and the location under the pointer stays in place. Pinching is never animated, neither is
+    /// anything when motion is reduced (see [`Map::reduced_motion`]). Disabled, i.e. zero, by
+    /// default.
+    pub fn zoom_animation(mut self, duration: Duration) -> Self {
+        self.options.zoom_animation = duration;
+        self
+    }
+
     /// Set whether to enable double click primary mouse button to zoom
     pub fn double_click_to_zoom(mut self, enabled: bool) -> Self {
         self.options.double_click_to_zoom = enabled;
@@ -153,6 +330,14 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         self
     }
 
+    /// Set whether two quick taps at nearly the same spot zoom in by one level towards it, like
+    /// [`Map::double_click_to_zoom`] does with the mouse. Taps made while another finger touches
+    /// the screen, e.g. during a pinch, are not counted. Disabled by default.
+    pub fn double_tap_to_zoom(mut self, enabled: bool) -> Self {
+        self.options.double_tap_to_zoom = enabled;
+        self
+    }
+
     /// Sets the zoom behaviour
     ///
     /// When enabled zoom is done with mouse wheel while holding <kbd>ctrl</kbd> key on native
@@ -167,6 +352,16 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         self
     }
 
+    /// Set whether the user can move the map around, by dragging, scrolling or with touch
+    /// gestures. When disabled, zooming keeps the center in place, instead of the place under the
+    /// pointer, so the map cannot be moved away from it, e.g. for a constrained embed. Enabled by
+    /// default. Moving the map programmatically, e.g. with [`MapMemory::center_at`], still works.
+    /// Zooming can be disabled likewise with [`Map::zoom_gesture`].
+    pub fn with_pan_enabled(mut self, enabled: bool) -> Self {
+        self.options.pan_enabled = enabled;
+        self
+    }
+
     /// Set if we can pan with mouse wheel.
     /// By default, panning is disabled when zooming with ctrl is disabled.
     /// Allow to disable panning even when zooming with ctrl is enabled.
@@ -184,6 +379,110 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         self
     }
 
+    /// Set how far, in points, the pointer needs to move while pressed before the map starts
+    /// panning. Shorter drags, e.g. a finger jittering while tapping a marker, count as clicks,
+    /// which plugins see via [`Response::clicked`]. Defaults to 8. Egui itself does not tell a
+    /// drag from a click below its [`egui::InputOptions::max_click_dist`], 6 by default.
+    pub fn drag_threshold(mut self, threshold: f32) -> Self {
+        self.options.drag_threshold = threshold;
+        self
+    }
+
+    /// Show a message in the middle of the map when tile sources have no data for the whole
+    /// viewport, e.g. because all tiles were responded with 404. It is not shown while tiles are
+    /// still being loaded.
+    pub fn with_empty_coverage_hint(mut self, text: impl Into<String>) -> Self {
+        self.options.empty_coverage_hint = Some(text.into());
+        self
+    }
+
+    /// Show [`Map::attributions`] in the given corner of the map. By default, they are not shown,
+    /// and it is up to the application to credit the sources.
+    pub fn with_attributions(mut self, anchor: Align2) -> Self {
+        self.options.attributions = Some(anchor);
+        self
+    }
+
+    /// Attributions of the tiles, all the layers and plugins, in this order, without duplicates.
+    pub fn attributions(&self) -> Vec<Attribution> {
+        let mut attributions: Vec<Attribution> = Vec::new();
+        let tiles = self.tiles.iter().map(|tiles| tiles.attribution());
+        let layers = self.layers.iter().map(|layer| layer.tiles.attribution());
+        let plugins = self
+            .plugins
+            .iter()
+            .filter_map(|plugin| plugin.attribution());
+
+        for attribution in tiles.chain(layers).chain(plugins) {
+            if !attributions
+                .iter()
+                .any(|a| a.text == attribution.text && a.url == attribution.url)
+            {
+                attributions.push(attribution);
+            }
+        }
+        attributions
+    }
+
+    /// Enable cooperative gestures, useful when the map is embedded in a scrolling page.
+    ///
+    /// Plain mouse wheel does not pan nor zoom the map, so that it can scroll the page instead.
+    /// Zooming requires holding <kbd>ctrl</kbd> (<kbd>cmd</kbd> on Mac), and a hint telling
+    /// that is shown for a moment when the user scrolls without it. Takes precedence over
+    /// [`Map::zoom_with_ctrl`] and [`Map::panning`]. Has no effect on touch gestures.
+    pub fn with_cooperative_gestures(mut self, enabled: bool) -> Self {
+        self.options.cooperative_gestures = enabled;
+        self
+    }
+
+    /// Limit zoom to the given range. By default, zoom is limited to what the tile sources
+    /// support, as told by [`Tiles::zoom_range`].
+    pub fn zoom_range(mut self, range: RangeInclusive<f64>) -> Self {
+        self.options.zoom_range = Some(range);
+        self
+    }
+
+    /// Fill the map with given color before drawing tiles, so that areas which are still loading,
+    /// and transparent parts of tiles, show it instead of whatever is behind the widget.
+    pub fn with_background(mut self, color: Color32) -> Self {
+        self.options.background = Some(color);
+        self
+    }
+
+    /// Collect [`FrameStats`], such as the number of tiles drawn and time spent in plugins, which
+    /// can be read with [`MapMemory::frame_stats`] after the map is shown. Disabled by default,
+    /// as measuring has its own cost.
+    pub fn frame_stats(mut self, enabled: bool) -> Self {
+        self.options.frame_stats = enabled;
+        self
+    }
+
+    /// Outline every visible tile and label it with its `zoom/x/y`, highlighting the one under
+    /// the pointer, to debug tile sources. Disabled by default.
+    pub fn with_tile_debug(mut self, enabled: bool) -> Self {
+        self.options.tile_debug = enabled;
+        self
+    }
+
+    /// Draw only the part of the map within the given rect, in screen coordinates, e.g. for
+    /// picture-in-picture, or to compare two maps side by side with a divider. The map is still
+    /// laid out, centered and controlled within the whole widget, so maps sharing a
+    /// [`MapMemory`] line up. Tiles outside the rect are not even requested, and plugins get
+    /// a [`Projector::clipped`] to it.
+    pub fn with_clip_rect(mut self, rect: egui::Rect) -> Self {
+        self.options.clip_rect = Some(rect);
+        self
+    }
+
+    /// Reduce motion, e.g. to respect the "prefers reduced motion" setting of the operating
+    /// system. When enabled, the map stops as soon as it is released instead of gliding due to
+    /// inertia, jumps straight back to `my_position` instead of being pulled to it, and zoom
+    /// snaps without an animation. Plugins can follow it with [`MapMemory::reduced_motion`].
+    pub fn reduced_motion(mut self, enabled: bool) -> Self {
+        self.options.reduced_motion = enabled;
+        self
+    }
+
     /// Show the map widget inside a [`egui::Ui`].
     pub fn show<R>(
         mut self,
@@ -193,8 +492,70 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         let (rect, mut response) =
             ui.allocate_exact_size(ui.available_size(), Sense::click_and_drag());
 
+        self.memory.reduced_motion = self.options.reduced_motion;
+        let zoom_before = self.memory.zoom();
         let mut changed = self.handle_gestures(ui, &response);
+
+        if let Some((press, released)) = released_press(ui, &response) {
+            if press.zoomed {
+                // Pinching is never a click, even if the fingers hardly moved.
+                response.flags.remove(Flags::CLICKED);
+            } else if response.drag_stopped_by(PointerButton::Primary)
+                && press.origin.distance(released) < self.options.drag_threshold
+            {
+                // Released within the drag threshold, so it was a click after all, even though
+                // egui saw a drag.
+                response.flags.insert(Flags::FAKE_PRIMARY_CLICKED);
+            }
+        }
+
+        if changed {
+            // User takes over.
+            self.memory.flight = None;
+        }
         let delta_time = ui.ctx().input(|reader| reader.stable_dt);
+        changed |= self
+            .memory
+            .update_zoom_animation(delta_time, self.options.zoom_animation);
+        self.memory.zooming = self.memory.zoom() != zoom_before;
+
+        // Snap only once the user is done zooming: fingers are lifted, or the wheel was not
+        // scrolled for a moment.
+        let (now, touching) = ui.input(|i| (i.time, i.any_touches()));
+        if touching {
+            self.memory.zoomed_at = None;
+        } else if self.memory.zooming {
+            self.memory.zoomed_at = Some(now);
+        }
+        let quiet_for = self.memory.zoomed_at.map_or(f64::INFINITY, |at| now - at);
+        if !touching && quiet_for >= ZOOM_SNAPPING_DELAY {
+            self.memory.zoomed_at = None;
+            let zoom_range = self.effective_zoom_range();
+            changed |= self.memory.update_zoom_snapping(
+                delta_time,
+                self.options.reduced_motion,
+                zoom_range,
+            );
+        } else if self.memory.zoom_snapping.is_some() && !touching {
+            ui.ctx()
+                .request_repaint_after(Duration::from_secs_f64(ZOOM_SNAPPING_DELAY - quiet_for));
+        }
+
+        changed |=
+            self.memory
+                .update_flight(delta_time, self.my_position, self.options.reduced_motion);
+        changed |= self.memory.update_fit(rect.size());
+
+        if let Some(range) = self.effective_zoom_range() {
+            self.memory.zoom.clamp(&range);
+        }
+
+        changed |= self.memory.update_screen_point(rect.center());
+        changed |= self.memory.update_pan(self.my_position);
+
+        // Map repeats horizontally, so keep the longitude within the range of the "main" world.
+        self.memory.center_mode = self.memory.center_mode.clone().wrap_longitude();
+
         let zoom = self.memory.zoom;
         changed |= self
             .memory
@@ -207,26 +568,134 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         }
 
         let map_center = self.position();
-        let painter = ui.painter().with_clip_rect(rect);
+
+        // Plugins and layers are consumed below, so collect these beforehand.
+        let attributions = self
+            .options
+            .attributions
+            .map(|anchor| (anchor, self.attributions()));
+        let tile_debug = self.options.tile_debug.then(|| {
+            self.tiles
+                .as_deref()
+                .or_else(|| self.layers.first().map(|layer| &*layer.tiles))
+                .map_or(256, |tiles| tiles.tile_size())
+        });
+        let clip_rect = self
+            .options
+            .clip_rect
+            .map_or(rect, |clip_rect| clip_rect.intersect(rect));
+        let painter = ui.painter().with_clip_rect(clip_rect);
+        let mut coverage = Coverage::default();
+
+        if let Some(background) = self.options.background {
+            painter.rect_filled(rect, 0., background);
+        }
+
+        // Plugins are run from the bottom layer to the top. Sort is stable, so order in which
+        // plugins were added is kept within the layer. Background ones go below the tiles.
+        let projector =
+            Projector::new(response.rect, self.memory, self.my_position).clipped(clip_rect);
+        let mut plugins: Vec<_> = self.plugins.into_iter().enumerate().collect();
+        plugins.sort_by_key(|(_, plugin)| plugin.layer());
+
+        // Topmost plugin which takes the pointer hides it from the ones beneath.
+        let pointer_owner = response.hover_pos().and_then(|pos| {
+            plugins
+                .iter()
+                .rposition(|(_, plugin)| plugin.takes_pointer(pos, &projector))
+        });
+        let covered_response = without_pointer(&response);
+        let (background_plugins, plugins): (Vec<_>, Vec<_>) = plugins
+            .into_iter()
+            .enumerate()
+            .map(|(order, (idx, plugin))| {
+                let covered = pointer_owner.is_some_and(|owner| order < owner);
+                (idx, covered, plugin)
+            })
+            .partition(|(_, _, plugin)| plugin.layer() == PluginLayer::Background);
+
+        let mut plugins_time = Duration::ZERO;
+        let plugins_start = self.options.frame_stats.then(web_time::Instant::now);
+        run_plugins(
+            background_plugins,
+            ui,
+            rect,
+            &painter,
+            [&response, &covered_response],
+            &projector,
+            self.memory,
+        );
+        if let Some(start) = plugins_start {
+            plugins_time += start.elapsed();
+        }
 
         if let Some(tiles) = self.tiles {
-            draw_tiles(&painter, map_center, zoom, tiles, 1.0);
+            coverage += draw_tiles(&painter, rect.center(), map_center, zoom, tiles, 1.0);
         }
 
         for layer in self.layers {
-            draw_tiles(&painter, map_center, zoom, layer.tiles, layer.transparency);
+            let painter = layer
+                .clip_rect
+                .map_or_else(|| painter.clone(), |rect| painter.with_clip_rect(rect));
+            coverage += draw_tiles(
+                &painter,
+                rect.center(),
+                map_center,
+                zoom,
+                layer.tiles,
+                layer.transparency,
+            );
         }
 
-        // Run plugins.
-        let projector = Projector::new(response.rect, self.memory, self.my_position);
-        for (idx, plugin) in self.plugins.into_iter().enumerate() {
-            let mut child_ui = ui.new_child(UiBuilder::new().max_rect(rect).id_salt(idx));
-            plugin.run(&mut child_ui, &response, &projector, self.memory);
+        if let Some(hint) = &self.options.empty_coverage_hint {
+            if coverage.empty() {
+                painter.text(
+                    clip_rect.center(),
+                    Align2::CENTER_CENTER,
+                    hint,
+                    FontId::proportional(16.),
+                    ui.visuals().weak_text_color(),
+                );
+            }
+        }
+
+        if let Some(tile_size) = tile_debug {
+            draw_tile_debug(&painter, &projector, tile_size, response.hover_pos());
         }
 
+        let plugins_start = self.options.frame_stats.then(web_time::Instant::now);
+        run_plugins(
+            plugins,
+            ui,
+            rect,
+            &painter,
+            [&response, &covered_response],
+            &projector,
+            self.memory,
+        );
+
+        self.memory.frame_stats = plugins_start.map(|start| FrameStats {
+            tiles_drawn: coverage.drawn,
+            tiles_upscaled: coverage.upscaled,
+            placeholders: coverage
+                .visible
+                .saturating_sub(coverage.drawn)
+                .saturating_sub(coverage.missing),
+            tiles_missing: coverage.missing,
+            plugins_time: plugins_time + start.elapsed(),
+        });
+
         let mut child_ui = ui.new_child(UiBuilder::new().max_rect(rect).id_salt("inner"));
         let inner = add_contents(&mut child_ui, &response, &projector, self.memory);
 
+        if let Some((anchor, attributions)) = attributions {
+            draw_attributions(ui, clip_rect, anchor, &attributions);
+        }
+
+        if self.options.cooperative_gestures {
+            draw_cooperative_gestures_hint(ui, &painter, &response);
+        }
+
         InnerResponse { inner, response }
     }
 }
@@ -234,59 +703,96 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
 impl Map<'_, '_, '_> {
     /// Handle user inputs and recalculate everything accordingly. Returns whether something changed.
     fn handle_gestures(&mut self, ui: &mut Ui, response: &Response) -> bool {
-        let zoom_delta = self.zoom_delta(ui, response);
+        let double_tap = double_tap(ui, response).filter(|_| self.options.double_tap_to_zoom);
+        let zoom_delta = match double_tap {
+            // Exactly one level, whatever the zoom speed.
+            Some(_) => 1. + 1. / self.options.zoom_speed,
+            None => self.zoom_delta(ui, response),
+        };
 
         // Zooming and dragging need to be exclusive, otherwise the map will get dragged when
         // pinch gesture is used.
         let changed = if (zoom_delta - 1.0).abs() > 0.001
-            && ui.ui_contains_pointer()
+            && (double_tap.is_some() || ui.ui_contains_pointer())
             && self.options.zoom_gesture_enabled
         {
             // Displacement of mouse pointer relative to widget center
-            let offset = input_offset(ui, response);
+            let offset = match double_tap {
+                Some(pos) => Some(pos - response.rect.center()),
+                None => input_offset(ui, response),
+            };
 
             // While zooming, we want to keep the location under the mouse pointer fixed on the
-            // screen. To achieve this, we first move the location to the widget's center,
-            // then adjust zoom level, finally move the location back to the original screen
-            // position.
-            if let Some(offset) = offset {
-                // If map is tracking `my_position` and the input offset is close, just let it be.
-                if self.memory.detached().is_some()
-                    || offset.length() > self.options.pull_to_my_position_threshold
-                {
-                    self.memory.center_mode = Center::Exact(
-                        AdjustedPosition::new(self.position()).shift(-offset, self.memory.zoom()),
-                    );
-                }
-            }
+            // screen. To achieve this, the location is found before changing the zoom, and then
+            // the center is placed relative to it, once the final zoom is known. If map is
+            // tracking `my_position` and the input offset is close, just let it be.
+            let anchor = offset
+                .filter(|_| self.options.pan_enabled)
+                .filter(|offset| {
+                    self.memory.detached().is_some()
+                        || offset.length() > self.options.pull_to_my_position_threshold
+                })
+                .map(|offset| {
+                    let position = AdjustedPosition::new(self.position())
+                        .shift(-offset, self.memory.zoom())
+                        .position();
+                    (position, offset)
+                });
 
             // Shift by 1 because of the values given by zoom_delta(). Multiple by zoom_speed(defaults to 2.0),
             // because then it felt right with both mouse wheel, and an Android phone.
-            self.memory
-                .zoom
-                .zoom_by((zoom_delta - 1.) * self.options.zoom_speed);
+            let zoom_by = (zoom_delta - 1.) * self.options.zoom_speed;
 
-            if let Some(offset) = offset {
-                self.memory.center_mode = self
+            if self.animates_zoom(ui) && double_tap.is_none() {
+                // Scrolling while the animation is still going moves its target further.
+                let mut target = self
                     .memory
-                    .center_mode
-                    .clone()
-                    .shift(offset, self.memory.zoom());
+                    .zoom_animation_target()
+                    .unwrap_or(self.memory.zoom);
+                target.zoom_by(zoom_by);
+                if let Some(range) = self.effective_zoom_range() {
+                    target.clamp(&range);
+                }
+
+                self.memory.zoom_animation = Some(ZoomAnimation {
+                    target,
+                    zoom: self.memory.zoom(),
+                    anchor,
+                });
+            } else {
+                self.memory.zoom.zoom_by(zoom_by);
+
+                // Clamp before placing the center, otherwise it would be placed for a zoom which
+                // is never shown, making the map slide away from the pointer.
+                if let Some(range) = self.effective_zoom_range() {
+                    self.memory.zoom.clamp(&range);
+                }
+
+                if let Some((position, offset)) = anchor {
+                    self.memory.center_mode = Center::Exact(
+                        AdjustedPosition::new(position).shift(offset, self.memory.zoom()),
+                    );
+                }
             }
 
             true
-        } else {
+        } else if self.options.pan_enabled && !self.within_drag_threshold(ui) {
             self.memory.center_mode.handle_gestures(
                 response,
                 self.my_position,
                 self.options.pull_to_my_position_threshold,
                 self.options.drag_pan_buttons,
+                self.options.reduced_motion,
             )
+        } else {
+            false
         };
 
         // Only enable panning with mouse_wheel if we are zooming with ctrl. But always allow touch devices to pan
-        let panning_enabled =
-            self.options.panning && (ui.input(|i| i.any_touches()) || self.options.zoom_with_ctrl);
+        let panning_enabled = self.options.pan_enabled
+            && self.options.panning
+            && (ui.input(|i| i.any_touches())
+                || (self.options.zoom_with_ctrl && !self.options.cooperative_gestures));
 
         if ui.ui_contains_pointer() && panning_enabled {
             // Panning by scrolling, e.g. two-finger drag on a touchpad:
@@ -301,6 +807,26 @@ impl Map<'_, '_, '_> {
         changed
     }
 
+    /// Whether zoom changes are animated in this frame, see [`Map::zoom_animation`].
+    fn animates_zoom(&self, ui: &Ui) -> bool {
+        !self.options.zoom_animation.is_zero()
+            && !self.options.reduced_motion
+            && !ui.input(|input| input.any_touches())
+    }
+
+    /// Whether the pointer is pressed, but has not moved far enough to start panning yet. Once
+    /// the map is panning, it keeps doing so, even if the pointer gets back.
+    fn within_drag_threshold(&self, ui: &Ui) -> bool {
+        !self.memory.dragging()
+            && ui.input(|input| {
+                input
+                    .pointer
+                    .press_origin()
+                    .zip(input.pointer.interact_pos())
+                    .is_some_and(|(origin, pos)| origin.distance(pos) < self.options.drag_threshold)
+            })
+    }
+
     /// Calculate the zoom delta based on the input.
     fn zoom_delta(&self, ui: &mut Ui, response: &Response) -> f64 {
         let mut zoom_delta = ui.input(|input| input.zoom_delta()) as f64;
@@ -319,7 +845,7 @@ impl Map<'_, '_, '_> {
             zoom_delta = 0.0;
         }
 
-        if !self.options.zoom_with_ctrl && zoom_delta == 1.0 {
+        if !self.options.zoom_with_ctrl && !self.options.cooperative_gestures && zoom_delta == 1.0 {
             // We only use the raw scroll values, if we are zooming without ctrl,
             // and zoom_delta is not already over/under 1.0 (eg. a ctrl + scroll event or a pinch zoom)
             // These values seem to correspond to the same values as one would get in `zoom_delta()`
@@ -333,6 +859,20 @@ impl Map<'_, '_, '_> {
         zoom_delta
     }
 
+    /// Zoom range set by the user or, if not set, the one which any of the tile sources support.
+    fn effective_zoom_range(&self) -> Option<RangeInclusive<f64>> {
+        if let Some(range) = &self.options.zoom_range {
+            return Some(range.clone());
+        }
+
+        self.tiles
+            .iter()
+            .map(|tiles| tiles.zoom_range())
+            .chain(self.layers.iter().map(|layer| layer.tiles.zoom_range()))
+            .reduce(|a, b| *a.start().min(b.start())..=*a.end().max(b.end()))
+            .map(|range| *range.start() as f64..=*range.end() as f64)
+    }
+
     /// Get the real position at the map's center.
     fn position(&self) -> Position {
         self.memory.center_mode.position(self.my_position)
@@ -345,6 +885,315 @@ impl Widget for Map<'_, '_, '_> {
     }
 }
 
+/// For how long the cooperative gestures hint stays visible after the last plain scroll.
+const COOPERATIVE_GESTURES_HINT_DURATION: f64 = 1.5;
+
+/// Run the plugins, each tagged with whether a plugin above it took the pointer, in which case it
+/// gets the second of the `responses`.
+fn run_plugins(
+    plugins: Vec<(usize, bool, Box<dyn Plugin + '_>)>,
+    ui: &mut Ui,
+    rect: egui::Rect,
+    painter: &Painter,
+    [response, covered_response]: [&Response; 2],
+    projector: &Projector,
+    memory: &MapMemory,
+) {
+    for (idx, covered, plugin) in plugins {
+        let mut child_ui = ui.new_child(UiBuilder::new().max_rect(rect).id_salt(idx));
+        child_ui.set_clip_rect(painter.clip_rect());
+        let response = if covered { covered_response } else { response };
+        plugin.run(&mut child_ui, painter, response, projector, memory);
+    }
+}
+
+/// Plugin with everything it paints faded, see [`Map::with_plugin_opacity`].
+struct WithOpacity<P> {
+    plugin: P,
+    opacity: f32,
+}
+
+impl<P: Plugin> Plugin for WithOpacity<P> {
+    fn run(
+        self: Box<Self>,
+        ui: &mut Ui,
+        painter: &Painter,
+        response: &Response,
+        projector: &Projector,
+        map_memory: &MapMemory,
+    ) {
+        let mut painter = painter.clone();
+        painter.multiply_opacity(self.opacity);
+        ui.multiply_opacity(self.opacity);
+        Box::new(self.plugin).run(ui, &painter, response, projector, map_memory);
+    }
+
+    fn layer(&self) -> PluginLayer {
+        self.plugin.layer()
+    }
+
+    fn takes_pointer(&self, pos: Pos2, projector: &Projector) -> bool {
+        self.plugin.takes_pointer(pos, projector)
+    }
+
+    fn attribution(&self) -> Option<Attribution> {
+        self.plugin.attribution()
+    }
+}
+
+/// Map's response as seen by plugins beneath the one which took the pointer.
+fn without_pointer(response: &Response) -> Response {
+    let mut response = response.clone();
+    response.flags.remove(
+        Flags::CONTAINS_POINTER
+            | Flags::HOVERED
+            | Flags::CLICKED
+            | Flags::FAKE_PRIMARY_CLICKED
+            | Flags::LONG_TOUCHED,
+    );
+    response
+}
+
+/// Stack the attributions in the corner of the map, each linking to its source.
+fn draw_attributions(ui: &mut Ui, rect: egui::Rect, anchor: Align2, attributions: &[Attribution]) {
+    if attributions.is_empty() {
+        return;
+    }
+
+    let [horizontal, vertical] = anchor.0;
+    let bottom_up = vertical == Align::Max;
+    let layout = if bottom_up {
+        Layout::bottom_up(horizontal)
+    } else {
+        Layout::top_down(horizontal)
+    };
+
+    let mut child_ui = ui.new_child(
+        UiBuilder::new()
+            .max_rect(rect.shrink(4.))
+            .layout(layout)
+            .id_salt("attributions"),
+    );
+
+    Frame::new()
+        .fill(child_ui.visuals().window_fill.gamma_multiply(0.8))
+        .corner_radius(4.)
+        .inner_margin(4.)
+        .show(&mut child_ui, |ui| {
+            // In bottom-up layout, the first one added ends up at the bottom.
+            let ordered: Box<dyn Iterator<Item = &Attribution>> = if bottom_up {
+                Box::new(attributions.iter().rev())
+            } else {
+                Box::new(attributions.iter())
+            };
+
+            for attribution in ordered {
+                ui.horizontal(|ui| {
+                    let logo = if ui.visuals().dark_mode {
+                        &attribution.logo_dark
+                    } else {
+                        &attribution.logo_light
+                    };
+                    if let Some(logo) = logo {
+                        ui.add(Image::new(logo.clone()).max_height(16.));
+                    }
+                    ui.hyperlink_to(RichText::new(attribution.text).small(), attribution.url);
+                });
+            }
+        });
+}
+
+/// Outline and label the visible tiles, highlighting the one under the pointer.
+fn draw_tile_debug(
+    painter: &Painter,
+    projector: &Projector,
+    tile_size: u32,
+    pointer: Option<Pos2>,
+) {
+    let color = Color32::from_rgb(255, 0, 255);
+    for (tile_id, rect) in projector.visible_tiles(tile_size) {
+        if pointer.is_some_and(|pointer| rect.contains(pointer)) {
+            painter.rect(
+                rect,
+                0.,
+                color.gamma_multiply(0.2),
+                Stroke::new(3., color),
+                StrokeKind::Inside,
+            );
+        } else {
+            painter.rect_stroke(rect, 0., Stroke::new(1., color), StrokeKind::Inside);
+        }
+
+        // Keep the label on the screen, even if most of the tile is not.
+        let label = painter.clip_rect().intersect(rect).left_top() + Vec2::splat(4.);
+        painter.text(
+            label,
+            Align2::LEFT_TOP,
+            format!("{}/{}/{}", tile_id.zoom, tile_id.x, tile_id.y),
+            FontId::monospace(12.),
+            color,
+        );
+    }
+}
+
+/// Show "Use Ctrl + scroll to zoom" hint for a moment after user scrolled over the map without
+/// holding the zoom modifier. See [`cooperative_gestures_hint`].
+fn draw_cooperative_gestures_hint(ui: &Ui, painter: &Painter, response: &Response) {
+    let id = response.id.with("cooperative_gestures_hint");
+    let zoom_modifier = ui
+        .ctx()
+        .options(|options| options.input_options.zoom_modifier);
+    let (now, plain_scroll) = ui.input(|input| {
+        (
+            input.time,
+            input.raw_scroll_delta != Vec2::ZERO && !input.modifiers.matches_any(zoom_modifier),
+        )
+    });
+
+    if plain_scroll && response.contains_pointer() {
+        ui.data_mut(|data| data.insert_temp(id, now));
+    }
+
+    let Some(since) = ui.data(|data| data.get_temp::<f64>(id)) else {
+        return;
+    };
+
+    let elapsed = now - since;
+    if elapsed > COOPERATIVE_GESTURES_HINT_DURATION {
+        ui.data_mut(|data| data.remove::<f64>(id));
+        return;
+    }
+
+    // Fade out during the last third.
+    let opacity = ((COOPERATIVE_GESTURES_HINT_DURATION - elapsed)
+        / (COOPERATIVE_GESTURES_HINT_DURATION / 3.))
+        .min(1.) as f32;
+
+    let rect = painter.clip_rect();
+    painter.rect_filled(
+        rect,
+        0.,
+        Color32::from_black_alpha(128).gamma_multiply(opacity),
+    );
+    painter.text(
+        rect.center(),
+        Align2::CENTER_CENTER,
+        cooperative_gestures_hint(ui.ctx(), zoom_modifier),
+        FontId::proportional(20.),
+        Color32::WHITE.gamma_multiply(opacity),
+    );
+    ui.ctx().request_repaint();
+}
+
+/// Text of the cooperative gestures hint, naming the modifier the way the platform does, e.g.
+/// Cmd on macOS.
+fn cooperative_gestures_hint(ctx: &egui::Context, zoom_modifier: egui::Modifiers) -> String {
+    format!(
+        "Use {} + scroll to zoom",
+        ctx.format_modifiers(zoom_modifier)
+    )
+}
+
+/// Where the pointer was pressed, and whether the map was zoomed since. Kept in egui's memory.
+#[derive(Clone, Copy)]
+struct Press {
+    origin: Pos2,
+    zoomed: bool,
+}
+
+/// The press and where it was released, if the pointer was released in this frame.
+fn released_press(ui: &Ui, response: &Response) -> Option<(Press, Pos2)> {
+    let id = response.id.with("press");
+    let (origin, zooming) = ui.input(|input| {
+        (
+            input.pointer.press_origin(),
+            input.zoom_delta() != 1. || input.multi_touch().is_some(),
+        )
+    });
+    let press: Option<Press> = ui.data(|data| data.get_temp(id));
+
+    // egui forgets the origin in the frame the pointer is released, so it is kept here.
+    let Some(origin) = origin else {
+        ui.data_mut(|data| data.remove::<Press>(id));
+        return press.zip(ui.input(|input| input.pointer.interact_pos()));
+    };
+
+    let zoomed = zooming || press.is_some_and(|press| press.origin == origin && press.zoomed);
+    ui.data_mut(|data| data.insert_temp(id, Press { origin, zoomed }));
+    None
+}
+
+/// Touch being watched for becoming a tap, and the last tap, if recent. Kept in egui's memory.
+#[derive(Clone, Default)]
+struct Taps {
+    /// Number of fingers on the screen.
+    touches: usize,
+    /// Touch which started alone over the map, its start position and time.
+    candidate: Option<(egui::TouchId, Pos2, f64)>,
+    /// Position and time of the last tap.
+    last: Option<(Pos2, f64)>,
+}
+
+/// Position of the second tap, if the map was double tapped in this frame. Taps follow the same
+/// limits of time and distance as clicks do, see [`egui::InputOptions`].
+fn double_tap(ui: &Ui, response: &Response) -> Option<Pos2> {
+    let id = response.id.with("double_tap");
+    let options = ui.ctx().options(|options| options.input_options);
+    let mut taps: Taps = ui.data(|data| data.get_temp(id)).unwrap_or_default();
+    let mut double_tap = None;
+
+    ui.input(|input| {
+        for event in &input.events {
+            let Event::Touch { id, phase, pos, .. } = *event else {
+                continue;
+            };
+
+            match phase {
+                TouchPhase::Start => {
+                    taps.touches += 1;
+                    taps.candidate = (taps.touches == 1 && response.rect.contains(pos))
+                        .then_some((id, pos, input.time));
+                    if taps.candidate.is_none() {
+                        taps.last = None;
+                    }
+                }
+                TouchPhase::Move => {
+                    if taps.candidate.is_some_and(|(candidate, start, _)| {
+                        candidate == id && start.distance(pos) > options.max_click_dist
+                    }) {
+                        taps.candidate = None;
+                    }
+                }
+                TouchPhase::End | TouchPhase::Cancel => {
+                    taps.touches = taps.touches.saturating_sub(1);
+                    let Some((candidate, start, started)) = taps.candidate.take() else {
+                        continue;
+                    };
+                    if candidate != id
+                        || phase == TouchPhase::Cancel
+                        || input.time - started > options.max_click_duration
+                    {
+                        continue;
+                    }
+
+                    match taps.last.take() {
+                        Some((last, at))
+                            if input.time - at <= options.max_double_click_delay
+                                && last.distance(start) <= options.max_click_dist * 2. =>
+                        {
+                            double_tap = Some(start);
+                        }
+                        _ => taps.last = Some((start, input.time)),
+                    }
+                }
+            }
+        }
+    });
+
+    ui.data_mut(|data| data.insert_temp(id, taps));
+    double_tap
+}
+
 /// Get the offset of the input (either mouse or touch) relative to the center.
 fn input_offset(ui: &mut Ui, response: &Response) -> Option<Vec2> {
     let mouse_offset = response.hover_pos();
@@ -357,3 +1206,1381 @@ fn input_offset(ui: &mut Ui, response: &Response) -> Option<Vec2> {
         .or(mouse_offset)
         .map(|pos| pos - response.rect.center())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lon_lat;
+    use crate::testing::{RecordingTiles, attribution};
+    use egui::{Event, Modifiers, MouseWheelUnit, Pos2, RawInput, Rect, pos2};
+
+    /// Run a frame with the map filling the whole screen, while the mouse wheel is scrolled over it.
+    fn scroll_over_map(memory: &mut MapMemory, cooperative: bool, modifiers: Modifiers) {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+
+        for events in [
+            vec![Event::PointerMoved(screen_rect.center())],
+            vec![Event::MouseWheel {
+                unit: MouseWheelUnit::Point,
+                delta: Vec2::new(0., 5.),
+                modifiers,
+            }],
+        ] {
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                events,
+                modifiers,
+                ..Default::default()
+            };
+
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(
+                        Map::new(None, memory, lon_lat(17.03664, 51.09916))
+                            .with_cooperative_gestures(cooperative),
+                    );
+                });
+            });
+        }
+    }
+
+    struct Recorder {
+        name: &'static str,
+        layer: PluginLayer,
+        log: std::rc::Rc<std::cell::RefCell<Vec<&'static str>>>,
+    }
+
+    impl Plugin for Recorder {
+        fn run(
+            self: Box<Self>,
+            _ui: &mut Ui,
+            _painter: &Painter,
+            _response: &Response,
+            _projector: &Projector,
+            _map_memory: &MapMemory,
+        ) {
+            self.log.borrow_mut().push(self.name);
+        }
+
+        fn layer(&self) -> PluginLayer {
+            self.layer
+        }
+    }
+
+    struct Animation;
+
+    impl Plugin for Animation {
+        fn run(
+            self: Box<Self>,
+            ui: &mut Ui,
+            _painter: &Painter,
+            _response: &Response,
+            _projector: &Projector,
+            _map_memory: &MapMemory,
+        ) {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn plugin_can_request_repaint() {
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+
+        let run = |memory: &mut MapMemory, animate: bool| {
+            let output = ctx.run(RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let mut map = Map::new(None, memory, lon_lat(17.03664, 51.09916));
+                    if animate {
+                        map = map.with_plugin(Animation);
+                    }
+                    ui.add(map);
+                });
+            });
+            output.viewport_output[&egui::ViewportId::ROOT].repaint_delay
+        };
+
+        // Let the first frames settle.
+        run(&mut memory, false);
+        run(&mut memory, false);
+        assert_eq!(run(&mut memory, false), std::time::Duration::MAX);
+
+        // Egui shortens the delay by the expected frame time.
+        let delay = run(&mut memory, true);
+        assert!(delay <= std::time::Duration::from_secs(1), "{delay:?}");
+    }
+
+    #[test]
+    fn plugins_are_run_by_layer_then_by_order_of_adding() {
+        let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let plugin = |name, layer| Recorder {
+            name,
+            layer,
+            log: log.clone(),
+        };
+
+        let mut memory = MapMemory::default();
+        show_map(
+            Map::new(None, &mut memory, lon_lat(17.03664, 51.09916))
+                .with_plugin(plugin("scale bar", PluginLayer::Controls))
+                .with_plugin(plugin("marker", PluginLayer::Markers))
+                .with_plugin(plugin("fill", PluginLayer::Shapes))
+                .with_plugin(plugin("another marker", PluginLayer::Markers)),
+        );
+
+        assert_eq!(
+            *log.borrow(),
+            ["fill", "marker", "another marker", "scale bar"]
+        );
+    }
+
+    struct CreditedTiles(&'static str);
+
+    impl Tiles for CreditedTiles {
+        fn at(&mut self, _tile_id: crate::TileId) -> Option<crate::TextureWithUv> {
+            None
+        }
+
+        fn attribution(&self) -> Attribution {
+            attribution(self.0)
+        }
+
+        fn tile_size(&self) -> u32 {
+            256
+        }
+    }
+
+    struct CreditedPlugin(&'static str);
+
+    impl Plugin for CreditedPlugin {
+        fn run(
+            self: Box<Self>,
+            _ui: &mut Ui,
+            _painter: &Painter,
+            _response: &Response,
+            _projector: &Projector,
+            _map_memory: &MapMemory,
+        ) {
+        }
+
+        fn attribution(&self) -> Option<Attribution> {
+            Some(attribution(self.0))
+        }
+    }
+
+    #[test]
+    fn attributions_are_collected_from_tiles_layers_and_plugins() {
+        let mut memory = MapMemory::default();
+        let mut base = CreditedTiles("OpenStreetMap");
+        let mut overlay = CreditedTiles("Radar");
+        let mut same_as_base = CreditedTiles("OpenStreetMap");
+
+        let map = Map::new(Some(&mut base), &mut memory, lon_lat(17.03664, 51.09916))
+            .with_layer(&mut overlay, 0.5)
+            .with_layer(&mut same_as_base, 0.5)
+            .with_plugin(Animation)
+            .with_plugin(CreditedPlugin("Data"))
+            .with_attributions(Align2::RIGHT_BOTTOM);
+
+        let texts: Vec<_> = map.attributions().iter().map(|a| a.text).collect();
+        assert_eq!(texts, ["OpenStreetMap", "Radar", "Data"]);
+
+        // And they are drawn without problems.
+        show_map(map);
+    }
+
+    /// Full tiles, upscaled tiles, missing ones, and ones still being loaded, depending on the
+    /// column.
+    struct MixedTiles(crate::Texture);
+
+    impl Tiles for MixedTiles {
+        fn at(&mut self, tile_id: crate::TileId) -> Option<crate::TextureWithUv> {
+            let uv = match tile_id.x % 4 {
+                0 => Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+                1 => Rect::from_min_max(pos2(0., 0.), pos2(0.5, 0.5)),
+                _ => return None,
+            };
+            Some(crate::TextureWithUv::new(self.0.clone(), uv))
+        }
+
+        fn missing(&self, tile_id: crate::TileId) -> bool {
+            tile_id.x % 4 == 2
+        }
+
+        fn attribution(&self) -> crate::sources::Attribution {
+            attribution("")
+        }
+
+        fn tile_size(&self) -> u32 {
+            256
+        }
+    }
+
+    #[test]
+    fn frame_stats_are_collected_only_when_enabled() {
+        let ctx = egui::Context::default();
+        let image = egui::ColorImage::filled([256, 256], egui::Color32::WHITE);
+        let mut tiles = MixedTiles(crate::Texture::from_color_image(image, &ctx));
+        let mut memory = MapMemory::default();
+
+        show_map(Map::new(Some(&mut tiles), &mut memory, lon_lat(0., 0.)));
+        assert_eq!(memory.frame_stats(), None);
+
+        show_map(Map::new(Some(&mut tiles), &mut memory, lon_lat(0., 0.)).frame_stats(true));
+        let stats = memory.frame_stats().expect("stats should be collected");
+        assert!(stats.tiles_upscaled > 0);
+        assert!(stats.tiles_drawn > stats.tiles_upscaled);
+        assert!(stats.tiles_missing > 0);
+        assert!(stats.placeholders > 0);
+    }
+
+    #[test]
+    fn hidden_layers_are_not_asked_for_tiles() {
+        let mut memory = MapMemory::default();
+        let mut visible = RecordingTiles::default();
+        let mut hidden = RecordingTiles::default();
+
+        let map = Map::new(None, &mut memory, lon_lat(17.03664, 51.09916))
+            .with_layer_visible(&mut visible, 1.0, true)
+            .with_layer_visible(&mut hidden, 0.5, false);
+        assert_eq!(map.attributions().len(), 1);
+        show_map(map);
+
+        assert!(!visible.0.is_empty());
+        assert!(hidden.0.is_empty());
+    }
+
+    #[test]
+    fn layers_are_toggled_by_id() {
+        let mut memory = MapMemory::default();
+        memory.set_layer_visible("radar", false);
+        assert!(memory.layer_visible("base"));
+        assert!(!memory.layer_visible("radar"));
+
+        let mut base = RecordingTiles::default();
+        let mut radar = RecordingTiles::default();
+        show_map(
+            Map::new(None, &mut memory, lon_lat(17.03664, 51.09916))
+                .with_layer_id("base", &mut base, 1.0)
+                .with_layer_id("radar", &mut radar, 0.5),
+        );
+        assert!(!base.0.is_empty());
+        assert!(radar.0.is_empty());
+
+        // Shown again, e.g. from a legend.
+        memory.set_layer_visible("radar", true);
+        show_map(
+            Map::new(None, &mut memory, lon_lat(17.03664, 51.09916))
+                .with_layer_id("radar", &mut radar, 0.5),
+        );
+        assert!(!radar.0.is_empty());
+    }
+
+    #[test]
+    fn only_tiles_within_the_clip_rect_are_asked_for() {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let mut memory = MapMemory::default();
+
+        let mut tiles_within = |clip_rect: Option<Rect>| {
+            let mut tiles = RecordingTiles::default();
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(Frame::NONE)
+                    .show(ctx, |ui| {
+                        let mut map =
+                            Map::new(Some(&mut tiles), &mut memory, lon_lat(17.03664, 51.09916));
+                        if let Some(clip_rect) = clip_rect {
+                            map = map.with_clip_rect(clip_rect);
+                        }
+                        ui.add(map);
+                    });
+            });
+            tiles.0
+        };
+
+        // Far from the map's center, so that the tile there is not drawn at all.
+        let clip_rect = Rect::from_min_max(pos2(450., 0.), pos2(512., 100.));
+        let all = tiles_within(None);
+        let clipped = tiles_within(Some(clip_rect));
+        assert!(!clipped.is_empty());
+        assert!(clipped.len() < all.len());
+        assert!(clipped.is_subset(&all));
+
+        let projector = Projector::new(screen_rect, &memory, lon_lat(17.03664, 51.09916));
+        for (zoom, x, y) in clipped {
+            let tile_rect = projector.tile_rect(crate::TileId { x, y, zoom });
+            assert!(tile_rect.intersects(clip_rect));
+        }
+    }
+
+    #[test]
+    fn background_is_painted_below_tiles() {
+        let ctx = egui::Context::default();
+        let image = egui::ColorImage::filled([256, 256], egui::Color32::WHITE);
+        let mut tiles = MixedTiles(crate::Texture::from_color_image(image, &ctx));
+        let mut memory = MapMemory::default();
+
+        let output = ctx.run(RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(
+                    Map::new(Some(&mut tiles), &mut memory, lon_lat(0., 0.))
+                        .with_background(Color32::DARK_BLUE),
+                );
+            });
+        });
+
+        let filled = |shape: &egui::Shape| matches!(shape, egui::Shape::Rect(rect) if rect.fill == Color32::DARK_BLUE);
+        let background = output.shapes.iter().position(|s| filled(&s.shape));
+        let first_tile = output
+            .shapes
+            .iter()
+            .position(|s| matches!(s.shape, egui::Shape::Mesh(_)));
+        assert!(background.is_some());
+        assert!(background < first_tile);
+    }
+
+    #[test]
+    fn tile_debug_labels_tiles_and_highlights_the_hovered_one() {
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        let screen = egui::Rect::from_min_size(Pos2::ZERO, Vec2::splat(300.));
+        let pointer = Pos2::new(100., 200.);
+
+        let mut run = |tile_debug: bool| {
+            let input = RawInput {
+                screen_rect: Some(screen),
+                events: vec![Event::PointerMoved(pointer)],
+                ..Default::default()
+            };
+            ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(Frame::NONE)
+                    .show(ctx, |ui| {
+                        ui.add(
+                            Map::new(None, &mut memory, lon_lat(17., 51.))
+                                .with_tile_debug(tile_debug),
+                        );
+                    });
+            })
+        };
+
+        let texts = |output: &egui::FullOutput| -> Vec<String> {
+            output
+                .shapes
+                .iter()
+                .filter_map(|clipped| match &clipped.shape {
+                    egui::Shape::Text(text) => Some(text.galley.text().to_owned()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        assert!(texts(&run(false)).is_empty());
+
+        let output = run(true);
+        let hovered =
+            Projector::new(screen, &memory, lon_lat(17., 51.)).tile_at_screen(pointer, 256);
+        let texts = texts(&output);
+        assert!(texts.len() > 1);
+        assert!(texts.contains(&format!("16/{}/{}", hovered.x, hovered.y)));
+
+        let highlighted: Vec<_> = output
+            .shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                egui::Shape::Rect(rect) if rect.fill != Color32::TRANSPARENT => Some(rect.rect),
+                _ => None,
+            })
+            .collect();
+        let [highlighted] = highlighted[..] else {
+            panic!("expected a single highlighted tile, got {highlighted:?}");
+        };
+        assert!(highlighted.contains(pointer));
+    }
+
+    /// Fills the whole map with given color.
+    struct Fill(Color32, PluginLayer);
+
+    impl Plugin for Fill {
+        fn run(
+            self: Box<Self>,
+            _ui: &mut Ui,
+            painter: &Painter,
+            response: &Response,
+            _projector: &Projector,
+            _map_memory: &MapMemory,
+        ) {
+            painter.rect_filled(response.rect, 0., self.0);
+        }
+
+        fn layer(&self) -> PluginLayer {
+            self.1
+        }
+    }
+
+    /// Records whether it was clicked. Takes the pointer within its area, if it has one.
+    struct Target {
+        name: &'static str,
+        layer: PluginLayer,
+        area: Option<Rect>,
+        clicked: std::rc::Rc<std::cell::RefCell<Vec<&'static str>>>,
+    }
+
+    impl Plugin for Target {
+        fn run(
+            self: Box<Self>,
+            _ui: &mut Ui,
+            _painter: &Painter,
+            response: &Response,
+            _projector: &Projector,
+            _map_memory: &MapMemory,
+        ) {
+            if response.clicked() {
+                self.clicked.borrow_mut().push(self.name);
+            }
+        }
+
+        fn layer(&self) -> PluginLayer {
+            self.layer
+        }
+
+        fn takes_pointer(&self, pos: Pos2, _projector: &Projector) -> bool {
+            self.area.is_some_and(|area| area.contains(pos))
+        }
+    }
+
+    #[test]
+    fn click_goes_to_the_topmost_plugin_taking_the_pointer() {
+        let click_at = |pos: Pos2| {
+            let ctx = egui::Context::default();
+            let mut memory = MapMemory::default();
+            let clicked = std::rc::Rc::default();
+            let target = |name, layer, area| Target {
+                name,
+                layer,
+                area,
+                clicked: std::rc::Rc::clone(&clicked),
+            };
+            let button = |pressed| Event::PointerButton {
+                pos,
+                button: PointerButton::Primary,
+                pressed,
+                modifiers: Modifiers::NONE,
+            };
+
+            for events in [
+                vec![Event::PointerMoved(pos)],
+                vec![button(true)],
+                vec![button(false)],
+            ] {
+                let input = RawInput {
+                    screen_rect: Some(Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.))),
+                    events,
+                    ..Default::default()
+                };
+                let _ = ctx.run(input, |ctx| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        let area =
+                            |size| Some(Rect::from_center_size(ui.max_rect().center(), size));
+                        ui.add(
+                            Map::new(None, &mut memory, lon_lat(0., 0.))
+                                .with_plugin(target("readout", PluginLayer::Controls, None))
+                                .with_plugin(target(
+                                    "marker",
+                                    PluginLayer::Markers,
+                                    area(Vec2::splat(20.)),
+                                ))
+                                .with_plugin(target(
+                                    "polygon",
+                                    PluginLayer::Shapes,
+                                    area(Vec2::splat(200.)),
+                                ))
+                                .with_plugin(target("sea", PluginLayer::Background, None)),
+                        );
+                    });
+                });
+            }
+            clicked.take()
+        };
+
+        // Plugins above the one taking the pointer are not affected.
+        assert_eq!(click_at(pos2(256., 256.)), ["marker", "readout"]);
+        assert_eq!(click_at(pos2(300., 256.)), ["polygon", "marker", "readout"]);
+        assert_eq!(
+            click_at(pos2(450., 256.)),
+            ["sea", "polygon", "marker", "readout"]
+        );
+    }
+
+    #[test]
+    fn plugin_is_painted_with_given_opacity() {
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+
+        let output = ctx.run(RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(
+                    Map::new(None, &mut memory, lon_lat(0., 0.))
+                        .with_plugin_opacity(Fill(Color32::RED, PluginLayer::Shapes), 0.5)
+                        .with_plugin(Fill(Color32::BLUE, PluginLayer::Shapes)),
+                );
+            });
+        });
+
+        let fills: Vec<Color32> = output
+            .shapes
+            .iter()
+            .filter_map(|s| match &s.shape {
+                egui::Shape::Rect(rect) => Some(rect.fill),
+                _ => None,
+            })
+            .collect();
+        assert!(fills.contains(&Color32::RED.gamma_multiply(0.5)));
+        assert!(fills.contains(&Color32::BLUE));
+    }
+
+    #[test]
+    fn background_plugins_are_drawn_below_tiles() {
+        let ctx = egui::Context::default();
+        let image = egui::ColorImage::filled([256, 256], egui::Color32::WHITE);
+        let mut tiles = MixedTiles(crate::Texture::from_color_image(image, &ctx));
+        let mut memory = MapMemory::default();
+
+        let output = ctx.run(RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(
+                    Map::new(Some(&mut tiles), &mut memory, lon_lat(0., 0.))
+                        .with_plugin(Fill(Color32::RED, PluginLayer::Shapes))
+                        .with_plugin(Fill(Color32::DARK_BLUE, PluginLayer::Background)),
+                );
+            });
+        });
+
+        let filled = |color| {
+            output
+                .shapes
+                .iter()
+                .position(|s| matches!(&s.shape, egui::Shape::Rect(rect) if rect.fill == color))
+        };
+        let first_tile = output
+            .shapes
+            .iter()
+            .position(|s| matches!(s.shape, egui::Shape::Mesh(_)));
+        assert!(filled(Color32::DARK_BLUE).is_some());
+        assert!(filled(Color32::DARK_BLUE) < first_tile);
+        assert!(filled(Color32::RED) > first_tile);
+    }
+
+    #[test]
+    fn same_tiles_are_shown_after_pixels_per_point_change() {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let mut memory = MapMemory::default();
+
+        let mut tiles_at = |pixels_per_point: f32| {
+            ctx.set_pixels_per_point(pixels_per_point);
+            let mut tiles = RecordingTiles::default();
+
+            // Only the last frame counts, first one applies the new scale.
+            for _ in 0..2 {
+                tiles.0.clear();
+                let input = RawInput {
+                    screen_rect: Some(screen_rect),
+                    ..Default::default()
+                };
+                let _ = ctx.run(input, |ctx| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        ui.add(Map::new(
+                            Some(&mut tiles),
+                            &mut memory,
+                            lon_lat(17.03664, 51.09916),
+                        ));
+                    });
+                });
+            }
+            tiles.0
+        };
+
+        // Map is laid out in points, so moving the window to a HiDPI display, or back, does not
+        // change which tiles are needed.
+        let standard = tiles_at(1.);
+        assert!(!standard.is_empty());
+        assert_eq!(tiles_at(2.), standard);
+        assert_eq!(tiles_at(1.), standard);
+    }
+
+    struct NarrowTiles;
+
+    impl Tiles for NarrowTiles {
+        fn at(&mut self, _tile_id: crate::TileId) -> Option<crate::TextureWithUv> {
+            None
+        }
+
+        fn attribution(&self) -> crate::sources::Attribution {
+            attribution("")
+        }
+
+        fn tile_size(&self) -> u32 {
+            256
+        }
+
+        fn zoom_range(&self) -> RangeInclusive<u8> {
+            5..=10
+        }
+    }
+
+    fn show_map(map: Map) {
+        let ctx = egui::Context::default();
+        let mut map = Some(map);
+        let _ = ctx.run(RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                if let Some(map) = map.take() {
+                    ui.add(map);
+                }
+            });
+        });
+    }
+
+    #[test]
+    fn zoom_is_clamped_to_what_tiles_support() {
+        let mut tiles = NarrowTiles;
+        let mut memory = MapMemory::default();
+        assert_eq!(memory.zoom(), 16.);
+
+        show_map(Map::new(
+            Some(&mut tiles),
+            &mut memory,
+            lon_lat(17.03664, 51.09916),
+        ));
+        assert_eq!(memory.zoom(), 10.);
+
+        // Explicit range takes precedence.
+        show_map(
+            Map::new(Some(&mut tiles), &mut memory, lon_lat(17.03664, 51.09916))
+                .zoom_range(2.0..=4.0),
+        );
+        assert_eq!(memory.zoom(), 4.);
+    }
+
+    /// Zoom in with two wheel notches over the given point, then idle for a second. Returns zoom
+    /// right after the notches and the position under the pointer at the end.
+    fn scroll_zoom(memory: &mut MapMemory, animation: Duration, pointer: Pos2) -> (f64, Position) {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let wheel = Event::MouseWheel {
+            unit: MouseWheelUnit::Point,
+            delta: Vec2::new(0., 5.),
+            modifiers: Modifiers::COMMAND,
+        };
+
+        let mut zoom_after_notches = 0.;
+        let mut frames = vec![
+            vec![Event::PointerMoved(pointer)],
+            vec![wheel.clone()],
+            vec![wheel],
+        ];
+        frames.extend(std::iter::repeat_n(vec![], 60));
+        for (frame, events) in frames.into_iter().enumerate() {
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                events,
+                modifiers: Modifiers::COMMAND,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| {
+                        ui.add(
+                            Map::new(None, memory, lon_lat(17.03664, 51.09916))
+                                .zoom_animation(animation),
+                        );
+                    });
+            });
+            if frame == 2 {
+                zoom_after_notches = memory.zoom();
+            }
+        }
+
+        let projector = Projector::new(screen_rect, memory, lon_lat(17.03664, 51.09916));
+        (zoom_after_notches, projector.unproject(pointer.to_vec2()))
+    }
+
+    #[test]
+    fn animated_scroll_zoom_glides_to_the_same_level_and_location() {
+        let pointer = pos2(128., 128.);
+        let under_pointer = Projector::new(
+            Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.)),
+            &MapMemory::default(),
+            lon_lat(17.03664, 51.09916),
+        )
+        .unproject(pointer.to_vec2());
+
+        let mut instant = MapMemory::default();
+        let (jumped_to, _) = scroll_zoom(&mut instant, Duration::ZERO, pointer);
+        assert!(jumped_to > 16.);
+
+        let mut animated = MapMemory::default();
+        let (glided_to, position) = scroll_zoom(&mut animated, Duration::from_millis(200), pointer);
+        assert!(16. < glided_to && glided_to < jumped_to, "{glided_to}");
+
+        // Both notches are taken into account, and the location stays under the pointer.
+        assert!((animated.zoom() - instant.zoom()).abs() < 1e-3);
+        assert!(animated.zoom_animation.is_none());
+        assert!((position.x() - under_pointer.x()).abs() < 1e-6);
+        assert!((position.y() - under_pointer.y()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn zoom_snaps_only_once_the_wheel_stops() {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let mut memory = MapMemory::default().with_zoom_snapping(1.);
+        let notch = vec![Event::MouseWheel {
+            unit: MouseWheelUnit::Point,
+            delta: Vec2::new(0., 20.),
+            modifiers: Modifiers::COMMAND,
+        }];
+
+        // Notches come slowly, with idle frames in between.
+        let mut frames = vec![vec![Event::PointerMoved(screen_rect.center())]];
+        for _ in 0..4 {
+            frames.push(notch.clone());
+            frames.extend(std::iter::repeat_n(vec![], 5));
+        }
+
+        let run = |frames: Vec<Vec<Event>>, memory: &mut MapMemory| {
+            let mut zooms = Vec::new();
+            for events in frames {
+                let input = RawInput {
+                    screen_rect: Some(screen_rect),
+                    events,
+                    modifiers: Modifiers::COMMAND,
+                    ..Default::default()
+                };
+                let _ = ctx.run(input, |ctx| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        ui.add(Map::new(None, memory, lon_lat(17.03664, 51.09916)));
+                    });
+                });
+                zooms.push(memory.zoom());
+            }
+            zooms
+        };
+        let zooms = run(frames, &mut memory);
+
+        // Zoom never went back towards where it started.
+        assert!(zooms.windows(2).all(|pair| pair[1] >= pair[0]), "{zooms:?}");
+        assert!(memory.zoom() > 16.5, "{}", memory.zoom());
+
+        run(std::iter::repeat_n(vec![], 60).collect(), &mut memory);
+        assert_eq!(memory.zoom(), 17.);
+    }
+
+    #[test]
+    fn plain_scroll_pans_the_map_by_default() {
+        let mut memory = MapMemory::default();
+        scroll_over_map(&mut memory, false, Modifiers::NONE);
+        assert!(memory.detached().is_some());
+    }
+
+    #[test]
+    fn plain_scroll_passes_through_with_cooperative_gestures() {
+        let mut memory = MapMemory::default();
+        let zoom = memory.zoom();
+        scroll_over_map(&mut memory, true, Modifiers::NONE);
+        assert!(memory.detached().is_none());
+        assert_eq!(memory.zoom(), zoom);
+    }
+
+    #[test]
+    fn cooperative_gestures_hint_names_the_modifier_of_the_platform() {
+        // Fonts are needed to tell whether symbols can be shown, and they come with a frame.
+        let hint = |os| {
+            let ctx = egui::Context::default();
+            ctx.set_os(os);
+            let mut hint = String::new();
+            let _ = ctx.run(RawInput::default(), |ctx| {
+                hint = cooperative_gestures_hint(ctx, Modifiers::COMMAND);
+            });
+            hint
+        };
+
+        assert_eq!(
+            hint(egui::os::OperatingSystem::Windows),
+            "Use Ctrl + scroll to zoom"
+        );
+        assert!(!hint(egui::os::OperatingSystem::Mac).contains("Ctrl"));
+    }
+
+    #[test]
+    fn ctrl_scroll_zooms_with_cooperative_gestures() {
+        let mut memory = MapMemory::default();
+        let zoom = memory.zoom();
+        scroll_over_map(&mut memory, true, Modifiers::COMMAND);
+        assert!(memory.zoom() > zoom);
+    }
+
+    /// Zoom in with the pointer over the map placed in `map_rect`, and check that the place under
+    /// the pointer does not move.
+    fn zoom_keeps_place_under_pointer(map_rect: Rect, pointer: Pos2) {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let mut memory = MapMemory::default();
+        memory.center_at(lon_lat(17.03664, 51.09916));
+        assert_eq!(memory.set_zoom(8.3), Ok(()));
+
+        let wheel = Event::MouseWheel {
+            unit: MouseWheelUnit::Point,
+            delta: Vec2::new(0., 50.),
+            modifiers: Modifiers::COMMAND,
+        };
+
+        let mut frames = vec![vec![Event::PointerMoved(pointer)]];
+        frames.extend(std::iter::repeat_n(vec![wheel.clone(), wheel], 10));
+        frames.push(Vec::new());
+
+        let mut under_pointer = Vec::new();
+        for events in frames {
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                events,
+                modifiers: Modifiers::COMMAND,
+                ..Default::default()
+            };
+
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.scope_builder(UiBuilder::new().max_rect(map_rect), |ui| {
+                        let response = Map::new(None, &mut memory, lon_lat(0., 0.))
+                            .zoom_range(0.0..=10.0)
+                            .show(ui, |_, _, projector, _| {
+                                projector.unproject(pointer.to_vec2())
+                            });
+                        assert_eq!(response.response.rect, map_rect);
+                        under_pointer.push(response.inner);
+                    });
+                });
+            });
+        }
+
+        // Zoom went up to the limit, including events which only hit it.
+        assert_eq!(memory.zoom(), 10.);
+        let first = under_pointer[0];
+        for position in under_pointer {
+            approx::assert_relative_eq!(position.x(), first.x(), epsilon = 1e-9);
+            approx::assert_relative_eq!(position.y(), first.y(), epsilon = 1e-9);
+        }
+    }
+
+    /// Run a frame for every list of touches, each given by its id, phase and position. Returns
+    /// the place at `watched` after every frame.
+    fn touch_map(
+        memory: &mut MapMemory,
+        watched: Pos2,
+        frames: &[&[(u64, TouchPhase, Pos2)]],
+    ) -> Vec<Position> {
+        let ctx = egui::Context::default();
+        let mut places = Vec::new();
+        for (frame, touches) in frames.iter().enumerate() {
+            let input = RawInput {
+                screen_rect: Some(Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.))),
+                time: Some(frame as f64 * 0.05),
+                events: touches
+                    .iter()
+                    .map(|&(id, phase, pos)| Event::Touch {
+                        device_id: egui::TouchDeviceId(0),
+                        id: egui::TouchId(id),
+                        phase,
+                        pos,
+                        force: None,
+                    })
+                    .collect(),
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let place = Map::new(None, memory, lon_lat(0., 0.))
+                        .double_tap_to_zoom(true)
+                        .show(ui, |_, _, projector, _| {
+                            projector.unproject(watched.to_vec2())
+                        })
+                        .inner;
+                    places.push(place);
+                });
+            });
+        }
+        places
+    }
+
+    #[test]
+    fn double_tap_zooms_in_by_one_level_towards_the_tap() {
+        let at = pos2(400., 150.);
+        let mut memory = MapMemory::default();
+        let zoom = memory.zoom();
+
+        let places = touch_map(
+            &mut memory,
+            at,
+            &[
+                &[(0, TouchPhase::Start, at)],
+                &[(0, TouchPhase::End, at)],
+                &[(0, TouchPhase::Start, at + Vec2::splat(3.))],
+                &[(0, TouchPhase::End, at + Vec2::splat(3.))],
+            ],
+        );
+
+        approx::assert_relative_eq!(memory.zoom(), zoom + 1.);
+        let (before, after) = (places[0], places[3]);
+        approx::assert_relative_eq!(before.x(), after.x(), epsilon = 1e-3);
+        approx::assert_relative_eq!(before.y(), after.y(), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn fingers_of_a_pinch_are_not_taps() {
+        let (first, second) = (pos2(200., 250.), pos2(300., 250.));
+        let mut memory = MapMemory::default();
+        let zoom = memory.zoom();
+
+        touch_map(
+            &mut memory,
+            first,
+            &[
+                &[(0, TouchPhase::Start, first)],
+                &[(1, TouchPhase::Start, second)],
+                &[(1, TouchPhase::End, second), (0, TouchPhase::End, first)],
+                &[(0, TouchPhase::Start, first)],
+                &[(0, TouchPhase::End, first)],
+                // Too late for the previous one.
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[(0, TouchPhase::Start, first)],
+                &[(0, TouchPhase::End, first)],
+            ],
+        );
+
+        approx::assert_relative_eq!(memory.zoom(), zoom);
+    }
+
+    #[test]
+    fn place_under_pointer_stays_put_while_zooming() {
+        let map_rect = Rect::from_min_size(pos2(8., 8.), Vec2::splat(496.));
+        zoom_keeps_place_under_pointer(map_rect, pos2(400., 150.));
+    }
+
+    #[test]
+    fn place_under_pointer_stays_put_when_map_is_not_at_window_origin() {
+        // E.g. with a side panel on the left and a toolbar on the top.
+        let map_rect = Rect::from_min_max(pos2(200., 100.), pos2(500., 300.));
+        zoom_keeps_place_under_pointer(map_rect, pos2(450., 150.));
+    }
+
+    #[test]
+    fn panning_programmatically_moves_the_view_by_given_pixels() {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let mut memory = MapMemory::default();
+
+        let show = |memory: &mut MapMemory| {
+            let mut position = None;
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    position = Map::new(None, memory, lon_lat(17.03664, 51.09916))
+                        .show(ui, |_, response, projector, _| {
+                            projector
+                                .unproject(response.rect.center().to_vec2() + Vec2::new(30., -40.))
+                        })
+                        .inner
+                        .into();
+                });
+            });
+            position.expect("map was not shown")
+        };
+
+        let target = show(&mut memory);
+
+        // Works when following `my_position`, and accumulates.
+        memory.pan(Vec2::new(10., -20.));
+        memory.pan(Vec2::new(20., -20.));
+        assert!(memory.detached().is_none());
+        show(&mut memory);
+
+        let center = memory.detached().expect("map should be detached");
+        approx::assert_relative_eq!(center.x(), target.x(), epsilon = 1e-9);
+        approx::assert_relative_eq!(center.y(), target.y(), epsilon = 1e-9);
+
+        // Explicit centering cancels pending panning.
+        memory.pan(Vec2::new(100., 0.));
+        memory.center_at(lon_lat(1., 2.));
+        show(&mut memory);
+        let center = memory.detached().expect("map should be detached");
+        approx::assert_relative_eq!(center.x(), 1., epsilon = 1e-9);
+        approx::assert_relative_eq!(center.y(), 2., epsilon = 1e-9);
+    }
+
+    fn drag_map(memory: &mut MapMemory, reduced_motion: bool, release: bool) {
+        drag_map_by(
+            memory,
+            PointerButton::Primary,
+            DragPanButtons::PRIMARY,
+            reduced_motion,
+            release,
+        );
+    }
+
+    fn drag_map_by(
+        memory: &mut MapMemory,
+        button: PointerButton,
+        drag_pan_buttons: DragPanButtons,
+        reduced_motion: bool,
+        release: bool,
+    ) {
+        drag_configured_map(memory, button, release, |map| {
+            map.drag_pan_buttons(drag_pan_buttons)
+                .reduced_motion(reduced_motion)
+        });
+    }
+
+    fn drag_configured_map(
+        memory: &mut MapMemory,
+        button: PointerButton,
+        release: bool,
+        configure: impl for<'a, 'b, 'c> Fn(Map<'a, 'b, 'c>) -> Map<'a, 'b, 'c>,
+    ) {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let start = screen_rect.center();
+        let button = |pos, pressed| Event::PointerButton {
+            pos,
+            button,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+
+        let mut frames = vec![vec![Event::PointerMoved(start)], vec![button(start, true)]];
+        for step in 1..=5 {
+            frames.push(vec![Event::PointerMoved(
+                start + Vec2::new(20. * step as f32, 0.),
+            )]);
+        }
+        if release {
+            frames.push(vec![button(start + Vec2::new(100., 0.), false)]);
+        }
+
+        for events in frames {
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                events,
+                ..Default::default()
+            };
+
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(configure(Map::new(
+                        None,
+                        memory,
+                        lon_lat(17.03664, 51.09916),
+                    )));
+                });
+            });
+        }
+    }
+
+    #[test]
+    fn map_is_panned_only_by_configured_buttons() {
+        let mut memory = MapMemory::default();
+        drag_map_by(
+            &mut memory,
+            PointerButton::Primary,
+            DragPanButtons::MIDDLE,
+            true,
+            true,
+        );
+        assert!(memory.detached().is_none());
+
+        drag_map_by(
+            &mut memory,
+            PointerButton::Middle,
+            DragPanButtons::MIDDLE,
+            true,
+            true,
+        );
+        assert!(memory.detached().is_some());
+    }
+
+    #[test]
+    fn map_is_not_panned_when_panning_is_disabled() {
+        let mut memory = MapMemory::default();
+        drag_configured_map(&mut memory, PointerButton::Primary, true, |map| {
+            map.with_pan_enabled(false)
+        });
+        assert!(memory.detached().is_none());
+    }
+
+    /// Press the map, move the pointer right by `distance` in small steps and release it.
+    /// Returns whether plugins saw a click.
+    fn button(pos: Pos2, pressed: bool) -> Event {
+        Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    /// Press the primary button in the middle of the screen, move it by given distance in a few
+    /// steps and release it.
+    fn drag_frames(distance: f32) -> Vec<Vec<Event>> {
+        let start = pos2(256., 256.);
+        let mut frames = vec![vec![Event::PointerMoved(start)], vec![button(start, true)]];
+        for step in 1..=5 {
+            frames.push(vec![Event::PointerMoved(
+                start + Vec2::new(distance * step as f32 / 5., 0.),
+            )]);
+        }
+        frames.push(vec![button(start + Vec2::new(distance, 0.), false)]);
+        frames
+    }
+
+    /// Run frames of the map filling the whole screen. Returns whether a plugin saw a click.
+    fn clicked_after(
+        memory: &mut MapMemory,
+        frames: Vec<Vec<Event>>,
+        configure: impl for<'a, 'b, 'c> Fn(Map<'a, 'b, 'c>) -> Map<'a, 'b, 'c>,
+    ) -> bool {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let clicked = std::rc::Rc::default();
+
+        for events in frames {
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                events,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| {
+                        ui.add(
+                            configure(Map::new(None, memory, lon_lat(17.03664, 51.09916)))
+                                .with_plugin(Target {
+                                    name: "marker",
+                                    layer: PluginLayer::Markers,
+                                    area: None,
+                                    clicked: std::rc::Rc::clone(&clicked),
+                                }),
+                        );
+                    });
+            });
+        }
+        !clicked.take().is_empty()
+    }
+
+    fn drag_by_distance(memory: &mut MapMemory, distance: f32, threshold: f32) -> bool {
+        clicked_after(memory, drag_frames(distance), |map| {
+            map.drag_threshold(threshold).reduced_motion(true)
+        })
+    }
+
+    #[test]
+    fn short_drag_is_a_click_instead_of_a_pan() {
+        // Further than egui tells clicks from drags, but within the threshold.
+        let mut memory = MapMemory::default();
+        assert!(drag_by_distance(&mut memory, 15., 20.));
+        assert!(memory.detached().is_none());
+
+        let mut memory = MapMemory::default();
+        assert!(!drag_by_distance(&mut memory, 40., 20.));
+        assert!(memory.detached().is_some());
+    }
+
+    #[test]
+    fn panning_catches_up_with_the_pointer_past_the_threshold() {
+        let mut memory = MapMemory::default();
+        let projector = |memory: &MapMemory| {
+            Projector::new(
+                Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.)),
+                memory,
+                lon_lat(17.03664, 51.09916),
+            )
+        };
+        let grabbed = projector(&memory).unproject(Vec2::new(256., 256.));
+
+        drag_by_distance(&mut memory, 40., 20.);
+        let released = projector(&memory).unproject(Vec2::new(296., 256.));
+        assert!(
+            (grabbed.x() - released.x()).abs() < 1e-9,
+            "{grabbed:?} {released:?}"
+        );
+        assert!((grabbed.y() - released.y()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn long_drag_is_not_a_click_even_if_the_map_does_not_pan() {
+        let mut memory = MapMemory::default();
+        assert!(!clicked_after(&mut memory, drag_frames(40.), |map| map
+            .with_pan_enabled(false)));
+        assert!(!clicked_after(&mut memory, drag_frames(40.), |map| map
+            .drag_pan_buttons(DragPanButtons::MIDDLE)));
+        assert!(clicked_after(&mut memory, drag_frames(7.), |map| map
+            .with_pan_enabled(false)));
+    }
+
+    #[test]
+    fn pinch_is_not_a_click() {
+        let touch = |id: u64, phase, pos| Event::Touch {
+            device_id: egui::TouchDeviceId(0),
+            id: egui::TouchId(id),
+            phase,
+            pos,
+            force: None,
+        };
+        let (first, second) = (pos2(256., 256.), pos2(300., 256.));
+
+        let mut frames = vec![
+            vec![Event::PointerMoved(first)],
+            vec![
+                button(first, true),
+                touch(0, TouchPhase::Start, first),
+                touch(1, TouchPhase::Start, second),
+            ],
+        ];
+        // First finger, which the pointer follows, hardly moves.
+        let first_at = |step: usize| first - Vec2::new(step as f32, 0.);
+        for step in 1..=5 {
+            frames.push(vec![
+                Event::PointerMoved(first_at(step)),
+                touch(0, TouchPhase::Move, first_at(step)),
+                touch(
+                    1,
+                    TouchPhase::Move,
+                    second + Vec2::new(20. * step as f32, 0.),
+                ),
+            ]);
+        }
+        frames.push(vec![
+            touch(1, TouchPhase::End, second + Vec2::new(100., 0.)),
+            touch(0, TouchPhase::End, first_at(5)),
+            button(first_at(5), false),
+        ]);
+
+        let mut memory = MapMemory::default();
+        assert!(!clicked_after(&mut memory, frames, |map| map));
+        assert!(memory.zoom() > 16.);
+    }
+
+    #[test]
+    fn zoom_keeps_center_when_panning_is_disabled() {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let mut memory = MapMemory::default();
+        let center = lon_lat(17.03664, 51.09916);
+        memory.center_at(center);
+
+        let wheel = Event::MouseWheel {
+            unit: MouseWheelUnit::Point,
+            delta: Vec2::new(0., 50.),
+            modifiers: Modifiers::COMMAND,
+        };
+        for events in [vec![Event::PointerMoved(pos2(100., 100.))], vec![wheel]] {
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                events,
+                modifiers: Modifiers::COMMAND,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(Map::new(None, &mut memory, lon_lat(0., 0.)).with_pan_enabled(false));
+                });
+            });
+        }
+
+        assert!(memory.zoom() > 16.);
+        let detached = memory.detached().expect("map is detached");
+        approx::assert_relative_eq!(detached.x(), center.x(), epsilon = 1e-9);
+        approx::assert_relative_eq!(detached.y(), center.y(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn dragging_cancels_flight() {
+        let mut memory = MapMemory::default();
+        assert_eq!(
+            memory.fly_to(lon_lat(2.35, 48.86), 10., crate::Easing::Linear),
+            Ok(())
+        );
+        drag_map(&mut memory, false, true);
+        assert!(memory.flight.is_none());
+        assert!(memory.zoom() > 10.);
+    }
+
+    #[test]
+    fn released_map_glides_due_to_inertia() {
+        let mut memory = MapMemory::default();
+        drag_map(&mut memory, false, true);
+        assert!(memory.detached().is_some());
+        assert!(memory.animating());
+    }
+
+    #[test]
+    fn following_my_position_again_stops_the_glide() {
+        let mut memory = MapMemory::default();
+        drag_map(&mut memory, false, true);
+        assert!(memory.detached().is_some());
+
+        memory.follow_my_position();
+        assert!(memory.detached().is_none());
+
+        // Inertia does not detach the map again.
+        show_map(Map::new(None, &mut memory, lon_lat(17.03664, 51.09916)));
+        assert!(memory.detached().is_none());
+        assert!(!memory.moving());
+    }
+
+    #[test]
+    fn released_map_stops_when_motion_is_reduced() {
+        let mut memory = MapMemory::default();
+        drag_map(&mut memory, true, true);
+        assert!(memory.detached().is_some());
+        assert!(!memory.animating());
+        assert!(!memory.moving());
+    }
+
+    #[test]
+    fn dragging_is_reported_until_map_is_released() {
+        let mut memory = MapMemory::default();
+        drag_map(&mut memory, true, false);
+        assert!(memory.dragging());
+        assert!(memory.moving());
+        assert!(!memory.zooming());
+    }
+
+    #[test]
+    fn zooming_is_reported_only_for_the_frame_it_happens_in() {
+        let mut memory = MapMemory::default();
+        scroll_over_map(&mut memory, true, Modifiers::COMMAND);
+        assert!(memory.zooming());
+        assert!(memory.moving());
+        assert!(!memory.dragging());
+
+        show_map(Map::new(None, &mut memory, lon_lat(17.03664, 51.09916)));
+        assert!(!memory.zooming());
+        assert!(!memory.moving());
+    }
+}
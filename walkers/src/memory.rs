@@ -1,4 +1,26 @@
-use crate::{InvalidZoom, Position, center::Center, position::AdjustedPosition, zoom::Zoom};
+use std::{collections::BTreeSet, ops::RangeInclusive, time::Duration};
+
+use egui::{Pos2, Vec2};
+
+use crate::{
+    BoundingBox, FrameStats, InvalidZoom, Position, TileId,
+    center::Center,
+    flight::{Easing, Flight},
+    lon_lat,
+    mercator::{project, unproject},
+    position::{AdjustedPosition, Pixels},
+    zoom::Zoom,
+};
+
+/// Time constant of the zoom snapping animation.
+const ZOOM_SNAPPING_TAU: f64 = 0.1;
+
+/// How long, in seconds, the zoom needs to stay put before it snaps, so that it does not snap
+/// between the notches of a mouse wheel.
+pub(crate) const ZOOM_SNAPPING_DELAY: f64 = 0.15;
+
+/// Longest time step of [`MapMemory::fly_to`] animation, in seconds.
+const MAX_FLIGHT_STEP: f32 = 0.1;
 
 /// State of the map widget which must persist between frames.
 #[derive(Debug, Default, Clone)]
@@ -6,9 +28,107 @@ use crate::{InvalidZoom, Position, center::Center, position::AdjustedPosition, z
 pub struct MapMemory {
     pub(crate) center_mode: Center,
     pub(crate) zoom: Zoom,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) zoom_snapping: Option<f64>,
+    /// Set by [`MapMemory::center_at_screen_point`], resolved once the map's rect is known.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) pending_screen_point: Option<(Position, Pos2)>,
+    /// Accumulated by [`MapMemory::pan`], resolved once `my_position` is known.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) pending_pan: Vec2,
+    /// Set by [`MapMemory::fit_bounds`], resolved once the map's size is known.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) pending_fit: Option<BoundingBox>,
+    /// Started by [`MapMemory::fly_to`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) flight: Option<Flight>,
+    /// Started by scroll zoom, if animated, see [`crate::Map::zoom_animation`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) zoom_animation: Option<ZoomAnimation>,
+    /// `egui::InputState::time` the zoom was last changed by other means than touch, e.g. the
+    /// mouse wheel. Zoom snaps once it was long enough ago, or the fingers are lifted.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) zoomed_at: Option<f64>,
+    /// Whether zoom was changed by the user in the last frame.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) zooming: bool,
+    /// Set by [`MapMemory::with_home`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) home: Option<(Position, f64)>,
+    /// Ids of the tile layers hidden with [`MapMemory::set_layer_visible`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) hidden_layers: BTreeSet<String>,
+    /// Collected when enabled with [`crate::Map::frame_stats`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) frame_stats: Option<FrameStats>,
+    /// Copied from [`crate::Map::reduced_motion`] each frame, for the plugins.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) reduced_motion: bool,
+}
+
+/// Zoom gliding towards the target accumulated from scroll events.
+#[derive(Debug, Clone)]
+pub(crate) struct ZoomAnimation {
+    pub target: Zoom,
+    /// Zoom left by the animation in the last frame. If it is different now, something else has
+    /// changed the zoom in the meantime, so the animation is abandoned.
+    pub zoom: f64,
+    /// Position kept under the pointer, and the pointer's offset from the center of the map.
+    pub anchor: Option<(Position, Vec2)>,
 }
 
 impl MapMemory {
+    /// Make the zoom settle on the nearest multiple of `step` (e.g. `1.0` or `0.5`) once the
+    /// user stops zooming. Zooming itself stays continuous. Non-positive `step` disables
+    /// snapping.
+    pub fn with_zoom_snapping(mut self, step: f64) -> Self {
+        self.zoom_snapping = (step > 0.).then_some(step);
+        self
+    }
+
+    /// Position and zoom to go back to with [`MapMemory::reset_to_home`], e.g. the initial view of
+    /// the application.
+    pub fn with_home(mut self, position: Position, zoom: f64) -> Self {
+        self.home = Some((position, zoom));
+        self
+    }
+
+    /// Go back to the view given by [`MapMemory::with_home`], flying there with given `easing`, or
+    /// jumping if it is `None`. Without a home, the map follows `my_position` again, like by
+    /// default. Returns `Err(InvalidZoom)` if the home's zoom is out of range.
+    pub fn reset_to_home(&mut self, easing: Option<Easing>) -> Result<(), InvalidZoom> {
+        let Some((position, zoom)) = self.home else {
+            self.follow_my_position();
+            return Ok(());
+        };
+
+        match easing {
+            Some(easing) => self.fly_to(position, zoom, easing),
+            None => {
+                self.set_zoom(zoom)?;
+                self.center_at(position);
+                Ok(())
+            }
+        }
+    }
+
+    /// Show or hide the tile layer added with [`crate::Map::with_layer_id`], e.g. from a legend.
+    /// Layers are visible by default. Unlike [`crate::Map::with_layer_visible`], the choice is
+    /// remembered along with the rest of the map's state.
+    pub fn set_layer_visible(&mut self, id: impl Into<String>, visible: bool) {
+        let id = id.into();
+        if visible {
+            self.hidden_layers.remove(&id);
+        } else {
+            self.hidden_layers.insert(id);
+        }
+    }
+
+    /// Whether the tile layer of given id is visible. See [`MapMemory::set_layer_visible`].
+    pub fn layer_visible(&self, id: &str) -> bool {
+        !self.hidden_layers.contains(id)
+    }
+
     /// Try to zoom in, returning `Err(InvalidZoom)` if already at maximum.
     pub fn zoom_in(&mut self) -> Result<(), InvalidZoom> {
         self.zoom.zoom_in()
@@ -40,16 +160,555 @@ impl MapMemory {
     /// Whether the map is currently animating. Dragging, zooming and `my_position` changes are not
     /// considered animation.
     pub fn animating(&self) -> bool {
-        self.center_mode.animating()
+        self.center_mode.animating() || self.flight.is_some()
+    }
+
+    /// Whether the map is being dragged by mouse or finger.
+    pub fn dragging(&self) -> bool {
+        matches!(self.center_mode, Center::Moving { .. })
+    }
+
+    /// Whether the user changed the zoom, e.g. by scrolling or pinching, in the last frame the map
+    /// was shown.
+    pub fn zooming(&self) -> bool {
+        self.zooming
+    }
+
+    /// Whether the map is being dragged, zoomed or is animating. Useful for pausing expensive work
+    /// until the user is done interacting with the map.
+    pub fn moving(&self) -> bool {
+        self.dragging() || self.zooming() || self.animating()
+    }
+
+    /// Whether the map was shown with [`crate::Map::reduced_motion`] enabled in the last frame, so
+    /// that plugins can skip their animations as well.
+    pub fn reduced_motion(&self) -> bool {
+        self.reduced_motion
+    }
+
+    /// Statistics of the last frame the map was shown, if enabled with
+    /// [`crate::Map::frame_stats`].
+    pub fn frame_stats(&self) -> Option<&FrameStats> {
+        self.frame_stats.as_ref()
+    }
+
+    /// Move zoom a bit closer to the nearest snapping step within `zoom_range`, if snapping is
+    /// enabled, or right onto it if motion is reduced. Returns whether zoom has changed.
+    pub(crate) fn update_zoom_snapping(
+        &mut self,
+        delta_time: f32,
+        reduced_motion: bool,
+        zoom_range: Option<RangeInclusive<f64>>,
+    ) -> bool {
+        let Some(step) = self.zoom_snapping else {
+            return false;
+        };
+
+        // Map clamps the zoom to its range afterwards, so a step beyond it would never be reached.
+        let max_zoom = TileId::MAX_ZOOM as f64;
+        let zoom_range = zoom_range.unwrap_or(0. ..=max_zoom);
+        let min = zoom_range.start().clamp(0., max_zoom);
+        let max = zoom_range.end().clamp(0., max_zoom);
+        let lowest_step = (min / step).ceil() * step;
+        let highest_step = (max / step).floor() * step;
+        if lowest_step > highest_step {
+            // There is no step within the range.
+            return false;
+        }
+
+        let zoom = self.zoom();
+        let target = ((zoom / step).round() * step)
+            .clamp(lowest_step, highest_step)
+            .clamp(min, max);
+
+        let remaining = target - zoom;
+        if remaining == 0. {
+            false
+        } else if reduced_motion || remaining.abs() < 0.001 {
+            self.zoom = Zoom::try_from(target).unwrap_or(self.zoom);
+            true
+        } else {
+            // Exponentially drive the zoom towards the target.
+            let factor = 1. - (-(delta_time as f64) / ZOOM_SNAPPING_TAU).exp();
+            self.zoom.zoom_by(remaining * factor);
+            true
+        }
+    }
+
+    /// Target of the scroll zoom animation, unless zoom was changed in another way since.
+    pub(crate) fn zoom_animation_target(&self) -> Option<Zoom> {
+        self.zoom_animation
+            .as_ref()
+            .filter(|animation| animation.zoom == self.zoom())
+            .map(|animation| animation.target)
+    }
+
+    /// Move zoom a bit closer to the target of the scroll zoom animation, which is reached in
+    /// roughly given `duration`, keeping the anchor under the pointer. Returns whether zoom has
+    /// changed.
+    pub(crate) fn update_zoom_animation(&mut self, delta_time: f32, duration: Duration) -> bool {
+        let Some(target_zoom) = self.zoom_animation_target() else {
+            self.zoom_animation = None;
+            return false;
+        };
+
+        let target: f64 = target_zoom.into();
+        let remaining = target - self.zoom();
+        if remaining.abs() < 0.001 {
+            self.zoom = target_zoom;
+        } else {
+            // Exponentially drive the zoom towards the target, 95% of the way within `duration`.
+            let tau = duration.as_secs_f64() / 3.;
+            let factor = 1. - (-(delta_time as f64) / tau).exp();
+            self.zoom.zoom_by(remaining * factor);
+        }
+
+        let zoom = self.zoom();
+        let finished = zoom == target;
+        let dragging = self.dragging();
+        let Some(animation) = &mut self.zoom_animation else {
+            return true;
+        };
+
+        // Dragging takes over the center.
+        if let Some((position, offset)) = animation.anchor
+            && !dragging
+        {
+            self.center_mode = Center::Exact(AdjustedPosition::new(position).shift(offset, zoom));
+        }
+
+        if finished {
+            self.zoom_animation = None;
+        } else {
+            animation.zoom = zoom;
+        }
+        true
     }
 
     /// Point the map exactly at the given geographical position.
     pub fn center_at(&mut self, position: Position) {
+        self.pending_screen_point = None;
+        self.pending_pan = Vec2::ZERO;
+        self.pending_fit = None;
+        self.flight = None;
         self.center_mode = Center::Exact(AdjustedPosition::new(position));
     }
 
-    /// Start following `my_position` given in [`crate::Map::new`].
+    /// Move the map so that the given geographical position lands at the given screen point,
+    /// instead of the map's center. Useful when part of the map is covered by an overlay, such as
+    /// a bottom sheet. Map's rect is not known until it is shown, so this takes effect the next
+    /// time [`crate::Map`] is shown.
+    pub fn center_at_screen_point(&mut self, position: Position, point: Pos2) {
+        self.flight = None;
+        self.pending_screen_point = Some((position, point));
+    }
+
+    /// Resolve the position given to [`MapMemory::center_at_screen_point`], now that the map's
+    /// center is known. Returns whether the position has changed.
+    pub(crate) fn update_screen_point(&mut self, map_center: Pos2) -> bool {
+        let Some((position, point)) = self.pending_screen_point.take() else {
+            return false;
+        };
+
+        self.center_mode = Center::Exact(
+            AdjustedPosition::new(position).shift(point - map_center, self.zoom.into()),
+        );
+        true
+    }
+
+    /// Move the view by a number of pixels on the screen, e.g. `vec2(10., 0.)` reveals 10 more
+    /// pixels of the map on the right. Useful for custom controls, such as pan buttons. Map
+    /// becomes detached. This, and successive calls, take effect the next time [`crate::Map`] is
+    /// shown.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.flight = None;
+        self.pending_pan += delta;
+    }
+
+    /// Apply the offset given to [`MapMemory::pan`]. Returns whether the position has changed.
+    pub(crate) fn update_pan(&mut self, my_position: Position) -> bool {
+        let delta = std::mem::take(&mut self.pending_pan);
+        if delta == Vec2::ZERO {
+            return false;
+        }
+
+        self.center_mode = Center::Exact(
+            AdjustedPosition::new(self.center_mode.position(my_position))
+                .shift(-delta, self.zoom.into()),
+        );
+        true
+    }
+
+    /// Start following `my_position` given in [`crate::Map::new`]. Any movement in progress, such
+    /// as inertia after a drag, is cancelled.
+    ///
+    /// Together with [`MapMemory::detached`], it is enough for a "recenter" button:
+    ///
+    /// ```
+    /// # use walkers::MapMemory;
+    /// fn recenter_button(ui: &mut egui::Ui, map_memory: &mut MapMemory) {
+    ///     if map_memory.detached().is_some() && ui.button("Recenter").clicked() {
+    ///         map_memory.follow_my_position();
+    ///     }
+    /// }
+    /// ```
     pub fn follow_my_position(&mut self) {
+        self.pending_screen_point = None;
+        self.pending_pan = Vec2::ZERO;
+        self.pending_fit = None;
+        self.flight = None;
         self.center_mode = Center::MyPosition;
     }
+
+    /// Smoothly move the map to the given position and zoom, along the trajectory given by
+    /// `easing`. Map becomes detached. Any interaction with the map, or other method moving it,
+    /// cancels the flight. If [`crate::Map::reduced_motion`] is enabled, the map jumps right
+    /// to the destination.
+    pub fn fly_to(
+        &mut self,
+        position: Position,
+        zoom: f64,
+        easing: Easing,
+    ) -> Result<(), InvalidZoom> {
+        Zoom::try_from(zoom)?;
+        self.pending_screen_point = None;
+        self.pending_pan = Vec2::ZERO;
+        self.pending_fit = None;
+        self.flight = Some(Flight::new(position, zoom, easing));
+        Ok(())
+    }
+
+    /// Fly to `my_position`, like [`MapMemory::fly_to`], and follow it once arrived, like
+    /// [`MapMemory::follow_my_position`]. Handy for a "locate me" button. If `my_position`
+    /// given to [`crate::Map`] changes during the flight, the flight heads for the new one.
+    ///
+    /// ```
+    /// # use walkers::{Easing, MapMemory, Position};
+    /// fn locate_button(ui: &mut egui::Ui, map_memory: &mut MapMemory, my_position: Position) {
+    ///     if ui.button("Locate me").clicked() {
+    ///         let _ = map_memory.locate(my_position, 16., Easing::EaseInOut);
+    ///     }
+    /// }
+    /// ```
+    pub fn locate(
+        &mut self,
+        my_position: Position,
+        zoom: f64,
+        easing: Easing,
+    ) -> Result<(), InvalidZoom> {
+        self.fly_to(my_position, zoom, easing)?;
+        self.flight = Some(Flight::to_my_position(my_position, zoom, easing));
+        Ok(())
+    }
+
+    /// Move the flight started by [`MapMemory::fly_to`] forward. Returns whether the position
+    /// or zoom has changed.
+    pub(crate) fn update_flight(
+        &mut self,
+        delta_time: f32,
+        my_position: Position,
+        reduced_motion: bool,
+    ) -> bool {
+        let Some(flight) = &mut self.flight else {
+            return false;
+        };
+
+        if flight.follows_my_position() {
+            flight.retarget(my_position);
+        }
+
+        let delta_time = if reduced_motion {
+            f32::INFINITY
+        } else {
+            // Do not skip the flight after a stall, e.g. when the window was hidden.
+            delta_time.min(MAX_FLIGHT_STEP)
+        };
+        let (position, zoom, arrived) = flight.advance(
+            delta_time,
+            self.center_mode.position(my_position),
+            self.zoom.into(),
+        );
+
+        let follow = arrived && flight.follows_my_position();
+        if arrived {
+            self.flight = None;
+        }
+        self.zoom = Zoom::try_from(zoom).unwrap_or(self.zoom);
+        self.center_mode = if follow {
+            Center::MyPosition
+        } else {
+            Center::Exact(AdjustedPosition::new(position))
+        };
+        true
+    }
+
+    /// Center and zoom the map so that the area, such as the one from [`crate::Tiles::bounds`],
+    /// fills the view. Map becomes detached. The map's size is not known until it is shown, so
+    /// this takes effect the next time [`crate::Map`] is shown.
+    pub fn fit_bounds(&mut self, bounds: impl Into<BoundingBox>) {
+        self.pending_screen_point = None;
+        self.pending_pan = Vec2::ZERO;
+        self.flight = None;
+        self.pending_fit = Some(bounds.into());
+    }
+
+    /// Resolve the bounds given to [`MapMemory::fit_bounds`], now that the map's size is known.
+    /// Returns whether the position has changed.
+    pub(crate) fn update_fit(&mut self, size: Vec2) -> bool {
+        let Some(bounds) = self.pending_fit.take() else {
+            return false;
+        };
+
+        // Area can cross the antimeridian, so the east edge is reached by going east from the
+        // west one.
+        let south_west = project(bounds.south_west(), 0.);
+        let north_east = project(
+            lon_lat(bounds.west() + bounds.longitude_span(), bounds.north()),
+            0.,
+        );
+        let width = (north_east.x() - south_west.x()).abs();
+        let height = (north_east.y() - south_west.y()).abs();
+
+        // For a single point, this ends up at the maximum zoom.
+        let zoom = (size.x as f64 / width).min(size.y as f64 / height).log2();
+        let zoom = zoom.clamp(0., TileId::MAX_ZOOM as f64);
+        self.zoom = Zoom::try_from(zoom).unwrap_or(self.zoom);
+
+        let center = Pixels::new(
+            (south_west.x() + north_east.x()) / 2.,
+            (south_west.y() + north_east.y()) / 2.,
+        );
+        self.center_mode = Center::Exact(AdjustedPosition::new(unproject(center, 0.)));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settle(memory: &mut MapMemory) {
+        for _ in 0..100 {
+            memory.update_zoom_snapping(1. / 60., false, None);
+        }
+    }
+
+    #[test]
+    fn zoom_does_not_snap_by_default() {
+        let mut memory = MapMemory::default();
+        assert_eq!(memory.set_zoom(10.3), Ok(()));
+        assert!(!memory.update_zoom_snapping(1. / 60., false, None));
+        assert_eq!(memory.zoom(), 10.3);
+    }
+
+    #[test]
+    fn zoom_settles_on_nearest_step() {
+        let mut memory = MapMemory::default().with_zoom_snapping(1.);
+        assert_eq!(memory.set_zoom(10.3), Ok(()));
+
+        // It is animated, not immediate.
+        assert!(memory.update_zoom_snapping(1. / 60., false, None));
+        assert!(memory.zoom() > 10. && memory.zoom() < 10.3);
+
+        settle(&mut memory);
+        assert_eq!(memory.zoom(), 10.);
+        assert!(!memory.update_zoom_snapping(1. / 60., false, None));
+
+        let mut memory = MapMemory::default().with_zoom_snapping(0.5);
+        assert_eq!(memory.set_zoom(10.3), Ok(()));
+        settle(&mut memory);
+        assert_eq!(memory.zoom(), 10.5);
+    }
+
+    #[test]
+    fn zoom_snaps_immediately_when_motion_is_reduced() {
+        let mut memory = MapMemory::default().with_zoom_snapping(1.);
+        assert_eq!(memory.set_zoom(10.3), Ok(()));
+        assert!(memory.update_zoom_snapping(1. / 60., true, None));
+        assert_eq!(memory.zoom(), 10.);
+    }
+
+    #[test]
+    fn zoom_snapping_stays_within_limits() {
+        // Nearest step would be 27, which is beyond the maximum zoom.
+        let mut memory = MapMemory::default().with_zoom_snapping(3.);
+        assert_eq!(memory.set_zoom(25.6), Ok(()));
+        settle(&mut memory);
+        assert_eq!(memory.zoom(), 24.);
+    }
+
+    #[test]
+    fn zoom_snaps_only_to_steps_within_zoom_range() {
+        let mut memory = MapMemory::default().with_zoom_snapping(1.);
+
+        // Nearest steps would be 16 and 3, which are beyond the range.
+        assert_eq!(memory.set_zoom(15.5), Ok(()));
+        assert!(memory.update_zoom_snapping(1. / 60., true, Some(3.5..=15.5)));
+        assert_eq!(memory.zoom(), 15.);
+
+        assert_eq!(memory.set_zoom(3.5), Ok(()));
+        assert!(memory.update_zoom_snapping(1. / 60., true, Some(3.5..=15.5)));
+        assert_eq!(memory.zoom(), 4.);
+
+        // No step within the range at all, so zoom stays put.
+        assert_eq!(memory.set_zoom(3.5), Ok(()));
+        assert!(!memory.update_zoom_snapping(1. / 60., true, Some(3.2..=3.8)));
+        assert_eq!(memory.zoom(), 3.5);
+    }
+
+    #[test]
+    fn position_lands_at_given_screen_point() {
+        use crate::{Projector, lon_lat};
+        use egui::{Rect, pos2, vec2};
+
+        let rect = Rect::from_min_size(pos2(100., 50.), vec2(400., 600.));
+        let position = lon_lat(17.03664, 51.09916);
+        let point = pos2(300., 450.);
+
+        let mut memory = MapMemory::default();
+        memory.center_at_screen_point(position, point);
+
+        // Nothing happens until the map's rect is known.
+        assert_eq!(memory.detached(), None);
+        assert!(memory.update_screen_point(rect.center()));
+        assert!(!memory.update_screen_point(rect.center()));
+
+        let projected = Projector::new(rect, &memory, lon_lat(0., 0.)).project(position);
+        assert!((projected.to_pos2() - point).length() < 0.01);
+    }
+
+    #[test]
+    fn bounds_fill_the_view() {
+        use crate::{Projector, lon_lat};
+        use egui::{Rect, pos2, vec2};
+
+        let rect = Rect::from_min_size(pos2(100., 50.), vec2(400., 600.));
+        let (south_west, north_east) = (lon_lat(14.12, 49.0), lon_lat(24.15, 54.84));
+
+        let mut memory = MapMemory::default();
+        memory.fit_bounds((south_west, north_east));
+
+        // Nothing happens until the map's size is known.
+        assert_eq!(memory.detached(), None);
+        assert!(memory.update_fit(rect.size()));
+        assert!(!memory.update_fit(rect.size()));
+
+        // Area is wider than tall, so it spans the whole width.
+        let projector = Projector::new(rect, &memory, lon_lat(0., 0.));
+        let south_west = projector.project(south_west).to_pos2();
+        let north_east = projector.project(north_east).to_pos2();
+        assert!((south_west.x - rect.left()).abs() < 0.01);
+        assert!((north_east.x - rect.right()).abs() < 0.01);
+        assert!(south_west.y < rect.bottom() && north_east.y > rect.top());
+        assert!(((south_west.y + north_east.y) / 2. - rect.center().y).abs() < 0.01);
+    }
+
+    #[test]
+    fn bounds_across_the_antimeridian_are_fit_the_short_way() {
+        let mut memory = MapMemory::default();
+        memory.fit_bounds(BoundingBox::new(lon_lat(170., -10.), lon_lat(-170., 10.)));
+        assert!(memory.update_fit(Vec2::new(400., 400.)));
+
+        let center = memory.detached().expect("map is detached");
+        assert!((center.x().abs() - 180.).abs() < 1e-9, "{center:?}");
+        assert!(center.y().abs() < 1e-9);
+
+        // Not the whole world, which would fit at zoom 0.
+        assert!((4. ..5.).contains(&memory.zoom()), "{}", memory.zoom());
+    }
+
+    #[test]
+    fn flight_is_animated_unless_motion_is_reduced() {
+        use crate::lon_lat;
+
+        let destination = lon_lat(2.35, 48.86);
+        let mut memory = MapMemory::default();
+        assert_eq!(
+            memory.fly_to(destination, 30., Easing::Linear),
+            Err(InvalidZoom)
+        );
+        assert_eq!(memory.fly_to(destination, 10., Easing::Linear), Ok(()));
+        assert!(memory.animating());
+
+        let arrived_at = |memory: &MapMemory, destination: Position| {
+            memory.detached().is_some_and(|center| {
+                (center.x() - destination.x()).abs() < 1e-9
+                    && (center.y() - destination.y()).abs() < 1e-9
+            })
+        };
+
+        // Steps are limited, so one second takes ten frames of 0.1s.
+        for _ in 0..5 {
+            assert!(memory.update_flight(1., lon_lat(17.04, 51.1), false));
+        }
+        assert!(memory.zoom() > 10. && memory.zoom() < 16.);
+        assert!(!arrived_at(&memory, destination));
+
+        for _ in 0..5 {
+            assert!(memory.update_flight(0.1, lon_lat(17.04, 51.1), false));
+        }
+        assert!(arrived_at(&memory, destination));
+        assert_eq!(memory.zoom(), 10.);
+        assert!(!memory.animating());
+        assert!(!memory.update_flight(0.1, lon_lat(17.04, 51.1), false));
+
+        // With reduced motion, it arrives at once.
+        let destination = lon_lat(0., 0.);
+        assert_eq!(memory.fly_to(destination, 5., Easing::EaseInOut), Ok(()));
+        assert!(memory.update_flight(0.01, lon_lat(17.04, 51.1), true));
+        assert!(arrived_at(&memory, destination));
+        assert_eq!(memory.zoom(), 5.);
+        assert!(!memory.animating());
+    }
+
+    #[test]
+    fn located_position_is_followed_after_arrival() {
+        use crate::lon_lat;
+
+        let mut memory = MapMemory::default();
+        memory.center_at(lon_lat(17.04, 51.1));
+        assert_eq!(
+            memory.locate(lon_lat(2.35, 48.86), 14., Easing::Linear),
+            Ok(())
+        );
+
+        // Position moves during the flight.
+        for _ in 0..5 {
+            assert!(memory.update_flight(0.1, lon_lat(2.35, 48.86), false));
+        }
+        assert!(memory.detached().is_some());
+
+        for _ in 0..5 {
+            assert!(memory.update_flight(0.1, lon_lat(2.4, 48.9), false));
+        }
+        assert_eq!(memory.detached(), None);
+        assert_eq!(memory.zoom(), 14.);
+        assert!(!memory.animating());
+    }
+
+    #[test]
+    fn reset_goes_back_home() {
+        use crate::lon_lat;
+
+        let home = lon_lat(17.04, 51.1);
+        let mut memory = MapMemory::default().with_home(home, 12.);
+        memory.center_at(lon_lat(2.35, 48.86));
+        assert_eq!(memory.set_zoom(5.), Ok(()));
+
+        assert_eq!(memory.reset_to_home(None), Ok(()));
+        let center = memory.detached().expect("map should be detached");
+        approx::assert_relative_eq!(center.x(), home.x(), epsilon = 1e-9);
+        approx::assert_relative_eq!(center.y(), home.y(), epsilon = 1e-9);
+        assert_eq!(memory.zoom(), 12.);
+
+        memory.center_at(lon_lat(2.35, 48.86));
+        assert_eq!(memory.reset_to_home(Some(Easing::EaseInOut)), Ok(()));
+        assert!(memory.animating());
+
+        // Without home, it is the default view.
+        let mut memory = MapMemory::default();
+        memory.center_at(lon_lat(2.35, 48.86));
+        assert_eq!(memory.reset_to_home(None), Ok(()));
+        assert_eq!(memory.detached(), None);
+    }
 }
@@ -4,9 +4,8 @@
 //! <https://www.netzwolf.info/osm/tilebrowser.html?lat=51.157800&lon=6.865500&zoom=14>
 
 use crate::{
-    lon_lat,
+    TileId, lon_lat,
     position::{Pixels, Position},
-    tiles::TileId,
 };
 use std::f64::consts::PI;
 
@@ -26,7 +25,13 @@ pub(crate) fn total_tiles(zoom: u8) -> u32 {
 }
 
 /// Size of a single tile in pixels. Walkers uses 256px tiles as most of the tile sources do.
-const TILE_SIZE: u32 = 256;
+pub(crate) const TILE_SIZE: u32 = 256;
+
+/// Length of the equator, in meters.
+pub(crate) const EARTH_CIRCUMFERENCE: f64 = 40_075_016.686;
+
+/// Meters in an inch, which DPI is given in.
+const METERS_PER_INCH: f64 = 0.0254;
 
 /// Project the position into the Mercator projection and normalize it to 0-1 range.
 fn mercator_normalized(position: Position) -> (f64, f64) {
@@ -47,7 +52,9 @@ pub(crate) fn tile_id(position: Position, mut zoom: u8, source_tile_size: u32) -
 
     // Some sources provide larger tiles, effectively bundling e.g. 4 256px tiles in one
     // 512px one. Walkers uses 256px internally, so we need to adjust the zoom level.
-    zoom -= (source_tile_size as f64 / TILE_SIZE as f64).log2() as u8;
+    // At the lowest zoom levels, one such tile is more than the whole world, so it gets scaled
+    // down instead.
+    zoom = zoom.saturating_sub((source_tile_size as f64 / TILE_SIZE as f64).log2() as u8);
 
     // Map that into a big bitmap made out of web tiles.
     let number_of_tiles = 2u32.pow(zoom as u32) as f64;
@@ -64,8 +71,8 @@ pub fn project(position: Position, zoom: f64) -> Pixels {
     Pixels::new(x * total_pixels, y * total_pixels)
 }
 
-/// Transforms screen pixels into a geographical position.
-pub(crate) fn unproject(pixels: Pixels, zoom: f64) -> Position {
+/// Transforms pixels of the 2D plane back into a geographical position. Inverse of [`project`].
+pub fn unproject(pixels: Pixels, zoom: f64) -> Position {
     let number_of_pixels: f64 = 2f64.powf(zoom) * (TILE_SIZE as f64);
 
     let lon = pixels.x();
@@ -81,6 +88,22 @@ pub(crate) fn unproject(pixels: Pixels, zoom: f64) -> Position {
     lon_lat(lon, lat)
 }
 
+/// Denominator of the map scale, e.g. `50000` for 1:50000, at given zoom level and latitude, when
+/// shown on a screen with given DPI, such as `96.` for a typical desktop screen. Egui's points are
+/// what counts as pixels here, so DPI needs to account for egui's pixels per point.
+pub fn scale_denominator(zoom: f64, latitude: f64, dpi: f64) -> f64 {
+    let meters_per_pixel = EARTH_CIRCUMFERENCE * latitude.to_radians().cos() / total_pixels(zoom);
+    meters_per_pixel * dpi / METERS_PER_INCH
+}
+
+/// Zoom level at which the map has given scale denominator at given latitude. Inverse of
+/// [`scale_denominator`]. Zoom levels out of the map's range are rejected when set, so that is
+/// where a "set scale" input would report an error.
+pub fn zoom_for_scale(denominator: f64, latitude: f64, dpi: f64) -> f64 {
+    let meters_per_pixel = denominator * METERS_PER_INCH / dpi;
+    (EARTH_CIRCUMFERENCE * latitude.to_radians().cos() / meters_per_pixel / TILE_SIZE as f64).log2()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,6 +150,31 @@ mod tests {
         approx::assert_relative_eq!(calculated.y(), citadel_proj.y(), max_relative = 0.5);
     }
 
+    #[test]
+    fn scale_denominator_and_zoom_convert_both_ways() {
+        // Well known value for zoom 0 at the equator, at 0.28 mm per pixel, as in WMTS.
+        approx::assert_relative_eq!(
+            scale_denominator(0., 0., METERS_PER_INCH / 0.00028),
+            559_082_264.03,
+            max_relative = 1e-9
+        );
+
+        // At 60 degrees, a pixel covers half as many meters as at the equator.
+        approx::assert_relative_eq!(
+            scale_denominator(10., 60., 96.),
+            scale_denominator(10., 0., 96.) / 2.,
+            max_relative = 1e-9
+        );
+
+        let zoom = zoom_for_scale(50_000., 51.1, 96.);
+        approx::assert_relative_eq!(
+            scale_denominator(zoom, 51.1, 96.),
+            50_000.,
+            max_relative = 1e-9
+        );
+        assert!(zoom > 12. && zoom < 13., "{zoom}");
+    }
+
     #[test]
     fn project_there_and_back() {
         let citadel = lat_lon(21.00027, 52.26470);
@@ -1,6 +1,6 @@
 //! Renderer for Mapbox Vector Tiles.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use egui::{
     Color32, Mesh, Pos2, Shape, Stroke,
@@ -119,6 +119,149 @@ pub fn render(data: &mvt_reader::Reader) -> Result<Vec<ShapeOrText>, Error> {
     Ok(shapes)
 }
 
+/// How features of a single vector tile layer are drawn. Geometries without a matching color are
+/// skipped. Sizes are in pixels of a tile drawn at 256 pixels wide.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerStyle {
+    /// Fill of polygons, e.g. landuse.
+    pub fill: Option<Color32>,
+
+    /// Stroke of lines, e.g. roads, and of polygon outlines.
+    pub stroke: Option<Stroke>,
+
+    /// Color of points, e.g. POIs.
+    pub point: Option<Color32>,
+
+    /// Radius of points.
+    pub point_radius: f32,
+}
+
+impl Default for LayerStyle {
+    fn default() -> Self {
+        Self {
+            fill: None,
+            stroke: None,
+            point: None,
+            point_radius: 3.,
+        }
+    }
+}
+
+/// Maps the name of a vector tile layer to its style. Layers for which it returns `None` are not
+/// drawn.
+pub type VectorStyle = Arc<dyn Fn(&str) -> Option<LayerStyle> + Send + Sync>;
+
+/// Number of MVT units in a pixel of a tile drawn at 256 pixels wide.
+const UNITS_PER_PIXEL: f32 = ONLY_SUPPORTED_EXTENT as f32 / 256.;
+
+/// Render MVT data using the user-provided style. Unlike [`render`], it does not assume any
+/// particular schema, so it works with any vector tile service, but draws just the geometry.
+pub fn render_with_style(
+    data: &mvt_reader::Reader,
+    style: &VectorStyle,
+) -> Result<Vec<ShapeOrText>, Error> {
+    let mut shapes = Vec::new();
+
+    for layer in data.get_layer_metadata()? {
+        let Some(layer_style) = style(&layer.name) else {
+            continue;
+        };
+
+        // Bring everything to the extent expected by `transformed`.
+        let scale = ONLY_SUPPORTED_EXTENT as f32 / layer.extent.max(1) as f32;
+        let to_pos2 = |x: f32, y: f32| pos2(x * scale, y * scale);
+
+        for feature in data.get_features(layer.layer_index)? {
+            styled_geometry(&feature.geometry, &layer_style, &to_pos2, &mut shapes)?;
+        }
+    }
+
+    Ok(shapes)
+}
+
+fn styled_geometry(
+    geometry: &Geometry<f32>,
+    style: &LayerStyle,
+    to_pos2: &impl Fn(f32, f32) -> Pos2,
+    shapes: &mut Vec<ShapeOrText>,
+) -> Result<(), Error> {
+    let stroke = style
+        .stroke
+        .map(|stroke| Stroke::new(stroke.width * UNITS_PER_PIXEL, stroke.color));
+    let line = |line_string: &geo_types::LineString<f32>| -> Vec<Pos2> {
+        line_string.0.iter().map(|p| to_pos2(p.x, p.y)).collect()
+    };
+
+    match geometry {
+        Geometry::Point(point) => {
+            if let Some(color) = style.point {
+                let radius = style.point_radius * UNITS_PER_PIXEL;
+                shapes.push(
+                    Shape::circle_filled(to_pos2(point.x(), point.y()), radius, color).into(),
+                );
+            }
+        }
+        Geometry::MultiPoint(multi_point) => {
+            for point in multi_point {
+                styled_geometry(&Geometry::Point(*point), style, to_pos2, shapes)?;
+            }
+        }
+        Geometry::LineString(line_string) => {
+            if let Some(stroke) = stroke {
+                shapes.push(Shape::line(line(line_string), stroke).into());
+            }
+        }
+        Geometry::MultiLineString(multi_line_string) => {
+            for line_string in multi_line_string {
+                styled_geometry(
+                    &Geometry::LineString(line_string.clone()),
+                    style,
+                    to_pos2,
+                    shapes,
+                )?;
+            }
+        }
+        Geometry::Polygon(polygon) => {
+            let exterior = line(polygon.exterior());
+            let interiors: Vec<_> = polygon.interiors().iter().map(line).collect();
+
+            if let Some(fill) = style.fill {
+                shapes.push(tessellate_polygon(&exterior, &interiors, fill)?.into());
+            }
+
+            if let Some(stroke) = stroke {
+                shapes.push(Shape::closed_line(exterior, stroke).into());
+                for interior in interiors {
+                    shapes.push(Shape::closed_line(interior, stroke).into());
+                }
+            }
+        }
+        Geometry::MultiPolygon(multi_polygon) => {
+            for polygon in multi_polygon {
+                styled_geometry(&Geometry::Polygon(polygon.clone()), style, to_pos2, shapes)?;
+            }
+        }
+        Geometry::Line(_)
+        | Geometry::GeometryCollection(_)
+        | Geometry::Rect(_)
+        | Geometry::Triangle(_) => {
+            // MVT does not encode these.
+        }
+    }
+    Ok(())
+}
+
+/// Vector tiles are often served gzipped, without the `Content-Encoding` header.
+pub fn gunzipped(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut buf = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(data), &mut buf)?;
+        Ok(buf)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
 /// Transform shapes from MVT space to screen space.
 pub fn transformed(shapes: &[ShapeOrText], rect: egui::Rect) -> Vec<ShapeOrText> {
     let transform = TSTransform {
@@ -341,3 +484,75 @@ fn tessellate_polygon(
 fn lyon_points(points: &[Pos2]) -> Vec<Point<f32>> {
     points.iter().map(|p| point(p.x, p.y)).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: u32, buf: &mut Vec<u8>) {
+        while value >= 0x80 {
+            buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        buf.push(value as u8);
+    }
+
+    fn field(tag: u32, payload: &[u8], buf: &mut Vec<u8>) {
+        varint(tag << 3 | 2, buf);
+        varint(payload.len() as u32, buf);
+        buf.extend_from_slice(payload);
+    }
+
+    /// Encode a layer with a single line from `(0, 0)` to `(x, 0)`.
+    fn layer_with_line(name: &str, extent: u32, x: u32) -> Vec<u8> {
+        let mut geometry = Vec::new();
+        // MoveTo(0, 0), LineTo(x, 0), zigzag encoded.
+        for value in [9, 0, 0, 10, x << 1, 0] {
+            varint(value, &mut geometry);
+        }
+
+        let mut feature = vec![3 << 3, 2]; // LINESTRING
+        field(4, &geometry, &mut feature);
+
+        let mut layer = vec![15 << 3, 2]; // Version 2.
+        field(1, name.as_bytes(), &mut layer);
+        field(2, &feature, &mut layer);
+        layer.push(5 << 3);
+        varint(extent, &mut layer);
+        layer
+    }
+
+    #[test]
+    fn layers_are_drawn_using_given_style() {
+        let mut tile = Vec::new();
+        field(3, &layer_with_line("roads", 512, 64), &mut tile);
+        field(3, &layer_with_line("rivers", 4096, 100), &mut tile);
+        let reader = mvt_reader::Reader::new(tile).expect("valid MVT");
+
+        let style: VectorStyle = Arc::new(|layer| {
+            (layer == "roads").then(|| LayerStyle {
+                stroke: Some(Stroke::new(1., Color32::GRAY)),
+                ..Default::default()
+            })
+        });
+
+        let shapes = render_with_style(&reader, &style).expect("rendered");
+
+        // Rivers are skipped, and roads are scaled to the 4096 extent, including the stroke width.
+        let [ShapeOrText::Shape(Shape::Path(path))] = shapes.as_slice() else {
+            panic!("expected a single line, got {shapes:?}");
+        };
+        assert_eq!(path.points, vec![pos2(0., 0.), pos2(512., 0.)]);
+        assert_eq!(path.stroke.width, 16.);
+    }
+
+    #[test]
+    fn gzipped_data_is_decompressed() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b"tile").expect("compressed");
+        let gzipped = encoder.finish().expect("compressed");
+
+        assert_eq!(gunzipped(&gzipped).expect("decompressed"), b"tile");
+        assert_eq!(gunzipped(b"tile").expect("passed through"), b"tile");
+    }
+}
@@ -1,10 +1,14 @@
 use crate::{
-    TextureWithUv, TileId, Tiles, download::Fetch, loader::Loader, sources::Attribution,
+    BoundingBox, TextureWithUv, TileId, Tiles,
+    download::{DEFAULT_CACHE_CAPACITY, Fetch},
+    loader::Loader,
+    sources::Attribution,
     tiles::interpolate_from_lower_zoom,
 };
 use bytes::Bytes;
-use pmtiles::{AsyncPmTilesReader, TileCoord};
+use pmtiles::{AsyncPmTilesReader, Header, TileCoord};
 use std::{
+    fs::File,
     io::{self, Read as _},
     path::{Path, PathBuf},
 };
@@ -15,12 +19,41 @@ use thiserror::Error;
 /// <https://docs.protomaps.com/guide/getting-started>
 pub struct PmTiles {
     loader: Loader,
+    bounds: Option<BoundingBox>,
 }
 
 impl PmTiles {
     pub fn new(path: impl AsRef<Path>) -> Self {
+        Self::new_with_texture_filter(path, egui::TextureFilter::Linear)
+    }
+
+    /// Like [`PmTiles::new`], but tiles are sampled using the given filter when drawn scaled, e.g.
+    /// [`egui::TextureFilter::Nearest`] for a crisp, pixelated look. Textures are created in the
+    /// background as tiles are read, so the filter is given upfront rather than set afterwards.
+    pub fn new_with_texture_filter(
+        path: impl AsRef<Path>,
+        texture_filter: egui::TextureFilter,
+    ) -> Self {
+        let bounds = read_header(path.as_ref())
+            .inspect_err(|err| log::warn!("Could not read PMTiles header: {err}"))
+            .ok()
+            .map(|header| {
+                BoundingBox::new(
+                    crate::lon_lat(header.min_longitude, header.min_latitude),
+                    crate::lon_lat(header.max_longitude, header.max_latitude),
+                )
+            });
+
         Self {
-            loader: Loader::new(PmTilesFetch::new(path.as_ref()), egui::Context::default()),
+            loader: Loader::new(
+                PmTilesFetch::new(path.as_ref()),
+                texture_filter,
+                false,
+                false,
+                DEFAULT_CACHE_CAPACITY,
+                egui::Context::default(),
+            ),
+            bounds,
         }
     }
 
@@ -32,11 +65,13 @@ impl PmTiles {
         loop {
             let (zoomed_tile_id, uv) = interpolate_from_lower_zoom(tile_id, zoom_candidate);
 
-            if let Some(Some(texture)) = self.loader.cache.get(&zoomed_tile_id) {
-                break Some(TextureWithUv {
-                    texture: texture.clone(),
-                    uv,
-                });
+            if let Some(texture) = self
+                .loader
+                .cache
+                .get(&zoomed_tile_id)
+                .and_then(|cached| cached.texture(uv))
+            {
+                break Some(texture);
             }
 
             // Keep zooming out until we find a donor or there is no more zoom levels.
@@ -53,12 +88,7 @@ impl Tiles for PmTiles {
             return None;
         }
 
-        let tile_id_to_download = if tile_id.zoom > 16 {
-            interpolate_from_lower_zoom(tile_id, 16).0
-        } else {
-            tile_id
-        };
-
+        let tile_id_to_download = tile_id_to_download(tile_id);
         self.loader.make_sure_is_downloaded(tile_id_to_download);
         self.get_from_cache_or_interpolate(tile_id)
     }
@@ -77,6 +107,37 @@ impl Tiles for PmTiles {
         // tile, the more details are visible.
         1024
     }
+
+    fn missing(&self, tile_id: TileId) -> bool {
+        self.loader.missing(tile_id_to_download(tile_id))
+    }
+
+    fn reload_textures(&mut self) {
+        self.loader.reload_textures();
+    }
+
+    /// As given in the file's header.
+    fn bounds(&self) -> Option<BoundingBox> {
+        self.bounds
+    }
+}
+
+/// Size of the PMTiles v3 header, which is at the very beginning of the file.
+const HEADER_SIZE: usize = 127;
+
+fn read_header(path: &Path) -> Result<Header, PmTilesError> {
+    let mut bytes = vec![0; HEADER_SIZE];
+    File::open(path)?.read_exact(&mut bytes)?;
+    Ok(Header::try_from_bytes(bytes.into())?)
+}
+
+/// Tiles above zoom 16 are not loaded, but interpolated from the lower ones.
+fn tile_id_to_download(tile_id: TileId) -> TileId {
+    if tile_id.zoom > 16 {
+        interpolate_from_lower_zoom(tile_id, 16).0
+    } else {
+        tile_id
+    }
 }
 
 #[derive(Debug, Error)]
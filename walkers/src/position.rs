@@ -1,6 +1,8 @@
 //! Types and functions for working with positions.
 
+#[cfg(feature = "widget")]
 use crate::mercator::{project, unproject};
+#[cfg(feature = "widget")]
 use egui::Vec2;
 
 /// Geographical position with latitude and longitude.
@@ -18,7 +20,203 @@ pub fn lon_lat(lon: f64, lat: f64) -> Position {
     Position::new(lon, lat)
 }
 
+/// Mean radius of the Earth, in meters.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Geographical calculations on a [`Position`], which, being a type from `geo_types`, cannot have
+/// methods of its own.
+pub trait PositionExt {
+    /// Position displaced by given number of meters to the north and to the east. Negative values
+    /// go south and west. Earth is treated as flat around the position, which is accurate for
+    /// offsets up to a few kilometers, except close to the poles.
+    fn offset_meters(&self, north: f64, east: f64) -> Position;
+
+    /// Great-circle distance to the other position, in meters.
+    fn distance_meters(&self, other: &Position) -> f64;
+
+    /// Whether the position is within the area, such as the one visible on the map. See
+    /// [`BoundingBox::contains`].
+    fn in_bounds(&self, bounds: impl Into<BoundingBox>) -> bool;
+}
+
+impl PositionExt for Position {
+    fn offset_meters(&self, north: f64, east: f64) -> Position {
+        let lat = self.y() + (north / EARTH_RADIUS).to_degrees();
+        let lon = self.x() + (east / (EARTH_RADIUS * self.y().to_radians().cos())).to_degrees();
+        lon_lat(lon, lat)
+    }
+
+    fn distance_meters(&self, other: &Position) -> f64 {
+        // Haversine formula.
+        let (lat1, lat2) = (self.y().to_radians(), other.y().to_radians());
+        let half_dlat = (lat2 - lat1) / 2.;
+        let half_dlon = (other.x() - self.x()).to_radians() / 2.;
+        let a = half_dlat.sin().powi(2) + lat1.cos() * lat2.cos() * half_dlon.sin().powi(2);
+        2. * EARTH_RADIUS * a.sqrt().asin()
+    }
+
+    fn in_bounds(&self, bounds: impl Into<BoundingBox>) -> bool {
+        bounds.into().contains(*self)
+    }
+}
+
+/// Items whose positions are within the bounds, e.g. to give a plugin only the markers which are
+/// currently visible. See [`PositionExt::in_bounds`].
+pub fn filter_in_bounds<'a, T: 'a>(
+    items: impl IntoIterator<Item = &'a T>,
+    position: impl Fn(&T) -> Position,
+    bounds: impl Into<BoundingBox>,
+) -> impl Iterator<Item = &'a T> {
+    let bounds = bounds.into();
+    items
+        .into_iter()
+        .filter(move |item| position(item).in_bounds(bounds))
+}
+
+/// Geographical area between its south-west and north-east corners. Longitudes wrap around, so the
+/// area can cross the antimeridian, either with east below west, or with longitudes beyond
+/// `-180..180`, as the bounds of a map scrolled past it do. It converts from and to a tuple of
+/// the corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct BoundingBox {
+    south_west: Position,
+    north_east: Position,
+}
+
+impl BoundingBox {
+    /// Area between the corners, kept as given, without wrapping the longitudes into `-180..180`.
+    pub fn new(south_west: Position, north_east: Position) -> Self {
+        Self {
+            south_west,
+            north_east,
+        }
+    }
+
+    pub fn south_west(&self) -> Position {
+        self.south_west
+    }
+
+    pub fn north_east(&self) -> Position {
+        self.north_east
+    }
+
+    /// Latitude of the north edge.
+    pub fn north(&self) -> f64 {
+        self.north_east.y()
+    }
+
+    /// Latitude of the south edge.
+    pub fn south(&self) -> f64 {
+        self.south_west.y()
+    }
+
+    /// Longitude of the east edge, as given, so it can be beyond 180 or below the west one.
+    pub fn east(&self) -> f64 {
+        self.north_east.x()
+    }
+
+    /// Longitude of the west edge, as given, so it can be below -180 or above the east one.
+    pub fn west(&self) -> f64 {
+        self.south_west.x()
+    }
+
+    /// Degrees of longitude which the area spans eastwards from its west edge, up to 360.
+    pub(crate) fn longitude_span(&self) -> f64 {
+        let span = self.east() - self.west();
+        if span >= 360. {
+            360.
+        } else {
+            span.rem_euclid(360.)
+        }
+    }
+
+    /// Whether the position is within the area, edges included.
+    pub fn contains(&self, position: Position) -> bool {
+        (self.south()..=self.north()).contains(&position.y())
+            && (position.x() - self.west()).rem_euclid(360.) <= self.longitude_span()
+    }
+
+    /// Whether the areas have any part in common, edges included.
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.south() <= other.north()
+            && other.south() <= self.north()
+            && ((other.west() - self.west()).rem_euclid(360.) <= self.longitude_span()
+                || (self.west() - other.west()).rem_euclid(360.) <= other.longitude_span())
+    }
+
+    /// Smallest area containing both areas, or an area and a [`Position`]. Horizontally, it goes
+    /// the shorter way around the world.
+    pub fn union(&self, other: impl Into<BoundingBox>) -> Self {
+        let other = other.into();
+
+        // Span needed to reach the end of `to`, when starting at the west edge of `from`.
+        let span = |from: &Self, to: &Self| {
+            ((to.west() - from.west()).rem_euclid(360.) + to.longitude_span())
+                .max(from.longitude_span())
+                .min(360.)
+        };
+        let (west, span) = if span(self, &other) <= span(&other, self) {
+            (self.west(), span(self, &other))
+        } else {
+            (other.west(), span(&other, self))
+        };
+
+        Self::new(
+            lon_lat(west, self.south().min(other.south())),
+            lon_lat(west + span, self.north().max(other.north())),
+        )
+    }
+
+    /// Area grown by given number of meters in every direction, but not beyond the poles. Degrees
+    /// of longitude are measured at the latitude farthest from the equator, so that the margin is
+    /// at least as wide everywhere.
+    pub fn expand(&self, meters: f64) -> Self {
+        let lat = (meters / EARTH_RADIUS).to_degrees();
+        let (south, north) = (
+            (self.south() - lat).max(-90.),
+            (self.north() + lat).min(90.),
+        );
+
+        let farthest = south.abs().max(north.abs()).to_radians();
+        let lon = (meters / (EARTH_RADIUS * farthest.cos())).to_degrees();
+        let span = (self.longitude_span() + 2. * lon).clamp(0., 360.);
+        let west = self.west() - (span - self.longitude_span()) / 2.;
+
+        Self::new(lon_lat(west, south), lon_lat(west + span, north))
+    }
+
+    /// Middle of the area, with longitude wrapped to `-180..180`.
+    pub fn center(&self) -> Position {
+        let lon = self.west() + self.longitude_span() / 2.;
+        lon_lat(
+            (lon + 180.).rem_euclid(360.) - 180.,
+            (self.south() + self.north()) / 2.,
+        )
+    }
+}
+
+impl From<(Position, Position)> for BoundingBox {
+    fn from((south_west, north_east): (Position, Position)) -> Self {
+        Self::new(south_west, north_east)
+    }
+}
+
+impl From<BoundingBox> for (Position, Position) {
+    fn from(bounds: BoundingBox) -> Self {
+        (bounds.south_west, bounds.north_east)
+    }
+}
+
+/// Area of a single point.
+impl From<Position> for BoundingBox {
+    fn from(position: Position) -> Self {
+        Self::new(position, position)
+    }
+}
+
 /// Geographical [`Position`] shifted by a number of pixels on the screen.
+#[cfg(feature = "widget")]
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub struct AdjustedPosition {
@@ -30,6 +228,7 @@ pub struct AdjustedPosition {
     zoom: f64,
 }
 
+#[cfg(feature = "widget")]
 impl AdjustedPosition {
     pub fn new(position: Position) -> Self {
         Self {
@@ -53,6 +252,20 @@ impl AdjustedPosition {
         }
     }
 
+    /// Shift the position by whole world widths, so that the real longitude is within
+    /// `-180..180` range. Map repeats horizontally, so it still points at the same place.
+    pub fn wrap_longitude(self) -> Self {
+        let turns = ((self.position().x() + 180.) / 360.).floor();
+        if turns == 0. {
+            self
+        } else {
+            Self {
+                position: lon_lat(self.position.x() - turns * 360., self.position.y()),
+                ..self
+            }
+        }
+    }
+
     pub fn offset_length(&self) -> f32 {
         self.offset.to_vec2().length()
     }
@@ -69,11 +282,13 @@ impl AdjustedPosition {
 /// Location projected on the screen or an abstract bitmap.
 pub type Pixels = geo_types::Point;
 
+#[cfg(feature = "widget")]
 pub trait PixelsExt {
     fn to_vec2(&self) -> egui::Vec2;
     fn from_vec2(_: egui::Vec2) -> Self;
 }
 
+#[cfg(feature = "widget")]
 impl PixelsExt for Pixels {
     fn to_vec2(&self) -> egui::Vec2 {
         egui::Vec2::new(self.x() as f32, self.y() as f32)
@@ -89,10 +304,145 @@ mod tests {
     use super::*;
     use approx::assert_relative_eq;
 
+    #[test]
+    fn offsetting_by_meters() {
+        let zoo = lat_lon(51.104465, 17.075169);
+
+        // One degree of latitude is about 111 km everywhere.
+        let north = zoo.offset_meters(1000., 0.);
+        assert_relative_eq!(north.y() - zoo.y(), 1000. / 111_195., epsilon = 1e-6);
+        assert_relative_eq!(north.x(), zoo.x());
+
+        // While a degree of longitude gets shorter away from the equator.
+        let east = zoo.offset_meters(0., 1000.);
+        assert_relative_eq!(
+            east.x() - zoo.x(),
+            1000. / (111_195. * 51.104465_f64.to_radians().cos()),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(east.y(), zoo.y());
+
+        // Going back and forth stays within a few centimeters.
+        let back = zoo.offset_meters(-250., 400.).offset_meters(250., -400.);
+        assert_relative_eq!(back.x(), zoo.x(), epsilon = 1e-6);
+        assert_relative_eq!(back.y(), zoo.y(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn measuring_distance() {
+        let zoo = lat_lon(51.104465, 17.075169);
+        assert_relative_eq!(zoo.distance_meters(&zoo), 0.);
+
+        // Agrees with the offsets for short distances.
+        let there = zoo.offset_meters(300., -400.);
+        assert_relative_eq!(zoo.distance_meters(&there), 500., epsilon = 0.01);
+        assert_relative_eq!(there.distance_meters(&zoo), 500., epsilon = 0.01);
+
+        // Quarter of the equator.
+        assert_relative_eq!(
+            lat_lon(0., 0.).distance_meters(&lat_lon(0., 90.)),
+            std::f64::consts::FRAC_PI_2 * EARTH_RADIUS
+        );
+    }
+
+    #[test]
+    fn filtering_positions_in_bounds() {
+        let places = [
+            lon_lat(17., 51.),
+            lon_lat(179., 0.),
+            lon_lat(-179., 0.),
+            lon_lat(-179., 60.),
+        ];
+        let filtered = |bounds| {
+            filter_in_bounds(&places, |place| *place, bounds)
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            filtered((lon_lat(10., 40.), lon_lat(20., 55.))),
+            [lon_lat(17., 51.)]
+        );
+
+        // Crossing the antimeridian, whether with longitudes beyond 180 or with east below west.
+        let across = [lon_lat(179., 0.), lon_lat(-179., 0.)];
+        assert_eq!(filtered((lon_lat(170., -10.), lon_lat(190., 10.))), across);
+        assert_eq!(filtered((lon_lat(170., -10.), lon_lat(-170., 10.))), across);
+        assert_eq!(
+            filtered((lon_lat(-190., -10.), lon_lat(-170., 10.))),
+            across
+        );
+
+        // Whole world, horizontally.
+        assert_eq!(
+            filtered((lon_lat(-300., 30.), lon_lat(300., 90.))),
+            [lon_lat(17., 51.), lon_lat(-179., 60.)]
+        );
+    }
+
+    #[test]
+    fn bounding_boxes_intersect_across_the_antimeridian() {
+        let pacific = BoundingBox::new(lon_lat(170., -20.), lon_lat(-170., 10.));
+        let fiji = BoundingBox::new(lon_lat(177., -19.), lon_lat(182., -16.));
+        let samoa = BoundingBox::new(lon_lat(-173., -15.), lon_lat(-171., -13.));
+        let europe = BoundingBox::new(lon_lat(-10., 35.), lon_lat(30., 70.));
+
+        assert!(pacific.intersects(&fiji) && fiji.intersects(&pacific));
+        assert!(pacific.intersects(&samoa) && samoa.intersects(&pacific));
+        assert!(!fiji.intersects(&samoa));
+        assert!(!pacific.intersects(&europe) && !europe.intersects(&pacific));
+
+        // Same longitudes, but different latitudes.
+        let north = BoundingBox::new(lon_lat(170., 20.), lon_lat(-170., 30.));
+        assert!(!pacific.intersects(&north));
+    }
+
+    #[test]
+    fn bounding_box_union_goes_the_shorter_way() {
+        let fiji = BoundingBox::new(lon_lat(177., -19.), lon_lat(182., -16.));
+        let samoa = BoundingBox::new(lon_lat(-173., -15.), lon_lat(-171., -13.));
+
+        for union in [fiji.union(samoa), samoa.union(fiji)] {
+            assert_relative_eq!(union.longitude_span(), 12., epsilon = 1e-9);
+            assert!(union.contains(lon_lat(180., -14.)));
+            assert!(!union.contains(lon_lat(0., -14.)));
+            assert_relative_eq!(union.south(), -19.);
+            assert_relative_eq!(union.north(), -13.);
+            assert_relative_eq!(union.center().x(), -177., epsilon = 1e-9);
+        }
+
+        // Growing by points.
+        let union = BoundingBox::from(lon_lat(17., 51.)).union(lon_lat(2., 49.));
+        assert_eq!(union, BoundingBox::new(lon_lat(2., 49.), lon_lat(17., 51.)));
+        assert_eq!(union.center(), lon_lat(9.5, 50.));
+    }
+
+    #[test]
+    fn expanding_bounding_box_by_meters() {
+        let zoo = lat_lon(51.104465, 17.075169);
+        let expanded = BoundingBox::from(zoo).expand(1000.);
+
+        // As wide as the farthest edge from the equator needs.
+        let north_east = zoo.offset_meters(1000., 0.);
+        let north_east = north_east.offset_meters(0., 1000.);
+        assert_relative_eq!(expanded.north(), north_east.y(), epsilon = 1e-9);
+        assert_relative_eq!(expanded.east(), north_east.x(), epsilon = 1e-9);
+        assert_relative_eq!(zoo.x() - expanded.west(), north_east.x() - zoo.x());
+        assert_relative_eq!(expanded.center().x(), zoo.x(), epsilon = 1e-9);
+        assert_relative_eq!(expanded.center().y(), zoo.y(), epsilon = 1e-9);
+
+        // Not beyond the poles, where the whole world is within any distance.
+        let expanded = BoundingBox::from(lon_lat(0., 89.99)).expand(10_000.);
+        assert_eq!(expanded.north(), 90.);
+        assert!(expanded.contains(lon_lat(180., 89.99)));
+    }
+
+    #[cfg(feature = "widget")]
     fn base_adjusted_position() -> AdjustedPosition {
         AdjustedPosition::new(lat_lon(51.0, 17.0))
     }
 
+    #[cfg(feature = "widget")]
     #[test]
     fn shifting_adjusted_position() {
         let position = base_adjusted_position().shift(Pixels::new(10.0, 20.0).to_vec2(), 10.0);
@@ -105,6 +455,7 @@ mod tests {
         assert_relative_eq!(position.position().y(), 55.21655462355652);
     }
 
+    #[cfg(feature = "widget")]
     #[test]
     fn shifting_adjusted_position_by_nothing() {
         let position = base_adjusted_position()
@@ -114,6 +465,7 @@ mod tests {
         assert_relative_eq!(position.position().y(), 55.21655462355652);
     }
 
+    #[cfg(feature = "widget")]
     #[test]
     fn shifting_adjusted_position_using_different_zoom() {
         let position = base_adjusted_position()
@@ -124,6 +476,7 @@ mod tests {
         assert_relative_eq!(position.position().y(), 51.017281581280216);
     }
 
+    #[cfg(feature = "widget")]
     #[test]
     fn test_adjusted_position_offset_length() {
         let position = base_adjusted_position().shift(Pixels::new(10.0, 0.0).to_vec2(), 10.0);
@@ -133,4 +486,19 @@ mod tests {
         let position = position.shift(Pixels::new(10.0, 0.0).to_vec2(), 10.0);
         assert_relative_eq!(position.offset_length(), 20.0);
     }
+
+    #[cfg(feature = "widget")]
+    #[test]
+    fn wrapping_adjusted_position() {
+        // Dragged past the antimeridian, by half of the world at zoom 0.
+        let position = AdjustedPosition::new(lon_lat(170.0, 0.0))
+            .shift(Pixels::new(-128.0, 0.0).to_vec2(), 0.0)
+            .wrap_longitude();
+        assert_relative_eq!(position.position().x(), -10.0, epsilon = 1e-9);
+        assert_relative_eq!(position.position().y(), 0.0, epsilon = 1e-9);
+
+        // Within the range, nothing changes.
+        let position = base_adjusted_position().wrap_longitude();
+        assert_eq!(position, base_adjusted_position());
+    }
 }
@@ -1,8 +1,8 @@
-use egui::{Rect, Vec2};
+use egui::{Pos2, Rect, Vec2};
 
 use crate::{
-    MapMemory, Position,
-    mercator::{project, unproject},
+    BoundingBox, MapMemory, Position, TileId,
+    mercator::{EARTH_CIRCUMFERENCE, project, total_pixels, total_tiles, unproject},
     position::{Pixels, PixelsExt as _},
 };
 
@@ -10,23 +10,89 @@ use crate::{
 #[derive(Clone)]
 pub struct Projector {
     clip_rect: Rect,
+
+    /// Part of the `clip_rect` which is actually drawn, see [`Projector::clipped`].
+    visible_rect: Rect,
+
     memory: MapMemory,
     my_position: Position,
+
+    /// Which copy of the world is being projected, as the map repeats horizontally. `0` is the
+    /// one with longitudes within `-180..180` range.
+    world_copy: i32,
 }
 
 impl Projector {
     pub fn new(clip_rect: Rect, map_memory: &MapMemory, my_position: Position) -> Self {
         Self {
             clip_rect,
+            visible_rect: clip_rect,
             memory: map_memory.to_owned(),
             my_position,
+            world_copy: 0,
         }
     }
 
+    /// Map repeats horizontally, so at low zoom levels the same place can be visible a couple of
+    /// times. This returns a projector for each copy of the world visible in the viewport, so that
+    /// plugins can draw their shapes in all of them.
+    pub fn world_copies(&self) -> impl Iterator<Item = Projector> + '_ {
+        let world_width = total_pixels(self.memory.zoom());
+        let center = project(
+            self.memory.center_mode.position(self.my_position),
+            self.memory.zoom(),
+        )
+        .x();
+        let left = center + (self.visible_rect.left() - self.clip_rect.center().x) as f64;
+        let right = center + (self.visible_rect.right() - self.clip_rect.center().x) as f64;
+
+        let first = (left / world_width).floor() as i32;
+        let last = (right / world_width).floor() as i32;
+
+        (first..=last).map(|world_copy| Projector {
+            world_copy,
+            ..self.clone()
+        })
+    }
+
+    /// Projector which puts everything `offset` pixels away from where this one does, e.g. to
+    /// draw a marker on its way to its position.
+    pub fn translated(&self, offset: Vec2) -> Projector {
+        Projector {
+            clip_rect: self.clip_rect.translate(offset),
+            visible_rect: self.visible_rect.translate(offset),
+            ..self.clone()
+        }
+    }
+
+    /// Projector for the same view, but with only the given part of the viewport being drawn,
+    /// e.g. by [`crate::Map::with_clip_rect`]. Positions are projected exactly as before, but
+    /// [`Projector::bounds`] and [`Projector::world_copies`] cover just that part.
+    pub fn clipped(&self, rect: Rect) -> Projector {
+        Projector {
+            visible_rect: self.visible_rect.intersect(rect),
+            ..self.clone()
+        }
+    }
+
+    /// Part of the viewport which is drawn. The whole of it, unless [`Projector::clipped`].
+    pub fn visible_rect(&self) -> Rect {
+        self.visible_rect
+    }
+
+    /// Horizontal offset of the projected copy of the world, in pixels.
+    fn world_offset(&self) -> f64 {
+        self.world_copy as f64 * total_pixels(self.memory.zoom())
+    }
+
     /// Project `position` into pixels on the viewport.
     pub fn project(&self, position: Position) -> Vec2 {
         // Turn that into a flat, mercator projection.
         let projected_position = project(position, self.memory.zoom());
+        let projected_position = Pixels::new(
+            projected_position.x() + self.world_offset(),
+            projected_position.y(),
+        );
 
         // We also need to know where the map center is.
         let map_center_projected_position = project(
@@ -48,12 +114,138 @@ impl Projector {
         // that we must do the arithmetic in f64 to avoid imprecision.
         let map_center_projected_position = project(center, zoom);
         let clip_center = self.clip_rect.center();
-        let x = map_center_projected_position.x() + (position.x as f64) - (clip_center.x as f64);
+        let x = map_center_projected_position.x() + (position.x as f64)
+            - (clip_center.x as f64)
+            - self.world_offset();
         let y = map_center_projected_position.y() + (position.y as f64) - (clip_center.y as f64);
 
         unproject(Pixels::new(x, y), zoom)
     }
 
+    /// Area visible in the viewport. Longitudes are not wrapped, so they go beyond `-180..180` if
+    /// the viewport does. Use it with [`crate::filter_in_bounds`] to find what is visible.
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            self.unproject(self.visible_rect.left_bottom().to_vec2()),
+            self.unproject(self.visible_rect.right_top().to_vec2()),
+        )
+    }
+
+    /// Current on-screen rectangle of the tile. Handy for debug overlays showing tile boundaries.
+    /// It does not depend on the size of tiles, as [`TileId::zoom`] already tells how much of the
+    /// world the tile covers.
+    pub fn tile_rect(&self, tile_id: TileId) -> Rect {
+        let tile_size = total_pixels(self.memory.zoom()) / total_tiles(tile_id.zoom) as f64;
+        let corner = tile_id.project(tile_size);
+        let corner = Pixels::new(corner.x() + self.world_offset(), corner.y());
+        let map_center_projected_position = project(
+            self.memory.center_mode.position(self.my_position),
+            self.memory.zoom(),
+        );
+        let min = self.clip_rect.center() + (corner - map_center_projected_position).to_vec2();
+        Rect::from_min_size(min, Vec2::splat(tile_size as f32))
+    }
+
+    /// Tile of a source with tiles of given size, such as [`crate::Tiles::tile_size`], under the
+    /// given screen position, at the zoom level which the map draws them in. Inverse of
+    /// [`Projector::tile_rect`].
+    pub fn tile_at_screen(&self, pos: Pos2, tile_size: u32) -> TileId {
+        let zoom = self.tile_zoom(tile_size);
+        let tiles = total_tiles(zoom) as f64;
+        let size = total_pixels(self.memory.zoom()) / tiles;
+
+        // Do not unproject into a position, as it loses the copy of the world.
+        let map_center_projected_position = project(
+            self.memory.center_mode.position(self.my_position),
+            self.memory.zoom(),
+        );
+        let x = map_center_projected_position.x() + (pos.x - self.clip_rect.center().x) as f64
+            - self.world_offset();
+        let y = map_center_projected_position.y() + (pos.y - self.clip_rect.center().y) as f64;
+
+        TileId {
+            x: (x / size).floor().rem_euclid(tiles) as u32,
+            y: (y / size).floor().clamp(0., tiles - 1.) as u32,
+            zoom,
+        }
+    }
+
+    /// Tiles of a source with tiles of given size which cover the viewport, in every copy of the
+    /// world, along with their on-screen rectangles.
+    pub(crate) fn visible_tiles(&self, tile_size: u32) -> Vec<(TileId, Rect)> {
+        let zoom = self.tile_zoom(tile_size);
+        let tiles = total_tiles(zoom) as f64;
+        let size = total_pixels(self.memory.zoom()) / tiles;
+        let map_center_projected_position = project(
+            self.memory.center_mode.position(self.my_position),
+            self.memory.zoom(),
+        );
+        let offset = self.visible_rect.min - self.clip_rect.center();
+        let left = map_center_projected_position.x() + offset.x as f64;
+        let top = map_center_projected_position.y() + offset.y as f64;
+
+        // Range of tiles between the given pixels of the world, or `None` if it is outside.
+        let range = |from: f64, to: f64| {
+            let first = (from / size).floor().max(0.);
+            let last = ((to / size).ceil() - 1.).min(tiles - 1.);
+            (first <= last).then_some(first as u32..=last as u32)
+        };
+
+        let mut visible = Vec::new();
+        let Some(rows) = range(top, top + self.visible_rect.height() as f64) else {
+            return visible;
+        };
+        for projector in self.world_copies() {
+            let left = left - projector.world_offset();
+            let Some(columns) = range(left, left + self.visible_rect.width() as f64) else {
+                continue;
+            };
+            for x in columns {
+                for y in rows.clone() {
+                    let tile_id = TileId { x, y, zoom };
+                    visible.push((tile_id, projector.tile_rect(tile_id)));
+                }
+            }
+        }
+        visible
+    }
+
+    /// Georeferencing of an image of [`Projector::visible_rect`], e.g. a screenshot of the map,
+    /// taken at given `pixels_per_point`, usually [`egui::Context::pixels_per_point`]. Written
+    /// next to the image, it lets GIS tools place it on the map.
+    pub fn world_file(&self, pixels_per_point: f32) -> WorldFile {
+        let zoom = self.memory.zoom();
+        let meters_per_point = EARTH_CIRCUMFERENCE / total_pixels(zoom);
+        let pixel_size = meters_per_point / pixels_per_point as f64;
+
+        let center = project(self.memory.center_mode.position(self.my_position), zoom);
+        let offset = self.visible_rect.min - self.clip_rect.center();
+        let left = center.x() + offset.x as f64 - self.world_offset();
+        let top = center.y() + offset.y as f64;
+
+        // World file points at the middle of the top left pixel.
+        WorldFile {
+            pixel_size,
+            x: left * meters_per_point - EARTH_CIRCUMFERENCE / 2. + pixel_size / 2.,
+            y: EARTH_CIRCUMFERENCE / 2. - top * meters_per_point - pixel_size / 2.,
+        }
+    }
+
+    /// Zoom level which tiles of given size are drawn in.
+    fn tile_zoom(&self, tile_size: u32) -> u8 {
+        let zoom = self
+            .memory
+            .zoom()
+            .round()
+            .clamp(0., TileId::MAX_ZOOM as f64) as u8;
+        zoom.saturating_sub((tile_size as f64 / 256.).log2() as u8)
+    }
+
+    /// Zoom level of the map being projected.
+    pub fn zoom(&self) -> f64 {
+        self.memory.zoom()
+    }
+
     /// What is the local scale of the map at the provided position and given the current zoom
     /// level?
     pub fn scale_pixel_per_meter(&self, position: Position) -> f32 {
@@ -66,7 +258,7 @@ impl Projector {
 
 /// Implementation of the scale computation.
 fn calculate_meters_per_pixel(latitude: f64, zoom: f64) -> f64 {
-    const EARTH_CIRCUMFERENCE: f64 = 40_075_016.686;
+    use crate::mercator::EARTH_CIRCUMFERENCE;
 
     // Number of pixels for width of world at this zoom level
     let total_pixels = crate::mercator::total_pixels(zoom);
@@ -76,10 +268,49 @@ fn calculate_meters_per_pixel(latitude: f64, zoom: f64) -> f64 {
     pixel_per_meter_equator / latitude_rad.cos()
 }
 
+/// Transform from pixels of an image of the map to Web Mercator (EPSG:3857) meters, as stored in
+/// [world files](https://en.wikipedia.org/wiki/World_file), e.g. `map.pgw` next to `map.png`, or
+/// `map.jgw` next to `map.jpg`. See [`Projector::world_file`].
+///
+/// Mercator is what the map is drawn in, so pixels are square and there is no rotation. Its
+/// [`std::fmt::Display`] is the content of the world file, six lines of:
+///
+/// 1. width of a pixel, in meters,
+/// 2. rotation about the y axis, always `0`,
+/// 3. rotation about the x axis, always `0`,
+/// 4. height of a pixel, in meters, negative as rows go south,
+/// 5. easting of the middle of the top left pixel,
+/// 6. northing of the middle of the top left pixel.
+///
+/// The file does not tell the coordinate system, so it needs to be set to EPSG:3857 when loading
+/// the image, or written to a `.prj` file alongside.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldFile {
+    /// Width and height of a pixel, in meters.
+    pub pixel_size: f64,
+
+    /// Easting of the middle of the top left pixel, in meters.
+    pub x: f64,
+
+    /// Northing of the middle of the top left pixel, in meters.
+    pub y: f64,
+}
+
+impl std::fmt::Display for WorldFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.pixel_size)?;
+        writeln!(f, "0")?;
+        writeln!(f, "0")?;
+        writeln!(f, "{}", -self.pixel_size)?;
+        writeln!(f, "{}", self.x)?;
+        writeln!(f, "{}", self.y)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lon_lat;
+    use crate::{PositionExt as _, lon_lat};
     use egui::Pos2;
 
     fn assert_approx_eq(a: f64, b: f64) {
@@ -151,4 +382,212 @@ mod tests {
         assert_approx_eq(original.x(), unprojected.x());
         assert_approx_eq(original.y(), unprojected.y());
     }
+
+    #[test]
+    fn world_copies_repeat_horizontally() {
+        let position = lon_lat(0., 0.);
+        let mut map_memory = MapMemory::default();
+        assert_eq!(map_memory.set_zoom(0.), Ok(()));
+
+        // At zoom 0, the world is 256 pixels wide, so a 1000 pixels wide viewport shows it
+        // almost 4 times, partially covering 5 copies.
+        let projector = Projector::new(
+            Rect::from_min_size(Pos2::ZERO, Vec2::new(1000., 100.)),
+            &map_memory,
+            position,
+        );
+
+        let copies: Vec<_> = projector.world_copies().collect();
+        assert_eq!(copies.len(), 5);
+
+        let projected: Vec<_> = copies.iter().map(|p| p.project(position).x).collect();
+        assert_eq!(projected, [-12., 244., 500., 756., 1012.]);
+
+        // Each copy unprojects back to the original position.
+        for copy in &copies {
+            let unprojected = copy.unproject(copy.project(lon_lat(10., 20.)));
+            assert_approx_eq(unprojected.x(), 10.);
+            assert_approx_eq(unprojected.y(), 20.);
+        }
+    }
+
+    #[test]
+    fn bounds_are_not_wrapped() {
+        let mut map_memory = MapMemory::default();
+        assert_eq!(map_memory.set_zoom(5.), Ok(()));
+        map_memory.center_at(lon_lat(180., 0.));
+
+        let projector = Projector::new(
+            Rect::from_min_size(Pos2::ZERO, Vec2::new(512., 256.)),
+            &map_memory,
+            lon_lat(0., 0.),
+        );
+
+        // At zoom 5, the world is 8192 pixels wide, so 512 pixels take 22.5 degrees.
+        let bounds = projector.bounds();
+        assert_approx_eq(bounds.west(), 168.75);
+        assert_approx_eq(bounds.east(), 191.25);
+        assert_approx_eq(bounds.south(), -bounds.north());
+        assert!(lon_lat(-179., 0.).in_bounds(projector.bounds()));
+    }
+
+    #[test]
+    fn tiles_are_found_under_their_rects() {
+        let mut map_memory = MapMemory::default();
+        assert_eq!(map_memory.set_zoom(10.3), Ok(()));
+        let projector = Projector::new(
+            Rect::from_min_size(Pos2::new(100., 50.), Vec2::new(512., 256.)),
+            &map_memory,
+            lon_lat(17.03664, 51.09916),
+        );
+
+        let tile_id = projector.tile_at_screen(Pos2::new(300., 200.), 256);
+        assert_eq!(
+            tile_id,
+            TileId::containing(projector.unproject(Vec2::new(300., 200.)), 10)
+        );
+
+        let rect = projector.tile_rect(tile_id);
+        assert!(rect.contains(Pos2::new(300., 200.)));
+        assert_approx_eq(rect.width() as f64, 256. * 2f64.powf(0.3));
+        assert_eq!(projector.tile_at_screen(rect.center(), 256), tile_id);
+        assert_eq!(
+            projector.tile_at_screen(rect.center() + Vec2::new(rect.width(), 0.), 256),
+            tile_id.east().expect("there is a tile to the east")
+        );
+
+        // Larger tiles come from a lower zoom level.
+        let large = projector.tile_at_screen(Pos2::new(300., 200.), 512);
+        assert_eq!(large, tile_id.parent().expect("there is a parent"));
+        assert!(projector.tile_rect(large).contains_rect(rect));
+    }
+
+    #[test]
+    fn tiles_wrap_around_the_world() {
+        let mut map_memory = MapMemory::default();
+        assert_eq!(map_memory.set_zoom(1.), Ok(()));
+        map_memory.center_at(lon_lat(180., 0.));
+        let projector = Projector::new(
+            Rect::from_min_size(Pos2::ZERO, Vec2::splat(256.)),
+            &map_memory,
+            lon_lat(0., 0.),
+        );
+
+        // Antimeridian is in the middle, with the westernmost tiles on the right.
+        assert_eq!(projector.tile_at_screen(Pos2::new(200., 100.), 256).x, 0);
+        assert_eq!(projector.tile_at_screen(Pos2::new(50., 100.), 256).x, 1);
+    }
+
+    #[test]
+    fn visible_tiles_cover_the_viewport_in_every_copy_of_the_world() {
+        let mut map_memory = MapMemory::default();
+        assert_eq!(map_memory.set_zoom(1.), Ok(()));
+        map_memory.center_at(lon_lat(180., 0.));
+        let clip_rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(600., 300.));
+        let projector = Projector::new(clip_rect, &map_memory, lon_lat(0., 0.));
+
+        // Antimeridian is in the middle, with both columns of each copy partially visible.
+        let visible = projector.visible_tiles(256);
+        let ids: Vec<_> = visible.iter().map(|(id, _)| (id.x, id.y)).collect();
+        assert_eq!(
+            ids,
+            [
+                (0, 0),
+                (0, 1),
+                (1, 0),
+                (1, 1),
+                (0, 0),
+                (0, 1),
+                (1, 0),
+                (1, 1)
+            ]
+        );
+        for (tile_id, rect) in &visible {
+            assert!(rect.intersects(clip_rect));
+            assert_eq!(projector.tile_at_screen(rect.center(), 256), *tile_id);
+        }
+    }
+
+    #[test]
+    fn clipped_projector_projects_the_same_but_covers_less() {
+        let mut map_memory = MapMemory::default();
+        assert_eq!(map_memory.set_zoom(10.), Ok(()));
+        let clip_rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(600., 300.));
+        let projector = Projector::new(clip_rect, &map_memory, lon_lat(17., 51.));
+
+        let right_half = Rect::from_min_max(Pos2::new(300., 0.), Pos2::new(600., 300.));
+        let clipped = projector.clipped(right_half);
+        assert_eq!(clipped.visible_rect(), right_half);
+
+        let position = lon_lat(17.5, 51.2);
+        assert_eq!(clipped.project(position), projector.project(position));
+
+        let (full, half) = (projector.bounds(), clipped.bounds());
+        assert_eq!(half.east(), full.east());
+        assert!((half.west() - 17.).abs() < 1e-9);
+
+        assert!(
+            clipped
+                .visible_tiles(256)
+                .iter()
+                .all(|(_, rect)| rect.intersects(right_half))
+        );
+        assert!(clipped.visible_tiles(256).len() < projector.visible_tiles(256).len());
+    }
+
+    #[test]
+    fn world_file_places_the_image_in_web_mercator_meters() {
+        let mut map_memory = MapMemory::default();
+        assert_eq!(map_memory.set_zoom(0.), Ok(()));
+        map_memory.center_at(lon_lat(0., 0.));
+        let clip_rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(256.));
+        let projector = Projector::new(clip_rect, &map_memory, lon_lat(0., 0.));
+
+        // Whole world in a 512px image.
+        let world_file = projector.world_file(2.);
+        let half = EARTH_CIRCUMFERENCE / 2.;
+        let pixel_size = EARTH_CIRCUMFERENCE / 512.;
+        assert!((world_file.pixel_size - pixel_size).abs() < 1e-6);
+        assert!((world_file.x - (-half + pixel_size / 2.)).abs() < 1e-6);
+        assert!((world_file.y - (half - pixel_size / 2.)).abs() < 1e-6);
+
+        let lines: Vec<f64> = world_file
+            .to_string()
+            .lines()
+            .map(|line| line.parse().expect("number"))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                world_file.pixel_size,
+                0.,
+                0.,
+                -world_file.pixel_size,
+                world_file.x,
+                world_file.y
+            ]
+        );
+
+        // Right half only.
+        let right_half = Rect::from_min_max(Pos2::new(128., 0.), Pos2::new(256., 256.));
+        let world_file = projector.clipped(right_half).world_file(1.);
+        assert!((world_file.x - EARTH_CIRCUMFERENCE / 512.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn translated_projector_is_shifted_by_offset() {
+        let position = lon_lat(21., 52.);
+        let projector = Projector::new(
+            Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.)),
+            &MapMemory::default(),
+            position,
+        );
+        let translated = projector.translated(Vec2::new(10., -5.));
+        assert_eq!(
+            translated.project(position),
+            projector.project(position) + Vec2::new(10., -5.)
+        );
+        let unprojected = translated.unproject(translated.project(lon_lat(21.001, 52.)));
+        assert_approx_eq(unprojected.x(), 21.001);
+    }
 }
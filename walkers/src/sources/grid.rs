@@ -0,0 +1,207 @@
+use crate::TileId;
+use crate::mercator::EARTH_CIRCUMFERENCE;
+
+/// Why a [`TileGrid`] could not be made.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum TileGridError {
+    #[error("Tile grid needs at least one resolution.")]
+    NoResolutions,
+
+    #[error("Resolution of level {0} is not finer than the one of the level before.")]
+    NotMonotonic(usize),
+
+    #[error("Resolution of level {0} does not match any Web Mercator zoom level.")]
+    NotQuadResolution(usize),
+
+    #[error("Origin of the grid is not on a tile boundary at level {0}.")]
+    MisalignedOrigin(usize),
+
+    #[error("Number of tiles at the top level must be a power of two, got {0}.")]
+    TopLevelTiles(u32),
+}
+
+/// Level of a [`TileGrid`], as a standard zoom level and the position of the grid's first tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Level {
+    zoom: u8,
+    x: i64,
+    y: i64,
+}
+
+/// Tile grid of a server which does not number the tiles as the standard Web Mercator quad, where
+/// the whole world is a single tile at zoom 0, e.g. one with several tiles at its top level or
+/// with the tiles counted from a regional origin.
+///
+/// The grid still has to be in the Web Mercator projection, and its tiles have to line up with
+/// the standard ones: each resolution must be the one of some standard zoom level and the origin
+/// must lie on a tile boundary. Levels may skip zoom levels, which are then interpolated.
+///
+/// ```
+/// # use walkers::sources::TileGrid;
+/// // Grid of a server which starts at zoom 1, where there are 2×2 tiles.
+/// let grid = TileGrid::with_top_level_tiles(2, 256).expect("power of two");
+/// assert_eq!(grid.zoom_range(), 1..=26);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileGrid {
+    tile_size: u32,
+    levels: Vec<Level>,
+}
+
+impl TileGrid {
+    /// Grid with its top-left corner at `origin`, in Web Mercator meters, and the given
+    /// resolutions, in meters per pixel, one for each of its levels, from the coarsest one.
+    pub fn new(
+        origin: (f64, f64),
+        tile_size: u32,
+        resolutions: &[f64],
+    ) -> Result<Self, TileGridError> {
+        if resolutions.is_empty() {
+            return Err(TileGridError::NoResolutions);
+        }
+
+        let half = EARTH_CIRCUMFERENCE / 2.;
+        let mut levels: Vec<Level> = Vec::with_capacity(resolutions.len());
+        for (index, &resolution) in resolutions.iter().enumerate() {
+            if index > 0 && resolution >= resolutions[index - 1] {
+                return Err(TileGridError::NotMonotonic(index));
+            }
+
+            let zoom = (EARTH_CIRCUMFERENCE / (tile_size as f64 * resolution)).log2();
+            if (zoom.round() - zoom).abs() > 1e-6
+                || !(0. ..=TileId::MAX_ZOOM as f64).contains(&zoom.round())
+            {
+                return Err(TileGridError::NotQuadResolution(index));
+            }
+            let zoom = zoom.round() as u8;
+
+            let tile_span = EARTH_CIRCUMFERENCE / 2f64.powi(zoom as i32);
+            let x = (origin.0 + half) / tile_span;
+            let y = (half - origin.1) / tile_span;
+            if (x.round() - x).abs() > 1e-6 || (y.round() - y).abs() > 1e-6 {
+                return Err(TileGridError::MisalignedOrigin(index));
+            }
+
+            levels.push(Level {
+                zoom,
+                x: x.round() as i64,
+                y: y.round() as i64,
+            });
+        }
+
+        Ok(Self { tile_size, levels })
+    }
+
+    /// Standard grid, but with `count`×`count` tiles at its top level instead of a single one.
+    pub fn with_top_level_tiles(count: u32, tile_size: u32) -> Result<Self, TileGridError> {
+        if !count.is_power_of_two() {
+            return Err(TileGridError::TopLevelTiles(count));
+        }
+
+        let top = count.trailing_zeros() as u8;
+        let levels = (top..=TileId::MAX_ZOOM)
+            .map(|zoom| Level { zoom, x: 0, y: 0 })
+            .collect();
+        Ok(Self { tile_size, levels })
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Standard zoom levels of the grid's coarsest and finest levels.
+    pub fn zoom_range(&self) -> std::ops::RangeInclusive<u8> {
+        let first = self.levels.first().map_or(0, |level| level.zoom);
+        let last = self.levels.last().map_or(0, |level| level.zoom);
+        first..=last
+    }
+
+    /// Highest standard zoom level which the grid has a level for, not above `zoom`.
+    pub(crate) fn zoom_at_or_below(&self, zoom: u8) -> Option<u8> {
+        self.levels
+            .iter()
+            .rev()
+            .map(|level| level.zoom)
+            .find(|&level| level <= zoom)
+    }
+
+    /// Tile of the grid covering the same area as the standard `tile_id`. `None` if the grid has
+    /// no such tile.
+    pub fn tile(&self, tile_id: TileId) -> Option<TileId> {
+        let (index, level) = self
+            .levels
+            .iter()
+            .enumerate()
+            .find(|(_, level)| level.zoom == tile_id.zoom)?;
+
+        Some(TileId {
+            x: u32::try_from(tile_id.x as i64 - level.x).ok()?,
+            y: u32::try_from(tile_id.y as i64 - level.y).ok()?,
+            zoom: u8::try_from(index).ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF: f64 = EARTH_CIRCUMFERENCE / 2.;
+
+    fn tile(x: u32, y: u32, zoom: u8) -> TileId {
+        TileId { x, y, zoom }
+    }
+
+    fn resolution(zoom: i32) -> f64 {
+        EARTH_CIRCUMFERENCE / 256. / 2f64.powi(zoom)
+    }
+
+    #[test]
+    fn resolutions_are_validated() {
+        assert_eq!(
+            TileGrid::new((-HALF, HALF), 256, &[]),
+            Err(TileGridError::NoResolutions)
+        );
+        assert_eq!(
+            TileGrid::new((-HALF, HALF), 256, &[resolution(3), resolution(2)]),
+            Err(TileGridError::NotMonotonic(1))
+        );
+        assert_eq!(
+            TileGrid::new((-HALF, HALF), 256, &[resolution(2), resolution(3) * 0.9]),
+            Err(TileGridError::NotQuadResolution(1))
+        );
+        assert_eq!(
+            TileGrid::new((0., HALF / 3.), 256, &[resolution(2)]),
+            Err(TileGridError::MisalignedOrigin(0))
+        );
+        assert_eq!(
+            TileGrid::with_top_level_tiles(3, 256),
+            Err(TileGridError::TopLevelTiles(3))
+        );
+    }
+
+    #[test]
+    fn standard_tiles_are_renumbered_from_the_origin() {
+        // Origin at the center of the world, levels at zooms 2 and 4.
+        let grid =
+            TileGrid::new((0., 0.), 256, &[resolution(2), resolution(4)]).expect("valid grid");
+        assert_eq!(grid.zoom_range(), 2..=4);
+
+        assert_eq!(grid.tile(tile(3, 2, 2)), Some(tile(1, 0, 0)));
+        assert_eq!(grid.tile(tile(9, 8, 4)), Some(tile(1, 0, 1)));
+
+        // Before the origin, and at a zoom the grid skips.
+        assert_eq!(grid.tile(tile(1, 2, 2)), None);
+        assert_eq!(grid.tile(tile(6, 4, 3)), None);
+        assert_eq!(grid.zoom_at_or_below(3), Some(2));
+        assert_eq!(grid.zoom_at_or_below(1), None);
+    }
+
+    #[test]
+    fn top_level_tiles_shift_the_zoom_levels() {
+        let grid = TileGrid::with_top_level_tiles(4, 256).expect("power of two");
+        assert_eq!(grid.zoom_range(), 2..=TileId::MAX_ZOOM);
+        assert_eq!(grid.tile(tile(3, 1, 2)), Some(tile(3, 1, 0)));
+        assert_eq!(grid.tile(tile(0, 0, 1)), None);
+    }
+}
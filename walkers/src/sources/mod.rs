@@ -1,13 +1,17 @@
 //! Some common HTTP tile sources. Make sure you follow terms of usage of the particular source.
 
 mod geoportal;
+mod grid;
 mod mapbox;
 mod openstreetmap;
+mod template;
 
-use crate::TileId;
+use crate::{BoundingBox, TileId};
 pub use geoportal::Geoportal;
+pub use grid::{TileGrid, TileGridError};
 pub use mapbox::{Mapbox, MapboxStyle};
 pub use openstreetmap::OpenStreetMap;
+pub use template::UrlTemplate;
 
 #[derive(Clone)]
 pub struct Attribution {
@@ -27,7 +31,25 @@ pub trait TileSource {
         256
     }
 
+    /// Lowest zoom level for which the source has tiles. Map will not zoom out further.
+    fn min_zoom(&self) -> u8 {
+        0
+    }
+
+    /// Highest zoom level for which the source has tiles. Map can still be zoomed in further,
+    /// but tiles will be interpolated from this level instead of being downloaded.
     fn max_zoom(&self) -> u8 {
         19
     }
+
+    /// Area covered by the tiles. `None`, the default, means the whole world.
+    fn bounds(&self) -> Option<BoundingBox> {
+        None
+    }
+
+    /// Grid which the tile ids passed to [`TileSource::tile_url`] are in. `None`, the default,
+    /// means the standard Web Mercator quad. Zoom levels outside of the grid are not downloaded.
+    fn tile_grid(&self) -> Option<TileGrid> {
+        None
+    }
 }
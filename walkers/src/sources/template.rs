@@ -0,0 +1,141 @@
+use super::{Attribution, TileGrid, TileSource};
+use crate::{BoundingBox, Position, TileId};
+
+/// Tile server given by a URL template, such as `https://example.com/{z}/{x}/{y}.png`.
+///
+/// Besides `{z}`, `{x}` and `{y}`, the template can contain `{time}`, which is filled in by
+/// [`crate::HttpTiles::set_time`]. It is meant for time-indexed layers, such as weather radar.
+pub struct UrlTemplate {
+    url: Url,
+    attribution: Attribution,
+    max_zoom: u8,
+    bounds: Option<BoundingBox>,
+    grid: Option<TileGrid>,
+}
+
+/// How [`UrlTemplate`] makes the URLs.
+enum Url {
+    Template(String),
+    Builder(Box<dyn Fn(TileId) -> String + Send + Sync>),
+}
+
+impl UrlTemplate {
+    pub fn new(template: impl Into<String>, attribution: Attribution) -> Self {
+        Self::from_url(Url::Template(template.into()), attribution)
+    }
+
+    /// Tile server with URLs too unusual for a template, e.g. signed or with hashed paths, made by
+    /// the given function instead. It is called once per download, as the downloaded tiles are
+    /// then kept in [`crate::HttpTiles`]' cache. `{time}` is still filled in, if the URL has it.
+    ///
+    /// ```
+    /// # use walkers::sources::{Attribution, UrlTemplate};
+    /// let source = UrlTemplate::from_fn(
+    ///     |tile_id| format!("https://example.com/{}/{:x}.png", tile_id.zoom, tile_id.x ^ tile_id.y),
+    ///     Attribution { text: "Example", url: "", logo_light: None, logo_dark: None },
+    /// );
+    /// ```
+    pub fn from_fn(
+        url: impl Fn(TileId) -> String + Send + Sync + 'static,
+        attribution: Attribution,
+    ) -> Self {
+        Self::from_url(Url::Builder(Box::new(url)), attribution)
+    }
+
+    fn from_url(url: Url, attribution: Attribution) -> Self {
+        Self {
+            url,
+            attribution,
+            max_zoom: 19,
+            bounds: None,
+            grid: None,
+        }
+    }
+
+    /// Highest zoom level for which the server has tiles. 19 by default.
+    pub fn with_max_zoom(mut self, max_zoom: u8) -> Self {
+        self.max_zoom = max_zoom;
+        self
+    }
+
+    /// Area for which the server has tiles, as its south-west and north-east corners. The whole
+    /// world by default.
+    pub fn with_bounds(mut self, south_west: Position, north_east: Position) -> Self {
+        self.bounds = Some(BoundingBox::new(south_west, north_east));
+        self
+    }
+
+    /// Grid of a server which does not number its tiles as the standard Web Mercator quad.
+    /// `{z}`, `{x}` and `{y}` are then filled in with the grid's level and tile.
+    pub fn with_grid(mut self, grid: TileGrid) -> Self {
+        self.grid = Some(grid);
+        self
+    }
+}
+
+impl TileSource for UrlTemplate {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        match &self.url {
+            Url::Template(template) => template
+                .replace("{z}", &tile_id.zoom.to_string())
+                .replace("{x}", &tile_id.x.to_string())
+                .replace("{y}", &tile_id.y.to_string()),
+            Url::Builder(url) => url(tile_id),
+        }
+    }
+
+    fn attribution(&self) -> Attribution {
+        self.attribution.clone()
+    }
+
+    fn max_zoom(&self) -> u8 {
+        self.max_zoom
+    }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        self.bounds
+    }
+
+    fn tile_grid(&self) -> Option<TileGrid> {
+        self.grid.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::attribution;
+
+    #[test]
+    fn tokens_are_replaced() {
+        let source = UrlTemplate::new(
+            "https://example.com/{time}/{z}/{x}/{y}.png",
+            attribution(""),
+        );
+        let tile_id = TileId {
+            x: 1,
+            y: 2,
+            zoom: 3,
+        };
+        assert_eq!(
+            source.tile_url(tile_id),
+            "https://example.com/{time}/3/1/2.png"
+        );
+    }
+
+    #[test]
+    fn url_can_be_made_by_function() {
+        let source = UrlTemplate::from_fn(
+            |tile_id| format!("https://example.com/{}.png", tile_id.x + tile_id.y),
+            attribution(""),
+        )
+        .with_max_zoom(12);
+        let tile_id = TileId {
+            x: 1,
+            y: 2,
+            zoom: 3,
+        };
+        assert_eq!(source.tile_url(tile_id), "https://example.com/3.png");
+        assert_eq!(source.max_zoom(), 12);
+    }
+}
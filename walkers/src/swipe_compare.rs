@@ -0,0 +1,204 @@
+use egui::{Color32, CursorIcon, Rect, Response, Sense, Stroke, Ui, Widget, pos2};
+
+use crate::{Map, MapMemory, Position, Tiles};
+
+/// Width of the area around the divider which can be grabbed.
+const HANDLE_WIDTH: f32 = 16.;
+
+/// Two tile sources in one map, split by a vertical divider which the user can drag, e.g. to
+/// compare imagery from before and after an event. `before` is shown left of the divider and
+/// `after` right of it. Both are drawn with the same [`MapMemory`], so they stay aligned while
+/// the map is moved or zoomed.
+///
+/// # Examples
+///
+/// ```
+/// # use walkers::{MapMemory, SwipeCompare, Tiles, lon_lat};
+///
+/// fn update(
+///     ui: &mut egui::Ui,
+///     before: &mut dyn Tiles,
+///     after: &mut dyn Tiles,
+///     map_memory: &mut MapMemory,
+///     divider: &mut f32,
+/// ) {
+///     ui.add(SwipeCompare::new(
+///         before,
+///         after,
+///         map_memory,
+///         lon_lat(17.03664, 51.09916),
+///         divider,
+///     ));
+/// }
+/// ```
+pub struct SwipeCompare<'a, 'b> {
+    before: &'b mut dyn Tiles,
+    after: &'b mut dyn Tiles,
+    memory: &'a mut MapMemory,
+    my_position: Position,
+    divider: &'a mut f32,
+}
+
+impl<'a, 'b> SwipeCompare<'a, 'b> {
+    /// `divider` is where the divider is, from `0.0` at the left edge of the map to `1.0` at the
+    /// right one. It is kept by the application, like [`MapMemory`], and updated as the user
+    /// drags the divider.
+    pub fn new(
+        before: &'b mut dyn Tiles,
+        after: &'b mut dyn Tiles,
+        memory: &'a mut MapMemory,
+        my_position: Position,
+        divider: &'a mut f32,
+    ) -> Self {
+        Self {
+            before,
+            after,
+            memory,
+            my_position,
+            divider,
+        }
+    }
+}
+
+impl Widget for SwipeCompare<'_, '_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let Self {
+            before,
+            after,
+            memory,
+            my_position,
+            divider,
+        } = self;
+
+        // Map takes all the available space.
+        let rect = ui.available_rect_before_wrap();
+        let x = rect.left() + rect.width() * divider.clamp(0., 1.);
+        let (left, right) = rect.split_left_right_at_x(x);
+
+        Map::new(None, memory, my_position)
+            .with_layer_clipped(before, 1.0, left)
+            .with_layer_clipped(after, 1.0, right)
+            .show(ui, |ui, response, _, _| {
+                // Added after the map, so it is on top of it and takes the drag for itself.
+                let handle = ui.interact(
+                    Rect::from_x_y_ranges(
+                        x - HANDLE_WIDTH / 2.0..=x + HANDLE_WIDTH / 2.0,
+                        response.rect.y_range(),
+                    ),
+                    response.id.with("swipe_compare_divider"),
+                    Sense::drag(),
+                );
+
+                if handle.dragged() && response.rect.width() > 0. {
+                    let dragged_to = x + handle.drag_delta().x - response.rect.left();
+                    *divider = (dragged_to / response.rect.width()).clamp(0., 1.);
+                    ui.ctx().request_repaint();
+                }
+
+                if handle.hovered() || handle.dragged() {
+                    ui.ctx().set_cursor_icon(CursorIcon::ResizeHorizontal);
+                }
+
+                let painter = ui.painter_at(response.rect);
+                let stroke = Stroke::new(2., Color32::WHITE);
+                painter.vline(x, response.rect.y_range(), stroke);
+                painter.circle(
+                    pos2(x, response.rect.center().y),
+                    HANDLE_WIDTH / 2.,
+                    Color32::WHITE,
+                    Stroke::new(1., Color32::DARK_GRAY),
+                );
+            })
+            .response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lon_lat, testing::RecordingTiles};
+    use egui::{Event, Frame, Modifiers, PointerButton, RawInput, Vec2};
+    use std::collections::BTreeSet;
+
+    /// Run frames of a swipe compare filling the whole screen, each with the given events.
+    fn run_frames(
+        memory: &mut MapMemory,
+        divider: &mut f32,
+        frames: Vec<Vec<Event>>,
+    ) -> (RecordingTiles, RecordingTiles) {
+        let ctx = egui::Context::default();
+        let screen_rect = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let mut before = RecordingTiles::default();
+        let mut after = RecordingTiles::default();
+
+        for events in frames {
+            before.0.clear();
+            after.0.clear();
+            let input = RawInput {
+                screen_rect: Some(screen_rect),
+                events,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(Frame::NONE)
+                    .show(ctx, |ui| {
+                        ui.add(SwipeCompare::new(
+                            &mut before,
+                            &mut after,
+                            memory,
+                            lon_lat(17.03664, 51.09916),
+                            divider,
+                        ));
+                    });
+            });
+        }
+        (before, after)
+    }
+
+    #[test]
+    fn sources_are_asked_for_tiles_on_their_side_of_the_divider() {
+        let mut memory = MapMemory::default();
+        let mut divider = 0.5;
+        let (before, after) = run_frames(&mut memory, &mut divider, vec![vec![]]);
+        assert!(!before.0.is_empty());
+        assert!(!after.0.is_empty());
+
+        let columns = |tiles: &RecordingTiles| -> BTreeSet<u32> {
+            tiles.0.iter().map(|(_, x, _)| *x).collect()
+        };
+        let (before, after) = (columns(&before), columns(&after));
+        assert!(before.first() < after.first());
+        assert!(before.last() < after.last());
+
+        // Divider at the edge leaves the whole map to one of the sources.
+        let mut divider = 1.0;
+        let (_, after) = run_frames(&mut memory, &mut divider, vec![vec![]]);
+        assert!(after.0.is_empty());
+    }
+
+    #[test]
+    fn dragging_the_divider_moves_it_instead_of_the_map() {
+        let mut memory = MapMemory::default();
+        let mut divider = 0.5;
+        let start = pos2(256., 100.);
+        let button = |pos, pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+
+        let mut frames = vec![vec![Event::PointerMoved(start)], vec![button(start, true)]];
+        for step in 1..=5 {
+            frames.push(vec![Event::PointerMoved(
+                start + Vec2::new(20. * step as f32, 0.),
+            )]);
+        }
+        frames.push(vec![button(start + Vec2::new(100., 0.), false)]);
+        run_frames(&mut memory, &mut divider, frames);
+
+        assert!(divider > 0.6, "divider is at {divider}");
+        assert!(memory.detached().is_none());
+    }
+}
@@ -0,0 +1,34 @@
+//! Fixtures used by tests of more than one module.
+
+use std::collections::BTreeSet;
+
+use crate::{TextureWithUv, TileId, Tiles, sources::Attribution};
+
+/// Attribution with the given text, and nothing else.
+pub(crate) fn attribution(text: &'static str) -> Attribution {
+    Attribution {
+        text,
+        url: "",
+        logo_light: None,
+        logo_dark: None,
+    }
+}
+
+/// Records which tiles were asked for.
+#[derive(Default)]
+pub(crate) struct RecordingTiles(pub(crate) BTreeSet<(u8, u32, u32)>);
+
+impl Tiles for RecordingTiles {
+    fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+        self.0.insert((tile_id.zoom, tile_id.x, tile_id.y));
+        None
+    }
+
+    fn attribution(&self) -> Attribution {
+        attribution("")
+    }
+
+    fn tile_size(&self) -> u32 {
+        256
+    }
+}
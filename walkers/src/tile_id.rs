@@ -0,0 +1,320 @@
+//! Tile grid arithmetic, independent of the GUI.
+
+use crate::Position;
+use crate::mercator::{tile_id, total_tiles};
+use crate::position::Pixels;
+
+/// Identifies the tile in the tile grid.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct TileId {
+    /// X number of the tile.
+    pub x: u32,
+
+    /// Y number of the tile.
+    pub y: u32,
+
+    /// Zoom level, where 0 means no zoom.
+    /// See: <https://wiki.openstreetmap.org/wiki/Zoom_levels>
+    pub zoom: u8,
+}
+
+impl TileId {
+    /// Tile of the standard, 256px tile grid which contains given position at given zoom level.
+    pub fn containing(position: Position, zoom: u8) -> TileId {
+        tile_id(position, zoom, 256)
+    }
+
+    /// Tile position (in pixels) on the "World bitmap".
+    pub fn project(&self, tile_size: f64) -> Pixels {
+        Pixels::new(self.x as f64 * tile_size, self.y as f64 * tile_size)
+    }
+
+    pub fn east(&self) -> Option<TileId> {
+        (self.x < total_tiles(self.zoom) - 1).then_some(TileId {
+            x: self.x + 1,
+            y: self.y,
+            zoom: self.zoom,
+        })
+    }
+
+    pub fn west(&self) -> Option<TileId> {
+        Some(TileId {
+            x: self.x.checked_sub(1)?,
+            y: self.y,
+            zoom: self.zoom,
+        })
+    }
+
+    pub fn north(&self) -> Option<TileId> {
+        Some(TileId {
+            x: self.x,
+            y: self.y.checked_sub(1)?,
+            zoom: self.zoom,
+        })
+    }
+
+    pub fn south(&self) -> Option<TileId> {
+        (self.y < total_tiles(self.zoom) - 1).then_some(TileId {
+            x: self.x,
+            y: self.y + 1,
+            zoom: self.zoom,
+        })
+    }
+
+    /// Deepest zoom level supported by Walkers.
+    pub const MAX_ZOOM: u8 = 26;
+
+    /// Tile at one zoom level lower, which covers this one. `None` for the root tile.
+    pub fn parent(&self) -> Option<TileId> {
+        Some(TileId {
+            x: self.x / 2,
+            y: self.y / 2,
+            zoom: self.zoom.checked_sub(1)?,
+        })
+    }
+
+    /// Four tiles at one zoom level higher, which together cover this one, in order: north-west,
+    /// north-east, south-west, south-east. `None` if this tile is already at
+    /// [`TileId::MAX_ZOOM`].
+    pub fn children(&self) -> Option<[TileId; 4]> {
+        if self.zoom >= Self::MAX_ZOOM {
+            return None;
+        }
+
+        let child = |dx, dy| TileId {
+            x: self.x * 2 + dx,
+            y: self.y * 2 + dy,
+            zoom: self.zoom + 1,
+        };
+
+        Some([child(0, 0), child(1, 0), child(0, 1), child(1, 1)])
+    }
+
+    /// Tiles at the same zoom level which touch this one, including diagonally, clockwise
+    /// starting from north. Tiles beyond the edges of the world are skipped.
+    pub fn neighbors(&self) -> impl Iterator<Item = TileId> {
+        let north = self.north();
+        let south = self.south();
+        [
+            north,
+            north.and_then(|tile_id| tile_id.east()),
+            self.east(),
+            south.and_then(|tile_id| tile_id.east()),
+            south,
+            south.and_then(|tile_id| tile_id.west()),
+            self.west(),
+            north.and_then(|tile_id| tile_id.west()),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    #[cfg(any(feature = "widget", test))]
+    pub(crate) fn valid(&self) -> bool {
+        self.x < total_tiles(self.zoom) && self.y < total_tiles(self.zoom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_tile_has_no_parent_nor_neighbors() {
+        let root = TileId {
+            x: 0,
+            y: 0,
+            zoom: 0,
+        };
+
+        assert_eq!(root.parent(), None);
+        assert_eq!(root.neighbors().count(), 0);
+        assert_eq!(
+            root.children(),
+            Some([
+                TileId {
+                    x: 0,
+                    y: 0,
+                    zoom: 1
+                },
+                TileId {
+                    x: 1,
+                    y: 0,
+                    zoom: 1
+                },
+                TileId {
+                    x: 0,
+                    y: 1,
+                    zoom: 1
+                },
+                TileId {
+                    x: 1,
+                    y: 1,
+                    zoom: 1
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn children_and_parent_are_inverse() {
+        let tile_id = TileId {
+            x: 5,
+            y: 3,
+            zoom: 4,
+        };
+
+        let children = tile_id.children().expect("zoom 4 has children");
+        for child in children {
+            assert_eq!(child.parent(), Some(tile_id));
+            assert!(child.valid());
+        }
+
+        let deepest = TileId {
+            x: 0,
+            y: 0,
+            zoom: TileId::MAX_ZOOM,
+        };
+        assert_eq!(deepest.children(), None);
+    }
+
+    #[test]
+    fn neighbors_of_edge_tiles_stay_within_the_world() {
+        // North-west corner at zoom 2 has only east, south-east and south neighbors.
+        let corner = TileId {
+            x: 0,
+            y: 0,
+            zoom: 2,
+        };
+        assert_eq!(
+            corner.neighbors().collect::<Vec<_>>(),
+            vec![
+                TileId {
+                    x: 1,
+                    y: 0,
+                    zoom: 2
+                },
+                TileId {
+                    x: 1,
+                    y: 1,
+                    zoom: 2
+                },
+                TileId {
+                    x: 0,
+                    y: 1,
+                    zoom: 2
+                },
+            ]
+        );
+
+        // Tile on the eastern edge at zoom 2.
+        let edge = TileId {
+            x: 3,
+            y: 1,
+            zoom: 2,
+        };
+        assert_eq!(
+            edge.neighbors().collect::<Vec<_>>(),
+            vec![
+                TileId {
+                    x: 3,
+                    y: 0,
+                    zoom: 2
+                },
+                TileId {
+                    x: 3,
+                    y: 2,
+                    zoom: 2
+                },
+                TileId {
+                    x: 2,
+                    y: 2,
+                    zoom: 2
+                },
+                TileId {
+                    x: 2,
+                    y: 1,
+                    zoom: 2
+                },
+                TileId {
+                    x: 2,
+                    y: 0,
+                    zoom: 2
+                },
+            ]
+        );
+
+        // Tile in the middle has all 8 neighbors.
+        let middle = TileId {
+            x: 1,
+            y: 1,
+            zoom: 2,
+        };
+        assert_eq!(middle.neighbors().count(), 8);
+        assert!(middle.neighbors().all(|tile_id| tile_id.valid()));
+    }
+
+    #[test]
+    fn tile_id_cannot_go_beyond_limits() {
+        // There is only one tile at zoom 0.
+        let tile_id = TileId {
+            x: 0,
+            y: 0,
+            zoom: 0,
+        };
+
+        assert_eq!(tile_id.west(), None);
+        assert_eq!(tile_id.north(), None);
+        assert_eq!(tile_id.south(), None);
+        assert_eq!(tile_id.east(), None);
+
+        // There are 2 tiles at zoom 1.
+        let tile_id = TileId {
+            x: 0,
+            y: 0,
+            zoom: 1,
+        };
+
+        assert_eq!(tile_id.west(), None);
+        assert_eq!(tile_id.north(), None);
+
+        assert_eq!(
+            tile_id.south(),
+            Some(TileId {
+                x: 0,
+                y: 1,
+                zoom: 1
+            })
+        );
+
+        assert_eq!(
+            tile_id.east(),
+            Some(TileId {
+                x: 1,
+                y: 0,
+                zoom: 1
+            })
+        );
+    }
+
+    #[test]
+    fn finding_tile_containing_position() {
+        let citadel = crate::lon_lat(21.00027, 52.26470);
+        assert_eq!(
+            TileId::containing(citadel, 20),
+            TileId {
+                x: 585455,
+                y: 345104,
+                zoom: 20
+            }
+        );
+        assert_eq!(
+            TileId::containing(citadel, 0),
+            TileId {
+                x: 0,
+                y: 0,
+                zoom: 0
+            }
+        );
+    }
+}
@@ -1,81 +1,124 @@
 #[cfg(feature = "vector_tiles")]
 use crate::mvt::{self, ShapeOrText};
 use std::collections::HashSet;
+use std::ops::RangeInclusive;
 
-use egui::{Color32, Context, Mesh, Rect, Vec2, pos2};
-use egui::{ColorImage, TextureHandle};
+use egui::{Color32, Context, Mesh, Pos2, Rect, Vec2, pos2};
+use egui::{ColorImage, TextureFilter, TextureHandle, TextureOptions};
 #[cfg(feature = "vector_tiles")]
-use egui::{FontId, Pos2, Shape};
+use egui::{FontId, Shape};
 use image::{ImageError, ImageReader};
 use thiserror::Error;
 
-use crate::Position;
-use crate::mercator::{project, tile_id, total_tiles};
+use crate::mercator::{project, tile_id, total_pixels, total_tiles};
 use crate::position::{Pixels, PixelsExt};
 use crate::sources::Attribution;
 use crate::zoom::Zoom;
+use crate::{BoundingBox, Position, TileId};
 
 /// Source of tiles to be put together to render the map.
+///
+/// [`crate::Map`] takes it as a trait object, so the source can be chosen at runtime, e.g. from
+/// a settings dropdown, without being generic over it.
+///
+/// # Examples
+///
+/// ```
+/// # use walkers::{HttpTiles, LocalTiles, Map, MapMemory, Tiles, lon_lat, sources::OpenStreetMap};
+///
+/// fn tiles(offline: bool, egui_ctx: egui::Context) -> Box<dyn Tiles> {
+///     if offline {
+///         Box::new(LocalTiles::new("tiles", egui_ctx))
+///     } else {
+///         Box::new(HttpTiles::new(OpenStreetMap, egui_ctx))
+///     }
+/// }
+///
+/// fn update(ui: &mut egui::Ui, tiles: &mut Box<dyn Tiles>, map_memory: &mut MapMemory) {
+///     ui.add(Map::new(Some(tiles), map_memory, lon_lat(17.03664, 51.09916)));
+/// }
+/// ```
 pub trait Tiles {
     fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv>;
     fn attribution(&self) -> Attribution;
     fn tile_size(&self) -> u32;
-}
 
-/// Identifies the tile in the tile grid.
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-pub struct TileId {
-    /// X number of the tile.
-    pub x: u32,
+    /// Whether the source is known to have no data for the given tile, as opposed to the tile
+    /// still being loaded.
+    fn missing(&self, _tile_id: TileId) -> bool {
+        false
+    }
+
+    /// Zoom levels at which the source can provide something to draw, either directly or
+    /// interpolated. [`crate::Map`] does not let the user zoom beyond this range.
+    fn zoom_range(&self) -> RangeInclusive<u8> {
+        0..=TileId::MAX_ZOOM
+    }
+
+    /// Create the textures again, e.g. after the GPU context was lost (resume from sleep, GPU
+    /// reset) and tiles stopped being drawn properly. Egui does not detect that, so it is up to the
+    /// application to call it.
+    fn reload_textures(&mut self) {}
 
-    /// Y number of the tile.
-    pub y: u32,
+    /// Area covered by the tiles, e.g. for a "zoom to layer" button using
+    /// [`crate::MapMemory::fit_bounds`]. `None` means the whole world.
+    fn bounds(&self) -> Option<BoundingBox> {
+        None
+    }
 
-    /// Zoom level, where 0 means no zoom.
-    /// See: <https://wiki.openstreetmap.org/wiki/Zoom_levels>
-    pub zoom: u8,
+    /// Tile in the middle of [`Tiles::bounds`] at given zoom level, clamped to
+    /// [`Tiles::zoom_range`], with the part of the texture it covers. Meant for thumbnails in
+    /// layer controls, e.g. with [`egui::Image::uv`]. Like [`Tiles::at`], it does not block, so it
+    /// returns `None` until the tile is loaded, and vector tiles have no texture to show.
+    fn preview(&mut self, zoom: u8) -> Option<(TextureHandle, Rect)> {
+        let center = self
+            .bounds()
+            .map(|bounds| bounds.center())
+            .unwrap_or_default();
+        let range = self.zoom_range();
+        let tile_id = TileId::containing(center, zoom.clamp(*range.start(), *range.end()));
+        match self.at(tile_id)? {
+            TextureWithUv {
+                texture: Texture::Raster(texture_handle),
+                uv,
+            } => Some((texture_handle, uv)),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
 }
 
-impl TileId {
-    /// Tile position (in pixels) on the "World bitmap".
-    pub fn project(&self, tile_size: f64) -> Pixels {
-        Pixels::new(self.x as f64 * tile_size, self.y as f64 * tile_size)
+impl<T: Tiles + ?Sized> Tiles for Box<T> {
+    fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+        (**self).at(tile_id)
     }
 
-    pub fn east(&self) -> Option<TileId> {
-        (self.x < total_tiles(self.zoom) - 1).then_some(TileId {
-            x: self.x + 1,
-            y: self.y,
-            zoom: self.zoom,
-        })
+    fn attribution(&self) -> Attribution {
+        (**self).attribution()
     }
 
-    pub fn west(&self) -> Option<TileId> {
-        Some(TileId {
-            x: self.x.checked_sub(1)?,
-            y: self.y,
-            zoom: self.zoom,
-        })
+    fn tile_size(&self) -> u32 {
+        (**self).tile_size()
     }
 
-    pub fn north(&self) -> Option<TileId> {
-        Some(TileId {
-            x: self.x,
-            y: self.y.checked_sub(1)?,
-            zoom: self.zoom,
-        })
+    fn missing(&self, tile_id: TileId) -> bool {
+        (**self).missing(tile_id)
     }
 
-    pub fn south(&self) -> Option<TileId> {
-        (self.y < total_tiles(self.zoom) - 1).then_some(TileId {
-            x: self.x,
-            y: self.y + 1,
-            zoom: self.zoom,
-        })
+    fn zoom_range(&self) -> RangeInclusive<u8> {
+        (**self).zoom_range()
     }
 
-    pub(crate) fn valid(&self) -> bool {
-        self.x < total_tiles(self.zoom) && self.y < total_tiles(self.zoom)
+    fn reload_textures(&mut self) {
+        (**self).reload_textures()
+    }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        (**self).bounds()
+    }
+
+    fn preview(&mut self, zoom: u8) -> Option<(TextureHandle, Rect)> {
+        (**self).preview(zoom)
     }
 }
 
@@ -111,37 +154,41 @@ pub enum TileError {
 
 impl Texture {
     pub fn new(image: &[u8], ctx: &Context) -> Result<Self, TileError> {
-        if image.is_empty() {
-            return Err(TileError::Empty);
-        }
-
-        let reader = ImageReader::new(std::io::Cursor::new(image)).with_guessed_format()?;
-        if reader.format().is_some() {
-            log::debug!("Decoding tile as raster image.");
-            let image = reader.decode()?.to_rgba8();
-            let pixels = image.as_flat_samples();
-            let image = ColorImage::from_rgba_unmultiplied(
-                [image.width() as _, image.height() as _],
-                pixels.as_slice(),
-            );
+        Self::with_filter(image, ctx, TextureFilter::Linear)
+    }
 
-            Ok(Self::from_color_image(image, ctx))
-        } else {
+    /// Same as [`Texture::new`], but raster images will be sampled using the given `filter`
+    /// when drawn scaled, e.g. during fractional zoom. Use [`TextureFilter::Nearest`] for
+    /// a crisp, pixelated look.
+    pub fn with_filter(
+        image: &[u8],
+        ctx: &Context,
+        filter: TextureFilter,
+    ) -> Result<Self, TileError> {
+        match decode(image)? {
+            Decoded::Image(image) => Ok(Self::from_color_image_with_filter(image, ctx, filter)),
             #[cfg(feature = "vector_tiles")]
-            {
-                log::debug!("Trying to decode tile as MVT vector tile.");
-                Ok(Self::from_mvt(image)?)
-            }
-            #[cfg(not(feature = "vector_tiles"))]
-            {
-                Err(TileError::UnrecognizedFormat)
-            }
+            Decoded::Texture(texture) => Ok(texture),
         }
     }
 
     /// Load the texture from egui's [`ColorImage`].
     pub fn from_color_image(color_image: ColorImage, ctx: &Context) -> Self {
-        Self::Raster(ctx.load_texture("image", color_image, Default::default()))
+        Self::from_color_image_with_filter(color_image, ctx, TextureFilter::Linear)
+    }
+
+    /// Load the texture from egui's [`ColorImage`], sampling it using the given `filter`.
+    pub fn from_color_image_with_filter(
+        color_image: ColorImage,
+        ctx: &Context,
+        filter: TextureFilter,
+    ) -> Self {
+        let options = TextureOptions {
+            magnification: filter,
+            minification: filter,
+            ..Default::default()
+        };
+        Self::Raster(ctx.load_texture("image", color_image, options))
     }
 
     #[cfg(feature = "vector_tiles")]
@@ -201,6 +248,71 @@ impl Texture {
     }
 }
 
+/// Tile data, decoded, but not uploaded to the GPU yet.
+pub(crate) enum Decoded {
+    /// Raster image, which needs to be uploaded as a texture.
+    Image(ColorImage),
+
+    /// Vector tile, which is not backed by any GPU texture.
+    #[cfg(feature = "vector_tiles")]
+    Texture(Texture),
+}
+
+/// Decode the tile data. It can be done outside the UI thread, as no texture is created.
+pub(crate) fn decode(image: &[u8]) -> Result<Decoded, TileError> {
+    if let Some(image) = decode_raster(image)? {
+        return Ok(Decoded::Image(image));
+    }
+
+    #[cfg(feature = "vector_tiles")]
+    {
+        log::debug!("Trying to decode tile as MVT vector tile.");
+        Ok(Decoded::Texture(Texture::from_mvt(image)?))
+    }
+    #[cfg(not(feature = "vector_tiles"))]
+    {
+        Err(TileError::UnrecognizedFormat)
+    }
+}
+
+/// Same as [`decode`], but vector tiles are drawn using the given style, instead of the built-in
+/// one.
+#[cfg(feature = "vector_tiles")]
+pub(crate) fn decode_with_style(
+    data: &[u8],
+    style: &mvt::VectorStyle,
+) -> Result<Decoded, TileError> {
+    if let Some(image) = decode_raster(data)? {
+        return Ok(Decoded::Image(image));
+    }
+
+    log::debug!("Trying to decode tile as MVT vector tile.");
+    let reader = mvt_reader::Reader::new(mvt::gunzipped(data)?).map_err(mvt::Error::from)?;
+    let shapes = mvt::render_with_style(&reader, style)?;
+    Ok(Decoded::Texture(Texture::Vector(shapes)))
+}
+
+/// Decode the tile as a raster image. `None` if it is not one. Image formats store colors
+/// unmultiplied, while egui blends premultiplied ones, so the alpha is applied here.
+fn decode_raster(image: &[u8]) -> Result<Option<ColorImage>, TileError> {
+    if image.is_empty() {
+        return Err(TileError::Empty);
+    }
+
+    let reader = ImageReader::new(std::io::Cursor::new(image)).with_guessed_format()?;
+    if reader.format().is_some() {
+        log::debug!("Decoding tile as raster image.");
+        let image = reader.decode()?.to_rgba8();
+        let pixels = image.as_flat_samples();
+        Ok(Some(ColorImage::from_rgba_unmultiplied(
+            [image.width() as _, image.height() as _],
+            pixels.as_slice(),
+        )))
+    } else {
+        Ok(None)
+    }
+}
+
 /// Texture with UV coordinates.
 pub struct TextureWithUv {
     pub texture: Texture,
@@ -211,77 +323,176 @@ impl TextureWithUv {
     pub fn new(texture: Texture, uv: Rect) -> Self {
         Self { texture, uv }
     }
+
+    /// Whether the drawn part of the texture is smaller than a tile of given size, e.g. because
+    /// it was interpolated from a lower zoom level.
+    fn upscaled(&self, tile_size: u32) -> bool {
+        let width = match &self.texture {
+            Texture::Raster(texture_handle) => texture_handle.size()[0] as f32 * self.uv.width(),
+            #[cfg(feature = "vector_tiles")]
+            Texture::Vector(_) => tile_size as f32 * self.uv.width(),
+        };
+        width < tile_size as f32 - 0.5
+    }
+}
+
+/// How many tiles were visible, how many of them were drawn and how many are known to be
+/// missing.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct Coverage {
+    pub visible: usize,
+    pub drawn: usize,
+    pub upscaled: usize,
+    pub missing: usize,
+}
+
+impl Coverage {
+    /// Whether the source has no data for the whole viewport.
+    pub fn empty(&self) -> bool {
+        self.visible > 0 && self.visible == self.missing
+    }
+}
+
+impl std::ops::AddAssign for Coverage {
+    fn add_assign(&mut self, other: Self) {
+        self.visible += other.visible;
+        self.drawn += other.drawn;
+        self.upscaled += other.upscaled;
+        self.missing += other.missing;
+    }
 }
 
+/// Draw tiles around `map_center`, which is at the `center` of the screen, within the painter's
+/// clip rect.
 pub(crate) fn draw_tiles(
     painter: &egui::Painter,
+    center: Pos2,
     map_center: Position,
     zoom: Zoom,
     tiles: &mut dyn Tiles,
     transparency: f32,
-) {
-    let mut meshes = Default::default();
+) -> Coverage {
+    // Otherwise, tiles touching its edge would be asked for.
+    if !painter.clip_rect().is_positive() {
+        return Coverage::default();
+    }
+
+    let map_center_projected_position = project(map_center, zoom.into());
+    let tile_zoom = tile_id(map_center, zoom.round(), tiles.tile_size()).zoom;
+    let corrected_tile_size = total_pixels(zoom.into()) / total_tiles(tile_zoom) as f64;
+
+    // Start from the middle of what is drawn, which is not the map's center if only a part of
+    // the map is.
+    let offset = painter.clip_rect().center() - center;
+    let x = map_center_projected_position.x() + offset.x as f64;
+    let y = map_center_projected_position.y() + offset.y as f64;
+    let row = (y / corrected_tile_size)
+        .floor()
+        .clamp(0., total_tiles(tile_zoom) as f64 - 1.) as u32;
+    let column = (x / corrected_tile_size).floor() as i64;
+    let start = wrapped_tile_at(
+        column,
+        TileId {
+            x: 0,
+            y: row,
+            zoom: tile_zoom,
+        },
+    );
+
     flood_fill_tiles(
         painter,
-        tile_id(map_center, zoom.round(), tiles.tile_size()),
-        project(map_center, zoom.into()),
+        center,
+        start,
+        map_center_projected_position,
         zoom.into(),
         tiles,
         transparency,
-        &mut meshes,
-    );
+    )
 }
 
 /// Use simple [flood fill algorithm](https://en.wikipedia.org/wiki/Flood_fill) to draw tiles on the map.
+///
+/// World repeats horizontally, so tiles are identified by their column on the screen, which can
+/// go beyond the world's edges, along with the [`TileId`] to be drawn there.
 fn flood_fill_tiles(
     painter: &egui::Painter,
-    tile_id: TileId,
+    center: Pos2,
+    start: (i64, TileId),
     map_center_projected_position: Pixels,
     zoom: f64,
     tiles: &mut dyn Tiles,
     transparency: f32,
-    meshes: &mut HashSet<TileId>,
-) {
-    // We need to make up the difference between integer and floating point zoom levels.
-    let corrected_tile_size = tiles.tile_size() as f64 * 2f64.powf(zoom - zoom.round());
-    let tile_projected = tile_id.project(corrected_tile_size);
-    let tile_screen_position = painter.clip_rect().center().to_vec2()
-        + (tile_projected - map_center_projected_position).to_vec2();
-
-    if painter
-        .clip_rect()
-        .intersects(rect(tile_screen_position, corrected_tile_size))
-        && meshes.insert(tile_id)
-    {
-        if let Some(tile) = tiles.at(tile_id) {
-            tile.texture.draw(
-                painter,
-                rect(tile_screen_position, corrected_tile_size),
-                tile.uv,
-                transparency,
-            )
-        }
+) -> Coverage {
+    let mut coverage = Coverage::default();
+    let mut visited = HashSet::new();
+
+    // Tiles taken from lower zoom levels are blurry stand-ins, so they are painted first and
+    // tiles of the exact zoom always end up on top of them.
+    let mut upscaled = Vec::new();
+    let mut exact = Vec::new();
+
+    // Explicit stack instead of recursion, as there can be thousands of tiles on a large screen.
+    let mut stack = vec![start];
+
+    // Tiles are of the source's size, e.g. 512px, at the nearest integer zoom level, so they
+    // need to be scaled to the map's, possibly fractional, zoom. Size of the world divided by
+    // the number of tiles covers both.
+    let corrected_tile_size = total_pixels(zoom) / total_tiles(start.1.zoom) as f64;
+
+    while let Some((column, tile_id)) = stack.pop() {
+        let tile_projected = Pixels::new(
+            column as f64 * corrected_tile_size,
+            tile_id.y as f64 * corrected_tile_size,
+        );
+        let tile_screen_position =
+            center.to_vec2() + (tile_projected - map_center_projected_position).to_vec2();
 
-        for next_tile_id in [
-            tile_id.north(),
-            tile_id.east(),
-            tile_id.south(),
-            tile_id.west(),
-        ]
-        .iter()
-        .flatten()
+        if !painter
+            .clip_rect()
+            .intersects(rect(tile_screen_position, corrected_tile_size))
+            || !visited.insert((column, tile_id.y))
         {
-            flood_fill_tiles(
-                painter,
-                *next_tile_id,
-                map_center_projected_position,
-                zoom,
-                tiles,
-                transparency,
-                meshes,
-            );
+            continue;
+        }
+
+        coverage.visible += 1;
+
+        if let Some(tile) = tiles.at(tile_id) {
+            coverage.drawn += 1;
+            let tile_rect = rect(tile_screen_position, corrected_tile_size);
+            if tile.upscaled(tiles.tile_size()) {
+                coverage.upscaled += 1;
+                upscaled.push((tile, tile_rect));
+            } else {
+                exact.push((tile, tile_rect));
+            }
+        } else if tiles.missing(tile_id) {
+            coverage.missing += 1;
         }
+
+        stack.extend(
+            [
+                tile_id.north().map(|north| (column, north)),
+                Some(wrapped_tile_at(column + 1, tile_id)),
+                tile_id.south().map(|south| (column, south)),
+                Some(wrapped_tile_at(column - 1, tile_id)),
+            ]
+            .into_iter()
+            .flatten(),
+        );
+    }
+
+    for (tile, tile_rect) in upscaled.into_iter().chain(exact) {
+        tile.texture.draw(painter, tile_rect, tile.uv, transparency);
     }
+
+    coverage
+}
+
+/// Tile in the same row as `tile_id`, which is drawn at the given `column`.
+fn wrapped_tile_at(column: i64, tile_id: TileId) -> (i64, TileId) {
+    let x = column.rem_euclid(total_tiles(tile_id.zoom) as i64) as u32;
+    (column, TileId { x, ..tile_id })
 }
 
 /// Take a piece of a tile with lower zoom level and use it as a required tile.
@@ -330,6 +541,7 @@ fn full_rect_of_clipped_tile(rect: Rect, uv: Rect) -> Rect {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::attribution;
 
     #[test]
     fn test_full_rect_of_clipped_tile() {
@@ -343,45 +555,255 @@ mod tests {
     }
 
     #[test]
-    fn tile_id_cannot_go_beyond_limits() {
-        // There is only one tile at zoom 0.
+    fn raster_texture_is_sampled_using_requested_filter() {
+        let ctx = Context::default();
+        let image = include_bytes!("../assets/blank-255-tile.png");
+
+        for filter in [TextureFilter::Linear, TextureFilter::Nearest] {
+            let Ok(Texture::Raster(handle)) = Texture::with_filter(image, &ctx, filter) else {
+                panic!("expected a raster texture");
+            };
+
+            let options = ctx
+                .tex_manager()
+                .read()
+                .meta(handle.id())
+                .map(|meta| meta.options);
+            assert_eq!(
+                options,
+                Some(TextureOptions {
+                    magnification: filter,
+                    minification: filter,
+                    ..Default::default()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn tiles_wrap_around_horizontally() {
         let tile_id = TileId {
             x: 0,
-            y: 0,
-            zoom: 0,
+            y: 1,
+            zoom: 2,
         };
 
-        assert_eq!(tile_id.west(), None);
-        assert_eq!(tile_id.north(), None);
-        assert_eq!(tile_id.south(), None);
-        assert_eq!(tile_id.east(), None);
+        assert_eq!(wrapped_tile_at(4, tile_id), (4, tile_id));
+        assert_eq!(
+            wrapped_tile_at(-1, tile_id),
+            (-1, TileId { x: 3, ..tile_id })
+        );
+        assert_eq!(wrapped_tile_at(5, tile_id), (5, TileId { x: 1, ..tile_id }));
+    }
 
-        // There are 2 tiles at zoom 1.
-        let tile_id = TileId {
-            x: 0,
-            y: 0,
-            zoom: 1,
+    #[test]
+    fn semi_transparent_overlay_blends_without_fringes() {
+        // Transparent-edged overlay: opaque white, white at half alpha and transparent "black",
+        // which is what most encoders put into fully transparent pixels.
+        let mut png = Vec::new();
+        image::RgbaImage::from_raw(
+            3,
+            1,
+            vec![255, 255, 255, 255, 255, 255, 255, 128, 0, 0, 0, 0],
+        )
+        .expect("valid size")
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .expect("encodable");
+
+        let Ok(Some(overlay)) = decode_raster(&png) else {
+            panic!("expected a raster image");
         };
 
-        assert_eq!(tile_id.west(), None);
-        assert_eq!(tile_id.north(), None);
-
         assert_eq!(
-            tile_id.south(),
-            Some(TileId {
-                x: 0,
-                y: 1,
-                zoom: 1
+            overlay.pixels,
+            [
+                Color32::WHITE,
+                Color32::from_rgba_premultiplied(128, 128, 128, 128),
+                Color32::TRANSPARENT
+            ]
+        );
+
+        // Over a gray base, the edge only lightens it, like white at half opacity should.
+        let base = Color32::from_gray(100);
+        let edge = base.blend(overlay.pixels[1]);
+        assert_eq!(edge, Color32::from_gray(178));
+
+        // Transparent pixels leave the base untouched.
+        assert_eq!(base.blend(overlay.pixels[2]), base);
+    }
+
+    /// Every other column is interpolated from a lower zoom level.
+    struct InterleavedTiles(Texture);
+
+    impl Tiles for InterleavedTiles {
+        fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+            let uv = if tile_id.x % 2 == 0 {
+                Rect::from_min_max(pos2(0., 0.), pos2(1., 1.))
+            } else {
+                Rect::from_min_max(pos2(0., 0.), pos2(0.5, 0.5))
+            };
+            Some(TextureWithUv::new(self.0.clone(), uv))
+        }
+
+        fn attribution(&self) -> Attribution {
+            attribution("")
+        }
+
+        fn tile_size(&self) -> u32 {
+            256
+        }
+    }
+
+    #[test]
+    fn exact_tiles_are_painted_over_upscaled_ones() {
+        let ctx = Context::default();
+        let image = ColorImage::filled([256, 256], Color32::WHITE);
+        let mut tiles = InterleavedTiles(Texture::from_color_image(image, &ctx));
+
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            let zoom = Zoom::try_from(3.).expect("valid zoom");
+            draw_tiles(
+                &painter,
+                painter.clip_rect().center(),
+                crate::lon_lat(0., 0.),
+                zoom,
+                &mut tiles,
+                1.,
+            );
+        });
+
+        // Width of the UV of each painted mesh, in the order they were painted.
+        let widths: Vec<f32> = output
+            .shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                egui::Shape::Mesh(mesh) => Some(mesh.vertices[1].uv.x - mesh.vertices[0].uv.x),
+                _ => None,
             })
+            .collect();
+
+        let first_exact = widths.iter().position(|width| *width == 1.);
+        assert!(first_exact.is_some_and(|idx| idx > 0));
+        assert!(
+            widths[first_exact.unwrap_or_default()..]
+                .iter()
+                .all(|width| *width == 1.)
         );
+    }
 
-        assert_eq!(
-            tile_id.east(),
-            Some(TileId {
-                x: 1,
-                y: 0,
-                zoom: 1
+    #[test]
+    fn preview_is_taken_from_the_middle_of_bounds() {
+        struct Bounded(InterleavedTiles);
+
+        impl Tiles for Bounded {
+            fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+                self.0.at(tile_id)
+            }
+
+            fn attribution(&self) -> Attribution {
+                self.0.attribution()
+            }
+
+            fn tile_size(&self) -> u32 {
+                256
+            }
+
+            fn zoom_range(&self) -> RangeInclusive<u8> {
+                2..=10
+            }
+
+            fn bounds(&self) -> Option<BoundingBox> {
+                Some(BoundingBox::new(
+                    crate::lon_lat(-80., -10.),
+                    crate::lon_lat(-60., 10.),
+                ))
+            }
+        }
+
+        let ctx = Context::default();
+        let image = ColorImage::filled([256, 256], Color32::WHITE);
+        let texture = Texture::from_color_image(image, &ctx);
+
+        // Zoom 0 tile, or 2 in this case, as that is where the source starts.
+        let mut tiles: Box<dyn Tiles> = Box::new(InterleavedTiles(texture.clone()));
+        let (_, uv) = tiles.preview(0).expect("tile is loaded");
+        assert_eq!(uv.width(), 1.);
+
+        // At zoom 2, the world is 4 tiles wide, so -70 degrees is in the second column.
+        let mut tiles = Bounded(InterleavedTiles(texture));
+        let (_, uv) = tiles.preview(0).expect("tile is loaded");
+        assert_eq!(uv.width(), 0.5);
+    }
+
+    /// Source of 512px tiles, remembering which zoom levels it was asked for.
+    struct LargeTiles(Texture, Vec<u8>);
+
+    impl Tiles for LargeTiles {
+        fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+            self.1.push(tile_id.zoom);
+            Some(TextureWithUv::new(
+                self.0.clone(),
+                Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+            ))
+        }
+
+        fn attribution(&self) -> Attribution {
+            attribution("")
+        }
+
+        fn tile_size(&self) -> u32 {
+            512
+        }
+    }
+
+    /// Zoom levels asked for and widths of the painted tiles.
+    fn draw_large_tiles(zoom: f64) -> (Vec<u8>, Vec<f32>) {
+        let ctx = Context::default();
+        let image = ColorImage::filled([512, 512], Color32::WHITE);
+        let mut tiles = LargeTiles(Texture::from_color_image(image, &ctx), Vec::new());
+
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            let zoom = Zoom::try_from(zoom).expect("valid zoom");
+            draw_tiles(
+                &painter,
+                painter.clip_rect().center(),
+                crate::lon_lat(17., 51.),
+                zoom,
+                &mut tiles,
+                1.,
+            );
+        });
+
+        let widths = output
+            .shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                egui::Shape::Mesh(mesh) => Some(mesh.vertices[1].pos.x - mesh.vertices[0].pos.x),
+                _ => None,
             })
+            .collect();
+        (tiles.1, widths)
+    }
+
+    #[test]
+    fn large_tiles_are_drawn_at_the_right_scale() {
+        // One 512px tile covers four 256px ones, so it comes from one zoom level lower.
+        let (zooms, widths) = draw_large_tiles(10.3);
+        assert!(!zooms.is_empty());
+        assert!(zooms.iter().all(|zoom| *zoom == 9));
+        assert!(!widths.is_empty());
+        assert!(
+            widths
+                .iter()
+                .all(|width| (*width as f64 - 512. * 2f64.powf(0.3)).abs() < 0.01)
         );
+
+        // At zoom 0, the whole world is only 256px wide, so the tile is scaled down.
+        let (zooms, widths) = draw_large_tiles(0.);
+        assert!(zooms.iter().all(|zoom| *zoom == 0));
+        assert!(!widths.is_empty());
+        assert!(widths.iter().all(|width| (*width - 256.).abs() < 0.01));
     }
 }
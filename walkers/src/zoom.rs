@@ -1,3 +1,7 @@
+use std::ops::RangeInclusive;
+
+use crate::TileId;
+
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
 #[error("invalid zoom level")]
 pub struct InvalidZoom;
@@ -48,6 +52,16 @@ impl Zoom {
         Ok(())
     }
 
+    /// Keep the zoom within the given range, which itself is limited to valid zoom levels.
+    pub fn clamp(&mut self, range: &RangeInclusive<f64>) {
+        let max_zoom = TileId::MAX_ZOOM as f64;
+        let min = range.start().clamp(0., max_zoom);
+        let max = range.end().clamp(0., max_zoom);
+        if min <= max {
+            self.0 = self.0.clamp(min, max);
+        }
+    }
+
     /// Zoom using a relative value.
     pub fn zoom_by(&mut self, value: f64) {
         if let Ok(new_self) = Self::try_from(self.0 + value) {
@@ -82,4 +96,17 @@ mod tests {
         assert_eq!(0, zoom.round());
         assert_eq!(Err(InvalidZoom), zoom.zoom_out());
     }
+
+    #[test]
+    fn test_clamping() {
+        let mut zoom = Zoom::default();
+        zoom.clamp(&(3.0..=10.0));
+        assert_eq!(10, zoom.round());
+        zoom.clamp(&(12.0..=20.0));
+        assert_eq!(12, zoom.round());
+
+        // Range is limited to valid zoom levels.
+        zoom.clamp(&(30.0..=40.0));
+        assert_eq!(26, zoom.round());
+    }
 }
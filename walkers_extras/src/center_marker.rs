@@ -0,0 +1,127 @@
+use egui::{Color32, Painter, Response, Stroke, Ui, vec2};
+use walkers::{MapMemory, Plugin, PluginLayer, Projector};
+
+/// Look of [`CenterMarker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CenterMarkerShape {
+    /// Two lines crossing at the center.
+    Crosshair,
+
+    /// Pin standing with its tip at the center.
+    Pin,
+}
+
+/// [`Plugin`] which marks the center of the map, e.g. for picking a location by dragging the
+/// map under it. The marker stays in place while the map moves. Position under it is
+/// `map_memory.detached()`, or `my_position` if the map is not detached.
+pub struct CenterMarker {
+    shape: CenterMarkerShape,
+    size: f32,
+    stroke: Stroke,
+}
+
+impl Default for CenterMarker {
+    fn default() -> Self {
+        Self {
+            shape: CenterMarkerShape::Crosshair,
+            size: 20.,
+            stroke: Stroke::new(2., Color32::from_rgb(219, 68, 55)),
+        }
+    }
+}
+
+impl CenterMarker {
+    /// Crosshair by default.
+    pub fn with_shape(mut self, shape: CenterMarkerShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Width of the crosshair, or height of the pin, in points. 20 by default.
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Color and width of the lines. Pin's head is filled with the same color.
+    pub fn with_stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+}
+
+impl Plugin for CenterMarker {
+    fn run(
+        self: Box<Self>,
+        _ui: &mut Ui,
+        painter: &Painter,
+        response: &Response,
+        _projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        let center = response.rect.center();
+        let half = self.size / 2.;
+
+        match self.shape {
+            CenterMarkerShape::Crosshair => {
+                painter.line_segment(
+                    [center - vec2(half, 0.), center + vec2(half, 0.)],
+                    self.stroke,
+                );
+                painter.line_segment(
+                    [center - vec2(0., half), center + vec2(0., half)],
+                    self.stroke,
+                );
+            }
+            CenterMarkerShape::Pin => {
+                let radius = self.size / 4.;
+                let head = center - vec2(0., self.size - radius);
+                painter.line_segment([center, head], self.stroke);
+                painter.circle(head, radius, self.stroke.color, Stroke::NONE);
+            }
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Controls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Shape;
+    use walkers::lon_lat;
+
+    #[test]
+    fn marker_is_drawn_at_center_of_the_map() {
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        memory.center_at(lon_lat(17.0, 51.0));
+        let mut center = egui::Pos2::ZERO;
+
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let response = ui.add(
+                    walkers::Map::new(None, &mut memory, lon_lat(0., 0.))
+                        .with_plugin(CenterMarker::default()),
+                );
+                center = response.rect.center();
+            });
+        });
+
+        let segments: Vec<_> = output
+            .shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                Shape::LineSegment { points, .. } => Some(*points),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(segments.len(), 2);
+        for [a, b] in segments {
+            assert_eq!(a.lerp(b, 0.5), center);
+            assert_eq!(a.distance(b), 20.);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use egui::{Align2, Color32, FontId, Painter, Response, Ui, vec2};
+use walkers::{MapMemory, Plugin, PluginLayer, Position, Projector};
+
+/// How [`CoordinateReadout`] formats the coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateFormat {
+    /// Decimal degrees, e.g. `51.09916, 17.03664`.
+    Decimal,
+
+    /// Degrees, minutes and seconds, e.g. `51°05'56.98"N 17°02'11.90"E`.
+    Dms,
+}
+
+/// [`Plugin`] which shows the latitude and longitude of the place under the mouse pointer in
+/// a corner of the map.
+pub struct CoordinateReadout {
+    format: CoordinateFormat,
+    precision: usize,
+    anchor: Align2,
+}
+
+impl Default for CoordinateReadout {
+    fn default() -> Self {
+        Self {
+            format: CoordinateFormat::Decimal,
+            precision: 5,
+            anchor: Align2::LEFT_BOTTOM,
+        }
+    }
+}
+
+impl CoordinateReadout {
+    /// Format of the coordinates. Decimal degrees by default.
+    pub fn with_format(mut self, format: CoordinateFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Number of decimal places: of degrees for [`CoordinateFormat::Decimal`] and of seconds
+    /// for [`CoordinateFormat::Dms`]. 5 by default.
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Corner (or an edge) of the map where the coordinates are shown. Bottom left by default.
+    pub fn with_anchor(mut self, anchor: Align2) -> Self {
+        self.anchor = anchor;
+        self
+    }
+}
+
+/// Distance from the edges of the map.
+const MARGIN: f32 = 10.;
+
+impl Plugin for CoordinateReadout {
+    fn run(
+        self: Box<Self>,
+        ui: &mut Ui,
+        painter: &Painter,
+        response: &Response,
+        projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        let Some(pointer) = response.hover_pos() else {
+            return;
+        };
+
+        let position = projector.unproject(pointer.to_vec2());
+        let galley = painter.layout_no_wrap(
+            format_position(position, self.format, self.precision),
+            FontId::monospace(12.),
+            ui.visuals().text_color(),
+        );
+
+        let rect = self
+            .anchor
+            .align_size_within_rect(galley.size(), response.rect.shrink(MARGIN));
+
+        painter.rect_filled(
+            rect.expand2(vec2(MARGIN / 2., MARGIN / 4.)),
+            4.,
+            ui.visuals().window_fill.gamma_multiply(0.8),
+        );
+        painter.galley(rect.min, galley, Color32::PLACEHOLDER);
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Controls
+    }
+}
+
+/// Format position as latitude followed by longitude.
+fn format_position(position: Position, format: CoordinateFormat, precision: usize) -> String {
+    // Map repeats horizontally, so the pointer can be over a copy of the world.
+    let lon = (position.x() + 180.).rem_euclid(360.) - 180.;
+    let lat = position.y();
+
+    match format {
+        CoordinateFormat::Decimal => format!("{lat:.precision$}, {lon:.precision$}"),
+        CoordinateFormat::Dms => format!(
+            "{} {}",
+            format_dms(lat, 'N', 'S', precision),
+            format_dms(lon, 'E', 'W', precision)
+        ),
+    }
+}
+
+fn format_dms(value: f64, positive: char, negative: char, precision: usize) -> String {
+    let hemisphere = if value < 0. { negative } else { positive };
+
+    // Round the seconds first, so that e.g. 59.999 does not show up as 60.00.
+    let scale = 10f64.powi(precision as i32);
+    let total_seconds = (value.abs() * 3600. * scale).round() / scale;
+
+    let degrees = (total_seconds / 3600.).floor();
+    let minutes = ((total_seconds - degrees * 3600.) / 60.).floor();
+    let seconds = total_seconds - degrees * 3600. - minutes * 60.;
+
+    // Width of the seconds field: two digits, plus the dot and the decimal places.
+    let width = if precision > 0 { precision + 3 } else { 2 };
+    format!("{degrees:.0}°{minutes:02.0}'{seconds:0width$.precision$}\"{hemisphere}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use walkers::lon_lat;
+
+    #[test]
+    fn formatting_decimal_degrees() {
+        let position = lon_lat(17.03664, 51.09916);
+        assert_eq!(
+            format_position(position, CoordinateFormat::Decimal, 5),
+            "51.09916, 17.03664"
+        );
+        assert_eq!(
+            format_position(position, CoordinateFormat::Decimal, 2),
+            "51.10, 17.04"
+        );
+    }
+
+    #[test]
+    fn formatting_degrees_minutes_seconds() {
+        let position = lon_lat(17.03664, 51.09916);
+        assert_eq!(
+            format_position(position, CoordinateFormat::Dms, 2),
+            "51°05'56.98\"N 17°02'11.90\"E"
+        );
+
+        let position = lon_lat(-74.0445, -40.6892);
+        assert_eq!(
+            format_position(position, CoordinateFormat::Dms, 0),
+            "40°41'21\"S 74°02'40\"W"
+        );
+    }
+
+    #[test]
+    fn seconds_are_carried_over_when_rounded() {
+        // 10°59'59.999"
+        let value = 10. + 59. / 60. + 59.999 / 3600.;
+        assert_eq!(format_dms(value, 'N', 'S', 2), "11°00'00.00\"N");
+    }
+
+    #[test]
+    fn longitude_is_wrapped() {
+        let position = lon_lat(17.03664 + 360., 51.09916);
+        assert_eq!(
+            format_position(position, CoordinateFormat::Decimal, 5),
+            "51.09916, 17.03664"
+        );
+    }
+}
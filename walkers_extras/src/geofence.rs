@@ -0,0 +1,146 @@
+use walkers::{Position, PositionExt};
+
+use crate::polygon::contains;
+
+/// Area watched by a [`Geofence`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeofenceShape {
+    /// Area inside the exterior ring, but not in any of the holes, like [`crate::Polygon`].
+    Polygon {
+        exterior: Vec<Position>,
+        holes: Vec<Vec<Position>>,
+    },
+    /// Area within `radius` meters from the `center`.
+    Circle { center: Position, radius: f64 },
+}
+
+impl GeofenceShape {
+    /// Whether the position is inside the area.
+    pub fn contains(&self, position: Position) -> bool {
+        match self {
+            GeofenceShape::Polygon { exterior, holes } => contains(exterior, holes, position),
+            GeofenceShape::Circle { center, radius } => {
+                center.distance_meters(&position) <= *radius
+            }
+        }
+    }
+}
+
+/// What happened since the previous [`Geofence::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeofenceEvent {
+    Entered,
+    Exited,
+}
+
+/// Tells when a tracked position, such as the one passed to [`walkers::Map::new`], enters or
+/// leaves an area. It is not a plugin, feed it with positions as they arrive.
+///
+/// ```
+/// use walkers::lat_lon;
+/// use walkers_extras::{Geofence, GeofenceEvent, GeofenceShape};
+///
+/// let mut geofence = Geofence::new(GeofenceShape::Circle {
+///     center: lat_lon(51.104465, 17.075169),
+///     radius: 500.,
+/// });
+///
+/// assert_eq!(geofence.update(lat_lon(51.103, 17.074)), Some(GeofenceEvent::Entered));
+/// assert_eq!(geofence.update(lat_lon(51.104, 17.075)), None);
+/// assert_eq!(geofence.update(lat_lon(51.2, 17.07)), Some(GeofenceEvent::Exited));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Geofence {
+    shape: GeofenceShape,
+    inside: bool,
+}
+
+impl Geofence {
+    /// Position is considered outside until the first [`Geofence::update`], so starting inside
+    /// the area yields [`GeofenceEvent::Entered`].
+    pub fn new(shape: GeofenceShape) -> Self {
+        Self {
+            shape,
+            inside: false,
+        }
+    }
+
+    /// Check the new position against the area. `Some` if it crossed the boundary.
+    pub fn update(&mut self, position: Position) -> Option<GeofenceEvent> {
+        let inside = self.shape.contains(position);
+        let event = match (self.inside, inside) {
+            (false, true) => Some(GeofenceEvent::Entered),
+            (true, false) => Some(GeofenceEvent::Exited),
+            _ => None,
+        };
+        self.inside = inside;
+        event
+    }
+
+    /// Whether the last position was inside the area.
+    pub fn is_inside(&self) -> bool {
+        self.inside
+    }
+
+    pub fn shape(&self) -> &GeofenceShape {
+        &self.shape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use walkers::lon_lat;
+
+    fn ring(size: f64) -> Vec<Position> {
+        vec![
+            lon_lat(-size, -size),
+            lon_lat(size, -size),
+            lon_lat(size, size),
+            lon_lat(-size, size),
+        ]
+    }
+
+    #[test]
+    fn entering_and_leaving_polygon() {
+        let mut geofence = Geofence::new(GeofenceShape::Polygon {
+            exterior: ring(0.1),
+            holes: vec![ring(0.02)],
+        });
+
+        assert_eq!(geofence.update(lon_lat(0.2, 0.)), None);
+        assert_eq!(
+            geofence.update(lon_lat(0.05, 0.)),
+            Some(GeofenceEvent::Entered)
+        );
+        assert!(geofence.is_inside());
+        assert_eq!(geofence.update(lon_lat(0.06, 0.05)), None);
+
+        // Hole is not a part of the area.
+        assert_eq!(
+            geofence.update(lon_lat(0., 0.)),
+            Some(GeofenceEvent::Exited)
+        );
+        assert_eq!(geofence.update(lon_lat(0., 0.01)), None);
+        assert!(!geofence.is_inside());
+    }
+
+    #[test]
+    fn entering_and_leaving_circle() {
+        let center = lon_lat(17., 51.);
+        let mut geofence = Geofence::new(GeofenceShape::Circle {
+            center,
+            radius: 100.,
+        });
+
+        assert_eq!(
+            geofence.update(center.offset_meters(0., 99.)),
+            Some(GeofenceEvent::Entered)
+        );
+        assert_eq!(
+            geofence.update(center.offset_meters(-101., 0.)),
+            Some(GeofenceEvent::Exited)
+        );
+        assert_eq!(geofence.update(center.offset_meters(0., -150.)), None);
+    }
+}
@@ -0,0 +1,284 @@
+use egui::Stroke;
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use thiserror::Error;
+use walkers::{Position, lon_lat};
+
+use crate::{LabeledSymbol, Polyline};
+
+/// Point of a GPX track, or a waypoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpxPoint {
+    pub position: Position,
+
+    /// Elevation, in meters, from `<ele>`.
+    pub elevation: Option<f64>,
+
+    /// Timestamp from `<time>`, as written in the file, which is ISO 8601, e.g.
+    /// `2024-05-01T07:30:00Z`.
+    pub time: Option<String>,
+
+    /// Name from `<name>`, usually given only to waypoints.
+    pub name: Option<String>,
+}
+
+/// Track of a GPX document, such as a recorded run. Each segment is a continuous part of it,
+/// e.g. between pauses of the recording.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpxTrack {
+    pub name: Option<String>,
+    pub segments: Vec<Vec<GpxPoint>>,
+}
+
+/// Tracks and waypoints parsed by [`parse_gpx`]. Routes are not supported.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gpx {
+    pub tracks: Vec<GpxTrack>,
+    pub waypoints: Vec<GpxPoint>,
+}
+
+impl Gpx {
+    /// Positions of every track segment, in the order they appear in the document.
+    pub fn segments(&self) -> impl Iterator<Item = Vec<Position>> + '_ {
+        self.tracks.iter().flat_map(|track| {
+            track
+                .segments
+                .iter()
+                .map(|segment| segment.iter().map(|point| point.position).collect())
+        })
+    }
+
+    /// [`Polyline`] for every track segment, ready to be added to the map.
+    ///
+    /// ```
+    /// # use walkers_extras::parse_gpx;
+    /// let gpx = parse_gpx(r#"
+    ///     <gpx version="1.1">
+    ///         <trk><trkseg>
+    ///             <trkpt lat="51.1" lon="17.03"/>
+    ///             <trkpt lat="51.11" lon="17.04"/>
+    ///         </trkseg></trk>
+    ///     </gpx>
+    /// "#).expect("valid GPX");
+    /// let polylines = gpx.polylines(egui::Stroke::new(3., egui::Color32::RED));
+    /// assert_eq!(polylines.len(), 1);
+    /// ```
+    pub fn polylines(&self, stroke: Stroke) -> Vec<Polyline> {
+        self.segments()
+            .map(|positions| Polyline::new(positions, stroke))
+            .collect()
+    }
+
+    /// Waypoints as places, labeled with their names, e.g. for [`crate::Places`].
+    pub fn places(&self) -> Vec<LabeledSymbol> {
+        self.waypoints
+            .iter()
+            .map(|point| LabeledSymbol::new(point.position, point.name.clone().unwrap_or_default()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GpxError {
+    #[error("XML error: {0}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("Failed to parse float: {0}")]
+    ParseFloat(#[from] std::num::ParseFloatError),
+    #[error("Point is missing the {0} attribute")]
+    MissingCoordinate(&'static str),
+}
+
+/// Parse a GPX document, such as one exported from a sports tracker.
+pub fn parse_gpx(input: &str) -> Result<Gpx, GpxError> {
+    let mut reader = Reader::from_str(input);
+    reader.config_mut().trim_text(true);
+
+    let mut gpx = Gpx::default();
+    let mut stack: Vec<String> = Vec::new();
+    let mut point: Option<GpxPoint> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => {
+                let name = decode(&start);
+                handle_start(&name, &start, &mut gpx, &mut point)?;
+                stack.push(name);
+            }
+            Event::Empty(start) => {
+                let name = decode(&start);
+                handle_start(&name, &start, &mut gpx, &mut point)?;
+                handle_end(&name, &mut gpx, &mut point);
+            }
+            Event::End(end) => {
+                let name = String::from_utf8_lossy(end.name().as_ref()).into_owned();
+                handle_end(&name, &mut gpx, &mut point);
+                stack.pop();
+            }
+            Event::Text(text) => {
+                let value = text.unescape()?;
+                handle_text(&stack, value.trim(), &mut gpx, &mut point)?;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(gpx)
+}
+
+fn decode(start: &BytesStart<'_>) -> String {
+    String::from_utf8_lossy(start.name().as_ref()).into_owned()
+}
+
+fn handle_start(
+    name: &str,
+    start: &BytesStart<'_>,
+    gpx: &mut Gpx,
+    point: &mut Option<GpxPoint>,
+) -> Result<(), GpxError> {
+    match name {
+        "trk" => gpx.tracks.push(GpxTrack::default()),
+        "trkseg" => {
+            if let Some(track) = gpx.tracks.last_mut() {
+                track.segments.push(Vec::new());
+            }
+        }
+        "trkpt" | "wpt" => {
+            let coordinate = |name: &'static str| -> Result<f64, GpxError> {
+                let attribute = start
+                    .try_get_attribute(name)
+                    .map_err(quick_xml::Error::from)?
+                    .ok_or(GpxError::MissingCoordinate(name))?;
+                Ok(attribute.unescape_value()?.trim().parse()?)
+            };
+            *point = Some(GpxPoint {
+                position: lon_lat(coordinate("lon")?, coordinate("lat")?),
+                elevation: None,
+                time: None,
+                name: None,
+            });
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_end(name: &str, gpx: &mut Gpx, point: &mut Option<GpxPoint>) {
+    match name {
+        "trkpt" => {
+            if let (Some(point), Some(segment)) = (
+                point.take(),
+                gpx.tracks
+                    .last_mut()
+                    .and_then(|track| track.segments.last_mut()),
+            ) {
+                segment.push(point);
+            }
+        }
+        "wpt" => gpx.waypoints.extend(point.take()),
+        _ => {}
+    }
+}
+
+fn handle_text(
+    stack: &[String],
+    value: &str,
+    gpx: &mut Gpx,
+    point: &mut Option<GpxPoint>,
+) -> Result<(), GpxError> {
+    let Some(element) = stack.last() else {
+        return Ok(());
+    };
+
+    if let Some(point) = point {
+        match element.as_str() {
+            "ele" => point.elevation = Some(value.parse()?),
+            "time" => point.time = Some(value.to_owned()),
+            "name" => point.name = Some(value.to_owned()),
+            _ => {}
+        }
+    } else if element == "name" && stack.len() >= 2 && stack[stack.len() - 2] == "trk" {
+        if let Some(track) = gpx.tracks.last_mut() {
+            track.name = Some(value.to_owned());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUN: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+            <metadata><name>Morning run</name></metadata>
+            <wpt lat="51.1" lon="17.03">
+                <name>Start &amp; finish</name>
+            </wpt>
+            <trk>
+                <name>Run</name>
+                <trkseg>
+                    <trkpt lat="51.1" lon="17.03">
+                        <ele>120.5</ele>
+                        <time>2024-05-01T07:30:00Z</time>
+                    </trkpt>
+                    <trkpt lat="51.101" lon="17.031"><ele>121</ele></trkpt>
+                </trkseg>
+                <trkseg>
+                    <trkpt lat="51.102" lon="17.032"/>
+                </trkseg>
+            </trk>
+            <trk>
+                <trkseg><trkpt lat="-33.9" lon="151.2"/></trkseg>
+            </trk>
+            <wpt lat="51.2" lon="17.1"/>
+        </gpx>"#;
+
+    #[test]
+    fn tracks_segments_and_waypoints_are_parsed() {
+        let gpx = parse_gpx(RUN).unwrap();
+
+        assert_eq!(gpx.tracks.len(), 2);
+        assert_eq!(gpx.tracks[0].name.as_deref(), Some("Run"));
+        assert_eq!(gpx.tracks[0].segments.len(), 2);
+        assert_eq!(
+            gpx.tracks[0].segments[0][0],
+            GpxPoint {
+                position: lon_lat(17.03, 51.1),
+                elevation: Some(120.5),
+                time: Some("2024-05-01T07:30:00Z".to_owned()),
+                name: None,
+            }
+        );
+        assert_eq!(gpx.tracks[0].segments[0][1].elevation, Some(121.));
+        assert_eq!(gpx.tracks[1].name, None);
+
+        assert_eq!(
+            gpx.segments().collect::<Vec<_>>(),
+            vec![
+                vec![lon_lat(17.03, 51.1), lon_lat(17.031, 51.101)],
+                vec![lon_lat(17.032, 51.102)],
+                vec![lon_lat(151.2, -33.9)],
+            ]
+        );
+
+        // Waypoints are not a part of any line.
+        assert_eq!(gpx.waypoints.len(), 2);
+        assert_eq!(gpx.waypoints[0].name.as_deref(), Some("Start & finish"));
+        let places = gpx.places();
+        assert_eq!(places[0].label, "Start & finish");
+        assert_eq!(places[1].position, lon_lat(17.1, 51.2));
+    }
+
+    #[test]
+    fn points_without_coordinates_are_rejected() {
+        assert!(matches!(
+            parse_gpx(r#"<gpx><trk><trkseg><trkpt lat="51.1"/></trkseg></trk></gpx>"#),
+            Err(GpxError::MissingCoordinate("lon"))
+        ));
+        assert!(matches!(
+            parse_gpx(r#"<gpx><wpt lat="north" lon="17"/></gpx>"#),
+            Err(GpxError::ParseFloat(_))
+        ));
+    }
+}
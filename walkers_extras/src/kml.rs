@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use egui::epaint::{Mesh, Vertex};
-use egui::{self, Color32, Pos2, Response, Shape, Stroke, Ui};
+use egui::{self, Color32, Painter, Pos2, Response, Shape, Stroke, Ui};
 use lyon_path::Path;
 use lyon_tessellation::{
     BuffersBuilder, FillOptions, FillRule, FillTessellator, FillVertex, VertexBuffers, math::point,
@@ -9,7 +9,7 @@ use lyon_tessellation::{
 use quick_xml::Reader;
 use quick_xml::events::{BytesStart, Event};
 use thiserror::Error;
-use walkers::{MapMemory, Plugin, Position, Projector, lon_lat};
+use walkers::{MapMemory, Plugin, PluginLayer, Position, Projector, lon_lat};
 
 /// Geometry variants supported by the KML parser.
 #[derive(Debug, Clone, PartialEq)]
@@ -365,8 +365,7 @@ struct KmlLayerState {
 }
 
 impl KmlLayerState {
-    fn draw(&self, ui: &mut Ui, response: &Response, projector: &Projector) {
-        let painter = ui.painter_at(response.rect);
+    fn draw(&self, painter: &Painter, projector: &Projector) {
         for feature in &self.features {
             for geometry in &feature.geometries {
                 match geometry {
@@ -387,14 +386,7 @@ impl KmlLayerState {
                         painter.add(Shape::line(points, stroke));
                     }
                     KmlGeometry::Polygon { exterior, holes } => {
-                        draw_polygon(
-                            &painter,
-                            projector,
-                            feature,
-                            exterior,
-                            holes,
-                            &self.defaults,
-                        );
+                        draw_polygon(painter, projector, feature, exterior, holes, &self.defaults);
                     }
                 }
             }
@@ -433,12 +425,19 @@ impl KmlLayer {
 impl Plugin for KmlLayer {
     fn run(
         self: Box<Self>,
-        ui: &mut Ui,
-        response: &Response,
+        _ui: &mut Ui,
+        painter: &Painter,
+        _response: &Response,
         projector: &Projector,
         _map_memory: &MapMemory,
     ) {
-        self.inner.draw(ui, response, projector);
+        for projector in projector.world_copies() {
+            self.inner.draw(painter, &projector);
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Shapes
     }
 }
 
@@ -533,7 +532,7 @@ fn draw_polygon(
     }
 }
 
-fn ring_to_screen_points(ring: &[Position], projector: &Projector) -> Option<Vec<Pos2>> {
+pub(crate) fn ring_to_screen_points(ring: &[Position], projector: &Projector) -> Option<Vec<Pos2>> {
     if ring.len() < 3 {
         return None;
     }
@@ -548,7 +547,7 @@ fn ring_to_screen_points(ring: &[Position], projector: &Projector) -> Option<Vec
     if points.len() < 3 { None } else { Some(points) }
 }
 
-fn tessellate_polygon(
+pub(crate) fn tessellate_polygon(
     exterior: &[Pos2],
     holes: &[Vec<Pos2>],
     fill_color: Color32,
@@ -1,12 +1,88 @@
-use super::places::{Group, Place};
-use egui::{Align2, Color32, FontId, Stroke, Ui, vec2};
+use super::places::{Group, Place, dominant_category};
+use egui::epaint::RectShape;
+use egui::{Align2, Color32, FontId, Mesh, Pos2, Rect, Shape, Stroke, Ui, Vec2, pos2, vec2};
+use std::f32::consts::{FRAC_PI_2, TAU};
 use walkers::{Position, Projector};
 
+/// Margin between the label's text and the edge of its background.
+const LABEL_PADDING: f32 = 5.;
+
+/// Gap between a symbol and its label, in pixels, above which a leader line is drawn, see
+/// [`LabeledSymbolStyle::leader_line`].
+const LEADER_LINE_MIN_GAP: f32 = 4.;
+
 #[derive(Clone)]
 /// Type of the symbol of a [`LabeledSymbol`].
 pub enum Symbol {
     Circle(String),
     TwoCorners(String),
+
+    /// Vector shape, filled with [`LabeledSymbolStyle::symbol_background`] and outlined with
+    /// [`LabeledSymbolStyle::symbol_stroke`]. Unlike characters, it does not depend on fonts.
+    Shape(ShapeKind),
+}
+
+/// Shape of a [`Symbol::Shape`]. All of them fit within a square of
+/// [`LabeledSymbolStyle::symbol_size`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShapeKind {
+    Square,
+    /// Pointing up.
+    Triangle,
+    Diamond,
+    /// Five-pointed, pointing up.
+    Star,
+    /// Regular polygon with given number of sides, at least three, with one of the corners at the
+    /// top.
+    RegularPolygon(usize),
+}
+
+impl ShapeKind {
+    /// Corners of the shape of given size, centered at given point, clockwise.
+    fn points(self, center: Pos2, size: f32) -> Vec<Pos2> {
+        let half_size = size / 2.;
+        match self {
+            ShapeKind::Square => vec![
+                center + vec2(-half_size, -half_size),
+                center + vec2(half_size, -half_size),
+                center + vec2(half_size, half_size),
+                center + vec2(-half_size, half_size),
+            ],
+            ShapeKind::Diamond => vec![
+                center + vec2(0., -half_size),
+                center + vec2(half_size, 0.),
+                center + vec2(0., half_size),
+                center + vec2(-half_size, 0.),
+            ],
+            ShapeKind::Triangle => ShapeKind::RegularPolygon(3).points(center, size),
+            ShapeKind::Star => (0..10)
+                .map(|i| {
+                    // Inner corners are where the lines of a regular pentagram cross.
+                    let radius = if i % 2 == 0 {
+                        half_size
+                    } else {
+                        half_size * 0.382
+                    };
+                    corner(center, radius, i as f32 * TAU / 10.)
+                })
+                .collect(),
+            ShapeKind::RegularPolygon(sides) => {
+                let sides = sides.max(3);
+                (0..sides)
+                    .map(|i| corner(center, half_size, i as f32 * TAU / sides as f32))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Point at given distance from the center, at given angle clockwise from the top.
+fn corner(center: Pos2, radius: f32, angle: f32) -> Pos2 {
+    let angle = angle - FRAC_PI_2;
+    pos2(
+        center.x + radius * angle.cos(),
+        center.y + radius * angle.sin(),
+    )
 }
 
 /// A symbol with a label to be drawn on the map.
@@ -24,6 +100,13 @@ pub struct LabeledSymbol {
 
     /// Visual style of this place.
     pub style: LabeledSymbolStyle,
+
+    /// Category of this place. See [`LabeledSymbolGroupStyle::category_backgrounds`].
+    pub category: Option<String>,
+
+    /// Whether the place is selected, e.g. after being clicked. Selected symbols are larger and
+    /// surrounded by [`LabeledSymbolStyle::selected_halo`].
+    pub selected: bool,
 }
 
 impl Place for LabeledSymbol {
@@ -31,36 +114,183 @@ impl Place for LabeledSymbol {
         self.position
     }
 
+    fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn contains(&self, pos: Pos2, projector: &Projector) -> bool {
+        LabeledSymbol::contains(self, pos, projector)
+    }
+
     fn draw(&self, ui: &Ui, projector: &Projector) {
         let screen_position = projector.project(self.position);
         let painter = ui.painter();
+        let label_opacity = self.style.label_opacity(projector.zoom());
+        let size = self.size();
+        let symbol_center = self.style.symbol_center(screen_position.to_pos2(), size);
+
+        if !self.label.is_empty() && label_opacity > 0. {
+            let font = self.style.label_font_at(projector.zoom());
+            self.draw_label(
+                painter,
+                screen_position,
+                font,
+                label_opacity,
+                symbol_center,
+                size,
+            );
+        }
 
-        if !self.label.is_empty() {
-            self.draw_label(painter, screen_position);
+        if self.selected {
+            painter.circle_stroke(
+                symbol_center,
+                size / 2. + self.style.selected_halo.width + 2.,
+                self.style.selected_halo,
+            );
         }
 
         match self.symbol {
             Some(Symbol::Circle(ref text)) => {
-                self.draw_circle_symbol(text.clone(), painter, screen_position.to_pos2())
+                self.draw_circle_symbol(text.clone(), painter, symbol_center, size)
             }
             Some(Symbol::TwoCorners(ref text)) => {
-                self.draw_two_corners_symbol(text.clone(), painter, screen_position.to_pos2())
+                self.draw_two_corners_symbol(text.clone(), painter, symbol_center, size)
             }
+            Some(Symbol::Shape(kind)) => self.draw_shape_symbol(kind, painter, symbol_center, size),
             None => {}
         }
     }
 }
 
 impl LabeledSymbol {
+    /// Place with a label, default style and no symbol. Use the `with_*` methods to customize it,
+    /// or construct the struct directly.
+    ///
+    /// ```
+    /// # use walkers::lon_lat;
+    /// # use walkers_extras::{LabeledSymbol, Symbol};
+    /// let place = LabeledSymbol::new(lon_lat(17.03664, 51.09916), "Wrocław")
+    ///     .with_symbol(Symbol::Circle("•".to_owned()))
+    ///     .with_size(5.)
+    ///     .with_color(egui::Color32::RED);
+    /// ```
+    pub fn new(position: Position, label: impl Into<String>) -> Self {
+        Self {
+            position,
+            label: label.into(),
+            symbol: None,
+            style: LabeledSymbolStyle::default(),
+            category: None,
+            selected: false,
+        }
+    }
+
+    /// Symbol drawn on the place. See [`LabeledSymbol::symbol`].
+    pub fn with_symbol(mut self, symbol: Symbol) -> Self {
+        self.symbol = Some(symbol);
+        self
+    }
+
+    /// Replace the whole style. Use it before other `with_*` methods, which adjust the style.
+    pub fn with_style(mut self, style: LabeledSymbolStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Size of the symbol. See [`LabeledSymbolStyle::symbol_size`].
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.style.symbol_size = size;
+        self
+    }
+
+    /// Color of the symbol's character. See [`LabeledSymbolStyle::symbol_color`].
+    pub fn with_color(mut self, color: Color32) -> Self {
+        self.style.symbol_color = color;
+        self
+    }
+
+    /// Background of the symbol. See [`LabeledSymbolStyle::symbol_background`].
+    pub fn with_background(mut self, background: Color32) -> Self {
+        self.style.symbol_background = background;
+        self
+    }
+
+    /// Font of the label, e.g. a larger one for major cities than for towns. See
+    /// [`LabeledSymbolStyle::label_font`].
+    pub fn with_label_font(mut self, font: FontId) -> Self {
+        self.style.label_font = font;
+        self
+    }
+
+    /// Label's font size growing with zoom. See [`LabeledSymbolStyle::label_size_by_zoom`].
+    pub fn with_label_size_by_zoom(mut self, size: ZoomScaledSize) -> Self {
+        self.style.label_size_by_zoom = Some(size);
+        self
+    }
+
+    /// Color of the label's text. See [`LabeledSymbolStyle::label_color`].
+    pub fn with_label_color(mut self, color: Color32) -> Self {
+        self.style.label_color = color;
+        self
+    }
+
+    /// See [`LabeledSymbol::category`].
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// See [`LabeledSymbol::selected`].
+    pub fn with_selected(mut self, selected: bool) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    /// Radius within which the symbol reacts to the pointer. See
+    /// [`LabeledSymbolStyle::hit_radius`].
+    pub fn with_hit_radius(mut self, radius: f32) -> Self {
+        self.style.hit_radius = Some(radius);
+        self
+    }
+
+    /// Whether the screen position hits the symbol, taking
+    /// [`LabeledSymbolStyle::hit_radius`] into account. Useful for making places clickable, e.g.
+    /// with [`egui::Response::hover_pos`] of the map.
+    pub fn contains(&self, pos: Pos2, projector: &Projector) -> bool {
+        let size = self.size();
+        let radius = self.style.hit_radius.unwrap_or(size / 2.);
+        projector.world_copies().any(|projector| {
+            let screen_position = projector.project(self.position).to_pos2();
+            self.style
+                .symbol_center(screen_position, size)
+                .distance(pos)
+                <= radius
+        })
+    }
+
+    /// Size of the symbol as drawn, i.e. scaled up when selected.
+    fn size(&self) -> f32 {
+        if self.selected {
+            self.style.symbol_size * self.style.selected_scale
+        } else {
+            self.style.symbol_size
+        }
+    }
+
     fn draw_circle_symbol(
         &self,
         text: String,
         painter: &egui::Painter,
         screen_position: egui::Pos2,
+        size: f32,
     ) {
         painter.circle(
             screen_position,
-            self.style.symbol_size / 2.,
+            size / 2.,
             self.style.symbol_background,
             self.style.symbol_stroke,
         );
@@ -79,40 +309,43 @@ impl LabeledSymbol {
         text: String,
         painter: &egui::Painter,
         screen_position: egui::Pos2,
+        size: f32,
     ) {
-        let half_size = self.style.symbol_size / 2.;
+        let half_size = size / 2.;
         let top_left = screen_position + vec2(-half_size, -half_size);
         let bottom_right = screen_position + vec2(half_size, half_size);
         let top_right = screen_position + vec2(half_size, -half_size);
         let bottom_left = screen_position + vec2(-half_size, half_size);
         let len = 4.;
 
-        // Background rectangle.
-        painter.rect_filled(
-            egui::Rect::from_min_max(top_left, bottom_right),
-            0.,
-            self.style.symbol_background,
-        );
-
-        // Top right.
-        painter.line_segment(
-            [top_right, top_right + vec2(-len, 0.)],
-            self.style.symbol_stroke,
-        );
-        painter.line_segment(
-            [top_right, top_right + vec2(0., len)],
-            self.style.symbol_stroke,
+        // Background rectangle. Neither it nor the corners are snapped to pixels, like the other
+        // symbols, so that they do not jitter while the map moves smoothly.
+        painter.add(
+            RectShape::filled(
+                Rect::from_min_max(top_left, bottom_right),
+                0.,
+                self.style.symbol_background,
+            )
+            .with_round_to_pixels(false),
         );
 
-        // Bottom left.
-        painter.line_segment(
-            [bottom_left, bottom_left + vec2(len, 0.)],
+        // Top right and bottom left, as paths, as line segments are snapped too.
+        painter.add(Shape::line(
+            vec![
+                top_right + vec2(-len, 0.),
+                top_right,
+                top_right + vec2(0., len),
+            ],
             self.style.symbol_stroke,
-        );
-        painter.line_segment(
-            [bottom_left, bottom_left + vec2(0., -len)],
+        ));
+        painter.add(Shape::line(
+            vec![
+                bottom_left + vec2(len, 0.),
+                bottom_left,
+                bottom_left + vec2(0., -len),
+            ],
             self.style.symbol_stroke,
-        );
+        ));
 
         // Text.
         painter.text(
@@ -124,36 +357,132 @@ impl LabeledSymbol {
         );
     }
 
-    fn draw_label(&self, painter: &egui::Painter, screen_position: egui::Vec2) {
+    fn draw_shape_symbol(
+        &self,
+        kind: ShapeKind,
+        painter: &egui::Painter,
+        screen_position: egui::Pos2,
+        size: f32,
+    ) {
+        let points = kind.points(screen_position, size);
+
+        // Not every shape is convex (e.g. the star), but each can be seen from its center, so
+        // fill it as a fan of triangles around it.
+        let mut mesh = Mesh::default();
+        mesh.colored_vertex(screen_position, self.style.symbol_background);
+        for point in &points {
+            mesh.colored_vertex(*point, self.style.symbol_background);
+        }
+        let corners = points.len() as u32;
+        for i in 0..corners {
+            mesh.add_triangle(0, i + 1, (i + 1) % corners + 1);
+        }
+
+        painter.add(mesh);
+        painter.add(Shape::closed_line(points, self.style.symbol_stroke));
+    }
+
+    fn draw_label(
+        &self,
+        painter: &egui::Painter,
+        screen_position: egui::Vec2,
+        font: FontId,
+        opacity: f32,
+        symbol_center: Pos2,
+        symbol_size: f32,
+    ) {
         let label = painter.layout_no_wrap(
             self.label.to_owned(),
-            self.style.label_font.clone(),
-            self.style.label_color,
+            font,
+            self.style.label_color.gamma_multiply(opacity),
         );
 
-        // Offset of the label, relative to the circle.
-        let offset = vec2(8., 8.);
+        let offset = if self.style.keep_label_in_view {
+            self.style.label_offset_in_view(
+                label.size(),
+                screen_position.to_pos2(),
+                painter.clip_rect(),
+            )
+        } else {
+            self.style.label_offset
+        };
+
+        let background = label
+            .rect
+            .translate(screen_position)
+            .translate(offset)
+            .expand(LABEL_PADDING);
+
+        // Drawn first, so that the label and the symbol cover its ends.
+        if self.style.leader_line
+            && let Some(line) = leader_line(background, symbol_center, symbol_size)
+        {
+            painter.line_segment(
+                line,
+                Stroke::new(1., self.style.label_background.gamma_multiply(opacity)),
+            );
+        }
 
         // Label background.
         painter.rect_filled(
-            label
-                .rect
-                .translate(screen_position)
-                .translate(offset)
-                .expand(5.),
+            background,
             self.style.label_corner_radius,
-            self.style.label_background,
+            self.style.label_background.gamma_multiply(opacity),
         );
 
         painter.galley((screen_position + offset).to_pos2(), label, Color32::BLACK);
     }
 }
 
+/// Line from the center of a symbol of given size to the nearest point of the label, if the label
+/// is far enough from the symbol to need it.
+fn leader_line(label: Rect, symbol_center: Pos2, symbol_size: f32) -> Option<[Pos2; 2]> {
+    let nearest = label.clamp(symbol_center);
+    let gap = symbol_center.distance(nearest) - symbol_size / 2.;
+    (gap > LEADER_LINE_MIN_GAP).then_some([symbol_center, nearest])
+}
+
+/// Size which changes with the zoom level. Between `min_zoom` and `max_zoom`, it grows linearly
+/// from `min_size` to `max_size`, and outside of that range, it stays at the nearest of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomScaledSize {
+    pub min_zoom: f64,
+    pub max_zoom: f64,
+    pub min_size: f32,
+    pub max_size: f32,
+}
+
+impl ZoomScaledSize {
+    /// Size at given zoom level.
+    pub fn at(&self, zoom: f64) -> f32 {
+        let range = self.max_zoom - self.min_zoom;
+        let t = if range > 0. {
+            ((zoom - self.min_zoom) / range).clamp(0., 1.)
+        } else if zoom < self.min_zoom {
+            0.
+        } else {
+            1.
+        };
+        self.min_size + (self.max_size - self.min_size) * t as f32
+    }
+}
+
 /// Visual style of a [`LabeledSymbol`].
 #[derive(Clone)]
 pub struct LabeledSymbolStyle {
+    /// Font of the label. Bold or otherwise styled text needs a font family registered in egui's
+    /// [`egui::FontDefinitions`], e.g. `FontFamily::Name("bold".into())`.
     pub label_font: FontId,
+
+    /// Font size of the label depending on the zoom level, e.g. so that labels swell when the
+    /// map is zoomed in, like names on a printed map. It overrides the size of
+    /// [`LabeledSymbolStyle::label_font`], keeping its family. `None`, the default, keeps the size
+    /// constant.
+    pub label_size_by_zoom: Option<ZoomScaledSize>,
+
+    /// Color of the label's text, independent of the symbol's.
     pub label_color: Color32,
+
     pub label_background: Color32,
     pub label_corner_radius: f32,
     pub symbol_font: FontId,
@@ -161,12 +490,118 @@ pub struct LabeledSymbolStyle {
     pub symbol_background: Color32,
     pub symbol_stroke: Stroke,
     pub symbol_size: f32,
+
+    /// Radius, in pixels, within which [`LabeledSymbol::contains`] considers the symbol hit. Can
+    /// be larger than the symbol, so that tiny markers are still easy to tap. `None` means half
+    /// of the symbol's size.
+    pub hit_radius: Option<f32>,
+
+    /// Label is hidden when the map is zoomed out below this level. Symbol is always shown.
+    pub label_min_zoom: Option<f64>,
+
+    /// Number of zoom levels above [`LabeledSymbolStyle::label_min_zoom`] across which the label
+    /// fades in. Zero means that it appears at once.
+    pub label_fade: f64,
+
+    /// How many times larger the symbol is when [`LabeledSymbol::selected`].
+    pub selected_scale: f32,
+
+    /// Ring drawn around the symbol when [`LabeledSymbol::selected`].
+    pub selected_halo: Stroke,
+
+    /// Point of the symbol which is placed at the geographical position, as a fraction of its
+    /// size. `(0.5, 0.5)`, the default, is the center, and `(0.5, 1.0)` is the middle of the
+    /// bottom edge, e.g. the tip of a pin.
+    pub anchor: Vec2,
+
+    /// Offset of the label's top left corner from the geographical position, in pixels.
+    pub label_offset: Vec2,
+
+    /// Flip the label to the other side of the symbol, horizontally, vertically or both, when it
+    /// would not fit in the viewport as given by [`LabeledSymbolStyle::label_offset`].
+    pub keep_label_in_view: bool,
+
+    /// Draw a thin line from the label back to the symbol when they are apart, e.g. because of a
+    /// large [`LabeledSymbolStyle::label_offset`], or the label being flipped by
+    /// [`LabeledSymbolStyle::keep_label_in_view`], so that it is clear which symbol the label
+    /// belongs to.
+    pub leader_line: bool,
+}
+
+impl LabeledSymbolStyle {
+    /// [`LabeledSymbolStyle::label_font`], sized for given zoom level.
+    fn label_font_at(&self, zoom: f64) -> FontId {
+        match self.label_size_by_zoom {
+            Some(size) => FontId::new(size.at(zoom), self.label_font.family.clone()),
+            None => self.label_font.clone(),
+        }
+    }
+
+    /// Opacity of the label at given zoom level, taking [`LabeledSymbolStyle::label_min_zoom`]
+    /// into account.
+    fn label_opacity(&self, zoom: f64) -> f32 {
+        match self.label_min_zoom {
+            Some(min_zoom) if zoom < min_zoom => 0.,
+            Some(min_zoom) if self.label_fade > 0. => {
+                ((zoom - min_zoom) / self.label_fade).min(1.) as f32
+            }
+            _ => 1.,
+        }
+    }
+
+    /// [`LabeledSymbolStyle::label_offset`], mirrored along the axes on which a label of given
+    /// size would stick out of the `viewport`, as long as it fits better on the other side.
+    fn label_offset_in_view(
+        &self,
+        label_size: Vec2,
+        screen_position: Pos2,
+        viewport: Rect,
+    ) -> Vec2 {
+        let flip = |offset: f32, size: f32, position: f32, min: f32, max: f32| {
+            let outside = |offset: f32| {
+                let start = position + offset - LABEL_PADDING;
+                let end = position + offset + size + LABEL_PADDING;
+                (min - start).max(0.) + (end - max).max(0.)
+            };
+            let flipped = -offset - size;
+            if outside(flipped) < outside(offset) {
+                flipped
+            } else {
+                offset
+            }
+        };
+
+        let offset = self.label_offset;
+        vec2(
+            flip(
+                offset.x,
+                label_size.x,
+                screen_position.x,
+                viewport.min.x,
+                viewport.max.x,
+            ),
+            flip(
+                offset.y,
+                label_size.y,
+                screen_position.y,
+                viewport.min.y,
+                viewport.max.y,
+            ),
+        )
+    }
+
+    /// Center of a symbol of given size, so that [`LabeledSymbolStyle::anchor`] lands at
+    /// `screen_position`.
+    fn symbol_center(&self, screen_position: Pos2, size: f32) -> Pos2 {
+        screen_position + (vec2(0.5, 0.5) - self.anchor) * size
+    }
 }
 
 impl Default for LabeledSymbolStyle {
     fn default() -> Self {
         Self {
             label_font: FontId::proportional(12.),
+            label_size_by_zoom: None,
             label_color: Color32::from_gray(200),
             label_background: Color32::BLACK.gamma_multiply(0.8),
             label_corner_radius: 10.,
@@ -175,6 +610,15 @@ impl Default for LabeledSymbolStyle {
             symbol_background: Color32::WHITE.gamma_multiply(0.8),
             symbol_stroke: Stroke::new(2., Color32::BLACK.gamma_multiply(0.8)),
             symbol_size: 10.,
+            hit_radius: None,
+            label_min_zoom: None,
+            label_fade: 0.,
+            selected_scale: 1.5,
+            selected_halo: Stroke::new(3., Color32::from_rgb(0x1e, 0x90, 0xff)),
+            anchor: vec2(0.5, 0.5),
+            label_offset: vec2(8., 8.),
+            keep_label_in_view: false,
+            leader_line: false,
         }
     }
 }
@@ -194,10 +638,18 @@ impl Group for LabeledSymbolGroup {
         let screen_position = projector.project(position);
         let painter = ui.painter();
 
+        let (background, color) = match self.style.color_for_count(places.len()) {
+            Some(background) => (background, contrasting_text(background)),
+            None => (
+                self.style.background_for(dominant_category(places)),
+                self.style.color,
+            ),
+        };
+
         painter.circle(
             screen_position.to_pos2(),
             10.,
-            self.style.background,
+            background,
             self.style.stroke,
         );
 
@@ -206,7 +658,7 @@ impl Group for LabeledSymbolGroup {
             Align2::CENTER_CENTER,
             format!("{}", places.len()),
             self.style.font.clone(),
-            self.style.color,
+            color,
         );
     }
 }
@@ -218,6 +670,75 @@ pub struct LabeledSymbolGroupStyle {
     pub color: Color32,
     pub background: Color32,
     pub stroke: Stroke,
+
+    /// If not empty, background of the group is picked from these colors, depending on the
+    /// dominant category of its places. The same category always gets the same color.
+    pub category_backgrounds: Vec<Color32>,
+
+    /// If not empty, background of the group is picked along this gradient, depending on the
+    /// number of its places, in logarithmic scale, and the count is drawn in black or white,
+    /// whichever is more legible. Takes precedence over
+    /// [`LabeledSymbolGroupStyle::category_backgrounds`]. See
+    /// [`LabeledSymbolGroupStyle::color_by_count`].
+    pub count_gradient: Vec<Color32>,
+
+    /// Number of places at which the end of [`LabeledSymbolGroupStyle::count_gradient`] is
+    /// reached.
+    pub count_gradient_max: usize,
+}
+
+impl LabeledSymbolGroupStyle {
+    /// Color groups along the gradient, e.g. from blue for sparse to red for dense ones. See
+    /// [`LabeledSymbolGroupStyle::count_gradient`].
+    pub fn color_by_count(mut self, gradient: Vec<Color32>) -> Self {
+        self.count_gradient = gradient;
+        self
+    }
+
+    /// Color from the [`LabeledSymbolGroupStyle::count_gradient`] for a group of `count`
+    /// places. Groups have at least two places, so that is where the gradient starts.
+    fn color_for_count(&self, count: usize) -> Option<Color32> {
+        let (first, rest) = self.count_gradient.split_first()?;
+        if rest.is_empty() {
+            return Some(*first);
+        }
+
+        let min = 2f32.ln();
+        let max = (self.count_gradient_max.max(3) as f32).ln();
+        let t = ((count.max(2) as f32).ln() - min) / (max - min);
+        let position = t.clamp(0., 1.) * rest.len() as f32;
+        let idx = (position.floor() as usize).min(rest.len() - 1);
+        Some(
+            self.count_gradient[idx]
+                .lerp_to_gamma(self.count_gradient[idx + 1], position - idx as f32),
+        )
+    }
+
+    fn background_for(&self, category: Option<&str>) -> Color32 {
+        match category {
+            Some(category) if !self.category_backgrounds.is_empty() => {
+                let idx = fnv1a(category.as_bytes()) as usize % self.category_backgrounds.len();
+                self.category_backgrounds[idx]
+            }
+            _ => self.background,
+        }
+    }
+}
+
+/// Black or white, whichever is more legible on the given background.
+fn contrasting_text(background: Color32) -> Color32 {
+    if background.intensity() > 0.5 {
+        Color32::BLACK
+    } else {
+        Color32::WHITE
+    }
+}
+
+/// Simple hash function, which, unlike the std ones, is guaranteed to be stable.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
 
 impl Default for LabeledSymbolGroupStyle {
@@ -227,6 +748,295 @@ impl Default for LabeledSymbolGroupStyle {
             color: Color32::WHITE.gamma_multiply(0.8),
             background: Color32::BLACK.gamma_multiply(0.8),
             stroke: Stroke::new(2., Color32::BLACK.gamma_multiply(0.8)),
+            category_backgrounds: Vec::new(),
+            count_gradient: Vec::new(),
+            count_gradient_max: 1000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_adjusts_given_style() {
+        let place = LabeledSymbol::new(walkers::lon_lat(17.0, 51.0), "Rynek")
+            .with_style(LabeledSymbolStyle {
+                label_corner_radius: 2.,
+                ..Default::default()
+            })
+            .with_size(5.)
+            .with_color(Color32::RED)
+            .with_label_font(FontId::monospace(20.))
+            .with_label_color(Color32::YELLOW)
+            .with_category("square");
+
+        assert_eq!(place.label, "Rynek");
+        assert!(place.symbol.is_none());
+        assert_eq!(place.style.label_corner_radius, 2.);
+        assert_eq!(place.style.symbol_size, 5.);
+        assert_eq!(place.style.symbol_color, Color32::RED);
+        assert_eq!(place.style.label_font, FontId::monospace(20.));
+        assert_eq!(place.style.label_color, Color32::YELLOW);
+        assert_eq!(place.style.symbol_font, FontId::proportional(14.));
+        assert_eq!(place.category.as_deref(), Some("square"));
+        assert!(!place.selected);
+    }
+
+    /// Left edge of the symbol's background, as tessellated, with the map panned by `pan` points.
+    fn background_left_edge(pan: f32) -> f32 {
+        let background = Color32::from_rgb(1, 2, 3);
+        let place = LabeledSymbol::new(walkers::lon_lat(17.0, 51.0), "")
+            .with_symbol(Symbol::TwoCorners("A".to_owned()))
+            .with_background(background);
+
+        let ctx = egui::Context::default();
+        let mut memory = walkers::MapMemory::default();
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let rect = ui.max_rect();
+                let projector = Projector::new(rect, &memory, place.position);
+                memory.center_at(projector.unproject(rect.center().to_vec2() + vec2(pan, 0.)));
+                ui.add(
+                    walkers::Map::new(None, &mut memory, place.position)
+                        .with_plugin(crate::Places::new(vec![place.clone()])),
+                );
+            });
+        });
+
+        ctx.tessellate(output.shapes, output.pixels_per_point)
+            .into_iter()
+            .filter_map(|clipped| match clipped.primitive {
+                egui::epaint::Primitive::Mesh(mesh) => Some(mesh),
+                egui::epaint::Primitive::Callback(_) => None,
+            })
+            .flat_map(|mesh| mesh.vertices)
+            .filter(|vertex| vertex.color == background)
+            .map(|vertex| vertex.pos.x)
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn symbols_move_by_fractions_of_a_pixel() {
+        let still = background_left_edge(0.);
+        for pan in [0.25, 0.5, 0.75] {
+            let moved = background_left_edge(pan);
+            assert!((still - moved - pan).abs() < 0.01, "{still} {moved} {pan}");
         }
     }
+
+    #[test]
+    fn shapes_fit_in_symbol_size() {
+        let center = pos2(100., 100.);
+        let bounds = egui::Rect::from_center_size(center, Vec2::splat(10.)).expand(0.001);
+
+        for (kind, corners) in [
+            (ShapeKind::Square, 4),
+            (ShapeKind::Triangle, 3),
+            (ShapeKind::Diamond, 4),
+            (ShapeKind::Star, 10),
+            (ShapeKind::RegularPolygon(6), 6),
+            (ShapeKind::RegularPolygon(1), 3),
+        ] {
+            let points = kind.points(center, 10.);
+            assert_eq!(points.len(), corners, "{kind:?}");
+            assert!(points.iter().all(|p| bounds.contains(*p)), "{kind:?}");
+        }
+
+        // Pointing up.
+        let top = ShapeKind::Star.points(center, 10.)[0];
+        assert!((top - pos2(100., 95.)).length() < 0.001);
+    }
+
+    #[test]
+    fn group_color_follows_count_in_logarithmic_scale() {
+        let style = LabeledSymbolGroupStyle {
+            count_gradient_max: 200,
+            ..Default::default()
+        }
+        .color_by_count(vec![Color32::BLUE, Color32::YELLOW, Color32::RED]);
+
+        assert_eq!(style.color_for_count(2), Some(Color32::BLUE));
+        // Geometric middle between 2 and 200.
+        assert_eq!(style.color_for_count(20), Some(Color32::YELLOW));
+        assert_eq!(style.color_for_count(200), Some(Color32::RED));
+        assert_eq!(style.color_for_count(5000), Some(Color32::RED));
+
+        assert_eq!(LabeledSymbolGroupStyle::default().color_for_count(10), None);
+        assert_eq!(
+            LabeledSymbolGroupStyle::default()
+                .color_by_count(vec![Color32::GREEN])
+                .color_for_count(10),
+            Some(Color32::GREEN)
+        );
+    }
+
+    #[test]
+    fn count_is_legible_on_any_background() {
+        assert_eq!(contrasting_text(Color32::YELLOW), Color32::BLACK);
+        assert_eq!(contrasting_text(Color32::WHITE), Color32::BLACK);
+        assert_eq!(contrasting_text(Color32::DARK_BLUE), Color32::WHITE);
+        assert_eq!(contrasting_text(Color32::BLACK), Color32::WHITE);
+    }
+
+    #[test]
+    fn group_background_is_stable_for_a_category() {
+        let style = LabeledSymbolGroupStyle {
+            category_backgrounds: vec![Color32::RED, Color32::GREEN, Color32::BLUE],
+            ..Default::default()
+        };
+
+        assert_eq!(style.background_for(None), style.background);
+        assert_eq!(
+            style.background_for(Some("cafe")),
+            style.background_for(Some("cafe"))
+        );
+        assert_eq!(fnv1a(b"cafe"), 0xb5387d90e8589028);
+
+        // Without palette, categories do not matter.
+        let style = LabeledSymbolGroupStyle::default();
+        assert_eq!(style.background_for(Some("cafe")), style.background);
+    }
+
+    #[test]
+    fn label_is_always_visible_by_default() {
+        let style = LabeledSymbolStyle::default();
+        assert_eq!(style.label_opacity(0.), 1.);
+        assert_eq!(style.label_opacity(20.), 1.);
+    }
+
+    #[test]
+    fn label_is_hidden_below_min_zoom() {
+        let style = LabeledSymbolStyle {
+            label_min_zoom: Some(14.),
+            ..Default::default()
+        };
+        assert_eq!(style.label_opacity(13.9), 0.);
+        assert_eq!(style.label_opacity(14.), 1.);
+        assert_eq!(style.label_opacity(18.), 1.);
+    }
+
+    #[test]
+    fn label_fades_in_above_min_zoom() {
+        let style = LabeledSymbolStyle {
+            label_min_zoom: Some(14.),
+            label_fade: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(style.label_opacity(13.9), 0.);
+        assert_eq!(style.label_opacity(14.), 0.);
+        assert_eq!(style.label_opacity(14.25), 0.5);
+        assert_eq!(style.label_opacity(14.5), 1.);
+        assert_eq!(style.label_opacity(15.), 1.);
+    }
+
+    #[test]
+    fn label_font_grows_with_zoom() {
+        let style = LabeledSymbolStyle::default();
+        assert_eq!(style.label_font_at(5.), style.label_font);
+        assert_eq!(style.label_font_at(18.), style.label_font);
+
+        let style = LabeledSymbolStyle {
+            label_font: FontId::monospace(12.),
+            label_size_by_zoom: Some(ZoomScaledSize {
+                min_zoom: 10.,
+                max_zoom: 16.,
+                min_size: 10.,
+                max_size: 22.,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(style.label_font_at(4.), FontId::monospace(10.));
+        assert_eq!(style.label_font_at(10.), FontId::monospace(10.));
+        assert_eq!(style.label_font_at(13.), FontId::monospace(16.));
+        assert_eq!(style.label_font_at(16.), FontId::monospace(22.));
+        assert_eq!(style.label_font_at(19.), FontId::monospace(22.));
+    }
+
+    #[test]
+    fn symbol_is_placed_according_to_anchor() {
+        let position = Pos2::new(100., 100.);
+        let style = LabeledSymbolStyle::default();
+        assert_eq!(style.symbol_center(position, 20.), position);
+
+        // Tip of a pin.
+        let style = LabeledSymbolStyle {
+            anchor: vec2(0.5, 1.),
+            ..Default::default()
+        };
+        assert_eq!(style.symbol_center(position, 20.), Pos2::new(100., 90.));
+
+        let style = LabeledSymbolStyle {
+            anchor: vec2(0., 0.),
+            ..Default::default()
+        };
+        assert_eq!(style.symbol_center(position, 20.), Pos2::new(110., 110.));
+    }
+
+    #[test]
+    fn hit_radius_can_exceed_symbol_size() {
+        let rect = egui::Rect::from_min_size(Pos2::ZERO, Vec2::splat(512.));
+        let mut memory = walkers::MapMemory::default();
+        memory.set_zoom(10.).unwrap();
+        let projector = Projector::new(rect, &memory, walkers::lon_lat(0., 0.));
+
+        let place = LabeledSymbol::new(walkers::lon_lat(0., 0.), "Bench").with_size(5.);
+        let center = projector.project(place.position).to_pos2();
+        assert!(place.contains(center + vec2(2., 0.), &projector));
+        assert!(!place.contains(center + vec2(10., 0.), &projector));
+
+        let place = place.with_hit_radius(22.);
+        assert!(place.contains(center + vec2(10., 0.), &projector));
+        assert!(place.contains(center + vec2(0., -22.), &projector));
+        assert!(!place.contains(center + vec2(23., 0.), &projector));
+    }
+
+    #[test]
+    fn label_is_flipped_to_stay_in_view() {
+        let viewport = Rect::from_min_size(Pos2::ZERO, Vec2::splat(200.));
+        let size = vec2(50., 10.);
+        let style = LabeledSymbolStyle {
+            keep_label_in_view: true,
+            ..Default::default()
+        };
+
+        // Plenty of space.
+        let offset = style.label_offset_in_view(size, Pos2::new(100., 100.), viewport);
+        assert_eq!(offset, vec2(8., 8.));
+
+        // Near the right edge, the label goes to the left of the symbol.
+        let offset = style.label_offset_in_view(size, Pos2::new(180., 100.), viewport);
+        assert_eq!(offset, vec2(-58., 8.));
+
+        // Near the bottom right corner, it goes above it as well.
+        let offset = style.label_offset_in_view(size, Pos2::new(180., 195.), viewport);
+        assert_eq!(offset, vec2(-58., -18.));
+
+        // No side is better in a tiny viewport.
+        let viewport = Rect::from_min_size(Pos2::ZERO, Vec2::splat(20.));
+        let offset = style.label_offset_in_view(size, Pos2::new(10., 5.), viewport);
+        assert_eq!(offset.y, 8.);
+    }
+
+    #[test]
+    fn leader_line_is_drawn_only_when_label_is_apart() {
+        let center = Pos2::new(100., 100.);
+        let label = |offset: Vec2| {
+            Rect::from_min_size(center + offset, vec2(50., 10.)).expand(LABEL_PADDING)
+        };
+
+        // Default offset puts the label right next to the symbol.
+        assert_eq!(leader_line(label(vec2(8., 8.)), center, 10.), None);
+        assert_eq!(leader_line(label(vec2(-58., 8.)), center, 10.), None);
+
+        assert_eq!(
+            leader_line(label(vec2(30., 30.)), center, 10.),
+            Some([center, Pos2::new(125., 125.)])
+        );
+        assert_eq!(
+            leader_line(label(vec2(-25., -40.)), center, 10.),
+            Some([center, Pos2::new(100., 75.)])
+        );
+    }
 }
@@ -1,11 +1,35 @@
 //! Extra functionalities that can be used with the map.
 
+mod center_marker;
+mod coordinate_readout;
+mod geofence;
+#[cfg(feature = "gpx")]
+mod gpx;
 mod kml;
 mod labeled_symbol;
+mod loading_indicator;
+mod my_position;
 mod places;
+mod polygon;
+mod polyline;
+mod ruler;
 
+pub use center_marker::{CenterMarker, CenterMarkerShape};
+pub use coordinate_readout::{CoordinateFormat, CoordinateReadout};
+pub use geofence::{Geofence, GeofenceEvent, GeofenceShape};
+#[cfg(feature = "gpx")]
+pub use gpx::{Gpx, GpxError, GpxPoint, GpxTrack, parse_gpx};
 pub use kml::{KmlFeature, KmlGeometry, KmlLayer, KmlStyle, KmlVisualDefaults, parse_kml};
 pub use labeled_symbol::{
-    LabeledSymbol, LabeledSymbolGroup, LabeledSymbolGroupStyle, LabeledSymbolStyle, Symbol,
+    LabeledSymbol, LabeledSymbolGroup, LabeledSymbolGroupStyle, LabeledSymbolStyle, ShapeKind,
+    Symbol, ZoomScaledSize,
 };
-pub use places::{Group, GroupedPlaces, GroupedPlacesTree, Place, Places};
+pub use loading_indicator::LoadingIndicator;
+pub use my_position::{MyPositionMarker, MyPositionStyle};
+pub use places::{
+    ClusterInfo, ClusterStrategy, Group, GroupedPlaces, GroupedPlacesTree, Place, PlaceDrag,
+    Places, dominant_category,
+};
+pub use polygon::Polygon;
+pub use polyline::Polyline;
+pub use ruler::{Ruler, RulerMode};
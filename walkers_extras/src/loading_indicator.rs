@@ -0,0 +1,99 @@
+use egui::{Align2, Color32, FontId, Painter, Rect, Response, Spinner, Ui, vec2};
+use walkers::{MapMemory, Plugin, PluginLayer, Projector};
+
+/// [`Plugin`] which shows a spinner in a corner of the map while tiles are being downloaded.
+/// It is meant to be constructed each frame, with the number of tiles in progress, typically
+/// taken from [`walkers::HttpStats::in_progress`].
+///
+/// ```
+/// # use walkers::{HttpTiles, Map, MapMemory, lon_lat};
+/// # use walkers_extras::LoadingIndicator;
+/// fn update(ui: &mut egui::Ui, tiles: &mut HttpTiles, map_memory: &mut MapMemory) {
+///     let loading = LoadingIndicator::new(tiles.stats().in_progress);
+///     ui.add(
+///         Map::new(Some(tiles), map_memory, lon_lat(17.03664, 51.09916)).with_plugin(loading),
+///     );
+/// }
+/// ```
+pub struct LoadingIndicator {
+    in_progress: usize,
+    anchor: Align2,
+    show_count: bool,
+}
+
+impl LoadingIndicator {
+    pub fn new(in_progress: usize) -> Self {
+        Self {
+            in_progress,
+            anchor: Align2::RIGHT_BOTTOM,
+            show_count: false,
+        }
+    }
+
+    /// Corner (or an edge) of the map where the spinner is shown. Bottom right by default.
+    pub fn with_anchor(mut self, anchor: Align2) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Whether to show how many tiles are being downloaded next to the spinner.
+    pub fn with_count(mut self, show_count: bool) -> Self {
+        self.show_count = show_count;
+        self
+    }
+}
+
+/// Distance from the edges of the map.
+const MARGIN: f32 = 10.;
+
+const SPINNER_SIZE: f32 = 16.;
+
+impl Plugin for LoadingIndicator {
+    fn run(
+        self: Box<Self>,
+        ui: &mut Ui,
+        painter: &Painter,
+        response: &Response,
+        _projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        if self.in_progress == 0 {
+            return;
+        }
+
+        let text = self.show_count.then(|| {
+            painter.layout_no_wrap(
+                format!("{} tiles…", self.in_progress),
+                FontId::proportional(12.),
+                ui.visuals().text_color(),
+            )
+        });
+
+        let text_width = text
+            .as_ref()
+            .map(|galley| galley.size().x + MARGIN / 2.)
+            .unwrap_or(0.);
+        let size = vec2(SPINNER_SIZE + text_width, SPINNER_SIZE);
+        let rect = self
+            .anchor
+            .align_size_within_rect(size, response.rect.shrink(MARGIN));
+
+        painter.rect_filled(
+            rect.expand(MARGIN / 2.),
+            4.,
+            ui.visuals().window_fill.gamma_multiply(0.8),
+        );
+
+        let spinner_rect = Rect::from_min_size(rect.min, vec2(SPINNER_SIZE, SPINNER_SIZE));
+        Spinner::new().size(SPINNER_SIZE).paint_at(ui, spinner_rect);
+
+        if let Some(galley) = text {
+            let position = rect.right_center() - vec2(galley.size().x, galley.size().y / 2.);
+            painter.galley(position, galley, Color32::PLACEHOLDER);
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Controls
+    }
+}
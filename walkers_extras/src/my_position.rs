@@ -0,0 +1,243 @@
+use egui::{Color32, Painter, Pos2, Response, Shape, Stroke, Ui, pos2};
+use walkers::{MapMemory, Plugin, PluginLayer, Position, Projector};
+
+/// Number of points on the arc closing the heading cone.
+const CONE_ARC_POINTS: usize = 8;
+
+/// Colors and sizes of [`MyPositionMarker`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MyPositionStyle {
+    /// Dot at the position itself.
+    pub dot: Color32,
+
+    /// Radius of the dot, in points.
+    pub dot_radius: f32,
+
+    /// Ring around the dot, separating it from the map.
+    pub dot_stroke: Stroke,
+
+    /// Fill of the accuracy circle. Keep it translucent, so that the map is visible through it.
+    pub accuracy_fill: Color32,
+
+    /// Outline of the accuracy circle.
+    pub accuracy_stroke: Stroke,
+
+    /// Fill of the heading cone.
+    pub heading: Color32,
+
+    /// Length of the heading cone, in points, measured from the position.
+    pub heading_length: f32,
+
+    /// How wide the heading cone opens, in degrees.
+    pub heading_spread: f32,
+}
+
+impl Default for MyPositionStyle {
+    fn default() -> Self {
+        let blue = Color32::from_rgb(26, 115, 232);
+        Self {
+            dot: blue,
+            dot_radius: 7.,
+            dot_stroke: Stroke::new(2., Color32::WHITE),
+            accuracy_fill: blue.gamma_multiply(0.15),
+            accuracy_stroke: Stroke::new(1., blue.gamma_multiply(0.4)),
+            heading: blue.gamma_multiply(0.4),
+            heading_length: 40.,
+            heading_spread: 60.,
+        }
+    }
+}
+
+/// [`Plugin`] which shows the user's current location, such as the one from a GPS sensor, as a
+/// dot surrounded by a circle of its accuracy, and optionally a cone pointing where the user is
+/// heading.
+///
+/// ```
+/// # use walkers::lat_lon;
+/// # use walkers_extras::MyPositionMarker;
+/// let marker = MyPositionMarker::new(lat_lon(51.104465, 17.075169))
+///     .with_accuracy(25.)
+///     .with_heading(90.);
+/// ```
+pub struct MyPositionMarker {
+    position: Position,
+    accuracy: Option<f64>,
+    heading: Option<f32>,
+    style: MyPositionStyle,
+}
+
+impl MyPositionMarker {
+    pub fn new(position: Position) -> Self {
+        Self {
+            position,
+            accuracy: None,
+            heading: None,
+            style: MyPositionStyle::default(),
+        }
+    }
+
+    /// Radius of the accuracy circle, in meters. No circle by default.
+    pub fn with_accuracy(mut self, meters: f64) -> Self {
+        self.accuracy = Some(meters);
+        self
+    }
+
+    /// Direction of the heading cone, in degrees clockwise from the north. No cone by default.
+    pub fn with_heading(mut self, degrees: f32) -> Self {
+        self.heading = Some(degrees);
+        self
+    }
+
+    pub fn with_style(mut self, style: MyPositionStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn draw(&self, painter: &Painter, projector: &Projector) {
+        let center = projector.project(self.position).to_pos2();
+
+        if let Some(accuracy) = self.accuracy {
+            let radius = accuracy as f32 * projector.scale_pixel_per_meter(self.position);
+            painter.circle(
+                center,
+                radius,
+                self.style.accuracy_fill,
+                self.style.accuracy_stroke,
+            );
+        }
+
+        if let Some(heading) = self.heading {
+            painter.add(Shape::convex_polygon(
+                cone(
+                    center,
+                    heading,
+                    self.style.heading_spread,
+                    self.style.heading_length,
+                ),
+                self.style.heading,
+                Stroke::NONE,
+            ));
+        }
+
+        painter.circle(
+            center,
+            self.style.dot_radius,
+            self.style.dot,
+            self.style.dot_stroke,
+        );
+    }
+}
+
+/// Points of a circular sector with its tip at `center`, facing `heading` degrees clockwise from
+/// the top.
+fn cone(center: Pos2, heading: f32, spread: f32, length: f32) -> Vec<Pos2> {
+    let first = heading - spread / 2.;
+    std::iter::once(center)
+        .chain((0..=CONE_ARC_POINTS).map(|i| {
+            let angle = (first + spread * i as f32 / CONE_ARC_POINTS as f32).to_radians();
+            pos2(
+                center.x + length * angle.sin(),
+                center.y - length * angle.cos(),
+            )
+        }))
+        .collect()
+}
+
+impl Plugin for MyPositionMarker {
+    fn run(
+        self: Box<Self>,
+        _ui: &mut Ui,
+        painter: &Painter,
+        _response: &Response,
+        projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        for projector in projector.world_copies() {
+            self.draw(painter, &projector);
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Markers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::epaint::CircleShape;
+    use walkers::lon_lat;
+
+    fn shapes(marker: MyPositionMarker, memory: &mut MapMemory) -> (Vec<Shape>, Pos2) {
+        let ctx = egui::Context::default();
+        let mut center = Pos2::ZERO;
+        let mut marker = Some(marker);
+
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let mut map = walkers::Map::new(None, memory, lon_lat(0., 0.));
+                if let Some(marker) = marker.take() {
+                    map = map.with_plugin(marker);
+                }
+                let response = ui.add(map);
+                center = response.rect.center();
+            });
+        });
+
+        let shapes = output
+            .shapes
+            .into_iter()
+            .map(|clipped| clipped.shape)
+            .filter(|shape| matches!(shape, Shape::Circle(_) | Shape::Path(_)))
+            .collect();
+        (shapes, center)
+    }
+
+    #[test]
+    fn accuracy_circle_is_sized_in_meters() {
+        let mut memory = MapMemory::default();
+        memory.set_zoom(16.).expect("valid zoom");
+        let scale = Projector::new(
+            egui::Rect::from_min_size(Pos2::ZERO, egui::Vec2::splat(100.)),
+            &memory,
+            lon_lat(0., 0.),
+        )
+        .scale_pixel_per_meter(lon_lat(0., 0.));
+
+        let marker = MyPositionMarker::new(lon_lat(0., 0.)).with_accuracy(50.);
+        let (shapes, center) = shapes(marker, &mut memory);
+
+        let circles: Vec<CircleShape> = shapes
+            .into_iter()
+            .filter_map(|shape| match shape {
+                Shape::Circle(circle) => Some(circle),
+                _ => None,
+            })
+            .collect();
+        let [accuracy, dot] = circles.as_slice() else {
+            panic!("expected two circles, got {circles:?}");
+        };
+        assert_eq!(accuracy.center, center);
+        assert!((accuracy.radius - 50. * scale).abs() < 0.01);
+        assert_eq!(dot.center, center);
+        assert_eq!(dot.radius, 7.);
+    }
+
+    #[test]
+    fn heading_cone_points_in_the_heading() {
+        let mut memory = MapMemory::default();
+        let marker = MyPositionMarker::new(lon_lat(0., 0.)).with_heading(90.);
+        let (shapes, center) = shapes(marker, &mut memory);
+
+        let Some(Shape::Path(cone)) = shapes.first() else {
+            panic!("expected the cone first, got {shapes:?}");
+        };
+        assert_eq!(cone.points[0], center);
+        for point in &cone.points[1..] {
+            // Facing east, within the spread of 60 degrees.
+            let direction = *point - center;
+            assert!((direction.length() - 40.).abs() < 0.01);
+            assert!(direction.x > 0. && direction.y.abs() <= direction.x * 0.58);
+        }
+    }
+}
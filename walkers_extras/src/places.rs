@@ -1,27 +1,189 @@
-use egui::{Id, Rect, Response, Sense, Ui, vec2};
+use egui::{Id, Painter, Pos2, Rect, Response, Sense, Ui, Vec2};
 use rstar::{PointDistance, RTree, RTreeObject};
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Duration;
 use walkers::{MapMemory, Plugin, Position, Projector, lon_lat, mercator};
 
 /// [`Plugin`] which shows places on the map. Place can be any type that implements the [`Place`]
 /// trait.
+///
+/// Places can be given in an [`Arc`], so that they do not need to be cloned every frame. The same
+/// goes for [`GroupedPlaces`] and [`GroupedPlacesTree`].
+///
+/// Places can be made draggable (see [`Places::draggable`]). Then it needs to persist between
+/// frames and be added to the map by a mutable reference, so that it can report
+/// [`Places::drag_event`]s.
 pub struct Places<T>
 where
     T: Place,
 {
-    places: Vec<T>,
+    places: Arc<Vec<T>>,
+    draggable: bool,
+
+    /// Index of the place being dragged, and where it is relative to the pointer.
+    dragged: Option<(usize, Vec2)>,
+    drag_event: Option<PlaceDrag>,
+}
+
+/// What happened to a dragged place in the last frame, see [`Places::draggable`]. Each variant
+/// carries the index of the place and where it would be dropped if the drag ended right now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaceDrag {
+    /// Place was grabbed. The position is the one of the place itself.
+    Started { index: usize, position: Position },
+
+    /// Place was moved, e.g. to preview its new position.
+    Moved { index: usize, position: Position },
+
+    /// Place was released, so its new position can be persisted.
+    Ended { index: usize, position: Position },
 }
 
 impl<T> Places<T>
 where
     T: Place,
 {
-    pub fn new(places: Vec<T>) -> Self {
-        Self { places }
+    pub fn new(places: impl Into<Arc<Vec<T>>>) -> Self {
+        Self {
+            places: places.into(),
+            draggable: false,
+            dragged: None,
+            drag_event: None,
+        }
+    }
+
+    /// Let the user drag places which say they are hit (see [`Place::contains`]), instead of
+    /// panning the map. Places do not move on their own: update them with the positions of
+    /// [`Places::drag_event`] (see [`Places::update`]). `false` by default.
+    ///
+    /// Dragging works only when places are kept between frames and given to the map as
+    /// `&mut Places`. Otherwise, there would be nothing to report the drag to.
+    pub fn draggable(mut self, draggable: bool) -> Self {
+        self.draggable = draggable;
+        self
+    }
+
+    /// What happened to a dragged place in the last frame, if anything.
+    pub fn drag_event(&self) -> Option<PlaceDrag> {
+        self.drag_event
+    }
+
+    pub fn places(&self) -> &[T] {
+        &self.places
+    }
+
+    /// Add a place, e.g. to a live-updating dataset kept between frames. Returns its index, by
+    /// which it can be updated or removed. Places shared with an [`Arc`] are cloned first.
+    pub fn insert(&mut self, place: T) -> usize
+    where
+        T: Clone,
+    {
+        let places = Arc::make_mut(&mut self.places);
+        places.push(place);
+        places.len() - 1
+    }
+
+    /// Replace the place of given index, e.g. with the one moved by a drag. Returns the previous
+    /// one, or `None` if there is no such place. Places shared with an [`Arc`] are cloned first.
+    pub fn update(&mut self, idx: usize, place: T) -> Option<T>
+    where
+        T: Clone,
+    {
+        let old = Arc::make_mut(&mut self.places).get_mut(idx)?;
+        Some(std::mem::replace(old, place))
+    }
+
+    /// Remove the place of given index. The last place takes its index, so that the others keep
+    /// theirs. If the removed place was being dragged, the drag is dropped.
+    pub fn remove(&mut self, idx: usize) -> Option<T>
+    where
+        T: Clone,
+    {
+        if idx >= self.places.len() {
+            return None;
+        }
+
+        let last = self.places.len() - 1;
+        self.dragged = match self.dragged {
+            Some((dragged, _)) if dragged == idx => None,
+            Some((dragged, offset)) if dragged == last => Some((idx, offset)),
+            dragged => dragged,
+        };
+        Some(Arc::make_mut(&mut self.places).swap_remove(idx))
+    }
+
+    fn draw(&self, ui: &mut Ui, projector: &Projector) {
+        for projector in projector.world_copies() {
+            for place in selected_last(self.places.iter()) {
+                place.draw(ui, &projector);
+            }
+        }
+    }
+
+    fn drag(&mut self, ui: &mut Ui, response: &Response, projector: &Projector) {
+        // Widget only sits under the pointer when there is a place there, so that elsewhere the
+        // map is panned as usual. Added after the map, it takes the drag for itself, and then
+        // the map is no longer hovered, so the pointer is checked directly.
+        let id = ui.id().with("places_drag");
+        let rect = if self.dragged.is_some() || ui.ctx().is_being_dragged(id) {
+            response.rect
+        } else if let Some(pos) = ui.ctx().pointer_hover_pos()
+            && response.rect.contains(pos)
+            && self.grabbed(pos, projector).is_some()
+        {
+            Rect::from_center_size(pos, Vec2::splat(DRAG_HANDLE_SIZE))
+        } else {
+            return;
+        };
+
+        let handle = ui.interact(rect, id, Sense::drag());
+        let Some(pointer) = handle.interact_pointer_pos() else {
+            // E.g. the drag was lost while the map was not shown.
+            self.dragged = None;
+            return;
+        };
+
+        if handle.drag_started() {
+            let press_origin = ui.input(|i| i.pointer.press_origin()).unwrap_or(pointer);
+            if let Some((index, place_pos)) = self.grabbed(press_origin, projector) {
+                self.dragged = Some((index, place_pos - press_origin.to_vec2()));
+                self.drag_event = Some(PlaceDrag::Started {
+                    index,
+                    position: self.places[index].position(),
+                });
+            }
+        } else if let Some((index, offset)) = self.dragged {
+            let position = projector.unproject(pointer.to_vec2() + offset);
+            if handle.drag_stopped() {
+                self.dragged = None;
+                self.drag_event = Some(PlaceDrag::Ended { index, position });
+            } else if handle.drag_delta() != Vec2::ZERO {
+                self.drag_event = Some(PlaceDrag::Moved { index, position });
+            }
+        }
+    }
+
+    /// Topmost place under given screen position, with its screen position.
+    fn grabbed(&self, pos: Pos2, projector: &Projector) -> Option<(usize, Vec2)> {
+        let mut order: Vec<_> = (0..self.places.len()).collect();
+        order.sort_by_key(|&idx| self.places[idx].selected());
+
+        projector.world_copies().find_map(|projector| {
+            order.iter().rev().find_map(|&idx| {
+                let place = &self.places[idx];
+                place
+                    .contains(pos, &projector)
+                    .then(|| (idx, projector.project(place.position())))
+            })
+        })
     }
 }
 
+/// Size of the area under the pointer which grabs a draggable place.
+const DRAG_HANDLE_SIZE: f32 = 16.;
+
 impl<T> Plugin for Places<T>
 where
     T: Place + 'static,
@@ -29,19 +191,89 @@ where
     fn run(
         self: Box<Self>,
         ui: &mut Ui,
+        _painter: &Painter,
         _response: &Response,
         projector: &Projector,
         _map_memory: &MapMemory,
     ) {
-        for place in &self.places {
-            place.draw(ui, projector);
+        self.draw(ui, projector);
+    }
+
+    fn takes_pointer(&self, pos: Pos2, projector: &Projector) -> bool {
+        self.places
+            .iter()
+            .any(|place| place.contains(pos, projector))
+    }
+}
+
+impl<T> Plugin for &mut Places<T>
+where
+    T: Place,
+{
+    fn run(
+        self: Box<Self>,
+        ui: &mut Ui,
+        _painter: &Painter,
+        response: &Response,
+        projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        self.drag_event = None;
+        if self.draggable {
+            self.drag(ui, response, projector);
         }
+        self.draw(ui, projector);
+    }
+
+    fn takes_pointer(&self, pos: Pos2, projector: &Projector) -> bool {
+        self.places
+            .iter()
+            .any(|place| place.contains(pos, projector))
     }
 }
 
 pub trait Place {
     fn position(&self) -> Position;
     fn draw(&self, ui: &Ui, projector: &Projector);
+
+    /// Category of the place. [`Group`]s can use it to resemble the places they consist of.
+    fn category(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether the place is selected. Selected places are drawn on top of the others.
+    fn selected(&self) -> bool {
+        false
+    }
+
+    /// Whether the screen position hits the place, so that [`Places`] takes the pointer from the
+    /// plugins beneath. See [`Plugin::takes_pointer`]. `false` by default.
+    fn contains(&self, _pos: Pos2, _projector: &Projector) -> bool {
+        false
+    }
+
+    /// Importance of the place, such as population or severity. See
+    /// [`GroupedPlaces::with_weight`], which takes precedence over it.
+    fn weight(&self) -> f64 {
+        1.
+    }
+}
+
+/// The most common category among given places. Ties are resolved by picking the first category
+/// in alphabetical order, so the result does not depend on the order of places and groups
+/// look the same between frames.
+pub fn dominant_category<'a, T: Place>(places: &[&'a T]) -> Option<&'a str> {
+    let mut counts = BTreeMap::new();
+    for category in places.iter().filter_map(|place| place.category()) {
+        *counts.entry(category).or_insert(0usize) += 1;
+    }
+
+    // `max_by_key` picks the last maximum, so iterate in reverse alphabetical order.
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(category, _)| category)
 }
 
 /// A group of places that can be drawn together on the map.
@@ -49,15 +281,71 @@ pub trait Group {
     fn draw<T: Place>(&self, places: &[&T], position: Position, projector: &Projector, ui: &mut Ui);
 }
 
+/// Distance, in pixels, within which [`GroupedPlaces`] puts places together by default.
+const CLUSTER_RADIUS: f32 = 50.;
+
+/// How [`GroupedPlaces`] decides which places go together. Both strategies use the same radius,
+/// see [`GroupedPlaces::with_cluster_radius`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ClusterStrategy {
+    /// Place joins the first group in which all places are closer than the radius. Groups follow
+    /// the actual density of places, but it takes `O(n²)` time for `n` places in the worst case.
+    #[default]
+    Greedy,
+
+    /// Map is divided into square cells as big as the radius, and places falling into the same
+    /// cell form a group. Cells are fixed to the map rather than to the screen, so groups do not
+    /// change while panning. It takes `O(n)` time, but places close to each other can end up in
+    /// separate groups if there is a cell boundary between them, so it works best when places are
+    /// spread evenly.
+    Grid,
+}
+
+/// See [`GroupedPlaces::with_weight`].
+type Weight<T> = Box<dyn Fn(&T) -> f64>;
+
 /// Similar to [`Places`], but groups places that are close together and draws them as a
 /// single [`Group`].
+///
+/// Places can be given different weights (see [`GroupedPlaces::with_weight`]), so that the
+/// important ones pull the group towards them.
+///
+/// To read back the clusters computed in the last frame (see [`GroupedPlaces::clusters`]), keep
+/// it between frames and add it to the map by a mutable reference.
 pub struct GroupedPlaces<T, G>
 where
     T: Place,
     G: Group,
 {
-    places: Vec<T>,
+    places: Arc<Vec<T>>,
     group: G,
+    weight: Option<Weight<T>>,
+    disable_clustering_at_zoom: Option<f64>,
+    strategy: ClusterStrategy,
+    cluster_radius: f32,
+    transition_duration: Option<Duration>,
+    transition: Option<Transition>,
+    clusters: Vec<ClusterInfo>,
+    /// Zoom the clusters were computed at, so that the ones which changed due to panning alone
+    /// are not animated.
+    zoom: Option<f64>,
+}
+
+/// A cluster of places computed by [`GroupedPlaces`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterInfo {
+    /// Centroid of the places.
+    pub position: Position,
+
+    /// Indices of the places, in the order they were given to [`GroupedPlaces::new`].
+    pub members: Vec<usize>,
+
+    /// Index of the place with the highest weight. First one wins if there are more.
+    pub representative: usize,
+
+    /// Whether the user expanded the cluster, so that its places are drawn individually.
+    /// Clusters of a single place are always drawn individually.
+    pub expanded: bool,
 }
 
 impl<T, G> GroupedPlaces<T, G>
@@ -65,15 +353,106 @@ where
     T: Place,
     G: Group,
 {
-    pub fn new(places: Vec<T>, group: G) -> Self {
-        Self { places, group }
+    pub fn new(places: impl Into<Arc<Vec<T>>>, group: G) -> Self {
+        Self {
+            places: places.into(),
+            group,
+            weight: None,
+            disable_clustering_at_zoom: None,
+            strategy: ClusterStrategy::default(),
+            cluster_radius: CLUSTER_RADIUS,
+            transition_duration: None,
+            transition: None,
+            clusters: Vec::new(),
+            zoom: None,
+        }
+    }
+
+    /// Draw every place individually, like [`Places`] does, once the map is zoomed in to this
+    /// level or further. Places are clustered at all zoom levels by default.
+    pub fn with_disable_clustering_at_zoom(mut self, zoom: f64) -> Self {
+        self.disable_clustering_at_zoom = Some(zoom);
+        self
+    }
+
+    /// Algorithm used to group places. See [`ClusterStrategy`] for the trade-offs.
+    pub fn with_cluster_strategy(mut self, strategy: ClusterStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Distance, in pixels, within which places are put together. Defaults to 50. Radius which is
+    /// not positive is ignored.
+    pub fn with_cluster_radius(mut self, radius: f32) -> Self {
+        if radius > 0. {
+            self.cluster_radius = radius;
+        }
+        self
+    }
+
+    /// Animate changes of the clusters caused by zooming: places move out of the center of the
+    /// cluster which splits and into the center of the one they merge into, taking `duration`.
+    /// Other changes, e.g. of the grid cells when panning, are not animated, and neither is
+    /// anything if [`MapMemory::reduced_motion`] is set. Disabled by default, as it compares the
+    /// clusters every frame. It needs to be kept between frames and added to the map by a mutable
+    /// reference, see [`GroupedPlaces::clusters`].
+    pub fn with_transitions(mut self, duration: Duration) -> Self {
+        self.transition_duration = Some(duration);
+        self
+    }
+
+    /// Importance of each place, such as population or severity. Group is positioned at the
+    /// weighted average of its places and [`Group::draw`] gets them sorted by weight, heaviest
+    /// first, so it can be styled after the most important one. By default, [`Place::weight`]
+    /// is used, which is `1.0` unless the place type says otherwise.
+    pub fn with_weight(mut self, weight: impl Fn(&T) -> f64 + 'static) -> Self {
+        self.weight = Some(Box::new(weight));
+        self
+    }
+
+    /// Clusters computed during the most recent draw, including the ones consisting of a single
+    /// place. Empty until the map is shown.
+    pub fn clusters(&self) -> &[ClusterInfo] {
+        &self.clusters
+    }
+
+    pub fn places(&self) -> &[T] {
+        &self.places
+    }
+
+    /// Add a place, e.g. to a live-updating dataset kept between frames. Returns its index, by
+    /// which it can be updated or removed. Places shared with an [`Arc`] are cloned first.
+    pub fn insert(&mut self, place: T) -> usize
+    where
+        T: Clone,
+    {
+        let places = Arc::make_mut(&mut self.places);
+        places.push(place);
+        places.len() - 1
+    }
+
+    /// Replace the place of given index. Returns the previous one, or `None` if there is no such
+    /// place.
+    pub fn update(&mut self, idx: usize, place: T) -> Option<T>
+    where
+        T: Clone,
+    {
+        let old = Arc::make_mut(&mut self.places).get_mut(idx)?;
+        Some(std::mem::replace(old, place))
+    }
+
+    /// Remove the place of given index. The last place takes its index, so that the others keep
+    /// theirs.
+    pub fn remove(&mut self, idx: usize) -> Option<T>
+    where
+        T: Clone,
+    {
+        (idx < self.places.len()).then(|| Arc::make_mut(&mut self.places).swap_remove(idx))
     }
 
     /// Handle user interactions. Returns whether group should be expanded.
     fn interact(&self, position: Position, projector: &Projector, ui: &Ui, id: Id) -> bool {
-        let screen_position = projector.project(position);
-        let rect = Rect::from_center_size(screen_position.to_pos2(), vec2(50., 50.));
-        let response = ui.interact(rect, id, Sense::click());
+        let response = ui.interact(cluster_hitbox(position, projector), id, Sense::click());
 
         if response.clicked() {
             // Toggle the visibility of the group when clicked.
@@ -87,9 +466,281 @@ where
                 .memory(|m| m.data.get_temp::<bool>(id).unwrap_or(false))
         }
     }
+
+    /// Indices of the places in each group, as seen through the projector.
+    fn group(&self, projector: &Projector) -> Vec<Vec<usize>> {
+        let clustering = self
+            .disable_clustering_at_zoom
+            .is_none_or(|zoom| projector.zoom() < zoom);
+
+        if !clustering {
+            return (0..self.places.len()).map(|idx| vec![idx]).collect();
+        }
+
+        match self.strategy {
+            ClusterStrategy::Greedy => groups(&self.places, projector, self.cluster_radius),
+            ClusterStrategy::Grid => grid_groups(&self.places, projector, self.cluster_radius),
+        }
+    }
+
+    fn weights(&self, members: &[usize]) -> Vec<f64> {
+        members
+            .iter()
+            .map(|&i| match &self.weight {
+                Some(weight) => weight(&self.places[i]),
+                None => self.places[i].weight(),
+            })
+            .collect()
+    }
+
+    fn position(&self, members: &[usize], weights: &[f64]) -> Position {
+        weighted_center(
+            &members
+                .iter()
+                .map(|&i| self.places[i].position())
+                .collect::<Vec<_>>(),
+            weights,
+        )
+    }
+
+    /// Whether the screen position is over the clickable area of a cluster, including the ones of
+    /// a single place. Clusters from the previous draw are used, so that the places are not
+    /// grouped twice in a frame, unless there are none yet.
+    fn hit(&self, pos: Pos2, projector: &Projector) -> bool {
+        projector.world_copies().any(|projector| {
+            if self.clusters.is_empty() {
+                self.group(&projector).iter().any(|members| {
+                    let position = self.position(members, &self.weights(members));
+                    cluster_hitbox(position, &projector).contains(pos)
+                })
+            } else {
+                self.clusters
+                    .iter()
+                    .any(|cluster| cluster_hitbox(cluster.position, &projector).contains(pos))
+            }
+        })
+    }
+
+    fn draw(&mut self, ui: &mut Ui, projector: &Projector, map_memory: &MapMemory) {
+        let previous = std::mem::take(&mut self.clusters);
+        let zoomed = self.zoom.replace(projector.zoom()) != Some(projector.zoom());
+
+        for (copy, projector) in projector.world_copies().enumerate() {
+            let mut single: Vec<(&T, Vec2)> = Vec::new();
+            let groups = self.group(&projector);
+
+            // Every copy of the world has the same clusters, so look for changes only once.
+            if copy == 0 {
+                self.update_transition(&previous, &groups, zoomed, map_memory.reduced_motion(), ui);
+            }
+
+            for (idx, members) in groups.into_iter().enumerate() {
+                let id = ui.id().with((copy, idx));
+                let weights = self.weights(&members);
+                let position = self.position(&members, &weights);
+
+                // Heaviest first. Sort is stable, so equal weights keep the original order.
+                let mut by_weight: Vec<usize> = (0..members.len()).collect();
+                by_weight.sort_by(|&a, &b| weights[b].total_cmp(&weights[a]));
+                let by_weight: Vec<usize> = by_weight.into_iter().map(|i| members[i]).collect();
+                let representative = by_weight[0];
+
+                // While merging, parts of the cluster are still drawn separately, moving towards
+                // its center. Otherwise, cluster moves as a whole, e.g. out of the one it split
+                // from.
+                let parts = self.transition.as_ref().map_or(&[][..], |t| t.parts(idx));
+                let drawn_position = match (parts, &self.transition) {
+                    ([part], Some(transition)) => transition.position(part, position),
+                    _ => position,
+                };
+                let expand = self.interact(drawn_position, &projector, ui, id);
+
+                let pieces: Vec<(Vec<usize>, Position)> = match (parts, &self.transition) {
+                    ([_, _, ..], Some(transition)) if !expand => parts
+                        .iter()
+                        .map(|part| {
+                            let members = by_weight
+                                .iter()
+                                .copied()
+                                .filter(|i| part.members.contains(i))
+                                .collect();
+                            (members, transition.position(part, position))
+                        })
+                        .collect(),
+                    _ => vec![(by_weight, drawn_position)],
+                };
+
+                for (piece, piece_position) in pieces {
+                    let places: Vec<&T> = piece.iter().map(|&i| &self.places[i]).collect();
+                    if places.len() >= 2 && !expand {
+                        self.group.draw(&places, piece_position, &projector, ui);
+                    } else {
+                        // Lone place is drawn where the piece is, expanded cluster keeps its
+                        // layout around it.
+                        let anchor = match places.as_slice() {
+                            [place] => place.position(),
+                            _ => position,
+                        };
+                        let offset = projector.project(piece_position) - projector.project(anchor);
+                        single.extend(places.into_iter().map(|place| (place, offset)));
+                    }
+                }
+
+                if copy == 0 {
+                    self.clusters.push(ClusterInfo {
+                        position,
+                        members,
+                        representative,
+                        expanded: expand,
+                    });
+                }
+            }
+
+            // Selected places are drawn on top. Sort is stable, so the others keep their order.
+            single.sort_by_key(|(place, _)| place.selected());
+            for (place, offset) in single {
+                if offset == Vec2::ZERO {
+                    place.draw(ui, &projector);
+                } else {
+                    place.draw(ui, &projector.translated(offset));
+                }
+            }
+        }
+    }
+
+    /// Start a transition if zooming made places grouped differently than in the previous frame,
+    /// or move the ongoing one forward.
+    fn update_transition(
+        &mut self,
+        previous: &[ClusterInfo],
+        groups: &[Vec<usize>],
+        zoomed: bool,
+        reduced_motion: bool,
+        ui: &Ui,
+    ) {
+        let Some(duration) = self.transition_duration.filter(|_| !reduced_motion) else {
+            self.transition = None;
+            return;
+        };
+
+        let changed = previous.len() != groups.len()
+            || previous
+                .iter()
+                .zip(groups)
+                .any(|(cluster, members)| &cluster.members != members);
+
+        if changed && (!zoomed || previous.is_empty()) {
+            // Ongoing transition is for clusters which are gone.
+            self.transition = None;
+        } else if changed {
+            // Where each place was drawn in the previous frame, so that it does not jump if the
+            // clusters change again before the ongoing transition is done.
+            let mut was_at = vec![None; self.places.len()];
+            for (idx, cluster) in previous.iter().enumerate() {
+                for &member in &cluster.members {
+                    let drawn = self.transition.as_ref().and_then(|transition| {
+                        let part = transition
+                            .parts(idx)
+                            .iter()
+                            .find(|part| part.members.contains(&member))?;
+                        Some(transition.position(part, cluster.position))
+                    });
+                    if let Some(slot) = was_at.get_mut(member) {
+                        *slot = Some(drawn.unwrap_or(cluster.position));
+                    }
+                }
+            }
+
+            let parts = groups
+                .iter()
+                .map(|members| {
+                    let mut parts: Vec<Part> = Vec::new();
+                    for &member in members {
+                        let start = was_at[member];
+                        match parts.iter_mut().find(|part| part.start == start) {
+                            Some(part) => part.members.push(member),
+                            None => parts.push(Part {
+                                start,
+                                members: vec![member],
+                            }),
+                        }
+                    }
+                    parts
+                })
+                .collect();
+            self.transition = Some(Transition {
+                parts,
+                progress: 0.,
+            });
+        } else if let Some(transition) = &mut self.transition {
+            let delta_time = ui.input(|input| input.stable_dt);
+            transition.progress += delta_time / duration.as_secs_f32().max(f32::EPSILON);
+            if transition.progress >= 1. {
+                self.transition = None;
+            }
+        }
+
+        if self.transition.is_some() {
+            ui.ctx().request_repaint();
+        }
+    }
+}
+
+/// Places moving from their previous clusters to the current ones, started when they get grouped
+/// differently. See [`GroupedPlaces::with_transitions`].
+struct Transition {
+    /// For each current cluster, its places split by the cluster they were in before, along with
+    /// its position. Places which were not there before are in a part without a position.
+    parts: Vec<Vec<Part>>,
+
+    /// From `0.0` to `1.0`.
+    progress: f32,
+}
+
+struct Part {
+    start: Option<Position>,
+    members: Vec<usize>,
+}
+
+impl Transition {
+    /// Parts of the cluster of given index.
+    fn parts(&self, idx: usize) -> &[Part] {
+        self.parts.get(idx).map_or(&[], Vec::as_slice)
+    }
+
+    /// Where to draw a part of a cluster which ends up at `target`.
+    fn position(&self, part: &Part, target: Position) -> Position {
+        let Some(start) = part.start else {
+            return target;
+        };
+        let t = self.progress.clamp(0., 1.) as f64;
+        let t = t * t * (3. - 2. * t);
+        start + (target - start) * t
+    }
 }
 
 impl<T, G> Plugin for GroupedPlaces<T, G>
+where
+    T: Place,
+    G: Group,
+{
+    fn run(
+        mut self: Box<Self>,
+        ui: &mut Ui,
+        _painter: &Painter,
+        _response: &Response,
+        projector: &Projector,
+        map_memory: &MapMemory,
+    ) {
+        self.draw(ui, projector, map_memory);
+    }
+
+    fn takes_pointer(&self, pos: Pos2, projector: &Projector) -> bool {
+        self.hit(pos, projector)
+    }
+}
+
+impl<T, G> Plugin for &mut GroupedPlaces<T, G>
 where
     T: Place,
     G: Group,
@@ -97,47 +748,88 @@ where
     fn run(
         self: Box<Self>,
         ui: &mut Ui,
+        _painter: &Painter,
         _response: &Response,
         projector: &Projector,
-        _map_memory: &MapMemory,
+        map_memory: &MapMemory,
     ) {
-        for (idx, places) in groups(&self.places, projector).iter().enumerate() {
-            let id = ui.id().with(idx);
-            let position = center(&places.iter().map(|p| p.position()).collect::<Vec<_>>());
-            let expand = self.interact(position, projector, ui, id);
+        self.draw(ui, projector, map_memory);
+    }
 
-            if places.len() >= 2 && !expand {
-                self.group.draw(places, position, projector, ui);
-            } else {
-                for place in places {
-                    place.draw(ui, projector);
-                }
-            }
-        }
+    fn takes_pointer(&self, pos: Pos2, projector: &Projector) -> bool {
+        self.hit(pos, projector)
     }
 }
 
-/// Group places that are close together.
-fn groups<'a, T>(places: &'a [T], projector: &Projector) -> Vec<Vec<&'a T>>
+/// Order places so that the selected ones come last, and therefore are drawn on top.
+fn selected_last<'a, T: Place + 'a>(
+    places: impl Iterator<Item = &'a T>,
+) -> impl Iterator<Item = &'a T> {
+    let (selected, unselected): (Vec<_>, Vec<_>) = places.partition(|place| place.selected());
+    unselected.into_iter().chain(selected)
+}
+
+/// Group places that are close together. Returns indices of the places.
+fn groups<T>(places: &[T], projector: &Projector, radius: f32) -> Vec<Vec<usize>>
 where
     T: Place,
 {
-    let mut groups: Vec<Vec<&T>> = Vec::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
 
-    for place in places {
+    for (idx, place) in places.iter().enumerate() {
         if let Some(group) = groups.iter_mut().find(|g| {
-            g.iter()
-                .all(|p| distance_projected(place.position(), p.position(), projector) < 50.0)
+            g.iter().all(|&i| {
+                distance_projected(place.position(), places[i].position(), projector) < radius
+            })
         }) {
-            group.push(place);
+            group.push(idx);
         } else {
-            groups.push(vec![place]);
+            groups.push(vec![idx]);
         }
     }
 
     groups
 }
 
+/// Group places falling into the same cell of a grid laid over the whole map at the current zoom.
+/// Groups are ordered by their first place, like in [`groups`]. Returns indices of the places.
+fn grid_groups<T>(places: &[T], projector: &Projector, radius: f32) -> Vec<Vec<usize>>
+where
+    T: Place,
+{
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut cells: HashMap<(i64, i64), usize> = HashMap::new();
+
+    for (idx, place) in places.iter().enumerate() {
+        // Cells are counted from the corner of the map, not the screen, so that they stay put
+        // while panning.
+        let world = mercator::project(place.position(), projector.zoom());
+        let radius = radius as f64;
+        let cell = (
+            (world.x() / radius).floor() as i64,
+            (world.y() / radius).floor() as i64,
+        );
+        let group = *cells.entry(cell).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(idx);
+    }
+
+    groups
+}
+
+/// Size of the clickable area of a cluster, in pixels.
+const CLUSTER_HITBOX: f32 = 50.;
+
+/// Area of the screen which expands or collapses the cluster drawn at given position.
+fn cluster_hitbox(position: Position, projector: &Projector) -> Rect {
+    Rect::from_center_size(
+        projector.project(position).to_pos2(),
+        Vec2::splat(CLUSTER_HITBOX),
+    )
+}
+
 /// Calculate the distance between two positions after being projected onto the screen.
 fn distance_projected(p1: Position, p2: Position, projector: &Projector) -> f32 {
     let screen_p1 = projector.project(p1).to_pos2();
@@ -156,6 +848,21 @@ fn center(positions: &[Position]) -> Position {
     }
 }
 
+/// Average of the positions, weighted by the given weights. Falls back to a plain average if the
+/// weights do not add up to anything positive.
+fn weighted_center(positions: &[Position], weights: &[f64]) -> Position {
+    let total: f64 = weights.iter().sum();
+    if total > 0. {
+        let sum = positions
+            .iter()
+            .zip(weights)
+            .fold(Position::default(), |acc, (&p, &w)| acc + p * w);
+        sum / total
+    } else {
+        center(positions)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct GroupedPlacesTreeSettings {
     pub screen_radius_px: Option<f32>,
@@ -177,13 +884,24 @@ impl Default for GroupedPlacesTreeSettings {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 struct Pt {
     idx: usize,
     lon: f64,
     lat: f64,
 }
 
+impl Pt {
+    fn new<T: Place>(idx: usize, place: &T) -> Self {
+        let pos = place.position();
+        Self {
+            idx,
+            lon: pos.x(),
+            lat: pos.y(),
+        }
+    }
+}
+
 impl RTreeObject for Pt {
     type Envelope = rstar::AABB<[f64; 2]>;
 
@@ -206,11 +924,12 @@ fn interact_cluster(
     projector: &Projector,
     center: Position,
     cluster_id: egui::Id,
-    hitbox_px: f32,
 ) -> bool {
-    let screen = projector.project(center).to_pos2();
-    let rect = egui::Rect::from_center_size(screen, egui::vec2(hitbox_px, hitbox_px));
-    let resp = ui.interact(rect, cluster_id, egui::Sense::click());
+    let resp = ui.interact(
+        cluster_hitbox(center, projector),
+        cluster_id,
+        egui::Sense::click(),
+    );
 
     if resp.clicked() {
         ui.ctx().memory_mut(|m| {
@@ -234,10 +953,12 @@ pub struct GroupedPlacesTree<T: Place, G: Group> {
 }
 
 impl<T: Place, G: Group> GroupedPlacesTree<T, G> {
-    pub fn new(places: Vec<T>, group: G) -> Self {
+    /// Build the spatial index of the places right away, so that drawing only queries it.
+    pub fn new(places: impl Into<Arc<Vec<T>>>, group: G) -> Self {
+        let places = places.into();
         let rtree = build_rtree(&places);
         Self {
-            places: Arc::new(places),
+            places,
             group: Arc::new(group),
             settings: GroupedPlacesTreeSettings::default(),
             rtree: Arc::new(rtree),
@@ -275,14 +996,71 @@ impl<T: Place, G: Group> GroupedPlacesTree<T, G> {
         self
     }
 
-    pub fn update_points(&mut self, places: Vec<T>) {
-        self.places = Arc::new(places);
+    /// Replace all places, rebuilding the spatial index. For a few changes, see
+    /// [`GroupedPlacesTree::insert`], [`GroupedPlacesTree::update`] and
+    /// [`GroupedPlacesTree::remove`] instead.
+    pub fn update_points(&mut self, places: impl Into<Arc<Vec<T>>>) {
+        self.places = places.into();
         self.rtree = Arc::new(build_rtree(&self.places));
         self.screen_positions.borrow_mut().clear();
     }
 
-    fn px_per_deg(&self, memory: &MapMemory, seed: [f64; 2]) -> (f64, f64) {
-        let zoom = memory.zoom();
+    pub fn places(&self) -> &[T] {
+        &self.places
+    }
+
+    /// Add a place to the spatial index, without rebuilding it. Returns its index, by which it
+    /// can be updated or removed. Places and the index shared with clones of the plugin are
+    /// cloned first.
+    pub fn insert(&mut self, place: T) -> usize
+    where
+        T: Clone,
+    {
+        let places = Arc::make_mut(&mut self.places);
+        let idx = places.len();
+        Arc::make_mut(&mut self.rtree).insert(Pt::new(idx, &place));
+        places.push(place);
+        idx
+    }
+
+    /// Replace the place of given index, moving it in the spatial index. Returns the previous
+    /// one, or `None` if there is no such place.
+    pub fn update(&mut self, idx: usize, place: T) -> Option<T>
+    where
+        T: Clone,
+    {
+        let old = Arc::make_mut(&mut self.places).get_mut(idx)?;
+        let (before, after) = (Pt::new(idx, old), Pt::new(idx, &place));
+        if before != after {
+            let rtree = Arc::make_mut(&mut self.rtree);
+            rtree.remove(&before);
+            rtree.insert(after);
+        }
+        Some(std::mem::replace(old, place))
+    }
+
+    /// Remove the place of given index from the spatial index. The last place takes its index,
+    /// so that the others keep theirs.
+    pub fn remove(&mut self, idx: usize) -> Option<T>
+    where
+        T: Clone,
+    {
+        if idx >= self.places.len() {
+            return None;
+        }
+
+        let places = Arc::make_mut(&mut self.places);
+        let rtree = Arc::make_mut(&mut self.rtree);
+        let last = places.len() - 1;
+        rtree.remove(&Pt::new(idx, &places[idx]));
+        if idx != last {
+            rtree.remove(&Pt::new(last, &places[last]));
+            rtree.insert(Pt::new(idx, &places[last]));
+        }
+        Some(places.swap_remove(idx))
+    }
+
+    fn px_per_deg(&self, zoom: f64, seed: [f64; 2]) -> (f64, f64) {
         let pos = lon_lat(seed[0], seed[1]);
         let base = mercator::project(pos, zoom);
         const D: f64 = 1e-4;
@@ -293,25 +1071,37 @@ impl<T: Place, G: Group> GroupedPlacesTree<T, G> {
         (px_per_deg_lon, px_per_deg_lat)
     }
 
-    fn px_to_deg_at(&self, memory: &MapMemory, seed: [f64; 2], r_px: f32) -> f64 {
-        let (px_per_deg_lon, px_per_deg_lat) = self.px_per_deg(memory, seed);
+    fn px_to_deg_at(&self, zoom: f64, seed: [f64; 2], r_px: f32) -> f64 {
+        let (px_per_deg_lon, px_per_deg_lat) = self.px_per_deg(zoom, seed);
         let r_px = r_px as f64;
         let dlon = r_px / px_per_deg_lon;
         let dlat = r_px / px_per_deg_lat;
         dlon.hypot(dlat)
     }
 
-    fn deg_to_px_at(&self, memory: &MapMemory, seed: [f64; 2], r_deg: f64) -> f32 {
-        let (px_per_deg_lon, px_per_deg_lat) = self.px_per_deg(memory, seed);
+    fn deg_to_px_at(&self, zoom: f64, seed: [f64; 2], r_deg: f64) -> f32 {
+        let (px_per_deg_lon, px_per_deg_lat) = self.px_per_deg(zoom, seed);
         let px_lon = r_deg * px_per_deg_lon;
         let px_lat = r_deg * px_per_deg_lat;
         px_lon.hypot(px_lat) as f32
     }
 
+    /// Screen positions of the places, cached to not allocate them every frame.
+    fn project(&self, projector: &Projector) -> std::cell::RefMut<'_, Vec<egui::Pos2>> {
+        let mut cache = self.screen_positions.borrow_mut();
+        if cache.len() != self.places.len() {
+            cache.resize(self.places.len(), egui::Pos2::new(0.0, 0.0));
+        }
+        for (pos, place) in cache.iter_mut().zip(self.places.iter()) {
+            *pos = projector.project(place.position()).to_pos2();
+        }
+        cache
+    }
+
     fn visit_clusters_with_cache<F>(
         &self,
         response_rect: egui::Rect,
-        memory: &MapMemory,
+        zoom: f64,
         screen_positions: &[egui::Pos2],
         mut handle: F,
     ) where
@@ -345,11 +1135,11 @@ impl<T: Place, G: Group> GroupedPlacesTree<T, G> {
             }
 
             let (query_r_deg, r_px_check) = if let Some(px) = s.screen_radius_px {
-                (self.px_to_deg_at(memory, [seed.lon, seed.lat], px), px)
+                (self.px_to_deg_at(zoom, [seed.lon, seed.lat], px), px)
             } else {
                 (
                     s.geo_radius_deg,
-                    self.deg_to_px_at(memory, [seed.lon, seed.lat], s.geo_radius_deg),
+                    self.deg_to_px_at(zoom, [seed.lon, seed.lat], s.geo_radius_deg),
                 )
             };
 
@@ -413,21 +1203,14 @@ impl<T: Place, G: Group> GroupedPlacesTree<T, G> {
     ) -> (usize, usize) {
         let mut clusters = 0usize;
         let mut max_size = 0usize;
-        let mut cache = self.screen_positions.borrow_mut();
-        if cache.len() != self.places.len() {
-            cache.resize(self.places.len(), egui::Pos2::new(0.0, 0.0));
-        }
-        for (pos, place) in cache.iter_mut().zip(self.places.iter()) {
-            *pos = projector.project(place.position()).to_pos2();
-        }
+        let cache = self.project(projector);
         self.visit_clusters_with_cache(
             response.rect,
-            memory,
+            memory.zoom(),
             &cache,
             |seed_idx, members, center| {
-                const HITBOX_PX: f32 = 50.0;
                 let cluster_id = ui.id().with(("rstar_cluster", seed_idx));
-                let expand = interact_cluster(ui, projector, center, cluster_id, HITBOX_PX);
+                let expand = interact_cluster(ui, projector, center, cluster_id);
 
                 if members.len() >= 2 && !expand {
                     let refs: Vec<&T> = members.iter().map(|&i| &self.places[i]).collect();
@@ -453,14 +1236,8 @@ impl<T: Place, G: Group> GroupedPlacesTree<T, G> {
     ) -> (usize, usize) {
         let mut clusters = 0usize;
         let mut max_size = 0usize;
-        let mut cache = self.screen_positions.borrow_mut();
-        if cache.len() != self.places.len() {
-            cache.resize(self.places.len(), egui::Pos2::new(0.0, 0.0));
-        }
-        for (pos, place) in cache.iter_mut().zip(self.places.iter()) {
-            *pos = projector.project(place.position()).to_pos2();
-        }
-        self.visit_clusters_with_cache(rect, memory, &cache, |_, members, _| {
+        let cache = self.project(projector);
+        self.visit_clusters_with_cache(rect, memory.zoom(), &cache, |_, members, _| {
             clusters += 1;
             max_size = max_size.max(members.len());
         });
@@ -472,12 +1249,25 @@ impl<T: Place, G: Group> Plugin for GroupedPlacesTree<T, G> {
     fn run(
         self: Box<Self>,
         ui: &mut Ui,
+        _painter: &Painter,
         response: &Response,
         projector: &Projector,
         memory: &MapMemory,
     ) {
         self.draw_once(ui, response, projector, memory);
     }
+
+    fn takes_pointer(&self, pos: Pos2, projector: &Projector) -> bool {
+        let cache = self.project(projector);
+        let mut hit = false;
+        self.visit_clusters_with_cache(
+            projector.visible_rect(),
+            projector.zoom(),
+            &cache,
+            |_, _, center| hit |= cluster_hitbox(center, projector).contains(pos),
+        );
+        hit
+    }
 }
 
 #[inline]
@@ -485,14 +1275,7 @@ fn build_rtree<T: Place>(places: &[T]) -> RTree<Pt> {
     let pts: Vec<Pt> = places
         .iter()
         .enumerate()
-        .map(|(idx, p)| {
-            let pos = p.position();
-            Pt {
-                idx,
-                lon: pos.x(),
-                lat: pos.y(),
-            }
-        })
+        .map(|(idx, place)| Pt::new(idx, place))
         .collect();
     RTree::bulk_load(pts)
 }
@@ -500,7 +1283,8 @@ fn build_rtree<T: Place>(places: &[T]) -> RTree<Pt> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use egui::{Pos2, Rect, Vec2};
+    use egui::{Pos2, Rect, Vec2, vec2};
+    use std::rc::Rc;
 
     #[derive(Clone)]
     struct DummyPlace(Position);
@@ -513,6 +1297,20 @@ mod tests {
         fn draw(&self, _ui: &Ui, _projector: &Projector) {}
     }
 
+    struct CategorizedPlace(Option<&'static str>);
+
+    impl Place for CategorizedPlace {
+        fn position(&self) -> Position {
+            Position::default()
+        }
+
+        fn draw(&self, _ui: &Ui, _projector: &Projector) {}
+
+        fn category(&self) -> Option<&str> {
+            self.0
+        }
+    }
+
     #[derive(Clone)]
     struct DummyGroup;
 
@@ -556,6 +1354,646 @@ mod tests {
         assert_eq!(max_near, 1);
     }
 
+    #[test]
+    fn tree_is_updated_in_place() {
+        let mut tree = GroupedPlacesTree::new(
+            vec![
+                DummyPlace(lon_lat(0.0, 0.0)),
+                DummyPlace(lon_lat(0.01, 0.0)),
+            ],
+            DummyGroup,
+        )
+        .with_screen_radius_px(50.0);
+        let (rect, memory, projector) = projector_for_zoom(8.0);
+        let stats = |tree: &GroupedPlacesTree<DummyPlace, DummyGroup>| {
+            tree.cluster_stats(rect, &projector, &memory)
+        };
+        let rebuilt = |tree: &GroupedPlacesTree<DummyPlace, DummyGroup>| {
+            stats(
+                &GroupedPlacesTree::new(tree.places().to_vec(), DummyGroup)
+                    .with_screen_radius_px(50.0),
+            )
+        };
+
+        // Shared with a clone, which is not affected.
+        let clone = tree.clone();
+
+        assert_eq!(tree.insert(DummyPlace(lon_lat(5.0, 0.0))), 2);
+        assert_eq!(stats(&tree), (2, 2));
+
+        assert!(tree.update(0, DummyPlace(lon_lat(5.01, 0.0))).is_some());
+        assert_eq!(stats(&tree), (2, 2));
+        assert_eq!(stats(&tree), rebuilt(&tree));
+
+        // Last place takes the index of the removed one.
+        assert!(tree.remove(0).is_some());
+        assert_eq!(tree.places()[0].0, lon_lat(5.0, 0.0));
+        assert_eq!(stats(&tree), (2, 1));
+        assert_eq!(stats(&tree), rebuilt(&tree));
+        assert!(tree.remove(2).is_none());
+
+        assert_eq!(stats(&clone), (1, 2));
+    }
+
+    #[test]
+    fn places_can_be_inserted_and_removed() {
+        let shared = Arc::new(vec![
+            DummyPlace(lon_lat(0.0, 0.0)),
+            DummyPlace(lon_lat(1.0, 0.0)),
+        ]);
+        let mut places = Places::new(shared.clone());
+
+        assert_eq!(places.insert(DummyPlace(lon_lat(2.0, 0.0))), 2);
+        assert_eq!(places.places().len(), 3);
+        assert_eq!(shared.len(), 2);
+
+        // Last place takes the index of the removed one, even while being dragged.
+        places.dragged = Some((2, Vec2::ZERO));
+        assert!(places.remove(0).is_some());
+        assert_eq!(places.places()[0].0, lon_lat(2.0, 0.0));
+        assert_eq!(places.dragged, Some((0, Vec2::ZERO)));
+
+        assert!(places.remove(0).is_some());
+        assert_eq!(places.dragged, None);
+        assert!(places.remove(1).is_none());
+    }
+
+    struct SelectablePlace(&'static str, bool);
+
+    impl Place for SelectablePlace {
+        fn position(&self) -> Position {
+            Position::default()
+        }
+
+        fn draw(&self, _ui: &Ui, _projector: &Projector) {}
+
+        fn selected(&self) -> bool {
+            self.1
+        }
+    }
+
+    #[test]
+    fn clusters_of_last_frame_can_be_read_back() {
+        let mut grouped = GroupedPlaces::new(
+            vec![
+                DummyPlace(lon_lat(17.0, 51.0)),
+                DummyPlace(lon_lat(17.0001, 51.0)),
+                DummyPlace(lon_lat(18.0, 51.0)),
+            ],
+            DummyGroup,
+        );
+        assert!(grouped.clusters().is_empty());
+
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(
+                    walkers::Map::new(None, &mut memory, lon_lat(17.0, 51.0))
+                        .with_plugin(&mut grouped),
+                );
+            });
+        });
+
+        let members: Vec<_> = grouped
+            .clusters()
+            .iter()
+            .map(|cluster| cluster.members.clone())
+            .collect();
+        assert_eq!(members, [vec![0, 1], vec![2]]);
+        assert_eq!(grouped.clusters()[0].position, lon_lat(17.00005, 51.0));
+        assert!(!grouped.clusters()[0].expanded);
+        assert_eq!(grouped.clusters()[0].representative, 0);
+    }
+
+    /// Remembers the order of places, by longitude, given to the last drawn group.
+    struct OrderRecorder(std::rc::Rc<RefCell<Vec<f64>>>);
+
+    impl Group for OrderRecorder {
+        fn draw<T: Place>(
+            &self,
+            places: &[&T],
+            _position: Position,
+            _projector: &Projector,
+            _ui: &mut Ui,
+        ) {
+            *self.0.borrow_mut() = places.iter().map(|p| p.position().x()).collect();
+        }
+    }
+
+    #[test]
+    fn heavy_places_dominate_the_cluster() {
+        let order = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let places = vec![
+            DummyPlace(lon_lat(17.0, 51.0)),
+            DummyPlace(lon_lat(17.0001, 51.0)),
+            DummyPlace(lon_lat(17.0002, 51.0)),
+        ];
+        let mut grouped = GroupedPlaces::new(places, OrderRecorder(order.clone()))
+            .with_weight(|place| if place.0.x() == 17.0002 { 3. } else { 1. });
+
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(
+                    walkers::Map::new(None, &mut memory, lon_lat(17.0, 51.0))
+                        .with_plugin(&mut grouped),
+                );
+            });
+        });
+
+        let cluster = &grouped.clusters()[0];
+        assert_eq!(cluster.members, [0, 1, 2]);
+        assert_eq!(cluster.representative, 2);
+        assert!((cluster.position.x() - 17.00014).abs() < 1e-9);
+        assert_eq!(*order.borrow(), [17.0002, 17.0, 17.0001]);
+    }
+
+    /// Domain type, which knows its own importance.
+    struct City {
+        location: Position,
+        population: u32,
+    }
+
+    impl Place for City {
+        fn position(&self) -> Position {
+            self.location
+        }
+
+        fn draw(&self, _ui: &Ui, _projector: &Projector) {}
+
+        fn weight(&self) -> f64 {
+            self.population as f64
+        }
+    }
+
+    #[test]
+    fn places_take_the_pointer_over_their_symbols() {
+        use crate::LabeledSymbol;
+
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(512.));
+        let memory = MapMemory::default();
+        let projector = Projector::new(rect, &memory, lon_lat(17.0, 51.0));
+        let places = Places::new(vec![LabeledSymbol::new(lon_lat(17.0, 51.0), "Wrocław")]);
+
+        assert!(places.takes_pointer(rect.center(), &projector));
+        assert!(!places.takes_pointer(rect.center() + vec2(20., 0.), &projector));
+
+        // Places which do not say where they are hit never take it.
+        let places = Places::new(vec![DummyPlace(lon_lat(17.0, 51.0))]);
+        assert!(!places.takes_pointer(rect.center(), &projector));
+    }
+
+    #[test]
+    fn clusters_take_the_pointer_over_their_hitbox() {
+        let (rect, _, projector) = projector_for_zoom(10.);
+
+        // About 7 pixels apart, so they end up in one cluster.
+        let places = vec![
+            DummyPlace(lon_lat(0.0, 0.0)),
+            DummyPlace(lon_lat(0.01, 0.0)),
+        ];
+
+        let mut grouped = GroupedPlaces::new(places.clone(), DummyGroup);
+        assert!(grouped.takes_pointer(rect.center(), &projector));
+        assert!(!grouped.takes_pointer(rect.center() + vec2(40., 0.), &projector));
+
+        // Once drawn, clusters are not computed again.
+        let moved = projector.unproject((rect.center() + vec2(100., 0.)).to_vec2());
+        grouped.clusters = vec![ClusterInfo {
+            position: moved,
+            members: vec![0, 1],
+            representative: 0,
+            expanded: false,
+        }];
+        assert!(!grouped.takes_pointer(rect.center(), &projector));
+        assert!(grouped.takes_pointer(rect.center() + vec2(100., 0.), &projector));
+
+        let tree = GroupedPlacesTree::new(places, DummyGroup);
+        assert!(tree.takes_pointer(rect.center(), &projector));
+        assert!(!tree.takes_pointer(rect.center() + vec2(40., 0.), &projector));
+    }
+
+    #[test]
+    fn dragging_a_place_reports_events_instead_of_panning() {
+        use crate::LabeledSymbol;
+
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        let mut places =
+            Places::new(vec![LabeledSymbol::new(lon_lat(17.0, 51.0), "Wrocław")]).draggable(true);
+        let start = Pos2::new(256., 256.);
+        let button = |pos, pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Default::default(),
+        };
+
+        let mut frames = vec![
+            vec![egui::Event::PointerMoved(start)],
+            vec![button(start, true)],
+        ];
+        for step in 1..=3 {
+            frames.push(vec![egui::Event::PointerMoved(
+                start + vec2(20. * step as f32, 0.),
+            )]);
+        }
+        frames.push(vec![button(start + vec2(60., 0.), false)]);
+
+        let mut events = Vec::new();
+        for frame in frames {
+            let input = egui::RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::splat(512.))),
+                events: frame,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| {
+                        ui.add(
+                            walkers::Map::new(None, &mut memory, lon_lat(17.0, 51.0))
+                                .with_plugin(&mut places),
+                        );
+                    });
+            });
+            events.extend(places.drag_event());
+        }
+
+        assert!(memory.detached().is_none(), "map was panned: {events:?}");
+        assert!(matches!(
+            events.first(),
+            Some(PlaceDrag::Started { index: 0, position }) if *position == lon_lat(17.0, 51.0)
+        ));
+        assert!(
+            events[1..events.len() - 1]
+                .iter()
+                .all(|event| matches!(event, PlaceDrag::Moved { index: 0, .. })),
+            "{events:?}"
+        );
+
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(512.));
+        let expected =
+            Projector::new(rect, &memory, lon_lat(17.0, 51.0)).unproject(vec2(316., 256.));
+        let Some(PlaceDrag::Ended { index: 0, position }) = events.last() else {
+            panic!("drag did not end: {events:?}");
+        };
+        assert!((position.x() - expected.x()).abs() < 1e-6);
+        assert!((position.y() - expected.y()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn places_given_by_value_are_not_dragged() {
+        use crate::LabeledSymbol;
+
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        let start = Pos2::new(256., 256.);
+        let button = |pos, pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Default::default(),
+        };
+
+        let mut frames = vec![
+            vec![egui::Event::PointerMoved(start)],
+            vec![button(start, true)],
+        ];
+        for step in 1..=3 {
+            frames.push(vec![egui::Event::PointerMoved(
+                start + vec2(20. * step as f32, 0.),
+            )]);
+        }
+        frames.push(vec![button(start + vec2(60., 0.), false)]);
+
+        for frame in frames {
+            let input = egui::RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::splat(512.))),
+                events: frame,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| {
+                        let places =
+                            Places::new(vec![LabeledSymbol::new(lon_lat(17.0, 51.0), "Wrocław")])
+                                .draggable(true);
+                        ui.add(
+                            walkers::Map::new(None, &mut memory, lon_lat(17.0, 51.0))
+                                .with_plugin(places),
+                        );
+                    });
+            });
+        }
+
+        // There is nothing to report the drag to, so the map is panned instead.
+        assert!(memory.detached().is_some());
+    }
+
+    #[test]
+    fn places_weigh_themselves_unless_told_otherwise() {
+        let cities = || {
+            vec![
+                City {
+                    location: lon_lat(17.0, 51.0),
+                    population: 1,
+                },
+                City {
+                    location: lon_lat(17.0002, 51.0),
+                    population: 3,
+                },
+            ]
+        };
+        let representative = |mut grouped: GroupedPlaces<City, DummyGroup>| {
+            let ctx = egui::Context::default();
+            let mut memory = MapMemory::default();
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(
+                        walkers::Map::new(None, &mut memory, lon_lat(17.0, 51.0))
+                            .with_plugin(&mut grouped),
+                    );
+                });
+            });
+            let cluster = &grouped.clusters()[0];
+            assert_eq!(cluster.members, [0, 1]);
+            (cluster.representative, cluster.position.x())
+        };
+
+        let (heaviest, x) = representative(GroupedPlaces::new(cities(), DummyGroup));
+        assert_eq!(heaviest, 1);
+        assert!((x - 17.00015).abs() < 1e-9);
+
+        let grouped = GroupedPlaces::new(cities(), DummyGroup).with_weight(|_| 1.);
+        let (heaviest, x) = representative(grouped);
+        assert_eq!(heaviest, 0);
+        assert!((x - 17.0001).abs() < 1e-9);
+    }
+
+    #[test]
+    fn clustering_is_disabled_above_given_zoom() {
+        let mut grouped = GroupedPlaces::new(
+            vec![
+                DummyPlace(lon_lat(17.0, 51.0)),
+                DummyPlace(lon_lat(17.0001, 51.0)),
+            ],
+            DummyGroup,
+        )
+        .with_disable_clustering_at_zoom(17.);
+
+        let ctx = egui::Context::default();
+        let mut cluster_sizes_at = |zoom| {
+            let mut memory = MapMemory::default();
+            memory.set_zoom(zoom).unwrap();
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(
+                        walkers::Map::new(None, &mut memory, lon_lat(17.0, 51.0))
+                            .with_plugin(&mut grouped),
+                    );
+                });
+            });
+            grouped
+                .clusters()
+                .iter()
+                .map(|cluster| cluster.members.len())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(cluster_sizes_at(16.), [2]);
+        assert_eq!(cluster_sizes_at(17.), [1, 1]);
+        assert_eq!(cluster_sizes_at(18.), [1, 1]);
+    }
+
+    #[test]
+    fn strategies_honor_cluster_radius() {
+        let (_, _, projector) = projector_for_zoom(8.0);
+
+        // About 18 and 9 pixels away from the first one, which is in the middle of the screen.
+        let places = vec![
+            DummyPlace(lon_lat(0.0, 0.0)),
+            DummyPlace(lon_lat(0.1, 0.0)),
+            DummyPlace(lon_lat(-0.05, 0.0)),
+        ];
+
+        assert_eq!(groups(&places, &projector, 50.), [vec![0, 1, 2]]);
+        assert_eq!(groups(&places, &projector, 5.), [[0], [1], [2]]);
+
+        // First place is 32768 pixels from the corner of the map, right at the boundary of the
+        // grid's cells, so the last place falls into the neighbouring one.
+        assert_eq!(grid_groups(&places, &projector, 64.), [vec![0, 1], vec![2]]);
+        assert_eq!(
+            grid_groups(&places, &projector, 512.),
+            [vec![0, 1], vec![2]]
+        );
+        assert_eq!(grid_groups(&places, &projector, 1000.), [vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn non_positive_cluster_radius_is_ignored() {
+        for radius in [0., -10., f32::NAN] {
+            let grouped = GroupedPlaces::new(Vec::<DummyPlace>::new(), DummyGroup)
+                .with_cluster_radius(radius);
+            assert_eq!(grouped.cluster_radius, CLUSTER_RADIUS);
+        }
+    }
+
+    /// Records where it was drawn.
+    struct TracedPlace(Position, Rc<RefCell<Vec<Pos2>>>);
+
+    impl Place for TracedPlace {
+        fn position(&self) -> Position {
+            self.0
+        }
+
+        fn draw(&self, _ui: &Ui, projector: &Projector) {
+            self.1
+                .borrow_mut()
+                .push(projector.project(self.0).to_pos2());
+        }
+    }
+
+    /// Distance between the two places, as drawn in each frame at given zoom. Zero when they are
+    /// drawn as a cluster.
+    fn spread_between_places(
+        transitions: Option<Duration>,
+        zooms: &[f64],
+        reduced_motion: bool,
+    ) -> Vec<f32> {
+        let drawn = Rc::new(RefCell::new(Vec::new()));
+        let mut grouped = GroupedPlaces::new(
+            vec![
+                TracedPlace(lon_lat(17.0, 51.0), drawn.clone()),
+                TracedPlace(lon_lat(17.0005, 51.0), drawn.clone()),
+            ],
+            DummyGroup,
+        );
+        if let Some(duration) = transitions {
+            grouped = grouped.with_transitions(duration);
+        }
+
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        zooms
+            .iter()
+            .map(|&zoom| {
+                drawn.borrow_mut().clear();
+                memory.set_zoom(zoom).unwrap();
+                let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        ui.add(
+                            walkers::Map::new(None, &mut memory, lon_lat(17.0, 51.0))
+                                .reduced_motion(reduced_motion)
+                                .with_plugin(&mut grouped),
+                        );
+                    });
+                });
+                match drawn.borrow().as_slice() {
+                    [a, b] => a.distance(*b),
+                    _ => 0.,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn split_clusters_move_out_of_their_parent() {
+        // Places pop out at once by default.
+        let spread = spread_between_places(None, &[15., 18.], false);
+        assert_eq!(spread[0], 0.);
+        assert!(spread[1] > 50.);
+
+        // With transitions, they start from the cluster's center and end up at their positions.
+        let mut zooms = vec![15.];
+        zooms.extend([18.; 30]);
+        let spread = spread_between_places(Some(Duration::from_millis(250)), &zooms, false);
+        assert!(spread[1] < 1.);
+        assert!(spread[5] > spread[1] && spread[5] < spread[29]);
+        assert!((spread[29] - spread_between_places(None, &[18.], false)[0]).abs() < 0.01);
+
+        // And when merging, they move towards the center before becoming a cluster.
+        let mut zooms = vec![18.];
+        zooms.extend([15.; 30]);
+        let spread = spread_between_places(Some(Duration::from_millis(250)), &zooms, false);
+        assert!(spread[1] > 10.);
+        assert!(spread[5] < spread[1]);
+        assert_eq!(spread[29], 0.);
+    }
+
+    #[test]
+    fn interrupted_transition_continues_from_where_places_are() {
+        // Zooming back out before places are done splitting makes them merge right from where
+        // they are, not from their final positions.
+        let mut zooms = vec![15.];
+        zooms.extend([18.; 5]);
+        zooms.extend([15.; 30]);
+        let spread = spread_between_places(Some(Duration::from_millis(250)), &zooms, false);
+
+        // Three zoom levels out, the same distance is eight times shorter on the screen.
+        assert!(spread[5] > 1.);
+        assert!(spread[6] <= spread[5] / 8.);
+        assert_eq!(spread[35], 0.);
+    }
+
+    #[test]
+    fn clusters_are_not_animated_when_motion_is_reduced() {
+        let spread = spread_between_places(Some(Duration::from_millis(250)), &[15., 18.], true);
+        assert!((spread[1] - spread_between_places(None, &[18.], false)[0]).abs() < 0.01);
+    }
+
+    #[test]
+    fn panning_does_not_animate_clusters() {
+        // Places about 23 pixels apart, so a grid following the screen would put them together
+        // only sometimes.
+        let mut grouped = GroupedPlaces::new(
+            vec![
+                DummyPlace(lon_lat(17.0, 51.0)),
+                DummyPlace(lon_lat(17.0005, 51.0)),
+            ],
+            DummyGroup,
+        )
+        .with_cluster_strategy(ClusterStrategy::Grid)
+        .with_transitions(Duration::from_millis(250));
+
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        memory.set_zoom(16.).unwrap();
+        let mut cluster_counts = Vec::new();
+        for step in 0..10 {
+            // Pan through a whole cell, about 5 pixels at a time.
+            memory.center_at(lon_lat(17.0 + step as f64 * 0.0001, 51.0));
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(
+                        walkers::Map::new(None, &mut memory, lon_lat(17.0, 51.0))
+                            .with_plugin(&mut grouped),
+                    );
+                });
+            });
+            assert!(grouped.transition.is_none());
+            cluster_counts.push(grouped.clusters().len());
+        }
+
+        assert!(
+            cluster_counts
+                .iter()
+                .all(|count| *count == cluster_counts[0])
+        );
+    }
+
+    #[test]
+    fn selected_places_are_drawn_last() {
+        let places = [
+            SelectablePlace("a", true),
+            SelectablePlace("b", false),
+            SelectablePlace("c", true),
+            SelectablePlace("d", false),
+        ];
+
+        let order: Vec<_> = selected_last(places.iter()).map(|place| place.0).collect();
+        assert_eq!(order, ["b", "d", "a", "c"]);
+    }
+
+    #[test]
+    fn dominant_category_does_not_depend_on_order() {
+        let bar = CategorizedPlace(Some("bar"));
+        let cafe = CategorizedPlace(Some("cafe"));
+        let other_cafe = CategorizedPlace(Some("cafe"));
+        let uncategorized = CategorizedPlace(None);
+
+        assert_eq!(
+            dominant_category(&[&bar, &cafe, &uncategorized, &other_cafe]),
+            Some("cafe")
+        );
+
+        // Ties are resolved alphabetically.
+        assert_eq!(dominant_category(&[&cafe, &bar]), Some("bar"));
+        assert_eq!(dominant_category(&[&bar, &cafe]), Some("bar"));
+
+        assert_eq!(dominant_category(&[&uncategorized]), None);
+        assert_eq!(dominant_category::<CategorizedPlace>(&[]), None);
+    }
+
+    #[test]
+    fn calculating_weighted_center() {
+        let positions = [Position::new(0.0, 0.0), Position::new(10.0, 20.0)];
+        assert_eq!(
+            weighted_center(&positions, &[1.0, 3.0]),
+            Position::new(7.5, 15.0)
+        );
+        assert_eq!(weighted_center(&positions, &[1.0, 1.0]), center(&positions));
+
+        // Nothing to weigh by.
+        assert_eq!(
+            weighted_center(&positions, &[0.0, 0.0]),
+            Position::new(5.0, 10.0)
+        );
+    }
+
     #[test]
     fn calculating_center() {
         assert_eq!(
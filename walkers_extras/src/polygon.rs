@@ -0,0 +1,199 @@
+use egui::{Color32, Painter, Pos2, Response, Shape, Stroke, Ui};
+use walkers::{MapMemory, Plugin, PluginLayer, Position, Projector};
+
+use crate::kml::{ring_to_screen_points, tessellate_polygon};
+
+/// Tolerance of the tessellation, in pixels.
+const FILL_TOLERANCE: f32 = 0.5;
+
+/// [`Plugin`] which draws a filled area, optionally with holes.
+pub struct Polygon {
+    exterior: Vec<Position>,
+    holes: Vec<Vec<Position>>,
+    fill: Color32,
+    stroke: Option<Stroke>,
+}
+
+impl Polygon {
+    pub fn new(exterior: Vec<Position>, fill: Color32) -> Self {
+        Self {
+            exterior,
+            holes: Vec::new(),
+            fill,
+            stroke: None,
+        }
+    }
+
+    /// Areas cut out of the polygon.
+    pub fn with_holes(mut self, holes: Vec<Vec<Position>>) -> Self {
+        self.holes = holes;
+        self
+    }
+
+    /// Outline of the polygon and its holes. None by default.
+    pub fn with_stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = Some(stroke);
+        self
+    }
+
+    /// Whether the screen position is inside the polygon, but not in any of its holes. Useful for
+    /// making areas clickable, e.g. with [`Response::hover_pos`] of the map.
+    pub fn contains(&self, pos: Pos2, projector: &Projector) -> bool {
+        projector.world_copies().any(|projector| {
+            self.screen_rings(&projector)
+                .is_some_and(|(exterior, holes)| contains(&exterior, &holes, pos))
+        })
+    }
+
+    fn screen_rings(&self, projector: &Projector) -> Option<(Vec<Pos2>, Vec<Vec<Pos2>>)> {
+        let exterior = ring_to_screen_points(&self.exterior, projector)?;
+        let holes = self
+            .holes
+            .iter()
+            .filter_map(|hole| ring_to_screen_points(hole, projector))
+            .collect();
+        Some((exterior, holes))
+    }
+}
+
+impl Plugin for Polygon {
+    fn run(
+        self: Box<Self>,
+        _ui: &mut Ui,
+        painter: &Painter,
+        _response: &Response,
+        projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        for projector in projector.world_copies() {
+            let Some((exterior, holes)) = self.screen_rings(&projector) else {
+                continue;
+            };
+
+            if let Some(mesh) = tessellate_polygon(&exterior, &holes, self.fill, FILL_TOLERANCE) {
+                painter.add(Shape::mesh(mesh));
+            }
+
+            if let Some(stroke) = self.stroke {
+                painter.add(Shape::closed_line(exterior, stroke));
+                for hole in holes {
+                    painter.add(Shape::closed_line(hole, stroke));
+                }
+            }
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Shapes
+    }
+
+    fn takes_pointer(&self, pos: Pos2, projector: &Projector) -> bool {
+        self.contains(pos, projector)
+    }
+}
+
+/// Point in whatever space the polygon lives in, be it screen or geographical coordinates.
+pub(crate) trait Point: Copy {
+    fn xy(self) -> (f64, f64);
+}
+
+impl Point for Pos2 {
+    fn xy(self) -> (f64, f64) {
+        (self.x as f64, self.y as f64)
+    }
+}
+
+impl Point for Position {
+    fn xy(self) -> (f64, f64) {
+        (self.x(), self.y())
+    }
+}
+
+/// Even-odd test, which is also how the polygon is filled, so holes are excluded.
+pub(crate) fn contains<P: Point>(exterior: &[P], holes: &[Vec<P>], pos: P) -> bool {
+    std::iter::once(exterior)
+        .chain(holes.iter().map(Vec::as_slice))
+        .filter(|ring| ring_crossed(ring, pos))
+        .count()
+        % 2
+        == 1
+}
+
+/// Whether a ray going right from `pos` crosses the ring an odd number of times.
+fn ring_crossed<P: Point>(ring: &[P], pos: P) -> bool {
+    let Some(&last) = ring.last() else {
+        return false;
+    };
+
+    let (x, y) = pos.xy();
+    let mut inside = false;
+    let (mut previous_x, mut previous_y) = last.xy();
+    for &current in ring {
+        let (current_x, current_y) = current.xy();
+        if (current_y > y) != (previous_y > y)
+            && x < previous_x
+                + (y - previous_y) * (current_x - previous_x) / (current_y - previous_y)
+        {
+            inside = !inside;
+        }
+        (previous_x, previous_y) = (current_x, current_y);
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    fn square(min: f32, max: f32) -> Vec<Pos2> {
+        vec![
+            pos2(min, min),
+            pos2(max, min),
+            pos2(max, max),
+            pos2(min, max),
+        ]
+    }
+
+    #[test]
+    fn point_inside_polygon() {
+        let exterior = square(0., 10.);
+        assert!(contains(&exterior, &[], pos2(5., 5.)));
+        assert!(!contains(&exterior, &[], pos2(15., 5.)));
+        assert!(!contains(&exterior, &[], pos2(5., -1.)));
+    }
+
+    #[test]
+    fn polygon_contains_screen_position() {
+        use egui::{Rect, Vec2};
+        use walkers::lon_lat;
+
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(512.));
+        let mut memory = MapMemory::default();
+        memory.set_zoom(10.).expect("valid zoom");
+        let projector = Projector::new(rect, &memory, lon_lat(0., 0.));
+
+        let ring = |size: f64| {
+            vec![
+                lon_lat(-size, -size),
+                lon_lat(size, -size),
+                lon_lat(size, size),
+                lon_lat(-size, size),
+            ]
+        };
+        let polygon = Polygon::new(ring(0.1), Color32::RED).with_holes(vec![ring(0.02)]);
+        let on_screen = |lon, lat| projector.project(lon_lat(lon, lat)).to_pos2();
+
+        assert!(polygon.contains(on_screen(0.05, 0.05), &projector));
+        assert!(!polygon.contains(on_screen(0., 0.), &projector));
+        assert!(!polygon.contains(on_screen(0.2, 0.), &projector));
+    }
+
+    #[test]
+    fn point_in_hole_is_outside() {
+        let exterior = square(0., 10.);
+        let holes = [square(4., 6.)];
+        assert!(!contains(&exterior, &holes, pos2(5., 5.)));
+        assert!(contains(&exterior, &holes, pos2(2., 5.)));
+    }
+}
@@ -0,0 +1,519 @@
+use egui::{Painter, Pos2, Rect, Response, Shape, Stroke, Ui, Vec2};
+use walkers::{MapMemory, Plugin, PluginLayer, Position, Projector, lon_lat};
+
+/// Longest great-circle step, in degrees of arc, used when [`Polyline::geodesic`] is enabled.
+const MAX_GEODESIC_STEP: f64 = 1.;
+
+/// Upper limit of steps per segment when using [`Polyline::with_geodesic_step`], so that long
+/// segments do not explode into millions of points when zoomed in.
+const MAX_GEODESIC_STEPS: f32 = 1000.;
+
+/// Angle, in radians, below which a segment's ends are considered antipodal, i.e. on the opposite
+/// sides of the globe.
+const ANTIPODAL_EPSILON: f64 = 1e-6;
+
+/// Shortest distance, in pixels, between the arrows of [`Polyline::with_direction_arrows`]. Lines
+/// with the arrows closer than that are drawn without them.
+const MIN_ARROW_SPACING: f32 = 1.;
+
+/// Upper limit of the arrows drawn along a line, so that a tiny spacing does not produce millions
+/// of them.
+const MAX_ARROWS: usize = 1000;
+
+/// Distance from the edge of the line, in pixels, within which it still takes the pointer. See
+/// [`Plugin::takes_pointer`].
+const POINTER_MARGIN: f32 = 2.;
+
+/// [`Plugin`] which draws a line through the given positions.
+pub struct Polyline {
+    positions: Vec<Position>,
+    stroke: Stroke,
+    geodesic: bool,
+    geodesic_step: Option<f32>,
+    direction_arrows: Option<(f32, f32)>,
+}
+
+impl Polyline {
+    pub fn new(positions: Vec<Position>, stroke: Stroke) -> Self {
+        Self {
+            positions,
+            stroke,
+            geodesic: false,
+            geodesic_step: None,
+            direction_arrows: None,
+        }
+    }
+
+    /// Follow the great circle between consecutive positions, i.e. the shortest path on the
+    /// globe, instead of a straight line on the screen. It matters for long segments, such as
+    /// flight paths, which are then drawn curved, like on other Mercator maps. Long segments are
+    /// subdivided into steps of at most one degree of arc, unless
+    /// [`Polyline::with_geodesic_step`] is set. Antipodal positions have no single shortest path
+    /// between them, so they are connected heading east.
+    pub fn geodesic(mut self, enabled: bool) -> Self {
+        self.geodesic = enabled;
+        self
+    }
+
+    /// Longest step of [`Polyline::geodesic`] segments, in screen pixels, instead of one degree
+    /// of arc. Number of steps then follows the on-screen length of each segment, so it is smooth
+    /// when zoomed in, and does not waste points when zoomed out. A few pixels, e.g. `5.`, is
+    /// enough for the line to look curved.
+    pub fn with_geodesic_step(mut self, pixels: f32) -> Self {
+        self.geodesic_step = Some(pixels);
+        self
+    }
+
+    /// Draw chevrons of given size along the line, pointing from the first position towards the
+    /// last one, e.g. to show the direction of travel of a route. They are `spacing` pixels
+    /// apart, placed anew in every frame, so that the spacing stays even at any zoom. Spacing below
+    /// a pixel draws no arrows.
+    pub fn with_direction_arrows(mut self, spacing: f32, size: f32) -> Self {
+        self.direction_arrows = Some((spacing, size));
+        self
+    }
+
+    /// Index of the segment nearest to the screen position, if it is within `tolerance` pixels.
+    /// Segment `i` goes from the `i`-th to the `i + 1`-th position. Useful for making the line
+    /// clickable, e.g. with [`Response::hover_pos`] of the map.
+    pub fn hit_test(&self, pos: Pos2, tolerance: f32, projector: &Projector) -> Option<usize> {
+        let segments = self.segments(projector);
+        let mut nearest: Option<(usize, f32)> = None;
+
+        for projector in projector.world_copies() {
+            for (index, segment) in segments.iter().enumerate() {
+                let points: Vec<_> = segment
+                    .iter()
+                    .map(|position| projector.project(*position).to_pos2())
+                    .collect();
+                let distance = points
+                    .windows(2)
+                    .map(|pair| distance_to_segment(pos, pair[0], pair[1]))
+                    .fold(f32::INFINITY, f32::min);
+
+                if distance <= tolerance && nearest.is_none_or(|(_, d)| distance < d) {
+                    nearest = Some((index, distance));
+                }
+            }
+        }
+
+        nearest.map(|(index, _)| index)
+    }
+
+    /// Positions along each segment, as drawn.
+    fn segments(&self, projector: &Projector) -> Vec<Vec<Position>> {
+        if self.geodesic {
+            great_circle_segments(&self.positions, self.screen_step(projector))
+        } else {
+            self.positions.windows(2).map(<[_]>::to_vec).collect()
+        }
+    }
+
+    fn screen_step<'p>(&self, projector: &'p Projector) -> Option<(f32, &'p Projector)> {
+        self.geodesic_step.map(|step| (step, projector))
+    }
+}
+
+impl Plugin for Polyline {
+    fn run(
+        self: Box<Self>,
+        _ui: &mut Ui,
+        painter: &Painter,
+        _response: &Response,
+        projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        if self.positions.len() < 2 {
+            return;
+        }
+
+        let positions = if self.geodesic {
+            great_circle_path(&self.positions, self.screen_step(projector))
+        } else {
+            self.positions
+        };
+
+        for projector in projector.world_copies() {
+            let points: Vec<_> = positions
+                .iter()
+                .map(|position| projector.project(*position).to_pos2())
+                .collect();
+
+            if let Some((spacing, size)) = self.direction_arrows {
+                let area = painter.clip_rect().expand(size);
+                for (pos, direction) in arrow_placements(&points, spacing, area) {
+                    let back = pos - direction * size / 2.;
+                    let side = direction.rot90() * size / 2.;
+                    painter.add(Shape::line(
+                        vec![back + side, pos + direction * size / 2., back - side],
+                        self.stroke,
+                    ));
+                }
+            }
+
+            painter.add(Shape::line(points, self.stroke));
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Shapes
+    }
+
+    fn takes_pointer(&self, pos: Pos2, projector: &Projector) -> bool {
+        let tolerance = self.stroke.width / 2. + POINTER_MARGIN;
+        self.hit_test(pos, tolerance, projector).is_some()
+    }
+}
+
+/// Positions and directions of the arrows placed every `spacing` pixels along the line, the first
+/// one half of that from its start. Only the ones within `area` are returned, so that a line
+/// going far off the screen when zoomed in does not produce countless arrows.
+fn arrow_placements(points: &[Pos2], spacing: f32, area: Rect) -> Vec<(Pos2, Vec2)> {
+    let mut placements = Vec::new();
+    if spacing.is_nan() || spacing < MIN_ARROW_SPACING {
+        return placements;
+    }
+
+    // Distance along the line where the segment starts.
+    let mut start = 0.;
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let length = a.distance(b);
+        if let Some((enter, exit)) = clip_segment(a, b, area) {
+            let direction = (b - a) / length;
+            let first = ((start + enter - spacing / 2.) / spacing).ceil().max(0.) as u32;
+            for index in first.. {
+                let at = spacing / 2. + index as f32 * spacing - start;
+                if at > exit || at >= length {
+                    break;
+                }
+                if placements.len() == MAX_ARROWS {
+                    return placements;
+                }
+                placements.push((a + direction * at, direction));
+            }
+        }
+        start += length;
+    }
+
+    placements
+}
+
+/// Distances from `a`, along the segment towards `b`, at which it enters and exits the `area`,
+/// or `None` if it misses it.
+fn clip_segment(a: Pos2, b: Pos2, area: Rect) -> Option<(f32, f32)> {
+    let length = a.distance(b);
+    if length <= 0. {
+        return None;
+    }
+
+    let direction = (b - a) / length;
+    let (mut enter, mut exit) = (0f32, length);
+    for (from, towards, min, max) in [
+        (a.x, direction.x, area.min.x, area.max.x),
+        (a.y, direction.y, area.min.y, area.max.y),
+    ] {
+        if towards == 0. {
+            if from < min || from > max {
+                return None;
+            }
+        } else {
+            let (near, far) = ((min - from) / towards, (max - from) / towards);
+            enter = enter.max(near.min(far));
+            exit = exit.min(near.max(far));
+        }
+    }
+
+    (enter <= exit).then_some((enter, exit))
+}
+
+/// Subdivide each segment along the great circle. Longitudes are unwrapped, i.e. they go beyond
+/// the `-180..180` range instead of jumping, so that the line stays continuous when crossing the
+/// antimeridian. Steps are up to one degree of arc, or up to given number of pixels on the screen.
+fn great_circle_path(
+    positions: &[Position],
+    screen_step: Option<(f32, &Projector)>,
+) -> Vec<Position> {
+    let mut path = Vec::with_capacity(positions.len());
+    path.extend(positions.first().copied());
+
+    for segment in great_circle_segments(positions, screen_step) {
+        // First position is the last one of the previous segment.
+        path.extend(segment.into_iter().skip(1));
+    }
+
+    path
+}
+
+/// Same as [`great_circle_path`], but separately for each segment. Each includes both of its
+/// ends.
+fn great_circle_segments(
+    positions: &[Position],
+    screen_step: Option<(f32, &Projector)>,
+) -> Vec<Vec<Position>> {
+    let mut segments = Vec::with_capacity(positions.len().saturating_sub(1));
+    let mut previous = positions.first().copied();
+
+    for segment in positions.windows(2) {
+        let a = to_unit_vector(segment[0]);
+        let b = to_unit_vector(segment[1]);
+        let angle = dot(a, b).clamp(-1., 1.).acos();
+        let steps = match screen_step {
+            None => (angle.to_degrees() / MAX_GEODESIC_STEP).ceil().max(1.) as usize,
+            Some((step, projector)) => {
+                let length = screen_length(segment, projector);
+                (length / step.max(f32::EPSILON))
+                    .ceil()
+                    .clamp(1., MAX_GEODESIC_STEPS) as usize
+            }
+        };
+
+        let start = previous.unwrap_or(segment[0]);
+        let mut path = vec![start];
+
+        for step in 1..=steps {
+            let position = if step == steps || angle < f64::EPSILON {
+                segment[1]
+            } else if std::f64::consts::PI - angle < ANTIPODAL_EPSILON {
+                // Every great circle through `a` leads to `b`, so take the one heading east. It
+                // goes no further from the equator than the ends do, so it stays on the map.
+                let t = step as f64 / steps as f64;
+                let east = [
+                    -segment[0].x().to_radians().sin(),
+                    segment[0].x().to_radians().cos(),
+                    0.,
+                ];
+                let (wa, we) = ((t * angle).cos(), (t * angle).sin());
+                from_unit_vector([
+                    wa * a[0] + we * east[0],
+                    wa * a[1] + we * east[1],
+                    wa * a[2] + we * east[2],
+                ])
+            } else {
+                let t = step as f64 / steps as f64;
+                let wa = ((1. - t) * angle).sin() / angle.sin();
+                let wb = (t * angle).sin() / angle.sin();
+                from_unit_vector([
+                    wa * a[0] + wb * b[0],
+                    wa * a[1] + wb * b[1],
+                    wa * a[2] + wb * b[2],
+                ])
+            };
+
+            let last = path.last().copied().unwrap_or(position);
+            path.push(unwrap_longitude(position, last));
+        }
+
+        previous = path.last().copied();
+        segments.push(path);
+    }
+
+    segments
+}
+
+/// On-screen length the great-circle segment would have, if all of it was stretched by the
+/// projection as much as its most stretched one-degree step. Dividing it by the step length
+/// gives the number of steps none of which is longer than that.
+fn screen_length(segment: &[Position], projector: &Projector) -> f32 {
+    let path = great_circle_path(segment, None);
+    let longest = path
+        .windows(2)
+        .map(|pair| (projector.project(pair[1]) - projector.project(pair[0])).length())
+        .fold(0., f32::max);
+    longest * (path.len() - 1) as f32
+}
+
+fn distance_to_segment(pos: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_sq() > 0. {
+        ((pos - a).dot(ab) / ab.length_sq()).clamp(0., 1.)
+    } else {
+        0.
+    };
+    pos.distance(a + t * ab)
+}
+
+/// Shift the longitude by whole turns, so that it is the closest to the previous one.
+fn unwrap_longitude(position: Position, previous: Position) -> Position {
+    let turns = ((position.x() - previous.x()) / 360.).round();
+    lon_lat(position.x() - turns * 360., position.y())
+}
+
+fn to_unit_vector(position: Position) -> [f64; 3] {
+    let (lon, lat) = (position.x().to_radians(), position.y().to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn from_unit_vector([x, y, z]: [f64; 3]) -> Position {
+    lon_lat(y.atan2(x).to_degrees(), z.atan2(x.hypot(y)).to_degrees())
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Position, b: Position) {
+        assert!(
+            (a.x() - b.x()).abs() < 1e-6 && (a.y() - b.y()).abs() < 1e-6,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn short_segments_are_not_subdivided() {
+        let positions = [lon_lat(17.0, 51.0), lon_lat(17.5, 51.2)];
+        assert_eq!(great_circle_path(&positions, None), positions);
+    }
+
+    #[test]
+    fn long_segments_bow_towards_the_pole() {
+        // Warsaw - New York.
+        let warsaw = lon_lat(21.01, 52.23);
+        let new_york = lon_lat(-74.01, 40.71);
+        let path = great_circle_path(&[warsaw, new_york], None);
+
+        // Roughly 6860 km, or 62 degrees of arc, so 62 steps.
+        assert_eq!(path.len(), 63);
+        assert_close(path[0], warsaw);
+        assert_close(path[path.len() - 1], new_york);
+
+        // Great circle goes well north of both ends.
+        let northmost = path.iter().map(|p| p.y()).fold(f64::MIN, f64::max);
+        assert!(northmost > 58., "{northmost}");
+    }
+
+    #[test]
+    fn antipodal_segments_head_east() {
+        let path = great_circle_path(&[lon_lat(0., 0.), lon_lat(180., 0.)], None);
+        assert_eq!(path.len(), 181);
+        assert_close(path[90], lon_lat(90., 0.));
+        assert_close(path[180], lon_lat(180., 0.));
+
+        let path = great_circle_path(&[lon_lat(17., 51.), lon_lat(-163., -51.)], None);
+        assert!(
+            path.iter()
+                .all(|p| p.x().is_finite() && p.y().abs() <= 51. + 1e-6),
+            "{path:?}"
+        );
+    }
+
+    #[test]
+    fn geodesic_steps_follow_the_screen_length() {
+        use egui::{Rect, Vec2};
+
+        let warsaw = lon_lat(21.01, 52.23);
+        let new_york = lon_lat(-74.01, 40.71);
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(512.));
+
+        let path_at = |zoom| {
+            let mut memory = MapMemory::default();
+            memory.set_zoom(zoom).unwrap();
+            let projector = Projector::new(rect, &memory, warsaw);
+            let path = great_circle_path(&[warsaw, new_york], Some((5., &projector)));
+            let longest = path
+                .windows(2)
+                .map(|pair| (projector.project(pair[1]) - projector.project(pair[0])).length())
+                .fold(0., f32::max);
+            (path, longest)
+        };
+
+        // Zoomed out, a few points are enough...
+        let (zoomed_out, longest) = path_at(1.);
+        assert!(zoomed_out.len() < 63, "{}", zoomed_out.len());
+        assert!(longest <= 5.5, "{longest}");
+
+        // ...while zoomed in, steps of a degree would be clearly visible.
+        let (zoomed_in, longest) = path_at(5.);
+        assert!(zoomed_in.len() > 63, "{}", zoomed_in.len());
+        assert!(longest <= 5.5, "{longest}");
+        assert_close(zoomed_in[zoomed_in.len() - 1], new_york);
+    }
+
+    #[test]
+    fn nearest_segment_within_tolerance_is_hit() {
+        use egui::{Rect, Vec2};
+
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(512.));
+        let mut memory = MapMemory::default();
+        memory.set_zoom(10.).expect("valid zoom");
+        let projector = Projector::new(rect, &memory, lon_lat(0., 0.));
+
+        let line = Polyline::new(
+            vec![lon_lat(-0.1, 0.), lon_lat(0., 0.), lon_lat(0., 0.1)],
+            Stroke::default(),
+        );
+        let on_screen = |lon, lat| projector.project(lon_lat(lon, lat)).to_pos2();
+
+        assert_eq!(line.hit_test(on_screen(-0.05, 0.), 1., &projector), Some(0));
+        assert_eq!(line.hit_test(on_screen(0., 0.05), 1., &projector), Some(1));
+        assert_eq!(
+            line.hit_test(on_screen(-0.05, 0.) + egui::vec2(0., 4.), 5., &projector),
+            Some(0)
+        );
+        assert_eq!(
+            line.hit_test(on_screen(-0.05, 0.) + egui::vec2(0., 4.), 3., &projector),
+            None
+        );
+    }
+
+    #[test]
+    fn distance_to_segment_is_measured_to_nearest_point() {
+        use egui::pos2;
+        let (a, b) = (pos2(0., 0.), pos2(10., 0.));
+        assert_eq!(distance_to_segment(pos2(5., 3.), a, b), 3.);
+        assert_eq!(distance_to_segment(pos2(-3., 4.), a, b), 5.);
+        assert_eq!(distance_to_segment(pos2(13., 0.), a, b), 3.);
+        assert_eq!(distance_to_segment(pos2(1., 1.), a, a), 2f32.sqrt());
+    }
+
+    #[test]
+    fn arrows_are_spaced_evenly_along_the_line() {
+        use egui::{pos2, vec2};
+
+        // Corner in the middle, so arrows carry on along the second segment.
+        let points = [pos2(0., 0.), pos2(100., 0.), pos2(100., 100.)];
+        let area = Rect::from_min_size(pos2(-10., -10.), Vec2::splat(200.));
+        let placements = arrow_placements(&points, 40., area);
+
+        assert_eq!(
+            placements,
+            [
+                (pos2(20., 0.), vec2(1., 0.)),
+                (pos2(60., 0.), vec2(1., 0.)),
+                (pos2(100., 0.), vec2(0., 1.)),
+                (pos2(100., 40.), vec2(0., 1.)),
+                (pos2(100., 80.), vec2(0., 1.)),
+            ]
+        );
+
+        // Only the ones within the area are placed.
+        let area = Rect::from_min_size(pos2(90., 30.), Vec2::splat(20.));
+        assert_eq!(
+            arrow_placements(&points, 40., area),
+            [(pos2(100., 40.), vec2(0., 1.))]
+        );
+        assert!(arrow_placements(&points, 0., area).is_empty());
+        assert!(arrow_placements(&points, 0.5, area).is_empty());
+        assert!(arrow_placements(&points, f32::NAN, area).is_empty());
+
+        // Even if the line is long and the spacing tiny, there is a limit.
+        let points = [pos2(0., 0.), pos2(1e6, 0.)];
+        let area = Rect::from_min_size(pos2(-10., -10.), vec2(2e6, 20.));
+        assert_eq!(arrow_placements(&points, 1., area).len(), MAX_ARROWS);
+    }
+
+    #[test]
+    fn path_crossing_antimeridian_is_continuous() {
+        // Tokyo - San Francisco.
+        let path = great_circle_path(&[lon_lat(139.69, 35.69), lon_lat(-122.42, 37.77)], None);
+
+        for pair in path.windows(2) {
+            assert!((pair[0].x() - pair[1].x()).abs() < 10.);
+        }
+        assert_close(path[path.len() - 1], lon_lat(-122.42 + 360., 37.77));
+    }
+}
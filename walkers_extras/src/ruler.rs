@@ -0,0 +1,407 @@
+use egui::{Align2, Color32, FontId, Painter, Pos2, Response, Shape, Stroke, Ui};
+use walkers::{MapMemory, Plugin, PluginLayer, Position, PositionExt, Projector};
+
+use crate::kml::{ring_to_screen_points, tessellate_polygon};
+
+/// Mean radius of the Earth, in meters, the same one [`PositionExt::distance_meters`] uses.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Tolerance of the tessellation, in pixels.
+const FILL_TOLERANCE: f32 = 0.5;
+
+/// Radius of the dots marking the clicked points, in points.
+const VERTEX_RADIUS: f32 = 4.;
+
+/// What [`Ruler`] measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RulerMode {
+    /// Length of the path going through the clicked points.
+    #[default]
+    Distance,
+
+    /// Area of the polygon closed by the clicked points.
+    Area,
+}
+
+/// [`Plugin`] which measures the map: each click adds a point, and either the length of the path
+/// going through them or the area they enclose is shown. It is stateful, so it is the `&mut`
+/// reference which is the plugin, and it needs to persist between frames.
+///
+/// ```
+/// # use walkers::lat_lon;
+/// # use walkers_extras::{Ruler, RulerMode};
+/// let mut ruler = Ruler::default().with_mode(RulerMode::Area);
+/// ruler.push(lat_lon(0., 0.));
+/// ruler.push(lat_lon(0., 1.));
+/// ruler.push(lat_lon(1., 1.));
+/// assert_eq!(ruler.label(), Some("6182.49 km²".to_owned()));
+/// ```
+pub struct Ruler {
+    mode: RulerMode,
+    points: Vec<Position>,
+    stroke: Stroke,
+    fill: Color32,
+}
+
+impl Default for Ruler {
+    fn default() -> Self {
+        let orange = Color32::from_rgb(244, 121, 32);
+        Self {
+            mode: RulerMode::default(),
+            points: Vec::new(),
+            stroke: Stroke::new(2., orange),
+            fill: orange.gamma_multiply(0.25),
+        }
+    }
+}
+
+impl Ruler {
+    /// Distance by default.
+    pub fn with_mode(mut self, mode: RulerMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Line of the path, or the outline of the area, and the dots at the points.
+    pub fn with_stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+
+    /// Fill of the area in [`RulerMode::Area`].
+    pub fn with_fill(mut self, fill: Color32) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    pub fn mode(&self) -> RulerMode {
+        self.mode
+    }
+
+    /// Switch what is measured. Points are kept, so the same ones can be measured both ways.
+    pub fn set_mode(&mut self, mode: RulerMode) {
+        self.mode = mode;
+    }
+
+    pub fn points(&self) -> &[Position] {
+        &self.points
+    }
+
+    /// Add a point, as if the map was clicked there.
+    pub fn push(&mut self, position: Position) {
+        self.points.push(position);
+    }
+
+    /// Remove all points, starting a new measurement.
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Length of the path going through the points, in meters, along great circles.
+    pub fn distance(&self) -> f64 {
+        self.points
+            .windows(2)
+            .map(|pair| pair[0].distance_meters(&pair[1]))
+            .sum()
+    }
+
+    /// Area of the polygon closed by the points, in square meters, on a spherical Earth. Zero
+    /// with fewer than three points.
+    pub fn area(&self) -> f64 {
+        spherical_area(&self.points)
+    }
+
+    /// Measurement in the current mode, formatted in the units best fitting its size. `None` if
+    /// there are not enough points for it yet.
+    pub fn label(&self) -> Option<String> {
+        match self.mode {
+            RulerMode::Distance if self.points.len() >= 2 => Some(format_distance(self.distance())),
+            RulerMode::Area if self.points.len() >= 3 => Some(format_area(self.area())),
+            _ => None,
+        }
+    }
+
+    fn draw(&self, painter: &Painter, projector: &Projector) {
+        let points: Vec<Pos2> = self
+            .points
+            .iter()
+            .map(|position| projector.project(*position).to_pos2())
+            .collect();
+
+        let label_position = match self.mode {
+            RulerMode::Area => {
+                if let Some(ring) = ring_to_screen_points(&self.points, projector) {
+                    if let Some(mesh) = tessellate_polygon(&ring, &[], self.fill, FILL_TOLERANCE) {
+                        painter.add(Shape::mesh(mesh));
+                    }
+                    painter.add(Shape::closed_line(ring.clone(), self.stroke));
+                    centroid(&ring)
+                } else {
+                    painter.add(Shape::line(points.clone(), self.stroke));
+                    None
+                }
+            }
+            RulerMode::Distance => {
+                painter.add(Shape::line(points.clone(), self.stroke));
+                points.last().copied()
+            }
+        };
+
+        for point in &points {
+            painter.circle(*point, VERTEX_RADIUS, Color32::WHITE, self.stroke);
+        }
+
+        if let (Some(position), Some(label)) = (label_position, self.label()) {
+            draw_label(painter, position, label);
+        }
+    }
+}
+
+impl Plugin for &mut Ruler {
+    fn run(
+        self: Box<Self>,
+        _ui: &mut Ui,
+        painter: &Painter,
+        response: &Response,
+        projector: &Projector,
+        _map_memory: &MapMemory,
+    ) {
+        if !response.changed()
+            && response.clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            self.points.push(projector.unproject(pos.to_vec2()));
+        }
+
+        for projector in projector.world_copies() {
+            self.draw(painter, &projector);
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Shapes
+    }
+}
+
+/// Measurement written on a white background, not to get lost in the map.
+fn draw_label(painter: &Painter, position: Pos2, label: String) {
+    let font = FontId::proportional(14.);
+    let galley = painter.layout_no_wrap(label, font, Color32::BLACK);
+    let rect = Align2::CENTER_BOTTOM
+        .anchor_size(position - egui::vec2(0., VERTEX_RADIUS * 2.), galley.size());
+    painter.rect_filled(rect.expand(4.), 4., Color32::WHITE.gamma_multiply(0.9));
+    painter.galley(rect.min, galley, Color32::BLACK);
+}
+
+/// Center of mass of the polygon on the screen, where its area label goes.
+fn centroid(ring: &[Pos2]) -> Option<Pos2> {
+    let (mut area, mut x, mut y) = (0., 0., 0.);
+    for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        let cross = a.x * b.y - b.x * a.y;
+        area += cross;
+        x += (a.x + b.x) * cross;
+        y += (a.y + b.y) * cross;
+    }
+
+    if area.abs() < f32::EPSILON {
+        None
+    } else {
+        Some(Pos2::new(x / (3. * area), y / (3. * area)))
+    }
+}
+
+/// Area of the ring from the spherical excess, summed over the triangles each of its edges makes
+/// with the pole.
+fn spherical_area(ring: &[Position]) -> f64 {
+    if ring.len() < 3 {
+        return 0.;
+    }
+
+    let excess: f64 = ring
+        .iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| {
+            let mut dlon = (b.x() - a.x()).to_radians();
+            // Take the shorter way around, for rings crossing the antimeridian.
+            if dlon > std::f64::consts::PI {
+                dlon -= std::f64::consts::TAU;
+            } else if dlon < -std::f64::consts::PI {
+                dlon += std::f64::consts::TAU;
+            }
+            let (tan_a, tan_b) = (
+                (a.y().to_radians() / 2.).tan(),
+                (b.y().to_radians() / 2.).tan(),
+            );
+            2. * ((dlon / 2.).tan() * (tan_a + tan_b)).atan2(1. + tan_a * tan_b)
+        })
+        .sum();
+
+    excess.abs() * EARTH_RADIUS * EARTH_RADIUS
+}
+
+fn format_distance(meters: f64) -> String {
+    if meters < 1000. {
+        format!("{meters:.0} m")
+    } else {
+        format!("{:.2} km", meters / 1000.)
+    }
+}
+
+fn format_area(square_meters: f64) -> String {
+    if square_meters < 10_000. {
+        format!("{square_meters:.0} m²")
+    } else if square_meters < 1_000_000. {
+        format!("{:.2} ha", square_meters / 10_000.)
+    } else {
+        format!("{:.2} km²", square_meters / 1_000_000.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use walkers::lat_lon;
+
+    fn ruler(mode: RulerMode, points: &[(f64, f64)]) -> Ruler {
+        let mut ruler = Ruler::default().with_mode(mode);
+        for &(lat, lon) in points {
+            ruler.push(lat_lon(lat, lon));
+        }
+        ruler
+    }
+
+    #[test]
+    fn area_is_the_spherical_excess() {
+        // One degree by one degree at the equator, about 111.2 km by 111.2 km.
+        let square = ruler(RulerMode::Area, &[(0., 0.), (0., 1.), (1., 1.), (1., 0.)]);
+        assert!(
+            (square.area() / 1e6 - 12364.).abs() < 1.,
+            "{}",
+            square.area()
+        );
+
+        // Same square across the antimeridian, in the opposite winding.
+        let square = ruler(
+            RulerMode::Area,
+            &[(0., 179.5), (1., 179.5), (1., -179.5), (0., -179.5)],
+        );
+        assert!(
+            (square.area() / 1e6 - 12364.).abs() < 1.,
+            "{}",
+            square.area()
+        );
+
+        // Octant of the sphere is an eighth of its surface.
+        let octant = ruler(RulerMode::Area, &[(0., 0.), (0., 90.), (90., 0.)]);
+        let expected = 4. * std::f64::consts::PI * EARTH_RADIUS * EARTH_RADIUS / 8.;
+        assert!((octant.area() / expected - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn measurements_are_labeled_in_fitting_units() {
+        let mut ruler = ruler(RulerMode::Distance, &[(0., 0.)]);
+        assert_eq!(ruler.label(), None);
+
+        ruler.push(lat_lon(0., 0.001));
+        assert_eq!(ruler.label(), Some("111 m".to_owned()));
+        ruler.push(lat_lon(0.001, 0.001));
+        assert_eq!(ruler.label(), Some("222 m".to_owned()));
+
+        ruler.set_mode(RulerMode::Area);
+        assert_eq!(ruler.label(), Some("6182 m²".to_owned()));
+        ruler.clear();
+        assert_eq!(ruler.label(), None);
+
+        assert_eq!(format_distance(12_345.), "12.35 km");
+        assert_eq!(format_area(123_456.), "12.35 ha");
+        assert_eq!(format_area(12_345_678.), "12.35 km²");
+    }
+
+    #[test]
+    fn clicks_add_points() {
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        let mut ruler = Ruler::default();
+
+        for frame in 0..3 {
+            let mut input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(
+                    Pos2::ZERO,
+                    egui::vec2(100., 100.),
+                )),
+                ..Default::default()
+            };
+            let pos = egui::pos2(50., 50.);
+            input.events = match frame {
+                0 => vec![egui::Event::PointerMoved(pos)],
+                1 => vec![pointer_button(pos, true)],
+                _ => vec![pointer_button(pos, false)],
+            };
+
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| {
+                        ui.add(
+                            walkers::Map::new(None, &mut memory, lat_lon(51., 17.))
+                                .with_plugin(&mut ruler),
+                        );
+                    });
+            });
+        }
+
+        let [point] = ruler.points() else {
+            panic!("expected a single point, got {:?}", ruler.points());
+        };
+        assert!(point.distance_meters(&lat_lon(51., 17.)) < 1.);
+    }
+
+    #[test]
+    fn short_drags_add_points() {
+        let ctx = egui::Context::default();
+        let mut memory = MapMemory::default();
+        let mut ruler = Ruler::default();
+
+        // egui sees a drag, but it is within the map's drag threshold, so it is a click.
+        let pos = egui::pos2(50., 50.);
+        let frames = [
+            vec![egui::Event::PointerMoved(pos)],
+            vec![pointer_button(pos, true)],
+            vec![egui::Event::PointerMoved(pos + egui::vec2(7., 0.))],
+            vec![pointer_button(pos + egui::vec2(7., 0.), false)],
+        ];
+
+        for events in frames {
+            let input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(
+                    Pos2::ZERO,
+                    egui::vec2(100., 100.),
+                )),
+                events,
+                ..Default::default()
+            };
+
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| {
+                        ui.add(
+                            walkers::Map::new(None, &mut memory, lat_lon(51., 17.))
+                                .with_plugin(&mut ruler),
+                        );
+                    });
+            });
+        }
+
+        assert_eq!(ruler.points().len(), 1);
+    }
+
+    fn pointer_button(pos: Pos2, pressed: bool) -> egui::Event {
+        egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Default::default(),
+        }
+    }
+}